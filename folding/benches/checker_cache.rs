@@ -0,0 +1,203 @@
+use ark_ff::{UniformRand, Zero};
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use folding::{
+    checker::{Checker, Column, Provide},
+    expressions::{FoldingCompatibleExpr, FoldingCompatibleExprInner},
+    Alphas, FoldingConfig, FoldingEnv, Instance, Side, Witness,
+};
+use kimchi::circuits::{expr::Variable, gate::CurrOrNext};
+use mina_curves::pasta::{Fp, Vesta};
+use poly_commitment::srs::SRS;
+use std::collections::HashMap;
+
+type Evals = Evaluations<Fp, Radix2EvaluationDomain<Fp>>;
+
+const COLUMNS: usize = 4;
+const ROWS: usize = 1 << 10;
+
+// Only the checker is benchmarked, over `COLUMNS` witness columns without
+// selectors nor challenges.
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct BenchConfig;
+
+#[derive(Clone)]
+struct BenchInstance {
+    commitments: Vec<Vesta>,
+    alphas: Alphas<Fp>,
+}
+
+impl Instance<Vesta> for BenchInstance {
+    fn combine(a: Self, b: Self, challenge: Fp) -> Self {
+        BenchInstance {
+            commitments: folding::combine_slices(&a.commitments, &b.commitments, challenge),
+            alphas: Alphas::combine(a.alphas, b.alphas, challenge),
+        }
+    }
+
+    fn alphas(&self) -> &Alphas<Fp> {
+        &self.alphas
+    }
+}
+
+#[derive(Clone)]
+struct BenchWitness(Vec<Evals>);
+
+impl Witness<Vesta> for BenchWitness {
+    fn combine(a: Self, b: Self, challenge: Fp) -> Self {
+        BenchWitness(folding::combine_slices(&a.0, &b.0, challenge))
+    }
+
+    fn rows(&self) -> usize {
+        self.0[0].evals.len()
+    }
+}
+
+struct BenchEnv {
+    instances: [BenchInstance; 2],
+    // Corresponds to the omega evaluations, for both sides
+    curr_witnesses: [BenchWitness; 2],
+    // Corresponds to the zeta*omega evaluations, for both sides
+    next_witnesses: [BenchWitness; 2],
+}
+
+impl FoldingEnv<Fp, BenchInstance, BenchWitness, Column, (), ()> for BenchEnv {
+    type Structure = ();
+
+    fn new(_structure: &(), instances: [&BenchInstance; 2], witnesses: [&BenchWitness; 2]) -> Self {
+        let curr_witnesses = [witnesses[0].clone(), witnesses[1].clone()];
+        let mut next_witnesses = curr_witnesses.clone();
+        for side in next_witnesses.iter_mut() {
+            for col in side.0.iter_mut() {
+                col.evals.rotate_left(1);
+            }
+        }
+        BenchEnv {
+            instances: [instances[0].clone(), instances[1].clone()],
+            curr_witnesses,
+            next_witnesses,
+        }
+    }
+
+    fn domain_size(&self) -> usize {
+        self.curr_witnesses[0].rows()
+    }
+
+    fn col(&self, col: Column, curr_or_next: CurrOrNext, side: Side) -> &Vec<Fp> {
+        let wit = match curr_or_next {
+            CurrOrNext::Curr => &self.curr_witnesses[side as usize],
+            CurrOrNext::Next => &self.next_witnesses[side as usize],
+        };
+        match col {
+            Column::X(i) => &wit.0[i].evals,
+            Column::Selector(_) => unreachable!("no selectors in this benchmark"),
+        }
+    }
+
+    fn challenge(&self, _challenge: (), _side: Side) -> Fp {
+        unreachable!("no challenges in this benchmark")
+    }
+
+    fn alpha(&self, i: usize, side: Side) -> Fp {
+        self.instances[side as usize].alphas.get(i).unwrap()
+    }
+
+    fn selector(&self, _s: &(), _side: Side) -> &Vec<Fp> {
+        unreachable!("no selectors in this benchmark")
+    }
+}
+
+impl FoldingConfig for BenchConfig {
+    type Column = Column;
+    type Selector = ();
+    type Challenge = ();
+    type Curve = Vesta;
+    type Srs = SRS<Vesta>;
+    type Instance = BenchInstance;
+    type Witness = BenchWitness;
+    type Structure = ();
+    type Env = BenchEnv;
+
+    fn identity_instance(
+        _structure: &(),
+        _srs: &SRS<Vesta>,
+        domain: Radix2EvaluationDomain<Fp>,
+        alphas: Alphas<Fp>,
+    ) -> (BenchInstance, BenchWitness) {
+        let zero = Evaluations::from_vec_and_domain(vec![Fp::zero(); domain.size()], domain);
+        let instance = BenchInstance {
+            commitments: vec![Vesta::zero(); COLUMNS],
+            alphas,
+        };
+        (instance, BenchWitness(vec![zero; COLUMNS]))
+    }
+}
+
+struct BenchProvider(BenchWitness);
+
+impl Provide<BenchConfig> for BenchProvider {
+    fn resolve(&self, inner: FoldingCompatibleExprInner<BenchConfig>) -> Vec<Fp> {
+        match inner {
+            FoldingCompatibleExprInner::Constant(c) => vec![c; self.0.rows()],
+            FoldingCompatibleExprInner::Cell(Variable {
+                col: Column::X(i),
+                row: CurrOrNext::Curr,
+            }) => self.0 .0[i].evals.clone(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Checker<BenchConfig> for BenchProvider {}
+
+/// A constraint of the given depth in which each level uses the previous one
+/// three times, like the folded constraints in which the same subterms are
+/// multiplied by different cross terms.
+fn shared_constraint(depth: usize) -> FoldingCompatibleExpr<BenchConfig> {
+    let cell = |i: usize| {
+        FoldingCompatibleExpr::Atom(FoldingCompatibleExprInner::Cell(Variable {
+            col: Column::X(i % COLUMNS),
+            row: CurrOrNext::Curr,
+        }))
+    };
+    (0..depth).fold(cell(0), |exp, i| {
+        let square = FoldingCompatibleExpr::Mul(Box::new(exp.clone()), Box::new(exp.clone()));
+        let sum = FoldingCompatibleExpr::Add(Box::new(square), Box::new(exp));
+        FoldingCompatibleExpr::Sub(Box::new(sum), Box::new(cell(i + 1)))
+    })
+}
+
+/// Compare evaluating a constraint with heavy subterm reuse with and without
+/// caching the evaluations of the subexpressions.
+pub fn bench_checker_cache(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Folding checker cache");
+    group.sample_size(10);
+
+    let mut rng = o1_utils::tests::make_test_rng();
+    let domain = Radix2EvaluationDomain::new(ROWS).unwrap();
+    let witness = BenchWitness(
+        (0..COLUMNS)
+            .map(|_| {
+                let evals = (0..ROWS).map(|_| Fp::rand(&mut rng)).collect();
+                Evaluations::from_vec_and_domain(evals, domain)
+            })
+            .collect(),
+    );
+    let provider = BenchProvider(witness);
+
+    for depth in [6, 8] {
+        let exp = shared_constraint(depth);
+
+        group.bench_function(format!("depth {depth}, uncached"), |b| {
+            b.iter(|| black_box(provider.check_rec(exp.clone())))
+        });
+
+        group.bench_function(format!("depth {depth}, cached"), |b| {
+            b.iter(|| black_box(provider.check_rec_cached(&exp, &mut HashMap::new())))
+        });
+    }
+}
+
+criterion_group!(benches, bench_checker_cache);
+criterion_main!(benches);
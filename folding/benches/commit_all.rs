@@ -0,0 +1,51 @@
+use ark_ff::UniformRand;
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use folding::commit_all;
+use mina_curves::pasta::{Fp, Vesta};
+use poly_commitment::{srs::SRS, SRS as _};
+
+const COLUMNS: usize = 16;
+
+/// Compare committing to the columns of a witness one at a time with
+/// committing to all of them with [commit_all].
+pub fn bench_commit_all(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Folding witness commitments");
+    group.sample_size(10);
+
+    let mut rng = o1_utils::tests::make_test_rng();
+
+    for log_size in [8, 10] {
+        let size = 1 << log_size;
+        let domain = Radix2EvaluationDomain::<Fp>::new(size).unwrap();
+        let mut srs = SRS::<Vesta>::create(size);
+        srs.add_lagrange_basis(domain);
+        let witness: Vec<_> = (0..COLUMNS)
+            .map(|_| {
+                let evals = (0..size).map(|_| Fp::rand(&mut rng)).collect();
+                Evaluations::from_vec_and_domain(evals, domain)
+            })
+            .collect();
+
+        group.bench_function(
+            format!("{COLUMNS} columns, one at a time (2^{log_size})"),
+            |b| {
+                b.iter(|| {
+                    let commitments: Vec<Vesta> = witness
+                        .iter()
+                        .map(|w| srs.commit_evaluations_non_hiding(domain, w).elems[0])
+                        .collect();
+                    black_box(commitments)
+                })
+            },
+        );
+
+        group.bench_function(
+            format!("{COLUMNS} columns, commit_all (2^{log_size})"),
+            |b| b.iter(|| black_box(commit_all(&srs, domain, &witness))),
+        );
+    }
+}
+
+criterion_group!(benches, bench_commit_all);
+criterion_main!(benches);
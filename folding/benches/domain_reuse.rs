@@ -0,0 +1,224 @@
+use ark_ff::{UniformRand, Zero};
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use folding::{
+    checker::Column,
+    commit_all,
+    expressions::{cell, FoldingCompatibleExpr},
+    make_srs, Alphas, FoldingConfig, FoldingEnv, FoldingScheme, Instance, RelaxedInstance,
+    RelaxedWitness, Side, Witness,
+};
+use kimchi::{circuits::gate::CurrOrNext, curve::KimchiCurve};
+use mina_curves::pasta::{Fp, Vesta, VestaParameters};
+use mina_poseidon::{constants::PlonkSpongeConstantsKimchi, sponge::DefaultFqSponge, FqSponge};
+use poly_commitment::srs::SRS;
+use rand::Rng;
+
+type Evals = Evaluations<Fp, Radix2EvaluationDomain<Fp>>;
+type BaseSponge = DefaultFqSponge<VestaParameters, PlonkSpongeConstantsKimchi>;
+
+const FOLDS: usize = 8;
+
+// A circuit with the single constraint `a b - c = 0` over three witness
+// columns, folded with a scheme holding its domain.
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct BenchConfig;
+
+#[derive(Clone)]
+struct BenchInstance {
+    commitments: Vec<Vesta>,
+    alphas: Alphas<Fp>,
+}
+
+impl Instance<Vesta> for BenchInstance {
+    fn combine(a: Self, b: Self, challenge: Fp) -> Self {
+        BenchInstance {
+            commitments: folding::combine_slices(&a.commitments, &b.commitments, challenge),
+            alphas: Alphas::combine(a.alphas, b.alphas, challenge),
+        }
+    }
+
+    fn alphas(&self) -> &Alphas<Fp> {
+        &self.alphas
+    }
+}
+
+#[derive(Clone)]
+struct BenchWitness(Vec<Evals>);
+
+impl Witness<Vesta> for BenchWitness {
+    fn combine(a: Self, b: Self, challenge: Fp) -> Self {
+        BenchWitness(folding::combine_slices(&a.0, &b.0, challenge))
+    }
+
+    fn rows(&self) -> usize {
+        self.0[0].evals.len()
+    }
+}
+
+struct BenchEnv {
+    instances: [BenchInstance; 2],
+    // Corresponds to the omega evaluations, for both sides
+    curr_witnesses: [BenchWitness; 2],
+    // Corresponds to the zeta*omega evaluations, for both sides
+    next_witnesses: [BenchWitness; 2],
+}
+
+impl FoldingEnv<Fp, BenchInstance, BenchWitness, Column, (), ()> for BenchEnv {
+    type Structure = ();
+
+    fn new(_structure: &(), instances: [&BenchInstance; 2], witnesses: [&BenchWitness; 2]) -> Self {
+        let curr_witnesses = [witnesses[0].clone(), witnesses[1].clone()];
+        let mut next_witnesses = curr_witnesses.clone();
+        for side in next_witnesses.iter_mut() {
+            for col in side.0.iter_mut() {
+                col.evals.rotate_left(1);
+            }
+        }
+        BenchEnv {
+            instances: [instances[0].clone(), instances[1].clone()],
+            curr_witnesses,
+            next_witnesses,
+        }
+    }
+
+    fn domain_size(&self) -> usize {
+        self.curr_witnesses[0].rows()
+    }
+
+    fn col(&self, col: Column, curr_or_next: CurrOrNext, side: Side) -> &Vec<Fp> {
+        let wit = match curr_or_next {
+            CurrOrNext::Curr => &self.curr_witnesses[side as usize],
+            CurrOrNext::Next => &self.next_witnesses[side as usize],
+        };
+        match col {
+            Column::X(i) => &wit.0[i].evals,
+            Column::Selector(_) => unreachable!("no selectors in this benchmark"),
+        }
+    }
+
+    fn challenge(&self, _challenge: (), _side: Side) -> Fp {
+        unreachable!("no challenges in this benchmark")
+    }
+
+    fn alpha(&self, i: usize, side: Side) -> Fp {
+        self.instances[side as usize].alphas.get(i).unwrap()
+    }
+
+    fn selector(&self, _s: &(), _side: Side) -> &Vec<Fp> {
+        unreachable!("no selectors in this benchmark")
+    }
+}
+
+impl FoldingConfig for BenchConfig {
+    type Column = Column;
+    type Selector = ();
+    type Challenge = ();
+    type Curve = Vesta;
+    type Srs = SRS<Vesta>;
+    type Instance = BenchInstance;
+    type Witness = BenchWitness;
+    type Structure = ();
+    type Env = BenchEnv;
+
+    fn identity_instance(
+        _structure: &(),
+        _srs: &SRS<Vesta>,
+        domain: Radix2EvaluationDomain<Fp>,
+        alphas: Alphas<Fp>,
+    ) -> (BenchInstance, BenchWitness) {
+        let zero = Evaluations::from_vec_and_domain(vec![Fp::zero(); domain.size()], domain);
+        let instance = BenchInstance {
+            commitments: vec![Vesta::zero(); 3],
+            alphas,
+        };
+        (instance, BenchWitness(vec![zero; 3]))
+    }
+}
+
+fn constraints() -> Vec<FoldingCompatibleExpr<BenchConfig>> {
+    let [a, b, c] = [0, 1, 2].map(|i| cell(Column::X(i)));
+    vec![a * b - c]
+}
+
+/// Creates a trace with random values of `a` and `b`, and `c = a b`, along
+/// with its instance
+fn random_trace<RNG: Rng>(
+    rng: &mut RNG,
+    srs: &SRS<Vesta>,
+    domain: Radix2EvaluationDomain<Fp>,
+) -> (BenchInstance, BenchWitness) {
+    let a: Vec<Fp> = (0..domain.size()).map(|_| Fp::rand(rng)).collect();
+    let b: Vec<Fp> = (0..domain.size()).map(|_| Fp::rand(rng)).collect();
+    let c = a.iter().zip(&b).map(|(a, b)| *a * b).collect();
+    let witness: Vec<Evals> = [a, b, c]
+        .into_iter()
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain))
+        .collect();
+    let instance = BenchInstance {
+        commitments: commit_all(srs, domain, &witness),
+        alphas: Alphas::new(Fp::rand(rng)),
+    };
+    (instance, BenchWitness(witness))
+}
+
+/// Folds the traces in sequence with `scheme`, replacing its domain by a
+/// newly built one before each fold when `rebuild_domain` is set
+fn fold_traces(
+    traces: Vec<(BenchInstance, BenchWitness)>,
+    scheme: &mut FoldingScheme<BenchConfig>,
+    rebuild_domain: bool,
+) -> (
+    RelaxedInstance<Vesta, BenchInstance>,
+    RelaxedWitness<Vesta, BenchWitness>,
+) {
+    let mut fq_sponge = BaseSponge::new(Vesta::other_curve_sponge_params());
+    let mut traces = traces.into_iter();
+    let (first, second) = (traces.next().unwrap(), traces.next().unwrap());
+    if rebuild_domain {
+        scheme.domain = Radix2EvaluationDomain::new(scheme.domain.size()).unwrap();
+    }
+    let (instance, witness, _) = scheme.fold_instance_witness_pair(first, second, &mut fq_sponge);
+    traces.fold((instance, witness), |acc, trace| {
+        if rebuild_domain {
+            scheme.domain = Radix2EvaluationDomain::new(scheme.domain.size()).unwrap();
+        }
+        let (instance, witness, _) = scheme.fold_instance_witness_pair(acc, trace, &mut fq_sponge);
+        (instance, witness)
+    })
+}
+
+/// Folds `FOLDS + 1` traces with [FoldingScheme::fold_instance_witness_pair]
+/// and a scheme built once, either reusing its domain and the precomputed
+/// roots of unity for every fold, or rebuilding the domain before each fold.
+pub fn bench_domain_reuse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Folding domain");
+    group.sample_size(10);
+
+    let mut rng = o1_utils::tests::make_test_rng();
+
+    for log_size in [8, 10] {
+        let size = 1 << log_size;
+        let domain = Radix2EvaluationDomain::<Fp>::new(size).unwrap();
+        let srs = make_srs::<Vesta>(domain);
+        let traces: Vec<_> = (0..=FOLDS)
+            .map(|_| random_trace(&mut rng, &srs, domain))
+            .collect();
+        let (mut scheme, _) = FoldingScheme::<BenchConfig>::new(constraints(), &srs, domain, &());
+
+        for (rebuild_domain, name) in [(true, "domain rebuilt per fold"), (false, "domain reused")]
+        {
+            group.bench_function(format!("{FOLDS} folds, {name} (2^{log_size})"), |b| {
+                b.iter_batched(
+                    || traces.clone(),
+                    |traces| black_box(fold_traces(traces, &mut scheme, rebuild_domain)),
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+}
+
+criterion_group!(benches, bench_domain_reuse);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::UniformRand;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use folding::ProjectiveCommitments;
+use mina_curves::pasta::{Fp, Vesta};
+
+const COLUMNS: usize = 16;
+
+/// Compare combining the commitments of many instances in affine coordinates,
+/// converting after each fold, with accumulating them in projective
+/// coordinates and normalizing once at the end.
+pub fn bench_projective_commitments(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Folding commitments");
+    group.sample_size(10);
+
+    let mut rng = o1_utils::tests::make_test_rng();
+
+    for folds in [16, 64] {
+        let instances: Vec<Vec<Vesta>> = (0..=folds)
+            .map(|_| {
+                (0..COLUMNS)
+                    .map(|_| Vesta::prime_subgroup_generator().mul(Fp::rand(&mut rng)))
+                    .map(|c| c.into_affine())
+                    .collect()
+            })
+            .collect();
+        let challenges: Vec<Fp> = (0..folds).map(|_| Fp::rand(&mut rng)).collect();
+
+        group.bench_function(format!("{folds} folds, affine"), |b| {
+            b.iter(|| {
+                let mut acc = instances[0].clone();
+                for (instance, challenge) in instances[1..].iter().zip(challenges.iter()) {
+                    acc = acc
+                        .iter()
+                        .zip(instance)
+                        .map(|(a, c)| *a + c.mul(*challenge).into_affine())
+                        .collect();
+                }
+                black_box(acc)
+            })
+        });
+
+        group.bench_function(format!("{folds} folds, projective"), |b| {
+            b.iter(|| {
+                let mut acc = ProjectiveCommitments::<Vesta>::new(&instances[0]);
+                for (instance, challenge) in instances[1..].iter().zip(challenges.iter()) {
+                    acc.combine(instance, *challenge);
+                }
+                black_box(acc.into_affine())
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench_projective_commitments);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::UniformRand;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use folding::{combine_slices, combine_slices_deduplicated};
+use mina_curves::pasta::{Fp, Vesta};
+use poly_commitment::PolyComm;
+
+const COLUMNS: usize = 8;
+
+/// Compare combining the commitments of two instances sharing a large
+/// structure column, split in many chunks, with and without detecting the
+/// shared commitments.
+pub fn bench_shared_commitments(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Folding shared commitments");
+    group.sample_size(10);
+
+    let mut rng = o1_utils::tests::make_test_rng();
+    let challenge = Fp::rand(&mut rng);
+    let mut random_commitment = |chunks: usize| PolyComm {
+        elems: (0..chunks)
+            .map(|_| {
+                Vesta::prime_subgroup_generator()
+                    .mul(Fp::rand(&mut rng))
+                    .into_affine()
+            })
+            .collect::<Vec<Vesta>>(),
+    };
+
+    for chunks in [64, 256] {
+        let shared = random_commitment(chunks);
+        let a: Vec<_> = (0..COLUMNS)
+            .map(|_| random_commitment(1))
+            .chain([shared.clone()])
+            .collect();
+        let b: Vec<_> = (0..COLUMNS)
+            .map(|_| random_commitment(1))
+            .chain([shared])
+            .collect();
+
+        group.bench_function(format!("{chunks} shared chunks, naive"), |bench| {
+            bench.iter(|| black_box(combine_slices(&a, &b, challenge)))
+        });
+
+        group.bench_function(format!("{chunks} shared chunks, deduplicated"), |bench| {
+            bench.iter(|| black_box(combine_slices_deduplicated(&a, &b, challenge)))
+        });
+    }
+}
+
+criterion_group!(benches, bench_shared_commitments);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+use ark_ff::UniformRand;
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use folding::{combine_par, combine_slices};
+use mina_curves::pasta::Fp;
+
+const COLUMNS: usize = 16;
+
+/// Compare combining the columns of two witnesses sequentially and in
+/// parallel, for large domains.
+pub fn bench_witness_combine(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Folding witness combination");
+    group.sample_size(10);
+
+    let mut rng = o1_utils::tests::make_test_rng();
+    let challenge = Fp::rand(&mut rng);
+
+    for log_size in [16, 18] {
+        let domain = Radix2EvaluationDomain::<Fp>::new(1 << log_size).unwrap();
+        let mut random_witness = || -> Vec<_> {
+            (0..COLUMNS)
+                .map(|_| {
+                    let evals = (0..domain.size()).map(|_| Fp::rand(&mut rng)).collect();
+                    Evaluations::from_vec_and_domain(evals, domain)
+                })
+                .collect()
+        };
+        let a = random_witness();
+        let b = random_witness();
+
+        group.bench_function(format!("2^{log_size} rows, sequential"), |bench| {
+            bench.iter(|| black_box(combine_slices(&a, &b, challenge)))
+        });
+
+        group.bench_function(format!("2^{log_size} rows, parallel"), |bench| {
+            bench.iter(|| black_box(combine_par(&a, &b, challenge)))
+        });
+    }
+}
+
+criterion_group!(benches, bench_witness_combine);
+criterion_main!(benches);
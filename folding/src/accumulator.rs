@@ -0,0 +1,253 @@
+//! An accumulator for long folding chains, keeping a Merkle tree over the
+//! [AccumulationProof] of each step.
+//!
+//! The root of the tree commits to the whole transcript of the chain. To audit
+//! a single step, a verifier given the root only needs the proof of this step,
+//! the instances it folds and a [MerklePath] from the proof to the root,
+//! instead of replaying all the steps.
+//!
+//! The leaves are the Blake2b hashes of the serialized proofs. The tree is
+//! padded with empty leaves up to the next power of two, and leaves and inner
+//! nodes are hashed with different prefixes.
+//!
+//! [IvcAccumulator] gives a simpler loop-friendly interface to fold repeated
+//! computation steps with a [DecomposableFoldingScheme], without keeping a
+//! transcript.
+
+use crate::{
+    decomposable_folding::DecomposableFoldingScheme, AccumulationProof, BaseField, FoldingConfig,
+    FoldingScheme, RelaxedInstance, RelaxedWitness, ScalarField,
+};
+use ark_serialize::CanonicalSerialize;
+use blake2::{Blake2b512, Digest};
+use mina_poseidon::FqSponge;
+use poly_commitment::commitment::CommitmentCurve;
+
+/// A node of the transcript tree
+pub type Hash = [u8; 64];
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+fn to_hash(digest: &[u8]) -> Hash {
+    let mut hash = [0; 64];
+    hash.copy_from_slice(digest);
+    hash
+}
+
+fn hash_proof<G: CommitmentCurve>(proof: &AccumulationProof<G>) -> Hash {
+    let mut bytes = vec![LEAF_PREFIX];
+    for cross_term in proof.cross_terms.iter() {
+        for elem in cross_term.elems.iter() {
+            elem.serialize(&mut bytes).unwrap();
+        }
+    }
+    proof.challenge.serialize(&mut bytes).unwrap();
+    to_hash(&Blake2b512::digest(&bytes))
+}
+
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Blake2b512::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    to_hash(&hasher.finalize())
+}
+
+/// Returns the levels of the tree over `leaves`, from the padded leaves to the
+/// root
+fn levels(leaves: &[Hash]) -> Vec<Vec<Hash>> {
+    let mut level = leaves.to_vec();
+    level.resize(leaves.len().next_power_of_two(), [0; 64]);
+    let mut levels = vec![level];
+    while levels.last().unwrap().len() > 1 {
+        let level = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| hash_node(&pair[0], &pair[1]))
+            .collect();
+        levels.push(level);
+    }
+    levels
+}
+
+/// The path from a leaf of the transcript tree to its root
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerklePath {
+    /// The index of the step
+    pub index: usize,
+    /// The siblings of the nodes on the path, from the leaf to the root
+    pub siblings: Vec<Hash>,
+}
+
+impl MerklePath {
+    /// Check that `proof` is the proof of the step [Self::index] of the
+    /// transcript committed to by `root`
+    pub fn verify<G: CommitmentCurve>(&self, root: &Hash, proof: &AccumulationProof<G>) -> bool {
+        let mut index = self.index;
+        let mut node = hash_proof(proof);
+        for sibling in self.siblings.iter() {
+            node = if index % 2 == 0 {
+                hash_node(&node, sibling)
+            } else {
+                hash_node(sibling, &node)
+            };
+            index /= 2;
+        }
+        index == 0 && &node == root
+    }
+}
+
+/// A relaxed instance-witness pair accumulating fresh pairs, one step at a
+/// time, along with the transcript of the steps
+pub struct FoldingAccumulator<CF: FoldingConfig> {
+    pub instance: RelaxedInstance<CF::Curve, CF::Instance>,
+    pub witness: RelaxedWitness<CF::Curve, CF::Witness>,
+    proofs: Vec<AccumulationProof<CF::Curve>>,
+    leaves: Vec<Hash>,
+}
+
+impl<CF: FoldingConfig> FoldingAccumulator<CF> {
+    /// Starts a chain from the given accumulator, for instance
+    /// [FoldingScheme::identity_accumulator]
+    pub fn new(
+        instance: RelaxedInstance<CF::Curve, CF::Instance>,
+        witness: RelaxedWitness<CF::Curve, CF::Witness>,
+    ) -> Self {
+        Self {
+            instance,
+            witness,
+            proofs: vec![],
+            leaves: vec![],
+        }
+    }
+
+    /// Fold a fresh pair into the accumulator, recording the proof of the
+    /// step in the transcript
+    pub fn fold<Sponge>(
+        self,
+        scheme: &FoldingScheme<CF>,
+        pair: (CF::Instance, CF::Witness),
+        fq_sponge: &mut Sponge,
+    ) -> Self
+    where
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        let Self {
+            instance,
+            witness,
+            mut proofs,
+            mut leaves,
+        } = self;
+        let (instance, witness, proof) =
+            scheme.fold_with_accumulation_proof((instance, witness), pair, fq_sponge);
+        leaves.push(hash_proof(&proof));
+        proofs.push(proof);
+        Self {
+            instance,
+            witness,
+            proofs,
+            leaves,
+        }
+    }
+
+    /// Returns the number of pairs folded into the accumulator, the one it was
+    /// started from excluded
+    pub fn num_folds(&self) -> usize {
+        self.proofs.len()
+    }
+
+    /// Returns the proof of the step `i`
+    pub fn step_proof(&self, i: usize) -> Option<&AccumulationProof<CF::Curve>> {
+        self.proofs.get(i)
+    }
+
+    /// Returns the root of the Merkle tree over the proofs of the steps
+    pub fn transcript_root(&self) -> Hash {
+        levels(&self.leaves).pop().unwrap()[0]
+    }
+
+    /// Returns the path from the proof of the step `i` to
+    /// [Self::transcript_root]
+    pub fn transcript_path(&self, i: usize) -> Option<MerklePath> {
+        if i >= self.leaves.len() {
+            return None;
+        }
+        let levels = levels(&self.leaves);
+        let siblings = levels[..levels.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(depth, level)| level[(i >> depth) ^ 1])
+            .collect();
+        Some(MerklePath { index: i, siblings })
+    }
+}
+
+/// The running relaxed pair of an incremental verifiable computation, folding
+/// the instance-witness pair of each step of the computation into it
+pub struct IvcAccumulator<'a, 'b, CF: FoldingConfig> {
+    scheme: &'b DecomposableFoldingScheme<'a, CF>,
+    /// The running pair, only taken out while folding a step into it
+    #[allow(clippy::type_complexity)]
+    state: Option<(
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+    )>,
+    steps: usize,
+}
+
+impl<'a, 'b, CF: FoldingConfig> IvcAccumulator<'a, 'b, CF> {
+    /// Starts an accumulator folding with `scheme` from the pair of the first
+    /// step, which is only relaxed, see [DecomposableFoldingScheme::relax]
+    pub fn new(
+        scheme: &'b DecomposableFoldingScheme<'a, CF>,
+        instance: CF::Instance,
+        witness: CF::Witness,
+    ) -> Self {
+        Self {
+            scheme,
+            state: Some(scheme.relax(instance, witness)),
+            steps: 1,
+        }
+    }
+
+    /// Absorb the pair of a new step, folding it into the running pair with
+    /// [DecomposableFoldingScheme::fold_instance_witness_pair], which carries
+    /// the error terms over to the next step.
+    pub fn absorb_step<Sponge>(
+        &mut self,
+        instance: CF::Instance,
+        witness: CF::Witness,
+        selector: Option<CF::Selector>,
+        fq_sponge: &mut Sponge,
+    ) where
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        let accumulator = self.state.take().unwrap();
+        let (instance, witness, _) = self.scheme.fold_instance_witness_pair(
+            accumulator,
+            (instance, witness),
+            selector,
+            fq_sponge,
+        );
+        self.state = Some((instance, witness));
+        self.steps += 1;
+    }
+
+    /// Returns the number of steps absorbed so far, the first one included
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Returns the running pair
+    #[allow(clippy::type_complexity)]
+    pub fn finalize(
+        self,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+    ) {
+        self.state.unwrap()
+    }
+}
@@ -2,15 +2,19 @@
 //! for a zero result.
 
 use crate::{
-    expressions::{FoldingColumnTrait, FoldingCompatibleExpr, FoldingCompatibleExprInner},
+    expressions::{
+        ColumnKind, FoldingColumnTrait, FoldingCompatibleExpr, FoldingCompatibleExprInner,
+    },
     instance_witness::{Instance, Witness},
     ExpExtension, FoldingConfig, Radix2EvaluationDomain, RelaxedInstance, RelaxedWitness,
 };
 use ark_ec::AffineCurve;
-use ark_ff::{Field, Zero};
+use ark_ff::{BigInteger, Field, FpParameters, One, PrimeField, Zero};
 use ark_poly::Evaluations;
 use kimchi::circuits::{expr::Variable, gate::CurrOrNext};
-use std::ops::Index;
+use log::warn;
+use std::{collections::HashMap, fmt::Debug, ops::Index};
+use thiserror::Error;
 
 #[cfg(not(test))]
 use log::debug;
@@ -34,10 +38,10 @@ pub enum Column {
 // By default, we consider that the columns are all witness values and selectors
 // are public.
 impl FoldingColumnTrait for Column {
-    fn is_witness(&self) -> bool {
+    fn kind(&self) -> ColumnKind {
         match self {
-            Column::X(_) => true,
-            Column::Selector(_) => false,
+            Column::X(_) => ColumnKind::Witness,
+            Column::Selector(_) => ColumnKind::Fixed,
         }
     }
 }
@@ -193,6 +197,98 @@ where
     }
 }
 
+/// Returns whether an evaluation, lifted to an integer in `[0, p)`, is above
+/// `p / 2`, which usually means that it represents a negative integer.
+pub fn is_wrapping<F: PrimeField>(x: &F::BigInt) -> bool {
+    let mut half_modulus = F::Params::MODULUS;
+    half_modulus.div2();
+    x > &half_modulus
+}
+
+/// Returns the largest evaluation of each column, lifted to an integer in
+/// `[0, p)`, logging a warning for the columns for which it
+/// [wraps](is_wrapping).
+/// It is meant to be called on the witnesses given to a fold, to catch
+/// witness generation bugs like integer underflows. Folded witnesses are
+/// combined with random challenges and are expected to wrap.
+pub fn max_magnitudes<'a, F: PrimeField>(
+    columns: impl IntoIterator<Item = &'a Evaluations<F, Radix2EvaluationDomain<F>>>,
+) -> Vec<F::BigInt> {
+    columns
+        .into_iter()
+        .enumerate()
+        .map(|(i, col)| {
+            let max = col
+                .evals
+                .iter()
+                .map(|x| x.into_repr())
+                .max()
+                .unwrap_or_default();
+            if is_wrapping::<F>(&max) {
+                warn!("column {i} has an evaluation close to the modulus: {max}");
+            }
+            max
+        })
+        .collect()
+}
+
+/// Ways in which the dynamic selectors of a witness can be malformed
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SelectorError<S: Debug> {
+    #[error("selector {selector:?} is not boolean in row {row}")]
+    NotBoolean { selector: S, row: usize },
+    #[error("{active} selectors are active in row {row}, expected exactly one")]
+    NotExclusive { row: usize, active: usize },
+}
+
+/// A row in which a constraint does not evaluate to zero, see
+/// [Checker::check_detailed]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("check in row {row} failed, {value} != 0")]
+pub struct ConstraintViolation<F: Field> {
+    pub row: usize,
+    /// The evaluation of the constraint in the row
+    pub value: F,
+}
+
+/// Checks that the given dynamic selectors of a witness are boolean and that,
+/// in every row, exactly one of them is set.
+/// Decomposable folding relies on this to fold with a single selector enabled,
+/// so it should hold for the witnesses given to
+/// [DecomposableFoldingScheme](crate::decomposable_folding::DecomposableFoldingScheme).
+pub fn check_selector_wellformedness<C: FoldingConfig>(
+    witness: &C::Witness,
+    selectors: &[C::Selector],
+) -> Result<(), SelectorError<C::Selector>>
+where
+    C::Witness: Index<
+        C::Selector,
+        Output = Evaluations<
+            <C::Curve as AffineCurve>::ScalarField,
+            Radix2EvaluationDomain<<C::Curve as AffineCurve>::ScalarField>,
+        >,
+    >,
+{
+    for row in 0..witness.rows() {
+        let mut active = 0;
+        for selector in selectors {
+            let x = witness[*selector].evals[row];
+            if x.is_one() {
+                active += 1;
+            } else if !x.is_zero() {
+                return Err(SelectorError::NotBoolean {
+                    selector: *selector,
+                    row,
+                });
+            }
+        }
+        if active != 1 {
+            return Err(SelectorError::NotExclusive { row, active });
+        }
+    }
+    Ok(())
+}
+
 pub trait Checker<C: FoldingConfig>: Provide<C> {
     fn check_rec(
         &self,
@@ -238,12 +334,125 @@ pub trait Checker<C: FoldingConfig>: Provide<C> {
         res
     }
 
-    fn check(&self, exp: &FoldingCompatibleExpr<C>) {
-        let res = self.check_rec(exp.clone());
-        for (i, row) in res.iter().enumerate() {
-            if !row.is_zero() {
-                panic!("check in row {i} failed, {row} != 0");
+    /// Same as [Checker::check_rec], but evaluates each distinct subexpression
+    /// only once, the evaluations being memoized in `cache`. Folded
+    /// constraints often contain the same subterms many times, e.g. the
+    /// columns multiplied by the different cross terms.
+    fn check_rec_cached(
+        &self,
+        exp: &FoldingCompatibleExpr<C>,
+        cache: &mut HashMap<FoldingCompatibleExpr<C>, Vec<<C::Curve as AffineCurve>::ScalarField>>,
+    ) -> Vec<<C::Curve as AffineCurve>::ScalarField> {
+        if let Some(res) = cache.get(exp) {
+            return res.clone();
+        }
+        let res: Vec<_> = match exp {
+            FoldingCompatibleExpr::Atom(inner) => self.resolve(inner.clone()),
+            FoldingCompatibleExpr::Double(e) => {
+                let v = self.check_rec_cached(e, cache);
+                v.into_iter().map(|x| x.double()).collect()
+            }
+            FoldingCompatibleExpr::Square(e) => {
+                let v = self.check_rec_cached(e, cache);
+                v.into_iter().map(|x| x.square()).collect()
+            }
+            FoldingCompatibleExpr::Add(e1, e2) => {
+                let v1 = self.check_rec_cached(e1, cache);
+                let v2 = self.check_rec_cached(e2, cache);
+                v1.into_iter().zip(v2).map(|(a, b)| a + b).collect()
+            }
+            FoldingCompatibleExpr::Sub(e1, e2) => {
+                let v1 = self.check_rec_cached(e1, cache);
+                let v2 = self.check_rec_cached(e2, cache);
+                v1.into_iter().zip(v2).map(|(a, b)| a - b).collect()
+            }
+            FoldingCompatibleExpr::Mul(e1, e2) => {
+                let v1 = self.check_rec_cached(e1, cache);
+                let v2 = self.check_rec_cached(e2, cache);
+                v1.into_iter().zip(v2).map(|(a, b)| a * b).collect()
+            }
+            FoldingCompatibleExpr::Pow(e, exp) => {
+                let v = self.check_rec_cached(e, cache);
+                v.into_iter().map(|x| x.pow([*exp])).collect()
+            }
+        };
+        cache.insert(exp.clone(), res.clone());
+        res
+    }
+
+    /// Evaluates `exp` at a single row, e.g. to inspect the intermediate
+    /// values of a constraint that does not hold. The cells of the next row
+    /// are read from row `row + 1`, wrapping around to the first row.
+    fn eval_at_row(
+        &self,
+        exp: &FoldingCompatibleExpr<C>,
+        row: usize,
+    ) -> <C::Curve as AffineCurve>::ScalarField {
+        match exp {
+            FoldingCompatibleExpr::Atom(inner) => self.resolve(inner.clone())[row],
+            FoldingCompatibleExpr::Double(e) => self.eval_at_row(e, row).double(),
+            FoldingCompatibleExpr::Square(e) => self.eval_at_row(e, row).square(),
+            FoldingCompatibleExpr::Add(e1, e2) => {
+                self.eval_at_row(e1, row) + self.eval_at_row(e2, row)
             }
+            FoldingCompatibleExpr::Sub(e1, e2) => {
+                self.eval_at_row(e1, row) - self.eval_at_row(e2, row)
+            }
+            FoldingCompatibleExpr::Mul(e1, e2) => {
+                self.eval_at_row(e1, row) * self.eval_at_row(e2, row)
+            }
+            FoldingCompatibleExpr::Pow(e, exp) => self.eval_at_row(e, row).pow([*exp]),
+        }
+    }
+
+    fn check(&self, exp: &FoldingCompatibleExpr<C>) {
+        if let Err(violations) = self.check_detailed(exp) {
+            panic!("{}", violations[0]);
+        }
+    }
+
+    /// Same as [Checker::check], but returns the rows in which the constraint
+    /// does not evaluate to zero, along with their evaluation, instead of
+    /// panicking at the first one.
+    #[allow(clippy::type_complexity)]
+    fn check_detailed(
+        &self,
+        exp: &FoldingCompatibleExpr<C>,
+    ) -> Result<(), Vec<ConstraintViolation<<C::Curve as AffineCurve>::ScalarField>>> {
+        let violations: Vec<_> = self
+            .check_rec_cached(exp, &mut HashMap::new())
+            .into_iter()
+            .enumerate()
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(row, value)| ConstraintViolation { row, value })
+            .collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Same as [Checker::check_detailed], but only checks the rows set in
+    /// `active`, e.g. the rows in which a dynamic selector is enabled or the
+    /// rows of a witness before [padding](crate::pad_witness).
+    #[allow(clippy::type_complexity)]
+    fn check_active(
+        &self,
+        exp: &FoldingCompatibleExpr<C>,
+        active: &[bool],
+    ) -> Result<(), Vec<ConstraintViolation<<C::Curve as AffineCurve>::ScalarField>>> {
+        let violations: Vec<_> = match self.check_detailed(exp) {
+            Ok(()) => return Ok(()),
+            Err(violations) => violations
+                .into_iter()
+                .filter(|violation| active[violation.row])
+                .collect(),
+        };
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
         }
     }
 }
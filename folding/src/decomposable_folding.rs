@@ -5,21 +5,46 @@
 
 use crate::{
     columns::ExtendedFoldingColumn,
-    error_term::{compute_error, ExtendedEnv},
-    expressions::{ExpExtension, FoldingCompatibleExpr, FoldingCompatibleExprInner, FoldingExp},
-    instance_witness::{RelaxablePair, RelaxedInstance, RelaxedWitness},
-    BaseField, FoldingConfig, FoldingScheme, ScalarField,
+    error_term::{compute_cross_terms, compute_error, ExtendedEnv, Side},
+    expressions::{
+        extract_terms, ExpExtension, ExprConversionError, ExprSummary, FoldingCompatibleExpr,
+        FoldingCompatibleExprInner, FoldingExp, Sign, Term,
+    },
+    instance_witness::{RelaxableInstance, RelaxablePair, RelaxedInstance, RelaxedWitness},
+    BaseField, FoldingConfig, FoldingEnv, FoldingScheme, ScalarField,
 };
+use ark_ff::{One, Zero};
 use ark_poly::{Evaluations, Radix2EvaluationDomain};
+use kimchi::circuits::expr::{ChallengeTerm, ConstantExpr, Expr};
 use mina_poseidon::FqSponge;
 use poly_commitment::{PolyComm, SRS};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 pub struct DecomposableFoldingScheme<'a, CF: FoldingConfig> {
     inner: FoldingScheme<'a, CF>,
+    /// The groups of dynamic selectors, exactly one selector of each group
+    /// being enabled in each row
+    groups: Vec<Vec<CF::Selector>>,
+    /// The monomials of the constraints, with their sign and alpha, when the
+    /// constraints are of degree higher than `2`. They are then folded as
+    /// they are, see [compute_cross_terms], instead of being quadraticized by
+    /// the inner scheme.
+    native_constraints: Option<Vec<(FoldingExp<CF>, Sign, usize)>>,
+    degree: usize,
+    /// The summary of the final constraint returned with the scheme
+    summary: ExprSummary<CF>,
 }
 
 impl<'a, CF: FoldingConfig> DecomposableFoldingScheme<'a, CF> {
+    /// Creates the scheme, returning it with the relaxed relation satisfied
+    /// by the folded pairs.
+    /// Constraints enforcing that the selectors are boolean and that exactly
+    /// one of them is enabled in each row are added to the constraints.
+    /// The degree `d` of the constraints, the selectors included, is
+    /// computed first. Constraints of degree up to `2` are folded with two
+    /// cross terms. Constraints of higher degree are folded without
+    /// quadraticization and with `d` cross terms, every constraint of degree
+    /// `d_i` being multiplied by `u^(d - d_i)` in the relaxed relation.
     pub fn new(
         // constraints with a dynamic selector
         constraints: BTreeMap<CF::Selector, Vec<FoldingCompatibleExpr<CF>>>,
@@ -29,8 +54,83 @@ impl<'a, CF: FoldingConfig> DecomposableFoldingScheme<'a, CF> {
         domain: Radix2EvaluationDomain<ScalarField<CF>>,
         structure: &CF::Structure,
     ) -> (Self, FoldingCompatibleExpr<CF>) {
-        let constraints = constraints
+        Self::build(
+            vec![constraints],
+            common_constraints,
+            srs,
+            domain,
+            structure,
+            false,
+        )
+    }
+
+    /// Same as [DecomposableFoldingScheme::new], with several groups of
+    /// dynamic selectors describing orthogonal dimensions of the circuit,
+    /// e.g. the class of an instruction and its addressing mode. Each
+    /// constraint is multiplied by the selector of its group, and exactly one
+    /// selector of each group must be enabled in each row, see
+    /// [Self::fold_instance_witness_pair_with_selectors] to fold under a
+    /// combination of selectors. The groups must not share selectors.
+    pub fn new_with_groups(
+        groups: Vec<BTreeMap<CF::Selector, Vec<FoldingCompatibleExpr<CF>>>>,
+        common_constraints: Vec<FoldingCompatibleExpr<CF>>,
+        srs: &'a CF::Srs,
+        domain: Radix2EvaluationDomain<ScalarField<CF>>,
+        structure: &CF::Structure,
+    ) -> (Self, FoldingCompatibleExpr<CF>) {
+        Self::build(groups, common_constraints, srs, domain, structure, false)
+    }
+
+    /// Same as [DecomposableFoldingScheme::new], but reduces constraints of
+    /// degree higher than `2` with
+    /// [quadraticization](crate::quadraticization), folding them with two
+    /// cross terms at the cost of extra witness columns.
+    pub fn new_quadraticized(
+        constraints: BTreeMap<CF::Selector, Vec<FoldingCompatibleExpr<CF>>>,
+        common_constraints: Vec<FoldingCompatibleExpr<CF>>,
+        srs: &'a CF::Srs,
+        domain: Radix2EvaluationDomain<ScalarField<CF>>,
+        structure: &CF::Structure,
+    ) -> (Self, FoldingCompatibleExpr<CF>) {
+        Self::build(
+            vec![constraints],
+            common_constraints,
+            srs,
+            domain,
+            structure,
+            true,
+        )
+    }
+
+    fn build(
+        groups: Vec<BTreeMap<CF::Selector, Vec<FoldingCompatibleExpr<CF>>>>,
+        common_constraints: Vec<FoldingCompatibleExpr<CF>>,
+        srs: &'a CF::Srs,
+        domain: Radix2EvaluationDomain<ScalarField<CF>>,
+        structure: &CF::Structure,
+        quadraticize: bool,
+    ) -> (Self, FoldingCompatibleExpr<CF>) {
+        let groups: Vec<_> = groups
+            .into_iter()
+            .filter(|group| !group.is_empty())
+            .collect();
+        let selectors: Vec<Vec<_>> = groups
+            .iter()
+            .map(|group| group.keys().copied().collect())
+            .collect();
+        let distinct: BTreeSet<_> = selectors.iter().flatten().collect();
+        assert_eq!(
+            distinct.len(),
+            selectors.iter().map(Vec::len).sum::<usize>(),
+            "the groups of selectors must be disjoint"
+        );
+        let well_formed_selectors: Vec<_> = selectors
+            .iter()
+            .flat_map(|group| selector_constraints(group))
+            .collect();
+        let constraints = groups
             .into_iter()
+            .flatten()
             .flat_map(|(s, exps)| {
                 exps.into_iter().map(move |exp| {
                     let s = FoldingCompatibleExprInner::Extensions(ExpExtension::Selector(s));
@@ -39,9 +139,126 @@ impl<'a, CF: FoldingConfig> DecomposableFoldingScheme<'a, CF> {
                 })
             })
             .chain(common_constraints)
+            .chain(well_formed_selectors)
+            .collect::<Vec<_>>();
+        let terms: Vec<_> = constraints
+            .iter()
+            .cloned()
+            .enumerate()
+            .flat_map(|(alpha, exp)| {
+                extract_terms(exp.into_folding_exp())
+                    .map(move |Term { exp, sign }| (exp, sign, alpha))
+            })
             .collect();
-        let (inner, exp) = FoldingScheme::new(constraints, srs, domain, structure);
-        (DecomposableFoldingScheme { inner }, exp)
+        let degree = terms
+            .iter()
+            .map(|(exp, _, _)| exp.total_degree())
+            .max()
+            .unwrap_or(0);
+        if degree <= 2 || quadraticize {
+            let (inner, exp) = FoldingScheme::new(constraints, srs, domain, structure);
+            let scheme = DecomposableFoldingScheme {
+                inner,
+                groups: selectors,
+                native_constraints: None,
+                degree,
+                summary: exp.summary(),
+            };
+            (scheme, exp)
+        } else {
+            // The inner scheme only provides the commitments and the
+            // structure, the constraints being folded by this scheme
+            let (inner, _) = FoldingScheme::new(vec![], srs, domain, structure);
+            let exp = relaxed_relation(&terms, degree);
+            let scheme = DecomposableFoldingScheme {
+                inner,
+                groups: selectors,
+                native_constraints: Some(terms),
+                degree,
+                summary: exp.summary(),
+            };
+            (scheme, exp)
+        }
+    }
+
+    /// Returns the highest degree of the constraints, selectors included
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    /// Returns the number of cross terms of a fold, i.e. the degree of the
+    /// constraints when they are folded natively, and `2` otherwise
+    pub fn cross_terms_count(&self) -> usize {
+        match self.native_constraints {
+            Some(_) => self.degree,
+            None => 2,
+        }
+    }
+
+    /// Returns the degree of the final constraint in the witness columns, see
+    /// [ExprSummary::degree]
+    pub fn final_constraint_degree(&self) -> usize {
+        self.summary.degree
+    }
+
+    /// Returns the columns referenced by the final constraint
+    pub fn columns_used(&self) -> &HashSet<CF::Column> {
+        &self.summary.columns
+    }
+
+    /// Returns the number of alphas used by the final constraint
+    pub fn num_alphas(&self) -> usize {
+        self.summary.alphas
+    }
+
+    /// Relaxes a fresh instance-witness pair, see [FoldingScheme::relax]
+    #[allow(clippy::type_complexity)]
+    pub fn relax(
+        &self,
+        instance: CF::Instance,
+        witness: CF::Witness,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+    ) {
+        self.inner.relax(instance, witness)
+    }
+
+    /// Same as [DecomposableFoldingScheme::new], but takes the constraints as
+    /// kimchi expressions and converts them first, returning an error if any
+    /// of them cannot be used for folding.
+    #[allow(clippy::type_complexity)]
+    pub fn from_kimchi(
+        constraints: BTreeMap<CF::Selector, Vec<Expr<ConstantExpr<ScalarField<CF>>, CF::Column>>>,
+        common_constraints: Vec<Expr<ConstantExpr<ScalarField<CF>>, CF::Column>>,
+        srs: &'a CF::Srs,
+        domain: Radix2EvaluationDomain<ScalarField<CF>>,
+        structure: &CF::Structure,
+    ) -> Result<(Self, FoldingCompatibleExpr<CF>), ExprConversionError>
+    where
+        CF::Challenge: From<ChallengeTerm>,
+    {
+        let constraints = constraints
+            .into_iter()
+            .map(|(s, exps)| {
+                let exps = exps
+                    .into_iter()
+                    .map(FoldingCompatibleExpr::try_from_expr)
+                    .collect::<Result<_, _>>()?;
+                Ok((s, exps))
+            })
+            .collect::<Result<_, _>>()?;
+        let common_constraints = common_constraints
+            .into_iter()
+            .map(FoldingCompatibleExpr::try_from_expr)
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(
+            constraints,
+            common_constraints,
+            srs,
+            domain,
+            structure,
+        ))
     }
 
     #[allow(clippy::type_complexity)]
@@ -57,7 +274,189 @@ impl<'a, CF: FoldingConfig> DecomposableFoldingScheme<'a, CF> {
     ) -> (
         RelaxedInstance<CF::Curve, CF::Instance>,
         RelaxedWitness<CF::Curve, CF::Witness>,
-        [PolyComm<CF::Curve>; 2],
+        Vec<PolyComm<CF::Curve>>,
+    )
+    where
+        A: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        B: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        let env = self.extended_env(a, b, selector);
+        self.fold_env(env, fq_sponge)
+    }
+
+    #[allow(clippy::type_complexity)]
+    /// Same as [Self::fold_instance_witness_pair], with a selector of some of
+    /// the groups given to [Self::new_with_groups]. Folding assumes that, in
+    /// each of these groups, only the given selector is enabled (1) in all
+    /// rows, and any other selector of the group is 0 over all rows.
+    pub fn fold_instance_witness_pair_with_selectors<A, B, Sponge>(
+        &self,
+        a: A,
+        b: B,
+        selectors: &[CF::Selector],
+        fq_sponge: &mut Sponge,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+        Vec<PolyComm<CF::Curve>>,
+    )
+    where
+        A: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        B: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        let env = self.extended_env(a, b, selectors.iter().copied());
+        self.fold_env(env, fq_sponge)
+    }
+
+    #[allow(clippy::type_complexity)]
+    /// Same as [Self::fold_instance_witness_pair], returning the cross terms
+    /// in an array of size `N`, usually [FoldingConfig::NUM_CROSS_TERMS].
+    /// Panics if `N` is not [Self::cross_terms_count].
+    pub fn fold_instance_witness_pair_array<A, B, Sponge, const N: usize>(
+        &self,
+        a: A,
+        b: B,
+        selector: Option<CF::Selector>,
+        fq_sponge: &mut Sponge,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+        [PolyComm<CF::Curve>; N],
+    )
+    where
+        A: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        B: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        assert_eq!(
+            self.cross_terms_count(),
+            N,
+            "The scheme has {} cross terms, not {N}",
+            self.cross_terms_count()
+        );
+        let (instance, witness, cross_terms) =
+            self.fold_instance_witness_pair(a, b, selector, fq_sponge);
+        let cross_terms = cross_terms
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("the number of cross terms was checked"));
+        (instance, witness, cross_terms)
+    }
+
+    #[allow(clippy::type_complexity)]
+    /// Fold a list of at least two instance-witness pairs into a single
+    /// relaxed pair. The pairs are folded from left to right, the first two
+    /// together and then the accumulator with each following pair, which gives
+    /// the same result as chaining [Self::fold_instance_witness_pair].
+    /// A challenge is derived at each step after absorbing the cross terms of
+    /// the step, and the cross terms of all the steps are returned in order,
+    /// [Self::cross_terms_count] per step.
+    pub fn fold_many<Sponge>(
+        &self,
+        pairs: Vec<(CF::Instance, CF::Witness)>,
+        selector: Option<CF::Selector>,
+        fq_sponge: &mut Sponge,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+        Vec<PolyComm<CF::Curve>>,
+    )
+    where
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        assert!(pairs.len() >= 2, "at least two pairs are required to fold");
+        let mut pairs = pairs.into_iter();
+        let (first, second) = (pairs.next().unwrap(), pairs.next().unwrap());
+        let (mut instance, mut witness, cross_terms) =
+            self.fold_instance_witness_pair(first, second, selector, fq_sponge);
+        let mut cross_terms = cross_terms;
+        for pair in pairs {
+            let (folded_instance, folded_witness, t) =
+                self.fold_instance_witness_pair((instance, witness), pair, selector, fq_sponge);
+            instance = folded_instance;
+            witness = folded_witness;
+            cross_terms.extend(t);
+        }
+        (instance, witness, cross_terms)
+    }
+
+    #[allow(clippy::type_complexity)]
+    /// Fold `a` and `b` like [Self::fold_instance_witness_pair], additionally
+    /// returning, for each row of the folded witness, the selectors that are
+    /// enabled in this row in any of the two inputs.
+    /// For instance, folding a pair of additions with a pair of subtractions
+    /// gives rows governed by both selectors.
+    pub fn fold_instance_witness_pair_with_provenance<A, B, Sponge>(
+        &self,
+        a: A,
+        b: B,
+        selector: Option<CF::Selector>,
+        fq_sponge: &mut Sponge,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+        Vec<PolyComm<CF::Curve>>,
+        Vec<BTreeSet<CF::Selector>>,
+    )
+    where
+        A: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        B: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        let env = self.extended_env(a, b, selector);
+        let mut provenance = vec![BTreeSet::new(); env.inner().domain_size()];
+        for side in [Side::Left, Side::Right] {
+            for s in self.groups.iter().flatten() {
+                let col = env.inner().selector(s, side);
+                for (row, value) in provenance.iter_mut().zip(col.iter()) {
+                    if !value.is_zero() {
+                        row.insert(*s);
+                    }
+                }
+            }
+        }
+        let (instance, witness, error_commitments) = self.fold_env(env, fq_sponge);
+        (instance, witness, error_commitments, provenance)
+    }
+
+    /// Compute the evaluations of the cross terms of the fold of `a` and `b`,
+    /// as done by [Self::fold_instance_witness_pair].
+    /// It allows provers to compute and commit to the cross terms separately,
+    /// before folding with [Self::fold_with_error_term].
+    pub fn compute_error_terms<A, B>(
+        &self,
+        a: A,
+        b: B,
+        selector: Option<CF::Selector>,
+    ) -> Vec<Vec<ScalarField<CF>>>
+    where
+        A: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        B: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+    {
+        let env = self.extended_env(a, b, selector);
+        self.cross_terms(&env)
+    }
+
+    #[allow(clippy::type_complexity)]
+    /// Fold `a` and `b` like [Self::fold_instance_witness_pair], using the
+    /// provided cross terms and their commitments `T_0`, `T_1`, ... instead of
+    /// computing them. This is meant for streaming provers which compute the
+    /// error terms incrementally and already have their commitments.
+    /// The commitments are trusted and absorbed as is. They are only checked
+    /// against the evaluations in debug builds.
+    pub fn fold_with_error_term<A, B, Sponge>(
+        &self,
+        a: A,
+        b: B,
+        selector: Option<CF::Selector>,
+        error: Vec<Vec<ScalarField<CF>>>,
+        error_commitments: Vec<PolyComm<CF::Curve>>,
+        fq_sponge: &mut Sponge,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+        Vec<PolyComm<CF::Curve>>,
     )
     where
         A: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
@@ -65,40 +464,161 @@ impl<'a, CF: FoldingConfig> DecomposableFoldingScheme<'a, CF> {
         Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
     {
         let scheme = &self.inner;
-        let a = a.relax(&scheme.zero_vec, scheme.zero_commitment.clone());
-        let b = b.relax(&scheme.zero_vec, scheme.zero_commitment.clone());
+        assert_eq!(error.len(), self.cross_terms_count());
+        assert_eq!(error_commitments.len(), self.cross_terms_count());
+        #[cfg(debug_assertions)]
+        for (e, t) in error.iter().zip(error_commitments.iter()) {
+            let e = Evaluations::from_vec_and_domain(e.clone(), scheme.domain);
+            let commitment = scheme.srs.commit_evaluations_non_hiding(scheme.domain, &e);
+            debug_assert_eq!(
+                &commitment, t,
+                "the error term does not match its commitment"
+            );
+        }
+        let env = self.extended_env(a, b, selector);
+        self.fold_extended_env(env, error, error_commitments, fq_sponge)
+    }
+
+    /// Check that `folded` is the fold of the instances `a` and `b` with the
+    /// commitments to the cross terms `T_0`, `T_1`, ... returned by
+    /// [Self::fold_instance_witness_pair].
+    /// The challenge is derived from `fq_sponge`, which must be in the same
+    /// state as the one of the prover before folding, and the instances are
+    /// recombined without accessing the witnesses. For this reason, the
+    /// commitments to the columns added by quadraticization must already be
+    /// part of `a` and `b`.
+    /// Malformed inputs, i.e. cross terms or instances with an unexpected
+    /// number of commitments, are rejected instead of causing a panic.
+    pub fn verify_folded_instance<A, B, Sponge>(
+        &self,
+        a: A,
+        b: B,
+        folded: &RelaxedInstance<CF::Curve, CF::Instance>,
+        error_commitments: &[PolyComm<CF::Curve>],
+        fq_sponge: &mut Sponge,
+    ) -> bool
+    where
+        A: RelaxableInstance<CF::Curve, CF::Instance>,
+        B: RelaxableInstance<CF::Curve, CF::Instance>,
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+        CF::Instance: PartialEq,
+    {
+        let scheme = &self.inner;
+        if error_commitments.len() != self.cross_terms_count()
+            || error_commitments.iter().any(|t| t.elems.len() != 1)
+        {
+            return false;
+        }
+        let a: RelaxedInstance<CF::Curve, CF::Instance> = a.relax(scheme.zero_commitment.clone());
+        let b: RelaxedInstance<CF::Curve, CF::Instance> = b.relax(scheme.zero_commitment.clone());
+        let extended = folded.inner_instance().extended.len();
+        if a.inner_instance().extended.len() != extended
+            || b.inner_instance().extended.len() != extended
+        {
+            return false;
+        }
 
-        let u = (a.0.u, b.0.u);
+        scheme.absorb_structure(fq_sponge);
+        for t in error_commitments.iter() {
+            fq_sponge.absorb_g(&t.elems);
+        }
+        let challenge = fq_sponge.challenge();
 
-        let (ins1, wit1) = a;
-        let (ins2, wit2) = b;
+        let expected = RelaxedInstance::combine_and_sub_error(a, b, challenge, error_commitments);
+        &expected == folded
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn fold_env<Sponge>(
+        &self,
+        env: ExtendedEnv<CF>,
+        fq_sponge: &mut Sponge,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+        Vec<PolyComm<CF::Curve>>,
+    )
+    where
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        let scheme = &self.inner;
+        let error = self.cross_terms(&env);
+        let error_commitments = error
+            .iter()
+            .map(|e| {
+                let e = Evaluations::from_vec_and_domain(e.clone(), scheme.domain);
+                scheme.srs.commit_evaluations_non_hiding(scheme.domain, &e)
+            })
+            .collect();
+        self.fold_extended_env(env, error, error_commitments, fq_sponge)
+    }
+
+    /// Computes the evaluations of the cross terms, natively for constraints
+    /// of degree higher than `2`
+    fn cross_terms(&self, env: &ExtendedEnv<CF>) -> Vec<Vec<ScalarField<CF>>> {
+        let u = (env.u(Side::Left), env.u(Side::Right));
+        match &self.native_constraints {
+            None => Vec::from(compute_error(&self.inner.expression, env, u)),
+            Some(constraints) => compute_cross_terms(constraints, self.degree, env, u),
+        }
+    }
+
+    /// Relax both pairs and compute their extended witness, the selectors of
+    /// the groups of the `enabled` ones being disabled
+    fn extended_env<A, B>(
+        &self,
+        a: A,
+        b: B,
+        enabled: impl IntoIterator<Item = CF::Selector>,
+    ) -> ExtendedEnv<CF>
+    where
+        A: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        B: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+    {
+        let enabled: BTreeSet<_> = enabled.into_iter().collect();
+        let disabled = self
+            .groups
+            .iter()
+            .filter(|group| group.iter().any(|s| enabled.contains(s)))
+            .flatten()
+            .filter(|s| !enabled.contains(s))
+            .copied()
+            .collect();
+        let scheme = &self.inner;
+        let (ins1, wit1) = a.relax(&scheme.zero_vec, scheme.zero_commitment.clone());
+        let (ins2, wit2) = b.relax(&scheme.zero_vec, scheme.zero_commitment.clone());
         let env = ExtendedEnv::new(
             &scheme.structure,
             [ins1, ins2],
             [wit1, wit2],
             scheme.domain,
-            selector,
+            disabled,
         );
-        let env = env.compute_extension(&scheme.extended_witness_generator, scheme.srs);
-        let error = compute_error(&scheme.expression, &env, u);
-        let error_evals = error.map(|e| Evaluations::from_vec_and_domain(e, scheme.domain));
-
-        let error_commitments = error_evals
-            .iter()
-            .map(|e| scheme.srs.commit_evaluations_non_hiding(scheme.domain, e))
-            .collect::<Vec<_>>();
-        let error_commitments: [PolyComm<CF::Curve>; 2] = error_commitments.try_into().unwrap();
-
-        let error = error_evals.into_iter().map(|e| e.evals).collect::<Vec<_>>();
-        let error: [Vec<_>; 2] = error.try_into().unwrap();
-
-        // sanity check to verify that we only have one commitment in polycomm
-        // (i.e. domain = poly size)
-        assert_eq!(error_commitments[0].elems.len(), 1);
-        assert_eq!(error_commitments[1].elems.len(), 1);
+        env.compute_extension(&scheme.extended_witness_generator, scheme.srs)
+    }
 
-        fq_sponge.absorb_g(&error_commitments[0].elems);
-        fq_sponge.absorb_g(&error_commitments[1].elems);
+    #[allow(clippy::type_complexity)]
+    fn fold_extended_env<Sponge>(
+        &self,
+        env: ExtendedEnv<CF>,
+        error: Vec<Vec<ScalarField<CF>>>,
+        error_commitments: Vec<PolyComm<CF::Curve>>,
+        fq_sponge: &mut Sponge,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+        Vec<PolyComm<CF::Curve>>,
+    )
+    where
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        self.inner.absorb_structure(fq_sponge);
+        for t in error_commitments.iter() {
+            // sanity check to verify that we only have one commitment in
+            // polycomm (i.e. domain = poly size)
+            assert_eq!(t.elems.len(), 1);
+            fq_sponge.absorb_g(&t.elems);
+        }
 
         let challenge = fq_sponge.challenge();
 
@@ -110,6 +630,58 @@ impl<'a, CF: FoldingConfig> DecomposableFoldingScheme<'a, CF> {
     }
 }
 
+/// Builds the relaxed relation `sum_i alpha_i sum_j u^(d - d_ij) m_ij + E` of
+/// constraints folded natively, given the monomials `m_ij` of the constraint
+/// `i`, of degree `d_ij`, `d` being the highest degree
+fn relaxed_relation<CF: FoldingConfig>(
+    terms: &[(FoldingExp<CF>, Sign, usize)],
+    degree: usize,
+) -> FoldingCompatibleExpr<CF> {
+    use FoldingCompatibleExpr::*;
+    let extension = |ext| Box::new(Atom(FoldingCompatibleExprInner::Extensions(ext)));
+    let init = Atom(FoldingCompatibleExprInner::Extensions(ExpExtension::Error));
+    terms.iter().fold(init, |acc, (exp, sign, alpha)| {
+        let term = Mul(
+            Box::new(exp.clone().into_compatible()),
+            extension(ExpExtension::Alpha(*alpha)),
+        );
+        let term = (exp.total_degree()..degree).fold(term, |term, _| {
+            Mul(Box::new(term), extension(ExpExtension::U))
+        });
+        match sign {
+            Sign::Pos => Add(Box::new(acc), Box::new(term)),
+            Sign::Neg => Sub(Box::new(acc), Box::new(term)),
+        }
+    })
+}
+
+/// Returns the constraints enforcing that the dynamic selectors are boolean
+/// and that exactly one of them is enabled in each row, see
+/// [check_selector_wellformedness](crate::checker::check_selector_wellformedness)
+/// for the same check on a witness. There are none without selectors.
+fn selector_constraints<CF: FoldingConfig>(
+    selectors: &[CF::Selector],
+) -> Vec<FoldingCompatibleExpr<CF>> {
+    use FoldingCompatibleExpr::*;
+    let selector = |s: &CF::Selector| {
+        Atom(FoldingCompatibleExprInner::Extensions(
+            ExpExtension::Selector(*s),
+        ))
+    };
+    let sum = match selectors.iter().map(selector).reduce(|acc, s| acc + s) {
+        Some(sum) => sum,
+        None => return vec![],
+    };
+    let one = Atom(FoldingCompatibleExprInner::Constant(
+        ScalarField::<CF>::one(),
+    ));
+    selectors
+        .iter()
+        .map(|s| selector(s) * selector(s) - selector(s))
+        .chain([sum - one])
+        .collect()
+}
+
 pub(crate) fn check_selector<C: FoldingConfig>(exp: &FoldingExp<C>) -> Option<&C::Selector> {
     match exp {
         FoldingExp::Atom(ExtendedFoldingColumn::Selector(s)) => Some(s),
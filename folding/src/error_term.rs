@@ -2,14 +2,21 @@ use crate::{
     columns::ExtendedFoldingColumn,
     decomposable_folding::check_selector,
     eval_leaf::EvalLeaf,
-    expressions::{Degree, FoldingExp, IntegratedFoldingExpr, Sign},
+    expressions::{
+        ColumnKind, Degree, FoldingColumnTrait, FoldingExp, IntegratedFoldingExpr, Sign,
+    },
     quadraticization::ExtendedWitnessGenerator,
     FoldingConfig, FoldingEnv, RelaxedInstance, RelaxedWitness, ScalarField,
 };
-use ark_ff::{Field, One};
+use ark_ff::{Field, One, UniformRand, Zero};
 use ark_poly::{Evaluations, Radix2EvaluationDomain};
 use kimchi::circuits::expr::Variable;
 use poly_commitment::SRS;
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+};
 
 /// This type refers to the two instances to be folded
 #[derive(Clone, Copy)]
@@ -27,10 +34,35 @@ impl Side {
     }
 }
 
+/// The values the error terms are computed from, given by [ExtendedEnv] when
+/// folding and by [PointEnv] when checking the error terms symbolically
+pub(crate) trait ErrorEnv<C: FoldingConfig> {
+    fn col(&self, col: &ExtendedFoldingColumn<C>, side: Side) -> EvalLeaf<ScalarField<C>>;
+
+    fn alpha(&self, i: usize, side: Side) -> ScalarField<C>;
+
+    fn zero_vec(&self) -> Vec<ScalarField<C>>;
+
+    /// Whether the dynamic selector is known to be zero in every row, see
+    /// [DecomposableFoldingScheme::fold_instance_witness_pair_with_selectors](crate::decomposable_folding::DecomposableFoldingScheme::fold_instance_witness_pair_with_selectors)
+    fn is_disabled(&self, selector: &C::Selector) -> bool;
+}
+
+/// Whether the product of `e1` and `e2` is multiplied by a disabled selector
+fn is_disabled<C: FoldingConfig, E: ErrorEnv<C>>(
+    e1: &FoldingExp<C>,
+    e2: &FoldingExp<C>,
+    env: &E,
+) -> bool {
+    check_selector(e1)
+        .or(check_selector(e2))
+        .map_or(false, |s| env.is_disabled(s))
+}
+
 /// Evaluates the expression in the provided side
-pub(crate) fn eval_sided<'a, C: FoldingConfig>(
+pub(crate) fn eval_sided<'a, C: FoldingConfig, E: ErrorEnv<C>>(
     exp: &FoldingExp<C>,
-    env: &'a ExtendedEnv<C>,
+    env: &'a E,
     side: Side,
 ) -> EvalLeaf<'a, ScalarField<C>> {
     use FoldingExp::*;
@@ -53,25 +85,20 @@ pub(crate) fn eval_sided<'a, C: FoldingConfig>(
         Sub(e1, e2) => eval_sided(e1, env, side) - eval_sided(e2, env, side),
         Mul(e1, e2) => {
             //this assumes to some degree that selectors don't multiply each other
-            let selector = check_selector(e1)
-                .or(check_selector(e2))
-                .zip(env.enabled_selector())
-                .map(|(s1, s2)| s1 == s2);
-            match selector {
-                Some(false) => EvalLeaf::Result(env.inner.zero_vec()),
-                Some(true) | None => {
-                    let d1 = e1.folding_degree();
-                    let d2 = e2.folding_degree();
-                    let e1 = match d1 {
-                        Degree::Two => eval_sided(e1, env, side),
-                        _ => eval_exp_error(e1, env, side),
-                    };
-                    let e2 = match d2 {
-                        Degree::Two => eval_sided(e2, env, side),
-                        _ => eval_exp_error(e2, env, side),
-                    };
-                    e1 * e2
-                }
+            if is_disabled(e1, e2, env) {
+                EvalLeaf::Result(env.zero_vec())
+            } else {
+                let d1 = e1.folding_degree();
+                let d2 = e2.folding_degree();
+                let e1 = match d1 {
+                    Degree::Two => eval_sided(e1, env, side),
+                    _ => eval_exp_error(e1, env, side),
+                };
+                let e2 = match d2 {
+                    Degree::Two => eval_sided(e2, env, side),
+                    _ => eval_exp_error(e2, env, side),
+                };
+                e1 * e2
             }
         }
         Pow(e, i) => match i {
@@ -89,9 +116,9 @@ pub(crate) fn eval_sided<'a, C: FoldingConfig>(
     }
 }
 
-pub(crate) fn eval_exp_error<'a, C: FoldingConfig>(
+pub(crate) fn eval_exp_error<'a, C: FoldingConfig, E: ErrorEnv<C>>(
     exp: &FoldingExp<C>,
-    env: &'a ExtendedEnv<C>,
+    env: &'a E,
     side: Side,
 ) -> EvalLeaf<'a, ScalarField<C>> {
     use FoldingExp::*;
@@ -122,13 +149,10 @@ pub(crate) fn eval_exp_error<'a, C: FoldingConfig>(
         Sub(e1, e2) => eval_exp_error(e1, env, side) - eval_exp_error(e2, env, side),
         Mul(e1, e2) => {
             //this assumes to some degree that selectors don't multiply each other
-            let selector = check_selector(e1)
-                .or(check_selector(e2))
-                .zip(env.enabled_selector())
-                .map(|(s1, s2)| s1 == s2);
-            match selector {
-                Some(false) => EvalLeaf::Result(env.inner.zero_vec()),
-                Some(true) | None => match (exp.folding_degree(), e1.folding_degree()) {
+            if is_disabled(e1, e2, env) {
+                EvalLeaf::Result(env.zero_vec())
+            } else {
+                match (exp.folding_degree(), e1.folding_degree()) {
                     (Degree::Two, Degree::One) => {
                         let first =
                             eval_exp_error(e1, env, side) * eval_exp_error(e2, env, side.other());
@@ -137,7 +161,7 @@ pub(crate) fn eval_exp_error<'a, C: FoldingConfig>(
                         first + second
                     }
                     _ => eval_exp_error(e1, env, side) * eval_exp_error(e2, env, side),
-                },
+                }
             }
         }
         Pow(_, 0) => EvalLeaf::Const(ScalarField::<C>::one()),
@@ -168,22 +192,22 @@ pub(crate) fn eval_exp_error<'a, C: FoldingConfig>(
     }
 }
 
-pub(crate) fn compute_error<C: FoldingConfig>(
+pub(crate) fn compute_error<C: FoldingConfig, E: ErrorEnv<C>>(
     exp: &IntegratedFoldingExpr<C>,
-    env: &ExtendedEnv<C>,
+    env: &E,
     u: (ScalarField<C>, ScalarField<C>),
 ) -> [Vec<ScalarField<C>>; 2] {
     let (ul, ur) = (u.0, u.1);
     let u_cross = ul * ur;
-    let zero = || EvalLeaf::Result(env.inner().zero_vec());
+    let zero = || EvalLeaf::Result(env.zero_vec());
 
     let t_0 = {
         let t_0 = (zero(), zero());
         let (l, r) = exp.degree_0.iter().fold(t_0, |(l, r), (exp, sign, alpha)| {
             //could be left or right, doesn't matter for constant terms
             let exp = eval_exp_error(exp, env, Side::Left);
-            let alpha_l = env.inner().alpha(*alpha, Side::Left);
-            let alpha_r = env.inner().alpha(*alpha, Side::Right);
+            let alpha_l = env.alpha(*alpha, Side::Left);
+            let alpha_r = env.alpha(*alpha, Side::Right);
             let left = exp.clone() * alpha_l;
             let right = exp * alpha_r;
             match sign {
@@ -205,8 +229,8 @@ pub(crate) fn compute_error<C: FoldingConfig>(
             .fold(t_1, |(l, cross, r), (exp, sign, alpha)| {
                 let expl = eval_exp_error(exp, env, Side::Left);
                 let expr = eval_exp_error(exp, env, Side::Right);
-                let alpha_l = env.inner().alpha(*alpha, Side::Left);
-                let alpha_r = env.inner().alpha(*alpha, Side::Right);
+                let alpha_l = env.alpha(*alpha, Side::Left);
+                let alpha_r = env.alpha(*alpha, Side::Right);
                 let expr_cross = expl.clone() * alpha_r + expr.clone() * alpha_l;
                 let left = expl * alpha_l;
                 let right = expr * alpha_r;
@@ -225,8 +249,8 @@ pub(crate) fn compute_error<C: FoldingConfig>(
         let expr = eval_sided(exp, env, Side::Right);
         //left or right matter in some way, but not at the top level call
         let cross = eval_exp_error(exp, env, Side::Left);
-        let alpha_l = env.inner().alpha(*alpha, Side::Left);
-        let alpha_r = env.inner().alpha(*alpha, Side::Right);
+        let alpha_l = env.alpha(*alpha, Side::Left);
+        let alpha_r = env.alpha(*alpha, Side::Right);
         let left = expl * alpha_r + cross.clone() * alpha_l;
         let right = expr * alpha_l + cross * alpha_r;
         match sign {
@@ -244,12 +268,111 @@ pub(crate) fn compute_error<C: FoldingConfig>(
     }
 }
 
+/// Evaluates the expression where every value `v` of degree `1` is replaced
+/// by `v_1 + x v_2`, `v_1` and `v_2` being its values on each side
+fn eval_folded<'a, C: FoldingConfig, E: ErrorEnv<C>>(
+    exp: &FoldingExp<C>,
+    env: &'a E,
+    x: ScalarField<C>,
+) -> EvalLeaf<'a, ScalarField<C>> {
+    use FoldingExp::*;
+
+    match exp {
+        Atom(col) => match exp.folding_degree() {
+            Degree::Zero => env.col(col, Side::Left),
+            _ => env.col(col, Side::Left) + env.col(col, Side::Right) * x,
+        },
+        Double(e) => eval_folded(e, env, x).map(Field::double, |f| {
+            Field::double_in_place(f);
+        }),
+        Square(e) => eval_folded(e, env, x).map(Field::square, |f| {
+            Field::square_in_place(f);
+        }),
+        Add(e1, e2) => eval_folded(e1, env, x) + eval_folded(e2, env, x),
+        Sub(e1, e2) => eval_folded(e1, env, x) - eval_folded(e2, env, x),
+        Mul(e1, e2) => eval_folded(e1, env, x) * eval_folded(e2, env, x),
+        Pow(e, i) => {
+            let e = eval_folded(e, env, x);
+            (0..*i).fold(EvalLeaf::Const(ScalarField::<C>::one()), |acc, _| {
+                acc * e.clone()
+            })
+        }
+    }
+}
+
+/// Returns the coefficients of the Lagrange polynomials over `points`
+fn lagrange_basis<F: Field>(points: &[F]) -> Vec<Vec<F>> {
+    points
+        .iter()
+        .enumerate()
+        .map(|(j, xj)| {
+            points.iter().enumerate().filter(|(m, _)| *m != j).fold(
+                vec![F::one()],
+                |acc, (_, xm)| {
+                    let inv = (*xj - xm).inverse().unwrap();
+                    poly_mul(&acc, &[-*xm * inv, inv])
+                },
+            )
+        })
+        .collect()
+}
+
+/// Computes the cross terms of the relaxed relation
+/// `sum_i alpha_i sum_j u^(d - d_ij) m_ij + E`, where the `m_ij` are the
+/// monomials of the constraint `i`, `d_ij` their degrees and `d` the highest
+/// of them, for constraints of any degree.
+/// As the alphas are folded too, replacing every folded value `v` by
+/// `v_1 + X v_2` gives a homogeneous polynomial of degree `d + 1` in `X`. It is
+/// evaluated in every row at the points `0, ..., d + 1` and interpolated, the
+/// `d` cross terms being its coefficients of `X` to `X^d`.
+pub(crate) fn compute_cross_terms<C: FoldingConfig, E: ErrorEnv<C>>(
+    terms: &[(FoldingExp<C>, Sign, usize)],
+    degree: usize,
+    env: &E,
+    u: (ScalarField<C>, ScalarField<C>),
+) -> Vec<Vec<ScalarField<C>>> {
+    let points: Vec<ScalarField<C>> = (0..degree as u64 + 2).map(Into::into).collect();
+    let evals: Vec<Vec<ScalarField<C>>> = points
+        .iter()
+        .map(|x| {
+            let u = u.0 + u.1 * x;
+            let init = EvalLeaf::Result(env.zero_vec());
+            let eval = terms.iter().fold(init, |acc, (exp, sign, alpha)| {
+                let alpha = env.alpha(*alpha, Side::Left) + env.alpha(*alpha, Side::Right) * x;
+                let scale = alpha * u.pow([(degree - exp.total_degree()) as u64]);
+                let term = eval_folded(exp, env, *x) * scale;
+                match sign {
+                    Sign::Pos => acc + term,
+                    Sign::Neg => acc - term,
+                }
+            });
+            eval.unwrap()
+        })
+        .collect();
+    let basis = lagrange_basis(&points);
+    let rows = env.zero_vec().len();
+    (1..=degree)
+        .map(|k| {
+            (0..rows)
+                .map(|row| {
+                    evals
+                        .iter()
+                        .zip(basis.iter())
+                        .map(|(f, l)| f[row] * l[k])
+                        .sum()
+                })
+                .collect()
+        })
+        .collect()
+}
+
 pub(crate) struct ExtendedEnv<CF: FoldingConfig> {
     inner: CF::Env,
     instances: [RelaxedInstance<CF::Curve, CF::Instance>; 2],
     witnesses: [RelaxedWitness<CF::Curve, CF::Witness>; 2],
     domain: Radix2EvaluationDomain<ScalarField<CF>>,
-    selector: Option<CF::Selector>,
+    /// The dynamic selectors that are zero in every row of both sides
+    disabled_selectors: BTreeSet<CF::Selector>,
 }
 
 impl<CF: FoldingConfig> ExtendedEnv<CF> {
@@ -259,7 +382,7 @@ impl<CF: FoldingConfig> ExtendedEnv<CF> {
         instances: [RelaxedInstance<CF::Curve, CF::Instance>; 2],
         witnesses: [RelaxedWitness<CF::Curve, CF::Witness>; 2],
         domain: Radix2EvaluationDomain<ScalarField<CF>>,
-        selector: Option<CF::Selector>,
+        disabled_selectors: BTreeSet<CF::Selector>,
     ) -> Self {
         let inner_instances = [
             instances[0].inner_instance().inner(),
@@ -272,17 +395,22 @@ impl<CF: FoldingConfig> ExtendedEnv<CF> {
             instances,
             witnesses,
             domain,
-            selector,
+            disabled_selectors,
         }
     }
-    pub fn enabled_selector(&self) -> Option<&CF::Selector> {
-        self.selector.as_ref()
+
+    pub fn is_disabled(&self, selector: &CF::Selector) -> bool {
+        self.disabled_selectors.contains(selector)
     }
 
     pub fn inner(&self) -> &CF::Env {
         &self.inner
     }
 
+    pub fn u(&self, side: Side) -> ScalarField<CF> {
+        self.instances[side as usize].u
+    }
+
     #[allow(clippy::type_complexity)]
     pub fn unwrap(
         self,
@@ -306,7 +434,12 @@ impl<CF: FoldingConfig> ExtendedEnv<CF> {
             Side::Right => (&self.instances[1], &self.witnesses[1]),
         };
         match col {
-            Inner(Variable { col, row }) => Col(self.inner().col(*col, *row, side)),
+            Inner(Variable { col, row }) => match col.kind() {
+                ColumnKind::Fixed => Col(self.inner().fixed_col(*col, *row)),
+                ColumnKind::Witness | ColumnKind::DynamicSelector => {
+                    Col(self.inner().col(*col, *row, side))
+                }
+            },
             WitnessExtended(i) => Col(&witness
                 .inner()
                 .extended
@@ -378,3 +511,167 @@ impl<CF: FoldingConfig> ExtendedEnv<CF> {
         self
     }
 }
+
+impl<CF: FoldingConfig> ErrorEnv<CF> for ExtendedEnv<CF> {
+    fn col(&self, col: &ExtendedFoldingColumn<CF>, side: Side) -> EvalLeaf<ScalarField<CF>> {
+        ExtendedEnv::col(self, col, side)
+    }
+
+    fn alpha(&self, i: usize, side: Side) -> ScalarField<CF> {
+        self.inner().alpha(i, side)
+    }
+
+    fn zero_vec(&self) -> Vec<ScalarField<CF>> {
+        self.inner().zero_vec()
+    }
+
+    fn is_disabled(&self, selector: &CF::Selector) -> bool {
+        ExtendedEnv::is_disabled(self, selector)
+    }
+}
+
+/// A single row where every column takes a random value, the same on both
+/// sides for the columns of degree `0`, which are not folded.
+/// The values are drawn from a fixed seed, so that the check is reproducible.
+pub(crate) struct PointEnv<C: FoldingConfig> {
+    #[allow(clippy::type_complexity)]
+    values: RefCell<HashMap<(ExtendedFoldingColumn<C>, usize), ScalarField<C>>>,
+    rng: RefCell<StdRng>,
+}
+
+impl<C: FoldingConfig> PointEnv<C> {
+    fn new() -> Self {
+        Self {
+            values: RefCell::new(HashMap::new()),
+            rng: RefCell::new(StdRng::seed_from_u64(0)),
+        }
+    }
+
+    fn random(&self) -> ScalarField<C> {
+        ScalarField::<C>::rand(&mut *self.rng.borrow_mut())
+    }
+
+    fn value(&self, col: &ExtendedFoldingColumn<C>, side: Side) -> ScalarField<C> {
+        use ExtendedFoldingColumn::*;
+        match col {
+            Error => panic!("shouldn't happen"),
+            Constant(c) => *c,
+            _ => {
+                let side = match FoldingExp::Atom(col.clone()).folding_degree() {
+                    Degree::Zero => Side::Left,
+                    _ => side,
+                };
+                *self
+                    .values
+                    .borrow_mut()
+                    .entry((col.clone(), side as usize))
+                    .or_insert_with(|| self.random())
+            }
+        }
+    }
+
+    /// Expands the expression where every column `w` is folded into
+    /// `w_1 + X w_2`, returning the coefficients of the polynomial in `X`
+    fn expand(&self, exp: &FoldingExp<C>) -> Vec<ScalarField<C>> {
+        use FoldingExp::*;
+        match exp {
+            Atom(col) => match exp.folding_degree() {
+                Degree::Zero => vec![self.value(col, Side::Left)],
+                _ => vec![self.value(col, Side::Left), self.value(col, Side::Right)],
+            },
+            Double(e) => {
+                let e = self.expand(e);
+                poly_add(&e, &e)
+            }
+            Square(e) => {
+                let e = self.expand(e);
+                poly_mul(&e, &e)
+            }
+            Add(e1, e2) => poly_add(&self.expand(e1), &self.expand(e2)),
+            Sub(e1, e2) => poly_sub(&self.expand(e1), &self.expand(e2)),
+            Mul(e1, e2) => poly_mul(&self.expand(e1), &self.expand(e2)),
+            Pow(e, i) => {
+                let e = self.expand(e);
+                (0..*i).fold(vec![ScalarField::<C>::one()], |acc, _| poly_mul(&acc, &e))
+            }
+        }
+    }
+}
+
+impl<C: FoldingConfig> ErrorEnv<C> for PointEnv<C> {
+    fn col(&self, col: &ExtendedFoldingColumn<C>, side: Side) -> EvalLeaf<ScalarField<C>> {
+        EvalLeaf::Const(self.value(col, side))
+    }
+
+    fn alpha(&self, i: usize, side: Side) -> ScalarField<C> {
+        self.value(&ExtendedFoldingColumn::Alpha(i), side)
+    }
+
+    fn zero_vec(&self) -> Vec<ScalarField<C>> {
+        vec![ScalarField::<C>::zero()]
+    }
+
+    fn is_disabled(&self, _selector: &C::Selector) -> bool {
+        false
+    }
+}
+
+fn poly_add<F: Field>(a: &[F], b: &[F]) -> Vec<F> {
+    let mut res = vec![F::zero(); a.len().max(b.len())];
+    for (i, x) in a.iter().enumerate() {
+        res[i] += x;
+    }
+    for (i, x) in b.iter().enumerate() {
+        res[i] += x;
+    }
+    res
+}
+
+fn poly_sub<F: Field>(a: &[F], b: &[F]) -> Vec<F> {
+    let b: Vec<F> = b.iter().map(|x| -*x).collect();
+    poly_add(a, &b)
+}
+
+fn poly_mul<F: Field>(a: &[F], b: &[F]) -> Vec<F> {
+    let mut res = vec![F::zero(); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            res[i + j] += *x * y;
+        }
+    }
+    res
+}
+
+/// Checks that the error terms computed by [compute_error] are the cross
+/// terms of the relaxed relation.
+/// The relation `u^2 d_0 + u d_1 + d_2`, where every term is multiplied by its
+/// alpha, is expanded in a formal variable `X` after replacing every folded
+/// column `w`, `u` and the alphas by `w_1 + X w_2`. The coefficients of `X`
+/// and `X^2` must be the error terms `T_0` and `T_1`.
+/// The columns are given pseudo-random values, so that by the Schwartz-Zippel
+/// lemma the check fails with overwhelming probability if the error terms are
+/// not the cross terms as polynomials in the columns.
+pub(crate) fn check_error_terms<C: FoldingConfig>(exp: &IntegratedFoldingExpr<C>) -> bool {
+    let env = PointEnv::<C>::new();
+    let u = (env.random(), env.random());
+    let [t0, t1] = compute_error(exp, &env, u);
+
+    let u_poly = [u.0, u.1];
+    let mut expansion = vec![ScalarField::<C>::zero(); 4];
+    for (terms, u_power) in [(&exp.degree_0, 2), (&exp.degree_1, 1), (&exp.degree_2, 0)] {
+        for (term, sign, alpha) in terms.iter() {
+            let alpha = [
+                env.alpha(*alpha, Side::Left),
+                env.alpha(*alpha, Side::Right),
+            ];
+            let term = (0..u_power).fold(poly_mul(&env.expand(term), &alpha), |acc, _| {
+                poly_mul(&acc, &u_poly)
+            });
+            expansion = match sign {
+                Sign::Pos => poly_add(&expansion, &term),
+                Sign::Neg => poly_sub(&expansion, &term),
+            };
+        }
+    }
+    expansion[1] == t0[0] && expansion[2] == t1[0]
+}
@@ -11,21 +11,26 @@ use crate::{
     error_term::Side,
     examples::{BaseSponge, Curve, Fp},
     expressions::FoldingCompatibleExprInner,
+    transcript::FoldingTranscript,
     Alphas, ExpExtension, FoldingCompatibleExpr, FoldingConfig, FoldingEnv, Instance,
     RelaxedInstance, RelaxedWitness, Witness,
 };
 use ark_ec::{AffineCurve, ProjectiveCurve};
 use ark_ff::{One, UniformRand, Zero};
-use ark_poly::{Evaluations, Radix2EvaluationDomain};
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
 use itertools::Itertools;
-use kimchi::circuits::{expr::Variable, gate::CurrOrNext};
-use poly_commitment::{srs::SRS, SRS as _};
+use kimchi::{
+    circuits::{expr::Variable, gate::CurrOrNext},
+    curve::KimchiCurve,
+};
+use mina_poseidon::FqSponge;
+use poly_commitment::{srs::SRS, PolyComm, SRS as _};
 use rand::thread_rng;
 
 /// The instance is the commitments to the polynomials and the challenges
 /// There are 3 commitments and challanges because there are 3 columns, A, B and
 /// C.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 struct TestInstance {
     commitments: [Curve; 3],
     challenges: [Fp; 3],
@@ -110,7 +115,7 @@ impl FoldingEnv<Fp, TestInstance, TestWitness, Column, TestChallenge, ()> for Te
     }
 
     fn domain_size(&self) -> usize {
-        2
+        self.curr_witnesses[0].rows()
     }
 
     fn col(&self, col: Column, curr_or_next: CurrOrNext, side: Side) -> &Vec<Fp> {
@@ -122,11 +127,17 @@ impl FoldingEnv<Fp, TestInstance, TestWitness, Column, TestChallenge, ()> for Te
             Column::X(0) => &wit[0].evals,
             Column::X(1) => &wit[1].evals,
             Column::X(2) => &wit[2].evals,
+            // Only 3 columns, the selectors are fixed columns
+            Column::X(_) | Column::Selector(_) => unreachable!(),
+        }
+    }
+
+    fn fixed_col(&self, col: Column, _curr_or_next: CurrOrNext) -> &Vec<Fp> {
+        match col {
             Column::Selector(0) => &self.structure.s_add,
             Column::Selector(1) => &self.structure.s_mul,
-            // Only 3 columns and 2 selectors
-            Column::X(_) => unreachable!(),
-            Column::Selector(_) => unreachable!(),
+            // Only 2 selectors, the other columns are witness columns
+            Column::Selector(_) | Column::X(_) => unreachable!(),
         }
     }
 
@@ -177,7 +188,7 @@ struct TestFoldingConfig;
 
 // Does not contain alpha because this one should be provided by folding itself
 #[allow(dead_code)]
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 enum TestChallenge {
     Beta,
     Gamma,
@@ -194,6 +205,59 @@ impl FoldingConfig for TestFoldingConfig {
     type Instance = TestInstance;
     type Witness = TestWitness;
     type Env = TestFoldingEnv;
+
+    fn identity_instance(
+        _structure: &Self::Structure,
+        _srs: &Self::Srs,
+        domain: Radix2EvaluationDomain<Fp>,
+        alphas: Alphas<Fp>,
+    ) -> (TestInstance, TestWitness) {
+        let instance = TestInstance {
+            commitments: [Curve::zero(); 3],
+            challenges: [Fp::zero(); 3],
+            alphas,
+        };
+        let zero_col = || Evaluations::from_vec_and_domain(vec![Fp::zero(); domain.size()], domain);
+        (instance, std::array::from_fn(|_| zero_col()))
+    }
+
+    // the selectors define which gate is used in each row, so they have to be
+    // bound to the folding challenges
+    fn structure_commitments(
+        structure: &Self::Structure,
+        srs: &Self::Srs,
+        domain: Radix2EvaluationDomain<Fp>,
+    ) -> Vec<PolyComm<Curve>> {
+        [&structure.s_add, &structure.s_mul]
+            .into_iter()
+            .map(|s| {
+                let evals = Evaluations::from_vec_and_domain(s.clone(), domain);
+                srs.commit_evaluations_non_hiding(domain, &evals)
+            })
+            .collect()
+    }
+}
+
+/// The transcript used by the example, on the prover and the verifier side
+type TestTranscript = FoldingTranscript<Curve, BaseSponge>;
+
+fn new_transcript() -> TestTranscript {
+    FoldingTranscript::new(BaseSponge::new(Curve::other_curve_sponge_params()))
+}
+
+/// Builds the instance committed to by `commitments`, deriving its challenges
+/// and alpha from a transcript absorbing the commitments. The verifier only
+/// needs the commitments to recompute the same instance.
+fn instance_from_commitments(commitments: [Curve; 3]) -> TestInstance {
+    let mut transcript = new_transcript();
+    transcript.absorb_commitments(&commitments);
+    let challenges = [(); 3].map(|_| transcript.challenge());
+    let alphas = Alphas::new(transcript.challenge());
+    TestInstance {
+        commitments,
+        challenges,
+        alphas,
+    }
 }
 
 fn instance_from_witness(
@@ -206,20 +270,21 @@ fn instance_from_witness(
         .map(|w| srs.commit_evaluations_non_hiding(domain, w))
         .map(|c| c.elems[0])
         .collect_vec();
-    let commitments: [_; 3] = commitments.try_into().unwrap();
-
-    // here we should absorve the commitments and similar things to later compute challenges
-    // but for this example I just use random values
-    let mut rng = thread_rng();
-    let mut challenge = || Fp::rand(&mut rng);
-    let challenges = [(); 3].map(|_| challenge());
-    let alpha = challenge();
-    let alphas = Alphas::new(alpha);
-    TestInstance {
-        commitments,
-        challenges,
-        alphas,
-    }
+    instance_from_commitments(commitments.try_into().unwrap())
+}
+
+/// Reference for the folded witness, computing `w1 + r w2` column by column
+/// without going through [Witness::combine] or the folding scheme.
+fn expected_folded_witness(w1: &TestWitness, w2: &TestWitness, challenge: Fp) -> TestWitness {
+    std::array::from_fn(|i| {
+        let evals = w1[i]
+            .evals
+            .iter()
+            .zip(w2[i].evals.iter())
+            .map(|(a, b)| *a + challenge * b)
+            .collect();
+        Evaluations::from_vec_and_domain(evals, w1[i].domain())
+    })
 }
 
 fn circuit() -> [Vec<Fp>; 2] {
@@ -353,11 +418,15 @@ mod checker {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::FoldingScheme;
+    use crate::{
+        accumulator::FoldingAccumulator, extension_field::QuadraticExtension,
+        r1cs::export_relaxed_r1cs, AccumulationProof, CrossTerms, FoldingError, FoldingScheme,
+        ProjectiveCommitments,
+    };
+    use ark_ff::Field;
     use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain as D};
     use checker::{ExtendedProvider, Provider};
-    use kimchi::curve::KimchiCurve;
-    use mina_poseidon::FqSponge;
+    use mina_poseidon::poseidon::ArithmeticSpongeParams;
     use std::println as debug;
 
     // this checks a single folding, it would be good to expand it in the future
@@ -434,7 +503,7 @@ mod tests {
         let right = (right_instance, right_witness);
 
         let folded = scheme.fold_instance_witness_pair(left, right, &mut fq_sponge);
-        let (folded_instance, folded_witness, [_t0, _t1]) = folded;
+        let (folded_instance, folded_witness, CrossTerms { t0: _, t1: _ }) = folded;
         {
             let checker = ExtendedProvider::new(structure, folded_instance, folded_witness);
             debug!("exp: \n {:#?}", final_constraint);
@@ -442,4 +511,1251 @@ mod tests {
             checker.check(&final_constraint);
         }
     }
+
+    #[test]
+    fn test_identity_accumulator() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        // the identity satisfies the relaxed relation on its own
+        let (identity_instance, identity_witness) = scheme.identity_accumulator();
+        assert!(identity_instance.u.is_zero());
+        let checker = ExtendedProvider::new(
+            structure.clone(),
+            identity_instance.clone(),
+            identity_witness.clone(),
+        );
+        checker.check(&final_constraint);
+
+        // 1 + 2 - 3 = 0 and 2 * 3 - 6 = 0
+        let witness: TestWitness = [
+            vec![Fp::from(1u32), Fp::from(2u32)],
+            vec![Fp::from(2u32), Fp::from(3u32)],
+            vec![Fp::from(3u32), Fp::from(6u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let instance = instance_from_witness(&witness, &srs, domain);
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (folded_instance, folded_witness, proof) = scheme.fold_with_accumulation_proof(
+            (identity_instance, identity_witness),
+            (instance.clone(), witness.clone()),
+            &mut fq_sponge,
+        );
+        let r = proof.challenge;
+
+        // no cross terms, and the folded pair is the one given scaled by r
+        assert_eq!(proof.cross_terms[0], scheme.zero_commitment);
+        assert_eq!(proof.cross_terms[1], scheme.zero_commitment);
+        assert_eq!(folded_instance.u, r);
+        assert_eq!(
+            folded_instance.get_error_column_commitment(),
+            &scheme.zero_commitment
+        );
+        let folded_commitments = &folded_instance.inner_instance().inner.commitments;
+        for (folded, c) in folded_commitments.iter().zip(instance.commitments) {
+            assert_eq!(*folded, c.mul(r).into_affine());
+        }
+        for (folded, w) in folded_witness.inner().inner.iter().zip(witness.iter()) {
+            for (folded, w) in folded.evals.iter().zip(w.evals.iter()) {
+                assert_eq!(*folded, r * w);
+            }
+        }
+        assert!(folded_witness.error_vec.evals.iter().all(|e| e.is_zero()));
+
+        let checker = ExtendedProvider::new(structure, folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_accumulation_proof() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let left_witness: TestWitness = [
+            vec![Fp::from(1u32), Fp::from(2u32)],
+            vec![Fp::from(2u32), Fp::from(3u32)],
+            vec![Fp::from(3u32), Fp::from(6u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let right_witness: TestWitness = [
+            vec![Fp::from(4u32), Fp::from(3u32)],
+            vec![Fp::from(5u32), Fp::from(6u32)],
+            vec![Fp::from(9u32), Fp::from(18u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let left_instance = instance_from_witness(&left_witness, &srs, domain);
+        let right_instance = instance_from_witness(&right_witness, &srs, domain);
+
+        // prover
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (folded_instance, folded_witness, proof) = scheme.fold_with_accumulation_proof(
+            (left_instance.clone(), left_witness),
+            (right_instance.clone(), right_witness),
+            &mut fq_sponge,
+        );
+
+        // verifier, starting from the same transcript state
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        assert!(scheme.verify_accumulation(
+            left_instance.clone(),
+            right_instance.clone(),
+            &folded_instance,
+            &proof,
+            &mut fq_sponge,
+        ));
+
+        // swapping the cross terms changes the challenge
+        let mut bad_proof = proof.clone();
+        bad_proof.cross_terms.swap(0, 1);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        assert!(!scheme.verify_accumulation(
+            left_instance.clone(),
+            right_instance.clone(),
+            &folded_instance,
+            &bad_proof,
+            &mut fq_sponge,
+        ));
+
+        // folding in the wrong order is rejected
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        assert!(!scheme.verify_accumulation(
+            right_instance,
+            left_instance,
+            &folded_instance,
+            &proof,
+            &mut fq_sponge,
+        ));
+
+        let checker = ExtendedProvider::new(structure, folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_fold_instance_matches_full_fold() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, _) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let witnesses: [TestWitness; 3] = [
+            [[1, 2], [2, 3], [3, 6]],
+            [[4, 3], [5, 6], [9, 18]],
+            [[2, 1], [2, 4], [4, 4]],
+        ]
+        .map(|columns| {
+            columns.map(|col| Evaluations::from_vec_and_domain(col.map(Fp::from).to_vec(), domain))
+        });
+        let instances = witnesses
+            .clone()
+            .map(|witness| instance_from_witness(&witness, &srs, domain));
+        let [w0, w1, w2] = witnesses;
+        let [i0, i1, i2] = instances;
+
+        // the prover folds full pairs, the verifier only the instances
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (acc_instance, acc_witness, proof) =
+            scheme.fold_with_accumulation_proof((i0.clone(), w0), (i1.clone(), w1), &mut fq_sponge);
+        let verifier_acc = scheme.fold_instance(i0, i1, &proof.cross_terms, proof.challenge);
+        assert_eq!(verifier_acc, acc_instance);
+
+        let (acc_instance, _, proof) = scheme.fold_with_accumulation_proof(
+            (acc_instance, acc_witness),
+            (i2.clone(), w2),
+            &mut fq_sponge,
+        );
+        let verifier_acc =
+            scheme.fold_instance(verifier_acc, i2, &proof.cross_terms, proof.challenge);
+        assert_eq!(verifier_acc, acc_instance);
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let srs = crate::make_srs::<Curve>(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, _) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let witnesses: [TestWitness; 3] = [
+            [[1, 2], [2, 3], [3, 6]],
+            [[4, 3], [5, 6], [9, 18]],
+            [[2, 1], [2, 4], [4, 4]],
+        ]
+        .map(|columns| {
+            columns.map(|col| Evaluations::from_vec_and_domain(col.map(Fp::from).to_vec(), domain))
+        });
+        let instances = witnesses
+            .clone()
+            .map(|witness| instance_from_witness(&witness, &srs, domain));
+        let [w0, w1, w2] = witnesses;
+        let [i0, i1, i2] = instances;
+
+        // a chain of two folds, and an independent one
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let cross_terms =
+            |proof: &AccumulationProof<Curve>| proof.cross_terms.clone().map(|t| t.elems[0]);
+        let (acc_instance, acc_witness, proof) = scheme.fold_with_accumulation_proof(
+            (i0.clone(), w0.clone()),
+            (i1.clone(), w1),
+            &mut fq_sponge,
+        );
+        let first = scheme.fold_record(i0.clone(), i1, acc_instance.clone());
+        let first = (first, cross_terms(&proof), proof.challenge);
+        let (folded, _, proof) = scheme.fold_with_accumulation_proof(
+            (acc_instance.clone(), acc_witness),
+            (i2.clone(), w2.clone()),
+            &mut fq_sponge,
+        );
+        let second = scheme.fold_record(acc_instance, i2.clone(), folded);
+        let second = (second, cross_terms(&proof), proof.challenge);
+        let (folded, _, proof) =
+            scheme.fold_with_accumulation_proof((i2.clone(), w2), (i0.clone(), w0), &mut fq_sponge);
+        let third = scheme.fold_record(i2, i0, folded);
+        let third = (third, cross_terms(&proof), proof.challenge);
+        let mut items = vec![first, second, third];
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        assert!(scheme.verify_batch(&items, &mut fq_sponge));
+
+        // changing a single cross term is detected
+        items[1].1[0] = items[1].1[0] + srs.g[0];
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        assert!(!scheme.verify_batch(&items, &mut fq_sponge));
+    }
+
+    #[test]
+    fn test_verify_batch_binds_extended_columns() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let srs = crate::make_srs::<Curve>(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, _) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let witnesses: [TestWitness; 2] = [[[1, 2], [2, 3], [3, 6]], [[4, 3], [5, 6], [9, 18]]]
+            .map(|columns| {
+                columns
+                    .map(|col| Evaluations::from_vec_and_domain(col.map(Fp::from).to_vec(), domain))
+            });
+        let [i0, i1] = witnesses
+            .clone()
+            .map(|witness| instance_from_witness(&witness, &srs, domain));
+        let [w0, w1] = witnesses;
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (folded, _, proof) =
+            scheme.fold_with_accumulation_proof((i0.clone(), w0), (i1.clone(), w1), &mut fq_sponge);
+        let mut record = scheme.fold_record(i0, i1, folded);
+        let r = proof.challenge;
+        let [t0, t1] = proof.cross_terms.clone().map(|t| t.elems[0]);
+
+        // an extended column, folded as the others
+        let (a, b) = (srs.g[0], srs.g[1]);
+        let f = a + b.mul(r).into_affine();
+        for (instance, comm) in [
+            (&mut record.left, a),
+            (&mut record.right, b),
+            (&mut record.folded, f),
+        ] {
+            instance
+                .inner_mut()
+                .extended
+                .push(PolyComm { elems: vec![comm] });
+        }
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        assert!(scheme.verify_batch(&[(record.clone(), [t0, t1], r)], &mut fq_sponge));
+
+        // A bad cross term, offset by the extended column using a challenge
+        // computed without absorbing the extended columns
+        let d = srs.h;
+        let t0 = t0 + d;
+        let rho = {
+            let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+            let errors = [&record.left, &record.right, &record.folded]
+                .map(|i| i.get_error_column_commitment().elems[0]);
+            fq_sponge.absorb_g(&errors);
+            fq_sponge.absorb_g(&[t0, t1]);
+            fq_sponge.absorb_fr(&[r]);
+            fq_sponge.challenge()
+        };
+        let forged = f + d.mul(-r * rho.inverse().unwrap()).into_affine();
+        record.folded.inner_mut().extended[0] = PolyComm {
+            elems: vec![forged],
+        };
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        assert!(!scheme.verify_batch(&[(record, [t0, t1], r)], &mut fq_sponge));
+    }
+
+    #[test]
+    fn test_degenerate_challenges() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let srs = crate::make_srs::<Curve>(domain);
+        let to_witness = |columns: [[u32; 2]; 3]| -> TestWitness {
+            columns.map(|col| Evaluations::from_vec_and_domain(col.map(Fp::from).to_vec(), domain))
+        };
+        let a = to_witness([[1, 2], [2, 3], [3, 6]]);
+        let b = to_witness([[4, 3], [5, 6], [9, 18]]);
+        let (ia, ib) = (
+            instance_from_witness(&a, &srs, domain),
+            instance_from_witness(&b, &srs, domain),
+        );
+
+        // folding with 0 gives back the left operand
+        assert_eq!(Instance::combine(ia.clone(), ib.clone(), Fp::zero()), ia);
+        assert_eq!(Witness::combine(a.clone(), b.clone(), Fp::zero()), a);
+
+        // folding with 1 gives the sum of the operands
+        let sum = Witness::combine(a.clone(), b.clone(), Fp::one());
+        for ((sum, a), b) in sum.iter().zip(&a).zip(&b) {
+            assert_eq!(sum, &(a + b));
+        }
+    }
+
+    /// A transcript whose challenges are all zero
+    struct ZeroTranscript;
+
+    impl FqSponge<ark_bn254::Fq, Curve, Fp> for ZeroTranscript {
+        fn new(_params: &'static ArithmeticSpongeParams<ark_bn254::Fq>) -> Self {
+            ZeroTranscript
+        }
+
+        fn absorb_fq(&mut self, _x: &[ark_bn254::Fq]) {}
+
+        fn absorb_g(&mut self, _g: &[Curve]) {}
+
+        fn absorb_fr(&mut self, _x: &[Fp]) {}
+
+        fn challenge_fq(&mut self) -> ark_bn254::Fq {
+            ark_bn254::Fq::zero()
+        }
+
+        fn challenge(&mut self) -> Fp {
+            Fp::zero()
+        }
+
+        fn digest_fq(self) -> ark_bn254::Fq {
+            ark_bn254::Fq::zero()
+        }
+
+        fn digest(self) -> Fp {
+            Fp::zero()
+        }
+    }
+
+    #[test]
+    fn test_reject_zero_challenge() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let srs = crate::make_srs::<Curve>(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (mut scheme, _) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+        let pair = |columns: [[u32; 2]; 3]| {
+            let witness: TestWitness = columns
+                .map(|col| Evaluations::from_vec_and_domain(col.map(Fp::from).to_vec(), domain));
+            (instance_from_witness(&witness, &srs, domain), witness)
+        };
+        let left = pair([[1, 2], [2, 3], [3, 6]]);
+        let right = pair([[4, 3], [5, 6], [9, 18]]);
+
+        // by default, a zero challenge folds into the left pair
+        let (instance, _, proof) = scheme
+            .try_fold_with_accumulation_proof(left.clone(), right.clone(), &mut ZeroTranscript)
+            .unwrap();
+        assert!(proof.challenge.is_zero());
+        assert_eq!(instance, scheme.relax(left.0.clone(), left.1.clone()).0);
+
+        scheme.reject_zero_challenge = true;
+        assert_eq!(
+            scheme
+                .try_fold_with_accumulation_proof(left.clone(), right.clone(), &mut ZeroTranscript)
+                .err(),
+            Some(FoldingError::ZeroChallenge)
+        );
+        assert!(!scheme.verify_accumulation(
+            left.0,
+            right.0,
+            &instance,
+            &proof,
+            &mut ZeroTranscript
+        ));
+    }
+
+    #[test]
+    fn test_relaxation_factor() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let witnesses: [TestWitness; 3] = [
+            [[1, 2], [2, 3], [3, 6]],
+            [[4, 3], [5, 6], [9, 18]],
+            [[2, 1], [2, 4], [4, 4]],
+        ]
+        .map(|columns| {
+            columns.map(|col| Evaluations::from_vec_and_domain(col.map(Fp::from).to_vec(), domain))
+        });
+        let pairs =
+            witnesses.map(|witness| (instance_from_witness(&witness, &srs, domain), witness));
+
+        // folds the three pairs, fresh pairs being relaxed with u = 1
+        let accumulate = || {
+            let [p0, p1, p2] = pairs.clone();
+            let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+            let (instance, witness, proof) =
+                scheme.fold_with_accumulation_proof(p0, p1, &mut fq_sponge);
+            assert_eq!(instance.u, Fp::one() + proof.challenge);
+            let u = instance.u;
+            let (instance, witness, proof) =
+                scheme.fold_with_accumulation_proof((instance, witness), p2, &mut fq_sponge);
+            assert_eq!(instance.u, u + proof.challenge);
+            (instance, witness)
+        };
+
+        let (instance, witness) = accumulate();
+        ExtendedProvider::new(structure.clone(), instance, witness).check(&final_constraint);
+
+        // the relaxed constraint depends on u, which must be the folded one
+        let (mut instance, witness) = accumulate();
+        instance.u += Fp::one();
+        let checker = ExtendedProvider::new(structure, instance, witness);
+        assert!(checker.check_detailed(&final_constraint).is_err());
+    }
+
+    #[test]
+    fn test_cached_evaluation() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let [left, right]: [TestWitness; 2] = [[[1, 2], [2, 3], [3, 6]], [[4, 3], [5, 6], [9, 18]]]
+            .map(|columns| {
+                columns
+                    .map(|col| Evaluations::from_vec_and_domain(col.map(Fp::from).to_vec(), domain))
+            });
+        let left = (instance_from_witness(&left, &srs, domain), left);
+        let right = (instance_from_witness(&right, &srs, domain), right);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (mut instance, witness, _) =
+            scheme.fold_instance_witness_pair(left, right, &mut fq_sponge);
+        // tamper with u for the constraint not to evaluate to zero
+        instance.u += Fp::one();
+        let checker = ExtendedProvider::new(structure, instance, witness);
+
+        // the folded constraint is used several times, along with its subterms
+        let shared = FoldingCompatibleExpr::Add(
+            Box::new(FoldingCompatibleExpr::Mul(
+                Box::new(final_constraint.clone()),
+                Box::new(final_constraint.clone()),
+            )),
+            Box::new(FoldingCompatibleExpr::Square(Box::new(
+                final_constraint.clone(),
+            ))),
+        );
+        for exp in [final_constraint, shared] {
+            let mut cache = std::collections::HashMap::new();
+            let expected = checker.check_rec(exp.clone());
+            assert!(expected.iter().any(|x| !x.is_zero()));
+            assert_eq!(checker.check_rec_cached(&exp, &mut cache), expected);
+            // the cached evaluation is reused
+            assert_eq!(cache.get(&exp), Some(&expected));
+            assert_eq!(checker.check_rec_cached(&exp, &mut cache), expected);
+        }
+    }
+
+    #[test]
+    fn test_prover_and_verifier_transcripts_agree() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let left_witness: TestWitness = [
+            vec![Fp::from(1u32), Fp::from(2u32)],
+            vec![Fp::from(2u32), Fp::from(3u32)],
+            vec![Fp::from(3u32), Fp::from(6u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let right_witness: TestWitness = [
+            vec![Fp::from(4u32), Fp::from(3u32)],
+            vec![Fp::from(5u32), Fp::from(6u32)],
+            vec![Fp::from(9u32), Fp::from(18u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+
+        // prover
+        let left_instance = instance_from_witness(&left_witness, &srs, domain);
+        let right_instance = instance_from_witness(&right_witness, &srs, domain);
+        let mut prover = new_transcript();
+        let (folded_instance, folded_witness, proof) = scheme.fold_with_accumulation_proof(
+            (left_instance.clone(), left_witness),
+            (right_instance.clone(), right_witness),
+            &mut prover,
+        );
+
+        // verifier, only given the commitments of the instances
+        let left = instance_from_commitments(left_instance.commitments);
+        let right = instance_from_commitments(right_instance.commitments);
+        assert_eq!(left, left_instance);
+        assert_eq!(right, right_instance);
+        let mut verifier = new_transcript();
+        assert!(scheme.verify_accumulation(left, right, &folded_instance, &proof, &mut verifier));
+
+        // both transcripts are in the same state after the fold
+        let prover_challenges = [(); 3].map(|_| prover.challenge());
+        let verifier_challenges = [(); 3].map(|_| verifier.challenge());
+        assert_eq!(prover_challenges, verifier_challenges);
+
+        // a transcript absorbing different values diverges
+        let mut other = new_transcript();
+        other.absorb_scalars(&[Fp::one()]);
+        assert_ne!(other.challenge(), new_transcript().challenge());
+
+        let checker = ExtendedProvider::new(structure, folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_export_relaxed_r1cs() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let left_witness: TestWitness = [
+            vec![Fp::from(1u32), Fp::from(2u32)],
+            vec![Fp::from(2u32), Fp::from(3u32)],
+            vec![Fp::from(3u32), Fp::from(6u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let right_witness: TestWitness = [
+            vec![Fp::from(4u32), Fp::from(3u32)],
+            vec![Fp::from(5u32), Fp::from(6u32)],
+            vec![Fp::from(9u32), Fp::from(18u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let left_instance = instance_from_witness(&left_witness, &srs, domain);
+        let right_instance = instance_from_witness(&right_witness, &srs, domain);
+
+        let mut fq_sponge = new_transcript();
+        let (folded_instance, folded_witness, _) = scheme.fold_instance_witness_pair(
+            (left_instance, left_witness),
+            (right_instance, right_witness),
+            &mut fq_sponge,
+        );
+        let u = folded_instance.u;
+        let checker = ExtendedProvider::new(structure, folded_instance, folded_witness);
+        let r1cs = export_relaxed_r1cs(&checker, &final_constraint).unwrap();
+
+        // one R1CS row per row of the circuit, z being u and the 3 columns
+        assert_eq!(r1cs.e.len(), domain.size());
+        assert_eq!(r1cs.z.len(), 1 + 3 * domain.size());
+        assert_eq!(r1cs.u, u);
+        assert!(r1cs.is_satisfied());
+        checker.check(&final_constraint);
+
+        let mut bad_z = r1cs.clone();
+        bad_z.z[1] += Fp::one();
+        assert!(!bad_z.is_satisfied());
+
+        let mut bad_error = r1cs;
+        bad_error.e[0] += Fp::one();
+        assert!(!bad_error.is_satisfied());
+    }
+
+    #[test]
+    fn test_structure_binds_challenge() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add: s_add.clone(),
+            s_mul: s_mul.clone(),
+            constants: vec![],
+        };
+        // the same gates in the other order
+        let swapped_structure = TestStructure {
+            s_add: s_mul,
+            s_mul: s_add,
+            constants: vec![],
+        };
+        let (scheme, _) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+        let (swapped_scheme, _) = FoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            &srs,
+            domain,
+            &swapped_structure,
+        );
+        assert_eq!(scheme.structure_commitments.len(), 2);
+        assert_ne!(
+            scheme.structure_commitments,
+            swapped_scheme.structure_commitments
+        );
+
+        let left_witness: TestWitness = [
+            vec![Fp::from(1u32), Fp::from(2u32)],
+            vec![Fp::from(2u32), Fp::from(3u32)],
+            vec![Fp::from(3u32), Fp::from(6u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let right_witness: TestWitness = [
+            vec![Fp::from(4u32), Fp::from(3u32)],
+            vec![Fp::from(5u32), Fp::from(6u32)],
+            vec![Fp::from(9u32), Fp::from(18u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let left_instance = instance_from_witness(&left_witness, &srs, domain);
+        let right_instance = instance_from_witness(&right_witness, &srs, domain);
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (folded_instance, _, proof) = scheme.fold_with_accumulation_proof(
+            (left_instance.clone(), left_witness),
+            (right_instance.clone(), right_witness),
+            &mut fq_sponge,
+        );
+
+        // with the same instances and cross terms, only the structure differs
+        let fold_instances = |scheme: &FoldingScheme<TestFoldingConfig>| {
+            let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+            scheme.fold_instance_pair(
+                left_instance.clone(),
+                right_instance.clone(),
+                proof.cross_terms.clone(),
+                &mut fq_sponge,
+            )
+        };
+        assert_eq!(fold_instances(&scheme), folded_instance);
+        assert_ne!(fold_instances(&swapped_scheme).u, folded_instance.u);
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        assert!(!swapped_scheme.verify_accumulation(
+            left_instance,
+            right_instance,
+            &folded_instance,
+            &proof,
+            &mut fq_sponge,
+        ));
+    }
+
+    #[test]
+    fn test_folded_witness_matches_reference() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, _) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        // the witnesses do not need to satisfy the constraints to be combined
+        let mut rng = thread_rng();
+        let mut random_witness = || -> TestWitness {
+            std::array::from_fn(|_| {
+                let evals = (0..domain.size()).map(|_| Fp::rand(&mut rng)).collect();
+                Evaluations::from_vec_and_domain(evals, domain)
+            })
+        };
+        let left_witness = random_witness();
+        let right_witness = random_witness();
+        let left_instance = instance_from_witness(&left_witness, &srs, domain);
+        let right_instance = instance_from_witness(&right_witness, &srs, domain);
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (_, folded_witness, proof) = scheme.fold_with_accumulation_proof(
+            (left_instance, left_witness.clone()),
+            (right_instance, right_witness.clone()),
+            &mut fq_sponge,
+        );
+
+        let expected = expected_folded_witness(&left_witness, &right_witness, proof.challenge);
+        assert_eq!(folded_witness.inner().inner, expected);
+    }
+
+    #[test]
+    fn test_folding_over_extension() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, _) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let left_witness: TestWitness = [
+            vec![Fp::from(1u32), Fp::from(2u32)],
+            vec![Fp::from(2u32), Fp::from(3u32)],
+            vec![Fp::from(3u32), Fp::from(6u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let right_witness: TestWitness = [
+            vec![Fp::from(4u32), Fp::from(3u32)],
+            vec![Fp::from(5u32), Fp::from(6u32)],
+            vec![Fp::from(9u32), Fp::from(18u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let left_instance = instance_from_witness(&left_witness, &srs, domain);
+        let right_instance = instance_from_witness(&right_witness, &srs, domain);
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let fold = scheme.fold_over_extension(
+            (left_instance, left_witness),
+            (right_instance, right_witness),
+            &mut fq_sponge,
+        );
+        assert!(!fold.challenge.c1.is_zero());
+
+        // the commitments to the components match the components
+        for (instance, witness) in [
+            (&fold.instance.c0, &fold.witness.c0),
+            (&fold.instance.c1, &fold.witness.c1),
+        ] {
+            for (commitment, col) in instance.inner.commitments.iter().zip(witness.inner.iter()) {
+                let expected = srs.commit_evaluations_non_hiding(domain, col);
+                assert_eq!(&expected.elems[0], commitment);
+            }
+        }
+        for (commitment, error) in [
+            (&fold.error_commitment.c0, &fold.error.c0),
+            (&fold.error_commitment.c1, &fold.error.c1),
+        ] {
+            assert_eq!(
+                commitment,
+                &srs.commit_evaluations_non_hiding(domain, error)
+            );
+        }
+
+        // the relaxed relation
+        // u alpha_0 s_add (a + b - c) + alpha_1 s_mul (a b - u c) + E = 0
+        // holds over the extension
+        let ext = QuadraticExtension::new;
+        let alpha = |i| {
+            ext(
+                fold.instance.c0.inner.alphas.get(i).unwrap(),
+                fold.instance.c1.inner.alphas.get(i).unwrap(),
+            )
+        };
+        let u = fold.u;
+        for row in 0..domain.size() {
+            let col = |i: usize| {
+                ext(
+                    fold.witness.c0.inner[i].evals[row],
+                    fold.witness.c1.inner[i].evals[row],
+                )
+            };
+            let (a, b, c) = (col(0), col(1), col(2));
+            let s_add = QuadraticExtension::from_base(structure.s_add[row]);
+            let s_mul = QuadraticExtension::from_base(structure.s_mul[row]);
+            let error = ext(fold.error.c0.evals[row], fold.error.c1.evals[row]);
+            let res =
+                u * alpha(0) * s_add * (a + b - c) + alpha(1) * s_mul * (a * b - u * c) + error;
+            assert_eq!(res, QuadraticExtension::from_base(Fp::zero()));
+        }
+    }
+
+    #[test]
+    fn test_soundness_bits() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, _) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+        // |Fp| is close to 2^253.6 and the degree 2 constraints give a relation
+        // of degree 3 in the challenge
+        let bits = scheme.soundness_bits();
+        assert!(bits > 251.9 && bits < 252.1, "unexpected soundness {bits}");
+    }
+
+    #[test]
+    fn test_error_terms_are_cross_terms() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+
+        let (scheme, _) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+        assert!(scheme.check_error_terms());
+
+        // a degree 2 constraint mixing the cases handled by the error terms:
+        // squares, challenges, constants and the next row, along with a
+        // degree 3 one reduced by quadraticization
+        let cell = |col, row| {
+            Box::new(FoldingCompatibleExpr::Atom(
+                FoldingCompatibleExprInner::Cell(Variable { col, row }),
+            ))
+        };
+        let a = cell(Column::X(0), CurrOrNext::Curr);
+        let b = cell(Column::X(1), CurrOrNext::Curr);
+        let c_next = cell(Column::X(2), CurrOrNext::Next);
+        let s_mul = cell(Column::Selector(1), CurrOrNext::Curr);
+        let beta = Box::new(FoldingCompatibleExpr::Atom(
+            FoldingCompatibleExprInner::Challenge(TestChallenge::Beta),
+        ));
+        let three = Box::new(FoldingCompatibleExpr::Atom(
+            FoldingCompatibleExprInner::Constant(Fp::from(3u32)),
+        ));
+        // (a + b)^2 s_mul - beta c_next + 3
+        let square = FoldingCompatibleExpr::Square(Box::new(FoldingCompatibleExpr::Add(
+            a.clone(),
+            b.clone(),
+        )));
+        let square = FoldingCompatibleExpr::Mul(Box::new(square), s_mul);
+        let challenge = FoldingCompatibleExpr::Mul(beta, c_next.clone());
+        let degree_2 = FoldingCompatibleExpr::Add(
+            Box::new(FoldingCompatibleExpr::Sub(
+                Box::new(square),
+                Box::new(challenge),
+            )),
+            three,
+        );
+        // a b c_next - a
+        let product =
+            FoldingCompatibleExpr::Mul(Box::new(FoldingCompatibleExpr::Mul(a.clone(), b)), c_next);
+        let degree_3 = FoldingCompatibleExpr::Sub(Box::new(product), a);
+
+        let (scheme, _) = FoldingScheme::<TestFoldingConfig>::new(
+            vec![degree_2, degree_3],
+            &srs,
+            domain,
+            &structure,
+        );
+        assert!(scheme.check_error_terms());
+    }
+
+    #[test]
+    fn test_fold_chains_of_different_lengths() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        // An addition in the first row, a multiplication in the second one
+        let pair = |a: [u32; 2], b: [u32; 2]| {
+            let witness: TestWitness = [
+                vec![Fp::from(a[0]), Fp::from(a[1])],
+                vec![Fp::from(b[0]), Fp::from(b[1])],
+                vec![Fp::from(a[0] + b[0]), Fp::from(a[1] * b[1])],
+            ]
+            .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+            (instance_from_witness(&witness, &srs, domain), witness)
+        };
+
+        // 2-step chain
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (instance, witness, _) = scheme.fold_instance_witness_pair(
+            pair([1, 2], [2, 3]),
+            pair([4, 3], [5, 6]),
+            &mut fq_sponge,
+        );
+        let chain_a = (instance, witness);
+
+        // 3-step chain, with its own transcript
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (instance, witness, _) = scheme.fold_instance_witness_pair(
+            pair([7, 1], [2, 8]),
+            pair([3, 9], [4, 2]),
+            &mut fq_sponge,
+        );
+        let (instance, witness, _) = scheme.fold_instance_witness_pair(
+            (instance, witness),
+            pair([6, 5], [1, 7]),
+            &mut fq_sponge,
+        );
+        let chain_b = (instance, witness);
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (instance, witness, _) = scheme.fold_chains(chain_a, chain_b, &mut fq_sponge);
+        let checker = ExtendedProvider::new(structure, instance, witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_transcript_merkle_tree() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let pair = |a: [u32; 2], b: [u32; 2]| {
+            let witness: TestWitness = [
+                vec![Fp::from(a[0]), Fp::from(a[1])],
+                vec![Fp::from(b[0]), Fp::from(b[1])],
+                vec![Fp::from(a[0] + b[0]), Fp::from(a[1] * b[1])],
+            ]
+            .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+            (instance_from_witness(&witness, &srs, domain), witness)
+        };
+
+        let (instance, witness) = scheme.identity_accumulator();
+        let mut accumulator = FoldingAccumulator::new(instance, witness);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        for (a, b) in [
+            ([1, 2], [2, 3]),
+            ([4, 3], [5, 6]),
+            ([7, 1], [2, 8]),
+            ([3, 9], [4, 2]),
+        ] {
+            accumulator = accumulator.fold(&scheme, pair(a, b), &mut fq_sponge);
+        }
+        assert_eq!(accumulator.num_folds(), 4);
+        assert!(accumulator.transcript_path(4).is_none());
+
+        let root = accumulator.transcript_root();
+        for i in 0..4 {
+            let path = accumulator.transcript_path(i).unwrap();
+            assert_eq!(path.siblings.len(), 2);
+            assert!(path.verify(&root, accumulator.step_proof(i).unwrap()));
+        }
+
+        // the path of a step does not authenticate the proof of another step
+        let path = accumulator.transcript_path(1).unwrap();
+        assert!(!path.verify(&root, accumulator.step_proof(2).unwrap()));
+
+        // nor a tampered proof
+        let mut bad_proof = accumulator.step_proof(1).unwrap().clone();
+        bad_proof.cross_terms.swap(0, 1);
+        assert!(!path.verify(&root, &bad_proof));
+
+        let checker = ExtendedProvider::new(structure, accumulator.instance, accumulator.witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_verify_fold_from_digest() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let left_witness: TestWitness = [
+            vec![Fp::from(1u32), Fp::from(2u32)],
+            vec![Fp::from(2u32), Fp::from(3u32)],
+            vec![Fp::from(3u32), Fp::from(6u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let right_witness: TestWitness = [
+            vec![Fp::from(4u32), Fp::from(3u32)],
+            vec![Fp::from(5u32), Fp::from(6u32)],
+            vec![Fp::from(9u32), Fp::from(18u32)],
+        ]
+        .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+        let left_instance = instance_from_witness(&left_witness, &srs, domain);
+        let right_instance = instance_from_witness(&right_witness, &srs, domain);
+
+        // the digest binds both instances, their alphas and challenges being
+        // derived from their commitments
+        let mut transcript = new_transcript();
+        transcript.absorb_commitments(&left_instance.commitments);
+        transcript.absorb_commitments(&right_instance.commitments);
+        let digest = transcript.into_sponge().digest();
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (folded_instance, folded_witness, proof) = scheme.fold_from_digest(
+            (left_instance, left_witness),
+            (right_instance, right_witness),
+            digest,
+            &mut fq_sponge,
+        );
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        assert!(scheme.verify_fold_from_digest(&proof, digest, &mut fq_sponge));
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        assert!(!scheme.verify_fold_from_digest(&proof, digest + Fp::one(), &mut fq_sponge));
+
+        let checker = ExtendedProvider::new(structure, folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_accumulator_num_folds() {
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let [s_add, s_mul] = circuit();
+        let structure = TestStructure {
+            s_add,
+            s_mul,
+            constants: vec![],
+        };
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &structure);
+
+        let (instance, witness) = scheme.identity_accumulator();
+        let mut accumulator = FoldingAccumulator::new(instance, witness);
+        assert_eq!(accumulator.num_folds(), 0);
+        for (i, (a, b)) in [([1, 2], [2, 3]), ([4, 3], [5, 6]), ([7, 1], [2, 8])]
+            .into_iter()
+            .enumerate()
+        {
+            let witness: TestWitness = [
+                vec![Fp::from(a[0]), Fp::from(a[1])],
+                vec![Fp::from(b[0]), Fp::from(b[1])],
+                vec![Fp::from(a[0] + b[0]), Fp::from(a[1] * b[1])],
+            ]
+            .map(|evals| Evaluations::from_vec_and_domain(evals, domain));
+            let pair = (instance_from_witness(&witness, &srs, domain), witness);
+            accumulator = accumulator.fold(&scheme, pair, &mut fq_sponge);
+            assert_eq!(accumulator.num_folds(), i + 1);
+        }
+        assert_eq!(accumulator.num_folds(), 3);
+
+        let checker = ExtendedProvider::new(structure, accumulator.instance, accumulator.witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_projective_commitments_match_affine() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+        let mut rng = thread_rng();
+
+        let instances: Vec<TestInstance> = (0..8)
+            .map(|_| {
+                let witness: TestWitness = std::array::from_fn(|_| {
+                    let evals = (0..2).map(|_| Fp::rand(&mut rng)).collect();
+                    Evaluations::from_vec_and_domain(evals, domain)
+                });
+                instance_from_witness(&witness, &srs, domain)
+            })
+            .collect();
+        let challenges: Vec<Fp> = (1..instances.len()).map(|_| Fp::rand(&mut rng)).collect();
+
+        let affine = instances[1..]
+            .iter()
+            .zip(challenges.iter())
+            .fold(instances[0].clone(), |acc, (instance, challenge)| {
+                TestInstance::combine(acc, instance.clone(), *challenge)
+            });
+
+        let mut projective = ProjectiveCommitments::<Curve>::new(&instances[0].commitments);
+        for (instance, challenge) in instances[1..].iter().zip(challenges.iter()) {
+            projective.combine(&instance.commitments, *challenge);
+        }
+
+        assert_eq!(projective.into_affine(), affine.commitments.to_vec());
+    }
+
+    mod homomorphism {
+        use super::*;
+        use proptest::prelude::*;
+        use rand::SeedableRng as _;
+
+        prop_compose! {
+            fn arb_fp()(seed: [u8; 32]) -> Fp {
+                let rng = &mut rand::rngs::StdRng::from_seed(seed);
+                Fp::rand(rng)
+            }
+        }
+
+        prop_compose! {
+            fn arb_witness()(seed: [u8; 32]) -> TestWitness {
+                let rng = &mut rand::rngs::StdRng::from_seed(seed);
+                let domain = D::<Fp>::new(2).unwrap();
+                std::array::from_fn(|_| {
+                    let evals = (0..2).map(|_| Fp::rand(rng)).collect();
+                    Evaluations::from_vec_and_domain(evals, domain)
+                })
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn test_alphas_serialization_round_trip(
+                alpha in arb_fp(),
+                challenges in proptest::collection::vec(arb_fp(), 0..4),
+            ) {
+                let n = 5;
+                let fresh = |alphas: Alphas<Fp>| {
+                    // request the powers used by the constraints
+                    (0..n).for_each(|i| {
+                        alphas.get(i);
+                    });
+                    alphas
+                };
+                let alphas = challenges.into_iter().fold(fresh(Alphas::new(alpha)), |acc, r| {
+                    Alphas::combine(acc, fresh(Alphas::new(alpha + r)), r)
+                });
+
+                let decoded = Alphas::<Fp>::from_bytes(&alphas.to_bytes()).unwrap();
+                prop_assert_eq!(&decoded, &alphas);
+                for i in 0..n {
+                    prop_assert_eq!(decoded.get(i), alphas.get(i));
+                }
+
+                let alphas_vec = alphas.iter().collect::<Vec<_>>();
+                prop_assert_eq!(alphas.len(), n);
+                for (i, alpha) in alphas_vec.into_iter().enumerate() {
+                    prop_assert_eq!(Some(alpha), alphas.get(i));
+                }
+
+                #[cfg(feature = "serde")]
+                {
+                    let json = serde_json::to_string(&alphas).unwrap();
+                    let decoded: Alphas<Fp> = serde_json::from_str(&json).unwrap();
+                    prop_assert_eq!(&decoded, &alphas);
+                    prop_assert_eq!(decoded.powers(), alphas.clone().powers());
+                }
+            }
+
+            // Folding relies on commit(W1 + r W2) = commit(W1) + r commit(W2),
+            // both for the witness and for the instance built from it.
+            #[test]
+            fn test_combine_commutes_with_commitment(
+                w1 in arb_witness(),
+                w2 in arb_witness(),
+                r in arb_fp(),
+            ) {
+                let domain = D::<Fp>::new(2).unwrap();
+                let mut srs = poly_commitment::srs::SRS::<Curve>::create(2);
+                srs.add_lagrange_basis(domain);
+
+                let i1 = instance_from_witness(&w1, &srs, domain);
+                let i2 = instance_from_witness(&w2, &srs, domain);
+
+                let combined_witness = TestWitness::combine(w1.clone(), w2.clone(), r);
+                let combined_instance = TestInstance::combine(i1, i2, r);
+
+                for i in 0..3 {
+                    let c1 = srs.commit_evaluations_non_hiding(domain, &w1[i]);
+                    let c2 = srs.commit_evaluations_non_hiding(domain, &w2[i]);
+                    let expected = &c1 + &c2.scale(r);
+                    let commitment =
+                        srs.commit_evaluations_non_hiding(domain, &combined_witness[i]);
+                    prop_assert_eq!(&commitment, &expected);
+                    prop_assert_eq!(commitment.elems[0], combined_instance.commitments[i]);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,337 @@
+//! This file shows how the [kind](FoldingColumnTrait::kind) of the columns
+//! decides how they are folded, with a single constraint
+//! `s(X) (a(X) k(X) - b(X)) = 0` mixing the three kinds of columns:
+//! - `a` and `b` are witness columns, committed to and folded.
+//! - `k` is a fixed column, part of the structure of the circuit. It is shared
+//! by the instances and is not folded, so it does not count in the degree of
+//! the constraint, which would be 3 otherwise. The environment gives it to
+//! folding through [FoldingEnv::fixed_col] rather than [FoldingEnv::col].
+//! - `s` is a dynamic selector given as a column, committed to and folded like
+//! the witness columns, without a separate selector type.
+use crate::{
+    checker::{Checker, ExtendedProvider, Provider},
+    error_term::Side,
+    examples::{Curve, Fp},
+    expressions::{cell, ColumnKind, FoldingColumnTrait},
+    Alphas, FoldingCompatibleExpr, FoldingConfig, FoldingEnv, Instance, Witness,
+};
+use ark_ff::{UniformRand, Zero};
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
+use kimchi::circuits::gate::CurrOrNext;
+use poly_commitment::srs::SRS;
+use rand::Rng;
+use std::ops::Index;
+
+type Evals = Evaluations<Fp, Radix2EvaluationDomain<Fp>>;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum TestColumn {
+    A,
+    B,
+    K,
+    S,
+}
+
+impl FoldingColumnTrait for TestColumn {
+    fn kind(&self) -> ColumnKind {
+        match self {
+            TestColumn::A | TestColumn::B => ColumnKind::Witness,
+            TestColumn::K => ColumnKind::Fixed,
+            TestColumn::S => ColumnKind::DynamicSelector,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum TestChallenge {}
+
+/// The instance is the commitments to the folded columns `a`, `b` and `s`
+#[derive(Debug, Clone)]
+pub struct TestInstance {
+    commitments: [Curve; 3],
+    alphas: Alphas<Fp>,
+}
+
+impl Instance<Curve> for TestInstance {
+    fn combine(a: Self, b: Self, challenge: Fp) -> Self {
+        TestInstance {
+            commitments: crate::combine_slices(&a.commitments, &b.commitments, challenge)
+                .try_into()
+                .unwrap(),
+            alphas: Alphas::combine(a.alphas, b.alphas, challenge),
+        }
+    }
+
+    fn alphas(&self) -> &Alphas<Fp> {
+        &self.alphas
+    }
+}
+
+/// The folded columns `a`, `b` and `s`, along with a copy of the fixed column
+/// `k` for the checker
+#[derive(Debug, Clone)]
+pub struct TestWitness {
+    cols: [Evals; 3],
+    fixed: Evals,
+}
+
+impl Witness<Curve> for TestWitness {
+    fn combine(a: Self, b: Self, challenge: Fp) -> Self {
+        // the fixed column is the same on both sides
+        assert_eq!(a.fixed, b.fixed);
+        TestWitness {
+            cols: crate::combine_slices(&a.cols, &b.cols, challenge)
+                .try_into()
+                .unwrap(),
+            fixed: a.fixed,
+        }
+    }
+
+    fn rows(&self) -> usize {
+        self.fixed.evals.len()
+    }
+}
+
+pub struct TestFoldingEnv {
+    instances: [TestInstance; 2],
+    // Corresponds to the omega evaluations, for both sides
+    curr_witnesses: [TestWitness; 2],
+    // Corresponds to the zeta*omega evaluations, for both sides
+    next_witnesses: [TestWitness; 2],
+    // The fixed column, at omega and zeta*omega
+    fixed: [Vec<Fp>; 2],
+}
+
+impl FoldingEnv<Fp, TestInstance, TestWitness, TestColumn, TestChallenge, ()> for TestFoldingEnv {
+    type Structure = Evals;
+
+    fn new(
+        structure: &Self::Structure,
+        instances: [&TestInstance; 2],
+        witnesses: [&TestWitness; 2],
+    ) -> Self {
+        let curr_witnesses = [witnesses[0].clone(), witnesses[1].clone()];
+        let mut next_witnesses = curr_witnesses.clone();
+        for side in next_witnesses.iter_mut() {
+            for col in side.cols.iter_mut() {
+                col.evals.rotate_left(1);
+            }
+        }
+        let mut fixed_next = structure.evals.clone();
+        fixed_next.rotate_left(1);
+        TestFoldingEnv {
+            instances: [instances[0].clone(), instances[1].clone()],
+            curr_witnesses,
+            next_witnesses,
+            fixed: [structure.evals.clone(), fixed_next],
+        }
+    }
+
+    fn domain_size(&self) -> usize {
+        self.curr_witnesses[0].rows()
+    }
+
+    fn col(&self, col: TestColumn, curr_or_next: CurrOrNext, side: Side) -> &Vec<Fp> {
+        let wit = match curr_or_next {
+            CurrOrNext::Curr => &self.curr_witnesses[side as usize],
+            CurrOrNext::Next => &self.next_witnesses[side as usize],
+        };
+        &wit[col].evals
+    }
+
+    fn fixed_col(&self, col: TestColumn, curr_or_next: CurrOrNext) -> &Vec<Fp> {
+        assert_eq!(col, TestColumn::K);
+        // the fixed column comes from the structure, whatever the side
+        &self.fixed[curr_or_next as usize]
+    }
+
+    fn challenge(&self, challenge: TestChallenge, _side: Side) -> Fp {
+        match challenge {}
+    }
+
+    fn alpha(&self, i: usize, side: Side) -> Fp {
+        let instance = &self.instances[side as usize];
+        instance.alphas.get(i).unwrap()
+    }
+
+    fn selector(&self, _s: &(), _side: Side) -> &Vec<Fp> {
+        unreachable!("the dynamic selector is a column in this example")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TestFoldingConfig;
+
+impl FoldingConfig for TestFoldingConfig {
+    type Structure = Evals;
+    type Column = TestColumn;
+    type Selector = ();
+    type Challenge = TestChallenge;
+    type Curve = Curve;
+    type Srs = SRS<Curve>;
+    type Instance = TestInstance;
+    type Witness = TestWitness;
+    type Env = TestFoldingEnv;
+
+    fn identity_instance(
+        structure: &Self::Structure,
+        _srs: &Self::Srs,
+        domain: Radix2EvaluationDomain<Fp>,
+        alphas: Alphas<Fp>,
+    ) -> (TestInstance, TestWitness) {
+        let instance = TestInstance {
+            commitments: [Curve::zero(); 3],
+            alphas,
+        };
+        let zero_col = || Evaluations::from_vec_and_domain(vec![Fp::zero(); domain.size()], domain);
+        let witness = TestWitness {
+            cols: std::array::from_fn(|_| zero_col()),
+            fixed: structure.clone(),
+        };
+        (instance, witness)
+    }
+}
+
+fn constraints() -> Vec<FoldingCompatibleExpr<TestFoldingConfig>> {
+    let [a, b, k, s] = [TestColumn::A, TestColumn::B, TestColumn::K, TestColumn::S].map(cell);
+    vec![s * (a * k - b)]
+}
+
+impl Index<TestChallenge> for TestInstance {
+    type Output = Fp;
+
+    fn index(&self, index: TestChallenge) -> &Self::Output {
+        match index {}
+    }
+}
+
+impl Index<TestColumn> for TestWitness {
+    type Output = Evals;
+
+    fn index(&self, index: TestColumn) -> &Self::Output {
+        match index {
+            TestColumn::A => &self.cols[0],
+            TestColumn::B => &self.cols[1],
+            TestColumn::S => &self.cols[2],
+            TestColumn::K => &self.fixed,
+        }
+    }
+}
+
+impl Index<()> for TestWitness {
+    type Output = Evals;
+
+    fn index(&self, _index: ()) -> &Self::Output {
+        unreachable!("the dynamic selector is a column in this example")
+    }
+}
+
+impl Checker<TestFoldingConfig> for Provider<TestFoldingConfig> {}
+impl Checker<TestFoldingConfig> for ExtendedProvider<TestFoldingConfig> {}
+
+/// Creates a trace with random values of `a` and `b = a k` in the rows where
+/// `s` is set, along with its instance
+fn random_trace<RNG: Rng>(
+    rng: &mut RNG,
+    srs: &<TestFoldingConfig as FoldingConfig>::Srs,
+    fixed: &Evals,
+) -> (TestInstance, TestWitness) {
+    let domain = fixed.domain();
+    let s: Vec<Fp> = (0..domain.size())
+        .map(|_| Fp::from(rng.gen::<bool>()))
+        .collect();
+    let a: Vec<Fp> = (0..domain.size()).map(|_| Fp::rand(rng)).collect();
+    let b = a
+        .iter()
+        .zip(&fixed.evals)
+        .zip(&s)
+        .map(|((a, k), s)| if s.is_zero() { Fp::rand(rng) } else { *a * k })
+        .collect();
+    let witness = TestWitness {
+        cols: [a, b, s].map(|evals| Evaluations::from_vec_and_domain(evals, domain)),
+        fixed: fixed.clone(),
+    };
+    let instance = TestInstance {
+        commitments: crate::commit_all(srs, domain, &witness.cols)
+            .try_into()
+            .unwrap(),
+        alphas: Alphas::new(Fp::rand(rng)),
+    };
+    (instance, witness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{examples::BaseSponge, expressions::Degree, FoldingScheme};
+    use kimchi::curve::KimchiCurve;
+    use mina_poseidon::FqSponge;
+
+    #[test]
+    fn test_column_kinds() {
+        use TestColumn::*;
+        assert!(A.is_witness() && B.is_witness());
+        assert!(!K.is_witness() && !S.is_witness());
+        assert_eq!(K.degree(), Degree::Zero);
+        assert_eq!(S.degree(), Degree::One);
+    }
+
+    #[test]
+    fn test_folding_column_kinds() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let domain = Radix2EvaluationDomain::<Fp>::new(4).unwrap();
+        let srs = crate::make_srs::<Curve>(domain);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let fixed = Evaluations::from_vec_and_domain(
+            (0..domain.size()).map(|_| Fp::rand(&mut rng)).collect(),
+            domain,
+        );
+
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &fixed);
+
+        let left = random_trace(&mut rng, &srs, &fixed);
+        let right = random_trace(&mut rng, &srs, &fixed);
+        for (instance, witness) in [&left, &right] {
+            let checker = Provider::<TestFoldingConfig>::new(instance.clone(), witness.clone());
+            constraints().iter().for_each(|c| checker.check(c));
+        }
+
+        let (folded_instance, folded_witness, _) =
+            scheme.fold_instance_witness_pair(left, right, &mut fq_sponge);
+        // only the witness columns and the selector are folded
+        assert_eq!(folded_witness.inner().inner.fixed, fixed);
+        let checker = ExtendedProvider::new(folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_accumulate_with_fixed_column() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let domain = Radix2EvaluationDomain::<Fp>::new(4).unwrap();
+        let srs = crate::make_srs::<Curve>(domain);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let fixed = Evaluations::from_vec_and_domain(
+            (0..domain.size()).map(|_| Fp::rand(&mut rng)).collect(),
+            domain,
+        );
+
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &fixed);
+
+        // fold the accumulator with fresh traces, the fixed column being
+        // read from the structure at each step
+        let left = random_trace(&mut rng, &srs, &fixed);
+        let right = random_trace(&mut rng, &srs, &fixed);
+        let (mut instance, mut witness, _) =
+            scheme.fold_instance_witness_pair(left, right, &mut fq_sponge);
+        for _ in 0..2 {
+            let trace = random_trace(&mut rng, &srs, &fixed);
+            (instance, witness, _) =
+                scheme.fold_instance_witness_pair((instance, witness), trace, &mut fq_sponge);
+        }
+        assert_eq!(witness.inner().inner.fixed, fixed);
+        let checker = ExtendedProvider::new(instance, witness);
+        checker.check(&final_constraint);
+    }
+}
@@ -1,15 +1,19 @@
 use crate::{
     checker::{Checker, ExtendedProvider},
+    combine_slices,
     error_term::Side,
     examples::{Curve, Fp},
-    expressions::{FoldingColumnTrait, FoldingCompatibleExprInner},
+    expressions::{cell, ColumnKind, FoldingColumnTrait, FoldingCompatibleExprInner},
+    transcript::DeterministicTranscript,
     Alphas, FoldingCompatibleExpr, FoldingConfig, FoldingEnv, Instance, Witness,
 };
-use ark_ec::{AffineCurve, ProjectiveCurve};
-use ark_ff::UniformRand;
-use ark_poly::{Evaluations, Radix2EvaluationDomain};
+use ark_ff::{UniformRand, Zero};
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
 use itertools::Itertools;
-use kimchi::circuits::{expr::Variable, gate::CurrOrNext};
+use kimchi::circuits::{
+    expr::{ChallengeTerm, Variable},
+    gate::CurrOrNext,
+};
 use poly_commitment::{srs::SRS, SRS as _};
 use rand::thread_rng;
 use std::{collections::BTreeMap, ops::Index};
@@ -32,15 +36,15 @@ pub enum DynamicSelector {
 
 impl FoldingColumnTrait for TestColumn {
     //in this case we have only witness, the other example shows non-witness columns
-    fn is_witness(&self) -> bool {
+    fn kind(&self) -> ColumnKind {
         match self {
-            TestColumn::A | TestColumn::B | TestColumn::C => true,
+            TestColumn::A | TestColumn::B | TestColumn::C => ColumnKind::Witness,
         }
     }
 }
 
 /// The instance is the commitments to the polynomials and the challenges
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TestInstance {
     // 3 from the normal witness + 2 from the dynamic selectors
     commitments: [Curve; 5],
@@ -53,10 +57,12 @@ pub struct TestInstance {
 impl Instance<Curve> for TestInstance {
     fn combine(a: Self, b: Self, challenge: Fp) -> Self {
         TestInstance {
-            commitments: std::array::from_fn(|i| {
-                a.commitments[i] + b.commitments[i].mul(challenge).into_affine()
-            }),
-            challenges: std::array::from_fn(|i| a.challenges[i] + challenge * b.challenges[i]),
+            commitments: combine_slices(&a.commitments, &b.commitments, challenge)
+                .try_into()
+                .unwrap(),
+            challenges: combine_slices(&a.challenges, &b.challenges, challenge)
+                .try_into()
+                .unwrap(),
             alphas: Alphas::combine(a.alphas, b.alphas, challenge),
         }
     }
@@ -73,6 +79,12 @@ impl Instance<Curve> for TestInstance {
 pub type TestWitness = [Evaluations<Fp, Radix2EvaluationDomain<Fp>>; 5];
 
 impl Witness<Curve> for TestWitness {
+    #[cfg(feature = "rayon")]
+    fn combine(a: Self, b: Self, challenge: Fp) -> Self {
+        crate::combine_par(&a, &b, challenge).try_into().unwrap()
+    }
+
+    #[cfg(not(feature = "rayon"))]
     fn combine(mut a: Self, b: Self, challenge: Fp) -> Self {
         for (a, b) in a.iter_mut().zip(b) {
             for (a, b) in a.evals.iter_mut().zip(b.evals) {
@@ -113,12 +125,13 @@ impl FoldingEnv<Fp, TestInstance, TestWitness, TestColumn, TestChallenge, Dynami
     ) -> Self {
         // here it is mostly storing the pairs into self, and also computing other things we may need
         // later like the shifted versions, note there are more efficient ways of handling the rotated
-        // witnesses, which are just for example as no contraint uses them anyway
+        // witnesses, which are used by the constraint of next_row_constraint()
         let curr_witnesses = [witnesses[0].clone(), witnesses[1].clone()];
         let mut next_witnesses = curr_witnesses.clone();
         for side in next_witnesses.iter_mut() {
             for col in side.iter_mut() {
-                //TODO: check this, while not relevant for this example I think it should be right rotation
+                // the next row of the row i is the row i + 1, wrapping around the
+                // domain, i.e. next[i] = curr[(i + 1) % n], hence the left rotation
                 col.evals.rotate_left(1);
             }
         }
@@ -130,9 +143,7 @@ impl FoldingEnv<Fp, TestInstance, TestWitness, TestColumn, TestChallenge, Dynami
     }
 
     fn domain_size(&self) -> usize {
-        // this works in the example but is not the best way as the envionment
-        // could get circuits of any size
-        2
+        self.curr_witnesses[0].rows()
     }
 
     // provide access to columns, here side refers to one of the two pairs you
@@ -182,21 +193,10 @@ impl FoldingEnv<Fp, TestInstance, TestWitness, TestColumn, TestChallenge, Dynami
 // this creates 2 single-constraint gates, each with a selector,
 // an addition gate, and a subtraction gate
 fn constraints() -> BTreeMap<DynamicSelector, Vec<FoldingCompatibleExpr<TestFoldingConfig>>> {
-    let get_col = |col| {
-        FoldingCompatibleExpr::Atom(FoldingCompatibleExprInner::Cell(Variable {
-            col,
-            row: CurrOrNext::Curr,
-        }))
-    };
-    let a = Box::new(get_col(TestColumn::A));
-    let b = Box::new(get_col(TestColumn::B));
-    let c = Box::new(get_col(TestColumn::C));
+    let [a, b, c] = [TestColumn::A, TestColumn::B, TestColumn::C].map(cell);
 
-    let add = FoldingCompatibleExpr::Add(a.clone(), b.clone());
-    let add = FoldingCompatibleExpr::Sub(add.into(), c.clone());
-
-    let sub = FoldingCompatibleExpr::Sub(a.clone(), b.clone());
-    let sub = FoldingCompatibleExpr::Sub(sub.into(), c.clone());
+    let add = &a + &b - c.clone();
+    let sub = a - b - c;
 
     [
         (DynamicSelector::SelecAdd, vec![add]),
@@ -206,18 +206,40 @@ fn constraints() -> BTreeMap<DynamicSelector, Vec<FoldingCompatibleExpr<TestFold
     .collect()
 }
 
+// a constraint chaining the rows, the output of a row being the first input
+// of the next one, with the first row following the last one: A(next) - C = 0
+fn next_row_constraint() -> FoldingCompatibleExpr<TestFoldingConfig> {
+    let get_col = |col, row| {
+        FoldingCompatibleExpr::Atom(FoldingCompatibleExprInner::Cell(Variable { col, row }))
+    };
+    let a_next = Box::new(get_col(TestColumn::A, CurrOrNext::Next));
+    let c = Box::new(get_col(TestColumn::C, CurrOrNext::Curr));
+    FoldingCompatibleExpr::Sub(a_next, c)
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct TestFoldingConfig;
 
 #[allow(dead_code)]
 // Does not contain alpha because it should be added to the expressions by folding
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub enum TestChallenge {
     Beta,
     Gamma,
     JointCombiner,
 }
 
+impl From<ChallengeTerm> for TestChallenge {
+    fn from(chal: ChallengeTerm) -> Self {
+        match chal {
+            ChallengeTerm::Beta => TestChallenge::Beta,
+            ChallengeTerm::Gamma => TestChallenge::Gamma,
+            ChallengeTerm::JointCombiner => TestChallenge::JointCombiner,
+            ChallengeTerm::Alpha => panic!("Alpha not allowed in folding expressions"),
+        }
+    }
+}
+
 impl FoldingConfig for TestFoldingConfig {
     type Structure = ();
     type Column = TestColumn;
@@ -228,8 +250,26 @@ impl FoldingConfig for TestFoldingConfig {
     type Instance = TestInstance;
     type Witness = TestWitness;
     type Env = TestFoldingEnv;
+
+    fn identity_instance(
+        _structure: &Self::Structure,
+        _srs: &Self::Srs,
+        domain: Radix2EvaluationDomain<Fp>,
+        alphas: Alphas<Fp>,
+    ) -> (TestInstance, TestWitness) {
+        let instance = TestInstance {
+            commitments: [Curve::zero(); 5],
+            challenges: [Fp::zero(); 3],
+            alphas,
+        };
+        let zero_col = || Evaluations::from_vec_and_domain(vec![Fp::zero(); domain.size()], domain);
+        (instance, std::array::from_fn(|_| zero_col()))
+    }
 }
 
+/// The seed of the transcript giving the challenges of the instances
+const INSTANCE_SEED: u64 = 0;
+
 //creates an instance from its witness
 fn instance_from_witness(
     witness: &TestWitness,
@@ -244,11 +284,10 @@ fn instance_from_witness(
     let commitments: [_; 5] = commitments.try_into().unwrap();
 
     // here we should absorb the commitments and similar things to later compute challenges
-    // but for this example I just use random values
-    let mut rng = thread_rng();
-    let mut challenge = || Fp::rand(&mut rng);
-    let challenges = [(); 3].map(|_| challenge());
-    let alpha = challenge();
+    // but for this example we use a seeded transcript, so that runs are reproducible
+    let mut transcript = DeterministicTranscript::<Curve>::new(INSTANCE_SEED);
+    let challenges = [(); 3].map(|_| transcript.challenge());
+    let alpha = transcript.challenge();
     let alphas = Alphas::new(alpha);
     TestInstance {
         commitments,
@@ -299,13 +338,213 @@ mod tests {
     use super::*;
     // Trick to print debug message while testing, as we in the test config env
     use crate::{
-        checker::ExtendedProvider, decomposable_folding::DecomposableFoldingScheme,
+        checker::{
+            check_selector_wellformedness, is_wrapping, max_magnitudes, ConstraintViolation,
+            ExtendedProvider, SelectorError,
+        },
+        combine_slices_deduplicated,
+        decomposable_folding::DecomposableFoldingScheme,
         examples::BaseSponge,
+        expressions::ExprConversionError,
+        AlphasError, CrossTerms, ExpExtension, RelaxedInstance, WitnessError,
     };
+    use ark_ec::{AffineCurve, ProjectiveCurve};
+    use ark_ff::{One, PrimeField};
     use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain as D};
-    use kimchi::curve::KimchiCurve;
+    use kimchi::{
+        circuits::expr::{ConstantExpr, Expr, ExprInner, Operations, RowOffset},
+        curve::KimchiCurve,
+    };
     use mina_poseidon::FqSponge;
-    use std::println as debug;
+    use poly_commitment::PolyComm;
+    use std::{collections::BTreeSet, println as debug, str::FromStr};
+
+    #[test]
+    fn test_expression_operators() {
+        let get_col = |col| {
+            Box::new(FoldingCompatibleExpr::Atom(
+                FoldingCompatibleExprInner::Cell(Variable {
+                    col,
+                    row: CurrOrNext::Curr,
+                }),
+            ))
+        };
+        let [a, b, c] = [TestColumn::A, TestColumn::B, TestColumn::C].map(get_col);
+        let add = FoldingCompatibleExpr::Add(a.clone(), b.clone());
+        let add = FoldingCompatibleExpr::Sub(add.into(), c.clone());
+        let sub = FoldingCompatibleExpr::Sub(a.clone(), b.clone());
+        let sub = FoldingCompatibleExpr::Sub(sub.into(), c.clone());
+        let mul = FoldingCompatibleExpr::Mul(a, b);
+        let mul = FoldingCompatibleExpr::Sub(mul.into(), c);
+
+        let constraints = constraints();
+        assert_eq!(constraints[&DynamicSelector::SelecAdd], vec![add]);
+        assert_eq!(constraints[&DynamicSelector::SelecSub], vec![sub]);
+
+        let [a, b, c] =
+            [TestColumn::A, TestColumn::B, TestColumn::C].map(cell::<TestFoldingConfig>);
+        // owned and borrowed operands build the same tree
+        assert_eq!(&a * &b - c.clone(), mul);
+        assert_eq!(a * b - c, mul);
+    }
+
+    #[test]
+    fn test_dedup_expression() {
+        let [a, b] = [TestColumn::A, TestColumn::B].map(cell::<TestFoldingConfig>);
+        let product = &a * &b;
+        let sum = &product + &product;
+        let exp = &sum * &sum - (a.clone() - a);
+        let deduped = exp.clone().dedup();
+        assert_eq!(exp.node_count(), 19);
+        // (2 (a * b))^2 - 0
+        assert_eq!(deduped.node_count(), 7);
+
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+        let (scheme, _) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+        let witness = int_to_witness(add_witness([1, 2], [3, 4]), domain);
+        let instance = instance_from_witness(&witness, &srs, domain);
+        let (instance, witness) = scheme.relax(instance, witness);
+        let provider = ExtendedProvider::new(instance, witness);
+        assert_eq!(provider.check_rec(exp), provider.check_rec(deduped));
+    }
+
+    #[test]
+    fn test_combine_mismatched_alphas() {
+        let alphas = |n| {
+            let alphas = Alphas::new(Fp::from(3u64));
+            (0..n).for_each(|i| {
+                alphas.get(i);
+            });
+            alphas
+        };
+        let challenge = Fp::from(5u64);
+        assert_eq!(
+            Alphas::try_combine(alphas(3), alphas(4), challenge),
+            Err(AlphasError::LengthMismatch { left: 3, right: 4 })
+        );
+        let combined = Alphas::try_combine(alphas(3), alphas(3), challenge).unwrap();
+        assert_eq!(combined, Alphas::combine(alphas(3), alphas(3), challenge));
+        assert_eq!(combined.get(2), Some(Fp::from(6u64 * 9)));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot combine 2 alphas with 3 alphas")]
+    fn test_combine_mismatched_alphas_panics() {
+        let alphas = Alphas::new(Fp::from(3u64));
+        alphas.get(1);
+        let other = Alphas::new(Fp::from(3u64));
+        other.get(2);
+        Alphas::combine(alphas, other, Fp::one());
+    }
+
+    fn constant(c: u64) -> FoldingCompatibleExpr<TestFoldingConfig> {
+        FoldingCompatibleExpr::Atom(FoldingCompatibleExprInner::Constant(Fp::from(c)))
+    }
+
+    #[test]
+    fn test_simplify_expression() {
+        use FoldingCompatibleExpr::{Double, Pow, Square};
+        let [a, b, c] =
+            [TestColumn::A, TestColumn::B, TestColumn::C].map(cell::<TestFoldingConfig>);
+
+        // arithmetic between constants
+        assert_eq!((constant(2) + constant(3)).simplify(), constant(5));
+        assert_eq!(
+            (constant(2) - constant(3)).simplify(),
+            FoldingCompatibleExpr::Atom(FoldingCompatibleExprInner::Constant(-Fp::one()))
+        );
+        assert_eq!((constant(2) * constant(3)).simplify(), constant(6));
+        assert_eq!(Double(Box::new(constant(2))).simplify(), constant(4));
+        assert_eq!(Square(Box::new(constant(3))).simplify(), constant(9));
+        assert_eq!(Pow(Box::new(constant(2)), 3).simplify(), constant(8));
+
+        // neutral operands
+        assert_eq!((a.clone() + constant(0)).simplify(), a);
+        assert_eq!((constant(0) + a.clone()).simplify(), a);
+        assert_eq!((a.clone() * constant(1)).simplify(), a);
+        assert_eq!((constant(1) * a.clone()).simplify(), a);
+        assert_eq!((a.clone() - constant(0)).simplify(), a);
+        let negation = constant(0) - a.clone();
+        assert_eq!(negation.clone().simplify(), negation);
+
+        // the rules apply bottom-up
+        let exp = (a.clone() + (constant(2) - constant(2))) * (constant(3) - constant(2));
+        assert_eq!(exp.simplify(), a);
+
+        // minimal expressions are left unchanged
+        let minimal = &a * &b - c;
+        assert_eq!(minimal.clone().simplify(), minimal);
+        for (_, exps) in constraints() {
+            for exp in exps {
+                assert_eq!(exp.clone().simplify(), exp);
+            }
+        }
+    }
+
+    mod simplification {
+        use super::*;
+        use proptest::prelude::*;
+        use rand::SeedableRng as _;
+
+        fn arb_expr() -> impl Strategy<Value = FoldingCompatibleExpr<TestFoldingConfig>> {
+            let leaf = prop_oneof![
+                prop::sample::select(vec![TestColumn::A, TestColumn::B, TestColumn::C])
+                    .prop_map(cell::<TestFoldingConfig>),
+                (0u64..3).prop_map(constant),
+            ];
+            leaf.prop_recursive(4, 32, 2, |inner| {
+                prop_oneof![
+                    (inner.clone(), inner.clone()).prop_map(|(a, b)| a + b),
+                    (inner.clone(), inner.clone()).prop_map(|(a, b)| a - b),
+                    (inner.clone(), inner.clone()).prop_map(|(a, b)| a * b),
+                    inner
+                        .clone()
+                        .prop_map(|a| FoldingCompatibleExpr::Double(Box::new(a))),
+                    inner
+                        .clone()
+                        .prop_map(|a| FoldingCompatibleExpr::Square(Box::new(a))),
+                    (inner, 0u64..4).prop_map(|(a, p)| FoldingCompatibleExpr::Pow(Box::new(a), p)),
+                ]
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn test_simplify_preserves_evaluation(exp in arb_expr(), seed: [u8; 32]) {
+                let rng = &mut rand::rngs::StdRng::from_seed(seed);
+                let domain = D::<Fp>::new(2).unwrap();
+                let mut srs = SRS::<Curve>::create(2);
+                srs.add_lagrange_basis(domain);
+                let (scheme, _) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+                    constraints(),
+                    vec![],
+                    &srs,
+                    domain,
+                    &(),
+                );
+                let witness: TestWitness = std::array::from_fn(|_| {
+                    let evals = (0..2).map(|_| Fp::rand(rng)).collect();
+                    Evaluations::from_vec_and_domain(evals, domain)
+                });
+                let instance = instance_from_witness(&witness, &srs, domain);
+                let (instance, witness) = scheme.relax(instance, witness);
+                let provider = ExtendedProvider::new(instance, witness);
+
+                let simplified = exp.clone().simplify();
+                prop_assert!(simplified.node_count() <= exp.node_count());
+                prop_assert_eq!(&simplified.clone().simplify(), &simplified);
+                prop_assert_eq!(provider.check_rec(simplified), provider.check_rec(exp));
+            }
+        }
+    }
 
     // two functions to create the entire witness from just the a and b columns
     fn add_witness(a: [u32; 2], b: [u32; 2]) -> [[u32; 2]; 5] {
@@ -324,6 +563,98 @@ mod tests {
         x.map(|row| Evaluations::from_vec_and_domain(row.map(Fp::from).to_vec(), domain))
     }
 
+    #[test]
+    fn test_referenced_challenges() {
+        // The add and sub gates only use witness columns
+        for (_, exps) in constraints() {
+            for exp in exps {
+                assert!(exp.referenced_challenges().is_empty());
+            }
+        }
+
+        let beta = Box::new(FoldingCompatibleExpr::<TestFoldingConfig>::Atom(
+            FoldingCompatibleExprInner::Challenge(TestChallenge::Beta),
+        ));
+        let a = Box::new(FoldingCompatibleExpr::Atom(
+            FoldingCompatibleExprInner::Cell(Variable {
+                col: TestColumn::A,
+                row: CurrOrNext::Curr,
+            }),
+        ));
+        // (β - a) * β^2
+        let exp = FoldingCompatibleExpr::Mul(
+            Box::new(FoldingCompatibleExpr::Sub(beta.clone(), a)),
+            Box::new(FoldingCompatibleExpr::Square(beta)),
+        );
+        let challenges = exp.referenced_challenges();
+        assert_eq!(challenges.len(), 1);
+        assert!(challenges.contains(&TestChallenge::Beta));
+    }
+
+    #[test]
+    fn test_pretty_print_constraints() {
+        let constraints = constraints();
+        let add = &constraints[&DynamicSelector::SelecAdd][0];
+        let sub = &constraints[&DynamicSelector::SelecSub][0];
+        assert_eq!(add.to_string(), "A + B - C");
+        assert_eq!(sub.to_string(), "A - B - C");
+
+        let name = |col| match col {
+            TestColumn::A => "x".to_string(),
+            TestColumn::B => "y".to_string(),
+            TestColumn::C => "z".to_string(),
+        };
+        assert_eq!(add.pretty(&name), "x + y - z");
+
+        // operands binding less than their operator are parenthesized
+        let selector = FoldingCompatibleExpr::Atom(FoldingCompatibleExprInner::Extensions(
+            ExpExtension::Selector(DynamicSelector::SelecSub),
+        ));
+        let alpha = FoldingCompatibleExpr::Atom(FoldingCompatibleExprInner::Extensions(
+            ExpExtension::Alpha(1),
+        ));
+        let exp = FoldingCompatibleExpr::Mul(
+            Box::new(FoldingCompatibleExpr::Mul(
+                Box::new(selector),
+                Box::new(sub.clone()),
+            )),
+            Box::new(alpha),
+        );
+        assert_eq!(exp.pretty(&name), "Selec(SelecSub) * (x - y - z) * α_1");
+        let exp = FoldingCompatibleExpr::Sub(
+            Box::new(FoldingCompatibleExpr::Square(Box::new(add.clone()))),
+            Box::new(sub.clone()),
+        );
+        assert_eq!(exp.pretty(&name), "(x + y - z)^2 - (x - y - z)");
+    }
+
+    #[test]
+    fn test_final_constraint_summary() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let (scheme, _) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+        // the add and sub constraints are linear in the columns, the selectors
+        // not being counted
+        assert_eq!(scheme.final_constraint_degree(), 1);
+        let columns = scheme.columns_used();
+        assert_eq!(columns.len(), 3);
+        for col in [TestColumn::A, TestColumn::B, TestColumn::C] {
+            assert!(columns.contains(&col));
+        }
+        // one alpha per constraint, and per selector constraint: a boolean
+        // constraint for each of the 2 selectors and their sum being one
+        let constraints_count: usize = constraints().values().map(Vec::len).sum();
+        assert_eq!(scheme.num_alphas(), constraints_count + 3);
+    }
+
     // in this test we will create 2 add witnesses, fold them together, create 2
     // sub witnesses,
     // fold them together, and then further fold the 2 resulting pairs into one
@@ -381,7 +712,7 @@ mod tests {
                 Some(DynamicSelector::SelecAdd),
                 &mut fq_sponge,
             );
-            let (folded_instance, folded_witness, [_t0, _t1]) = folded;
+            let (folded_instance, folded_witness, _) = folded;
             let checker = ExtendedProvider::new(folded_instance, folded_witness);
             debug!("exp: \n {:#?}", final_constraint.to_string());
             checker.check(&final_constraint);
@@ -409,7 +740,7 @@ mod tests {
                 Some(DynamicSelector::SelecSub),
                 &mut fq_sponge,
             );
-            let (folded_instance, folded_witness, [_t0, _t1]) = folded;
+            let (folded_instance, folded_witness, _) = folded;
 
             let checker = ExtendedProvider::new(folded_instance, folded_witness);
             debug!("exp: \n {:#?}", final_constraint.to_string());
@@ -425,7 +756,7 @@ mod tests {
         {
             // here we use already relaxed pairs, which have a trival x -> x implementation
             let folded = scheme.fold_instance_witness_pair(left, right, None, &mut fq_sponge);
-            let (folded_instance, folded_witness, [_t0, _t1]) = folded;
+            let (folded_instance, folded_witness, _) = folded;
 
             let checker = ExtendedProvider::new(folded_instance, folded_witness);
             debug!("exp: \n {:#?}", final_constraint.to_string());
@@ -433,4 +764,1189 @@ mod tests {
             checker.check(&final_constraint);
         };
     }
+
+    // the challenges come from seeded transcripts, and are compared to
+    // values recorded from a previous run
+    #[test]
+    fn test_deterministic_challenges() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        let fold = || {
+            let make_pair = |[a, b]: [[u32; 2]; 2]| {
+                let witness = int_to_witness(add_witness(a, b), domain);
+                (instance_from_witness(&witness, &srs, domain), witness)
+            };
+            let left = make_pair([[4, 2], [2, 1]]);
+            let right = make_pair([[5, 6], [4, 3]]);
+            let mut transcript = DeterministicTranscript::new(1);
+            let (instance, witness, _) = scheme.fold_instance_witness_pair(
+                left,
+                right,
+                Some(DynamicSelector::SelecAdd),
+                &mut transcript,
+            );
+            let checker = ExtendedProvider::new(instance, witness);
+            checker.check(&final_constraint);
+            checker.instance.inner_instance().inner.challenges
+        };
+
+        let fixture = [
+            "8417809713238256241163969714407523298864159173273550117982284406747684714250",
+            "6196757299574974584406886666883576706842035050147516319900967550331020844585",
+            "2299964939839245818042648422280715559362895406356707481461172210589663209945",
+        ]
+        .map(|c| Fp::from_str(c).unwrap());
+        assert_eq!(fold(), fixture);
+        assert_eq!(fold(), fold());
+    }
+
+    #[test]
+    fn test_cross_terms_by_name() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let (scheme, _) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        let make_pair = |a, b| {
+            let witness = int_to_witness(add_witness(a, b), domain);
+            (instance_from_witness(&witness, &srs, domain), witness)
+        };
+        let (instance, _, cross_terms) = scheme.fold_instance_witness_pair(
+            make_pair([4, 2], [2, 1]),
+            make_pair([5, 6], [4, 3]),
+            Some(DynamicSelector::SelecAdd),
+            &mut DeterministicTranscript::new(1),
+        );
+        let CrossTerms { t0, t1 } = CrossTerms::try_from(cross_terms).unwrap();
+
+        // the inputs being fresh, the error commitment of the folded instance
+        // is `-(r T_0 + r^2 T_1)`, `r` being the only challenge of the fold
+        let r = DeterministicTranscript::<Curve>::new(1).challenge();
+        let zero = PolyComm {
+            elems: vec![Curve::zero()],
+        };
+        let expected = &zero - &(&t0.scale(r) + &t1.scale(r * r));
+        assert_eq!(instance.get_error_column_commitment(), &expected);
+
+        // the conversion fails when there are not exactly 2 cross terms
+        assert_eq!(CrossTerms::try_from(vec![t0.clone()]), Err(vec![t0]));
+    }
+
+    // the domain size is read from the witnesses, and is not limited to 2
+    #[test]
+    fn test_decomposable_folding_domain_8() {
+        let constraints = constraints();
+        let domain = D::<Fp>::new(8).unwrap();
+        let mut srs = SRS::<Curve>::create(8);
+        srs.add_lagrange_basis(domain);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints,
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        let add_pair = |offset: u32| {
+            let a: Vec<u32> = (0..8).map(|i| i + offset).collect();
+            let b: Vec<u32> = (0..8).map(|i| 2 * i + 1).collect();
+            let c = a.iter().zip(&b).map(|(a, b)| a + b).collect();
+            let wit = [a, b, c, vec![1; 8], vec![0; 8]].map(|col| {
+                Evaluations::from_vec_and_domain(col.into_iter().map(Fp::from).collect(), domain)
+            });
+            let ins = instance_from_witness(&wit, &srs, domain);
+            (ins, wit)
+        };
+
+        let (folded_instance, folded_witness, _) = scheme.fold_instance_witness_pair(
+            add_pair(3),
+            add_pair(10),
+            Some(DynamicSelector::SelecAdd),
+            &mut fq_sponge,
+        );
+        assert_eq!(folded_witness.witness.inner.rows(), 8);
+        assert_eq!(folded_witness.error_vec.evals.len(), 8);
+        let checker = ExtendedProvider::new(folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_fold_padded_witnesses() {
+        let domain = D::<Fp>::new(4).unwrap();
+        let mut srs = SRS::<Curve>::create(4);
+        srs.add_lagrange_basis(domain);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        // additions over 3 rows, padded to the 4 rows of the domain
+        let padded_pair = |a: [u32; 3], b: [u32; 3]| {
+            let c = a.iter().zip(b).map(|(a, b)| a + b).collect();
+            let columns = [a.to_vec(), b.to_vec(), c, vec![1; 3], vec![0; 3]]
+                .map(|col| col.into_iter().map(Fp::from).collect())
+                .to_vec();
+            let (columns, rows) = crate::pad_witness(columns, domain);
+            assert_eq!(rows, 3);
+            let witness: TestWitness = columns.try_into().unwrap();
+            assert_eq!(witness.rows(), 4);
+            (instance_from_witness(&witness, &srs, domain), witness)
+        };
+        let (instance, witness, _) = scheme.fold_instance_witness_pair(
+            padded_pair([1, 2, 3], [4, 5, 6]),
+            padded_pair([7, 8, 9], [1, 0, 2]),
+            None,
+            &mut fq_sponge,
+        );
+        let checker = ExtendedProvider::new(instance, witness);
+        assert_eq!(
+            checker.check_active(&final_constraint, &[true, true, true, false]),
+            Ok(())
+        );
+        // no selector is enabled in the padding row
+        let violations = checker.check_detailed(&final_constraint).unwrap_err();
+        assert!(violations.iter().all(|violation| violation.row == 3));
+    }
+
+    #[test]
+    fn test_witness_from_columns() {
+        let domain = D::<Fp>::new(4).unwrap();
+        let to_fp = |col: Vec<u32>| col.into_iter().map(Fp::from).collect::<Vec<_>>();
+        let columns = |length: usize| (0..5).map(|i| to_fp(vec![i; length])).collect();
+
+        let witness = TestWitness::from_columns(columns(4), domain).unwrap();
+        assert_eq!(witness.rows(), 4);
+        assert!(witness.iter().all(|col| col.domain() == domain));
+
+        assert_eq!(
+            TestWitness::from_columns(columns(3), domain),
+            Err(WitnessError::WrongColumnLength {
+                column: 0,
+                length: 3,
+                domain_size: 4
+            })
+        );
+        let mut mismatched: Vec<_> = columns(4);
+        mismatched[2] = to_fp(vec![0; 8]);
+        assert_eq!(
+            TestWitness::from_columns(mismatched, domain),
+            Err(WitnessError::WrongColumnLength {
+                column: 2,
+                length: 8,
+                domain_size: 4
+            })
+        );
+        assert_eq!(
+            TestWitness::from_columns(vec![to_fp(vec![0; 4]); 3], domain),
+            Err(WitnessError::WrongColumnCount(3))
+        );
+    }
+
+    #[test]
+    fn test_check_detailed() {
+        let domain = D::<Fp>::new(4).unwrap();
+        let mut srs = SRS::<Curve>::create(4);
+        srs.add_lagrange_basis(domain);
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        // the output of the addition is off by 5 in row 2
+        let witness: TestWitness = [
+            vec![1, 2, 3, 4],
+            vec![5, 6, 7, 8],
+            vec![6, 8, 15, 12],
+            vec![1; 4],
+            vec![0; 4],
+        ]
+        .map(|col| {
+            Evaluations::from_vec_and_domain(col.into_iter().map(Fp::from).collect(), domain)
+        });
+        let instance = instance_from_witness(&witness, &srs, domain);
+        let alpha = instance.alphas.get(0).unwrap();
+        let (instance, witness) = scheme.relax(instance, witness);
+        let checker = ExtendedProvider::new(instance, witness);
+
+        let violations = checker.check_detailed(&final_constraint).unwrap_err();
+        // the add constraint, a + b - c, is the first one
+        assert_eq!(
+            violations,
+            vec![ConstraintViolation {
+                row: 2,
+                value: -Fp::from(5u32) * alpha,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_eval_at_row() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        // two valid additions, 1 + 2 = 3 and 3 + 4 = 7
+        let witness: TestWitness = [vec![1, 3], vec![2, 4], vec![3, 7], vec![1; 2], vec![0; 2]]
+            .map(|col| {
+                Evaluations::from_vec_and_domain(col.into_iter().map(Fp::from).collect(), domain)
+            });
+        let instance = instance_from_witness(&witness, &srs, domain);
+        let (scheme, _) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+        let (instance, witness) = scheme.relax(instance, witness);
+        let checker = ExtendedProvider::new(instance, witness);
+
+        let add = &constraints()[&DynamicSelector::SelecAdd][0];
+        assert_eq!(checker.eval_at_row(add, 0), Fp::zero());
+        assert_eq!(checker.eval_at_row(add, 1), Fp::zero());
+
+        // A(next) - C reads the first row from the last one
+        let next = next_row_constraint();
+        assert_eq!(checker.eval_at_row(&next, 0), Fp::zero());
+        assert_eq!(
+            checker.eval_at_row(&next, 1),
+            Fp::from(1u32) - Fp::from(7u32)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "check in row 0 failed")]
+    fn test_selectors_sum_to_one() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        // with b = 0, both the add and the sub constraints hold, and only the
+        // selectors are wrong
+        let witness = int_to_witness([[1, 2], [0, 0], [1, 2], [1, 1], [1, 1]], domain);
+        let instance = instance_from_witness(&witness, &srs, domain);
+        let (instance, witness) = scheme.relax(instance, witness);
+        let checker = ExtendedProvider::new(instance, witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_relax_then_fold() {
+        let constraints = constraints();
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+        let fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints,
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        let left = int_to_witness(add_witness([1, 2], [3, 4]), domain);
+        let right = int_to_witness(add_witness([5, 6], [7, 8]), domain);
+        let left_instance = instance_from_witness(&left, &srs, domain);
+        let right_instance = instance_from_witness(&right, &srs, domain);
+
+        let (relaxed_instance, relaxed_witness) = scheme.relax(left_instance.clone(), left.clone());
+        assert_eq!(relaxed_instance.u, Fp::one());
+        assert!(relaxed_witness.error_vec.evals.iter().all(|e| e.is_zero()));
+
+        let (fresh_instance, fresh_witness, fresh_cross_terms) = scheme.fold_instance_witness_pair(
+            (left_instance.clone(), left.clone()),
+            (right_instance.clone(), right.clone()),
+            Some(DynamicSelector::SelecAdd),
+            &mut fq_sponge.clone(),
+        );
+        let (folded_instance, folded_witness, folded_cross_terms) = scheme
+            .fold_instance_witness_pair(
+                (relaxed_instance, relaxed_witness),
+                scheme.relax(right_instance, right),
+                Some(DynamicSelector::SelecAdd),
+                &mut fq_sponge.clone(),
+            );
+
+        assert_eq!(fresh_cross_terms, folded_cross_terms);
+        assert_eq!(fresh_instance, folded_instance);
+        assert_eq!(fresh_witness.witness.inner, folded_witness.witness.inner);
+        assert_eq!(fresh_witness.error_vec, folded_witness.error_vec);
+        let checker = ExtendedProvider::new(folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_ivc_accumulator() {
+        use crate::accumulator::IvcAccumulator;
+
+        let constraints = constraints();
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints,
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        let witness = int_to_witness(add_witness([1, 2], [3, 4]), domain);
+        let instance = instance_from_witness(&witness, &srs, domain);
+
+        let mut accumulator = IvcAccumulator::new(&scheme, instance.clone(), witness.clone());
+        for _ in 1..5 {
+            accumulator.absorb_step(
+                instance.clone(),
+                witness.clone(),
+                Some(DynamicSelector::SelecAdd),
+                &mut fq_sponge,
+            );
+        }
+        assert_eq!(accumulator.steps(), 5);
+
+        let (folded_instance, folded_witness) = accumulator.finalize();
+        // the slack accumulates the challenges of the folds
+        assert_ne!(folded_instance.u, Fp::one());
+        let checker = ExtendedProvider::new(folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+
+    // the next row is read from the row below, wrapping around the domain, so
+    // that folding witnesses chaining their rows satisfies the constraint on
+    // the next row
+    #[test]
+    fn test_fold_next_row_constraint() {
+        // with two rows, the left and right rotations would coincide
+        let domain = D::<Fp>::new(4).unwrap();
+        let mut srs = SRS::<Curve>::create(4);
+        srs.add_lagrange_basis(domain);
+
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![next_row_constraint()],
+            &srs,
+            domain,
+            &(),
+        );
+
+        // additions whose output is the first input of the next row, the sum
+        // of the second inputs being zero to wrap around
+        let chained_add_witness = |a0: i64, b: [i64; 4]| {
+            let to_field = |x: i64| {
+                let abs = Fp::from(x.unsigned_abs());
+                if x < 0 {
+                    -abs
+                } else {
+                    abs
+                }
+            };
+            let mut a = vec![a0];
+            for b in b.iter().take(3) {
+                a.push(a.last().unwrap() + b);
+            }
+            let c: Vec<i64> = a.iter().zip(b.iter()).map(|(a, b)| a + b).collect();
+            assert_eq!(c[3], a0);
+            let witness: TestWitness =
+                [a, b.to_vec(), c, vec![1, 1, 1, 1], vec![0, 0, 0, 0]].map(|col| {
+                    Evaluations::from_vec_and_domain(
+                        col.into_iter().map(to_field).collect(),
+                        domain,
+                    )
+                });
+            let instance = instance_from_witness(&witness, &srs, domain);
+            (instance, witness)
+        };
+        let left = chained_add_witness(1, [2, 1, 4, -7]);
+        let right = chained_add_witness(2, [0, 3, 1, -4]);
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (folded_instance, folded_witness, _) = scheme.fold_instance_witness_pair(
+            left,
+            right,
+            Some(DynamicSelector::SelecAdd),
+            &mut fq_sponge,
+        );
+        let checker = ExtendedProvider::new(folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_verify_folded_instance() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let (scheme, _) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        let make_pair = |a, b| {
+            let witness = int_to_witness(add_witness(a, b), domain);
+            let instance = instance_from_witness(&witness, &srs, domain);
+            (instance, witness)
+        };
+        let left = make_pair([4, 2], [2, 1]);
+        let right = make_pair([5, 6], [4, 3]);
+        let (left_instance, right_instance) = (left.0.clone(), right.0.clone());
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (folded_instance, _, error_commitments) = scheme.fold_instance_witness_pair(
+            left,
+            right,
+            Some(DynamicSelector::SelecAdd),
+            &mut fq_sponge,
+        );
+
+        // the verifier only has the instances and the cross terms
+        let verify = |a: &TestInstance,
+                      b: &TestInstance,
+                      folded: &RelaxedInstance<Curve, TestInstance>,
+                      error_commitments: &[PolyComm<Curve>]| {
+            let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+            scheme.verify_folded_instance(
+                a.clone(),
+                b.clone(),
+                folded,
+                error_commitments,
+                &mut fq_sponge,
+            )
+        };
+        assert!(verify(
+            &left_instance,
+            &right_instance,
+            &folded_instance,
+            &error_commitments
+        ));
+
+        // a tampered folded instance
+        let mut tampered = folded_instance.clone();
+        tampered.inner_mut().inner.commitments[0] = Curve::prime_subgroup_generator();
+        assert!(!verify(
+            &left_instance,
+            &right_instance,
+            &tampered,
+            &error_commitments
+        ));
+        let mut tampered = folded_instance.clone();
+        tampered.u += Fp::one();
+        assert!(!verify(
+            &left_instance,
+            &right_instance,
+            &tampered,
+            &error_commitments
+        ));
+
+        // a tampered input instance
+        let mut tampered = right_instance.clone();
+        tampered.commitments[1] = Curve::prime_subgroup_generator();
+        assert!(!verify(
+            &left_instance,
+            &tampered,
+            &folded_instance,
+            &error_commitments
+        ));
+
+        // cross terms with an unexpected number of commitments
+        let mut malformed = error_commitments.clone();
+        malformed[0].elems.push(Curve::prime_subgroup_generator());
+        assert!(!verify(
+            &left_instance,
+            &right_instance,
+            &folded_instance,
+            &malformed
+        ));
+    }
+
+    #[test]
+    fn test_fold_many() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        let pairs: Vec<_> = [
+            ([4, 2], [2, 1]),
+            ([5, 6], [4, 3]),
+            ([1, 0], [7, 9]),
+            ([3, 3], [3, 3]),
+        ]
+        .into_iter()
+        .map(|(a, b)| {
+            let witness = int_to_witness(add_witness(a, b), domain);
+            let instance = instance_from_witness(&witness, &srs, domain);
+            (instance, witness)
+        })
+        .collect();
+        let selector = Some(DynamicSelector::SelecAdd);
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (instance, witness, cross_terms) =
+            scheme.fold_many(pairs.clone(), selector, &mut fq_sponge);
+        assert_eq!(cross_terms.len(), 6);
+
+        // the same as folding the pairs one after the other
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let mut pairs = pairs.into_iter();
+        let (first, second) = (pairs.next().unwrap(), pairs.next().unwrap());
+        let (mut expected_instance, mut expected_witness, mut expected_cross_terms) =
+            scheme.fold_instance_witness_pair(first, second, selector, &mut fq_sponge);
+        for pair in pairs {
+            let (i, w, t) = scheme.fold_instance_witness_pair(
+                (expected_instance, expected_witness),
+                pair,
+                selector,
+                &mut fq_sponge,
+            );
+            expected_instance = i;
+            expected_witness = w;
+            expected_cross_terms.extend(t);
+        }
+        assert_eq!(cross_terms, expected_cross_terms);
+        assert_eq!(instance.u, expected_instance.u);
+        assert_eq!(
+            instance.get_error_column_commitment(),
+            expected_instance.get_error_column_commitment()
+        );
+        assert_eq!(witness.error_vec, expected_witness.error_vec);
+
+        let checker = ExtendedProvider::new(instance, witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_fold_cubic_constraint() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let get_col = |col| {
+            Box::new(FoldingCompatibleExpr::Atom(
+                FoldingCompatibleExprInner::Cell(Variable {
+                    col,
+                    row: CurrOrNext::Curr,
+                }),
+            ))
+        };
+        let (a, b, c) = (
+            get_col(TestColumn::A),
+            get_col(TestColumn::B),
+            get_col(TestColumn::C),
+        );
+        // a b c - c^2, which holds when c = a b, is of degree 4 with the
+        // selector
+        let abc = FoldingCompatibleExpr::Mul(Box::new(FoldingCompatibleExpr::Mul(a, b)), c.clone());
+        let cubic =
+            FoldingCompatibleExpr::Sub(Box::new(abc), Box::new(FoldingCompatibleExpr::Square(c)));
+        let constraints = [(DynamicSelector::SelecAdd, vec![cubic])]
+            .into_iter()
+            .collect();
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints,
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+        assert_eq!(scheme.degree(), 4);
+        assert_eq!(scheme.cross_terms_count(), 4);
+
+        let make_pair = |a: [u32; 2], b: [u32; 2]| {
+            let c = [a[0] * b[0], a[1] * b[1]];
+            let witness = int_to_witness([a, b, c, [1, 1], [0, 0]], domain);
+            let instance = instance_from_witness(&witness, &srs, domain);
+            (instance, witness)
+        };
+        let left = make_pair([4, 2], [2, 1]);
+        let right = make_pair([5, 6], [4, 3]);
+        let (left_instance, right_instance) = (left.0.clone(), right.0.clone());
+
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (instance, witness, cross_terms) =
+            scheme.fold_instance_witness_pair(left, right, None, &mut fq_sponge);
+        assert_eq!(cross_terms.len(), 4);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        assert!(scheme.verify_folded_instance(
+            left_instance,
+            right_instance,
+            &instance,
+            &cross_terms,
+            &mut fq_sponge,
+        ));
+        let checker = ExtendedProvider::new(instance.clone(), witness.clone());
+        checker.check(&final_constraint);
+
+        // folding a fresh pair into the accumulator, with u different from 1
+        let (instance, witness, _) = scheme.fold_instance_witness_pair(
+            (instance, witness),
+            make_pair([3, 3], [1, 7]),
+            None,
+            &mut fq_sponge,
+        );
+        let checker = ExtendedProvider::new(instance, witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_fold_with_provided_error_term() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        let make_pair = |wit: TestWitness| {
+            let ins = instance_from_witness(&wit, &srs, domain);
+            (ins, wit)
+        };
+        let left = make_pair(int_to_witness(add_witness([4, 2], [2, 1]), domain));
+        let right = make_pair(int_to_witness(add_witness([5, 6], [4, 3]), domain));
+        let selector = Some(DynamicSelector::SelecAdd);
+
+        // the recomputing path
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (expected_instance, expected_witness, expected_t) = scheme.fold_instance_witness_pair(
+            left.clone(),
+            right.clone(),
+            selector,
+            &mut fq_sponge,
+        );
+
+        // a prover computing and committing to the error terms on its own
+        let error = scheme.compute_error_terms(left.clone(), right.clone(), selector);
+        let t = error
+            .iter()
+            .map(|e| {
+                let e = Evaluations::from_vec_and_domain(e.clone(), domain);
+                srs.commit_evaluations_non_hiding(domain, &e)
+            })
+            .collect();
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (folded_instance, folded_witness, folded_t) =
+            scheme.fold_with_error_term(left, right, selector, error, t, &mut fq_sponge);
+
+        assert_eq!(folded_t, expected_t);
+        assert_eq!(folded_instance.u, expected_instance.u);
+        assert_eq!(
+            folded_instance.get_error_column_commitment(),
+            expected_instance.get_error_column_commitment()
+        );
+        assert_eq!(
+            folded_instance.inner_instance().inner.commitments,
+            expected_instance.inner_instance().inner.commitments
+        );
+        assert_eq!(folded_witness.error_vec, expected_witness.error_vec);
+
+        let checker = ExtendedProvider::new(folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_fold_provenance() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+
+        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
+            constraints(),
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+
+        let make_pair = |wit: TestWitness| {
+            let ins = instance_from_witness(&wit, &srs, domain);
+            (ins, wit)
+        };
+
+        let mut fold = |witness: fn([u32; 2], [u32; 2]) -> [[u32; 2]; 5], selector| {
+            let left = make_pair(int_to_witness(witness([4, 2], [2, 1]), domain));
+            let right = make_pair(int_to_witness(witness([5, 6], [4, 3]), domain));
+            let (instance, witness, _, provenance) = scheme
+                .fold_instance_witness_pair_with_provenance(
+                    left,
+                    right,
+                    Some(selector),
+                    &mut fq_sponge,
+                );
+            let expected: BTreeSet<_> = [selector].into();
+            assert!(provenance.iter().all(|row| row == &expected));
+            (instance, witness)
+        };
+        let adds = fold(add_witness, DynamicSelector::SelecAdd);
+        let subs = fold(sub_witness, DynamicSelector::SelecSub);
+
+        // every row of the mixed fold comes from both an addition and a
+        // subtraction
+        let (instance, witness, _, provenance) =
+            scheme.fold_instance_witness_pair_with_provenance(adds, subs, None, &mut fq_sponge);
+        assert_eq!(provenance.len(), 2);
+        let expected: BTreeSet<_> = [DynamicSelector::SelecAdd, DynamicSelector::SelecSub].into();
+        assert!(provenance.iter().all(|row| row == &expected));
+
+        let checker = ExtendedProvider::new(instance, witness);
+        checker.check(&final_constraint);
+    }
+
+    #[test]
+    fn test_max_magnitudes_detects_underflow() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut witness = int_to_witness(sub_witness([4, 2], [2, 1]), domain);
+        let magnitudes = max_magnitudes(witness.iter());
+        assert_eq!(magnitudes[0], Fp::from(4u32).into_repr());
+        assert!(magnitudes.iter().all(|m| !is_wrapping::<Fp>(m)));
+
+        // 1 - 2, as it would be computed by a witness generator working over
+        // the field
+        witness[2].evals[1] = Fp::from(1u32) - Fp::from(2u32);
+        let magnitudes = max_magnitudes(witness.iter());
+        assert!(is_wrapping::<Fp>(&magnitudes[2]));
+    }
+
+    #[test]
+    fn test_from_kimchi_constraints() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+
+        // the constraints of constraints(), written as kimchi expressions
+        let cell = |col| -> Box<Expr<ConstantExpr<Fp>, TestColumn>> {
+            Box::new(Operations::Atom(ExprInner::Cell(Variable {
+                col,
+                row: CurrOrNext::Curr,
+            })))
+        };
+        let [a, b, c] = [TestColumn::A, TestColumn::B, TestColumn::C].map(cell);
+        let add = Operations::Sub(Box::new(Operations::Add(a.clone(), b.clone())), c.clone());
+        let sub = Operations::Sub(Box::new(Operations::Sub(a, b)), c);
+        let kimchi_constraints: BTreeMap<_, _> = [
+            (DynamicSelector::SelecAdd, vec![add]),
+            (DynamicSelector::SelecSub, vec![sub]),
+        ]
+        .into();
+
+        let (scheme, final_constraint) =
+            DecomposableFoldingScheme::<TestFoldingConfig>::from_kimchi(
+                kimchi_constraints.clone(),
+                vec![],
+                &srs,
+                domain,
+                &(),
+            )
+            .unwrap();
+
+        let make_pair = |wit: TestWitness| {
+            let ins = instance_from_witness(&wit, &srs, domain);
+            (ins, wit)
+        };
+        let left = make_pair(int_to_witness(add_witness([4, 2], [2, 1]), domain));
+        let right = make_pair(int_to_witness(add_witness([5, 6], [4, 3]), domain));
+        let (instance, witness, _) = scheme.fold_instance_witness_pair(
+            left,
+            right,
+            Some(DynamicSelector::SelecAdd),
+            &mut fq_sponge,
+        );
+        let checker = ExtendedProvider::new(instance, witness);
+        checker.check(&final_constraint);
+
+        // expressions that cannot be folded are reported instead of panicking
+        let mut unsupported = kimchi_constraints;
+        unsupported
+            .get_mut(&DynamicSelector::SelecAdd)
+            .unwrap()
+            .push(Operations::Atom(ExprInner::UnnormalizedLagrangeBasis(
+                RowOffset {
+                    zk_rows: false,
+                    offset: 0,
+                },
+            )));
+        let res = DecomposableFoldingScheme::<TestFoldingConfig>::from_kimchi(
+            unsupported,
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+        assert!(matches!(
+            res,
+            Err(ExprConversionError::UnnormalizedLagrangeBasis)
+        ));
+    }
+
+    #[test]
+    fn test_selector_wellformedness() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let selectors = [DynamicSelector::SelecAdd, DynamicSelector::SelecSub];
+        let check = |witness: &TestWitness| {
+            check_selector_wellformedness::<TestFoldingConfig>(witness, &selectors)
+        };
+
+        let witness = int_to_witness(add_witness([4, 2], [2, 1]), domain);
+        assert_eq!(check(&witness), Ok(()));
+
+        // e.g. the selectors of a folded witness
+        let mut not_boolean = witness.clone();
+        not_boolean[3].evals[1] = Fp::from(2u32);
+        assert_eq!(
+            check(&not_boolean),
+            Err(SelectorError::NotBoolean {
+                selector: DynamicSelector::SelecAdd,
+                row: 1
+            })
+        );
+
+        let mut both_active = witness.clone();
+        both_active[4].evals[0] = Fp::one();
+        assert_eq!(
+            check(&both_active),
+            Err(SelectorError::NotExclusive { row: 0, active: 2 })
+        );
+
+        let mut none_active = witness;
+        none_active[3].evals[1] = Fp::zero();
+        assert_eq!(
+            check(&none_active),
+            Err(SelectorError::NotExclusive { row: 1, active: 0 })
+        );
+    }
+
+    #[test]
+    fn test_fold_mmap_witnesses() {
+        use crate::mmap_witness::MmapWitness;
+
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut rng = thread_rng();
+        let challenge = Fp::rand(&mut rng);
+        let a = int_to_witness(add_witness([4, 2], [2, 1]), domain);
+        let b = int_to_witness(sub_witness([5, 6], [4, 3]), domain);
+
+        let dir = std::env::temp_dir();
+        let path = |name: &str| dir.join(format!("folding-{}-{name}", std::process::id()));
+        let (path_a, path_b) = (path("a"), path("b"));
+        let mmap_a = MmapWitness::create(&path_a, &a).unwrap();
+        let mmap_b = MmapWitness::create(&path_b, &b).unwrap();
+
+        let expected = <TestWitness as Witness<Curve>>::combine(a, b, challenge);
+        let folded = <MmapWitness<Fp> as Witness<Curve>>::combine(mmap_a, mmap_b, challenge);
+        assert_eq!(folded.columns(), expected.len());
+        for (i, col) in expected.iter().enumerate() {
+            assert_eq!(&folded.column(i).unwrap(), col);
+        }
+        drop(folded);
+
+        // the result was written in the file of the first witness
+        let reopened = MmapWitness::<Fp>::open(&path_a, expected.len(), domain).unwrap();
+        assert_eq!(reopened.column(2).unwrap(), expected[2]);
+        drop(reopened);
+
+        // a file of another size is rejected rather than resized
+        let wider = MmapWitness::<Fp>::open(&path_a, expected.len() + 1, domain);
+        assert_eq!(
+            wider.err().map(|e| e.kind()),
+            Some(std::io::ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            std::fs::metadata(&path_a).unwrap().len(),
+            std::fs::metadata(&path_b).unwrap().len()
+        );
+
+        // bytes that are not field elements are reported when read
+        std::fs::write(
+            &path_b,
+            vec![0xff; std::fs::metadata(&path_b).unwrap().len() as usize],
+        )
+        .unwrap();
+        let corrupted = MmapWitness::<Fp>::open(&path_b, expected.len(), domain).unwrap();
+        assert_eq!(
+            corrupted.column(0).err().map(|e| e.kind()),
+            Some(std::io::ErrorKind::InvalidData)
+        );
+        drop(corrupted);
+        std::fs::remove_file(path_a).unwrap();
+        std::fs::remove_file(path_b).unwrap();
+    }
+
+    /// A config of `N` columns, declared only once by its type, whose
+    /// instance and witness delegate their folding to [combine_slices]
+    struct ColumnsInstance<const N: usize> {
+        commitments: [Curve; N],
+        alphas: Alphas<Fp>,
+    }
+
+    impl<const N: usize> Instance<Curve> for ColumnsInstance<N> {
+        fn combine(a: Self, b: Self, challenge: Fp) -> Self {
+            ColumnsInstance {
+                commitments: combine_slices(&a.commitments, &b.commitments, challenge)
+                    .try_into()
+                    .unwrap(),
+                alphas: Alphas::combine(a.alphas, b.alphas, challenge),
+            }
+        }
+
+        fn alphas(&self) -> &Alphas<Fp> {
+            &self.alphas
+        }
+    }
+
+    struct ColumnsWitness<const N: usize>([Evaluations<Fp, D<Fp>>; N]);
+
+    impl<const N: usize> Witness<Curve> for ColumnsWitness<N> {
+        fn combine(a: Self, b: Self, challenge: Fp) -> Self {
+            ColumnsWitness(combine_slices(&a.0, &b.0, challenge).try_into().unwrap())
+        }
+
+        fn rows(&self) -> usize {
+            self.0[0].evals.len()
+        }
+    }
+
+    fn check_combine_columns<const N: usize>() {
+        let mut rng = thread_rng();
+        let domain = D::<Fp>::new(4).unwrap();
+        let random_pair = |rng: &mut _| {
+            let witness: [Evaluations<Fp, D<Fp>>; N] = std::array::from_fn(|_| {
+                let evals = (0..4).map(|_| Fp::rand(rng)).collect();
+                Evaluations::from_vec_and_domain(evals, domain)
+            });
+            let instance = ColumnsInstance::<N> {
+                commitments: std::array::from_fn(|_| {
+                    Curve::prime_subgroup_generator()
+                        .mul(Fp::rand(rng))
+                        .into_affine()
+                }),
+                alphas: Alphas::new(Fp::rand(rng)),
+            };
+            (instance, ColumnsWitness(witness))
+        };
+        let (ins1, wit1) = random_pair(&mut rng);
+        let (ins2, wit2) = random_pair(&mut rng);
+        let challenge = Fp::rand(&mut rng);
+
+        let expected_commitments: [Curve; N] = std::array::from_fn(|i| {
+            ins1.commitments[i] + ins2.commitments[i].mul(challenge).into_affine()
+        });
+        let expected_evals: [Vec<Fp>; N] = std::array::from_fn(|i| {
+            wit1.0[i]
+                .evals
+                .iter()
+                .zip(wit2.0[i].evals.iter())
+                .map(|(a, b)| *a + challenge * b)
+                .collect()
+        });
+
+        let instance = Instance::combine(ins1, ins2, challenge);
+        let witness = Witness::combine(wit1, wit2, challenge);
+        assert_eq!(instance.commitments, expected_commitments);
+        for (column, expected) in witness.0.iter().zip(expected_evals.iter()) {
+            assert_eq!(&column.evals, expected);
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct MacroInstance {
+        commitments: [Curve; 5],
+        challenges: [Fp; 3],
+        alphas: Alphas<Fp>,
+    }
+
+    crate::impl_instance!(
+        Curve,
+        MacroInstance {
+            commitments: [commitments],
+            scalars: [challenges],
+            alphas: alphas,
+        }
+    );
+
+    #[test]
+    fn test_impl_instance_macro() {
+        let mut rng = thread_rng();
+        let mut random_instance = || TestInstance {
+            commitments: std::array::from_fn(|_| {
+                Curve::prime_subgroup_generator()
+                    .mul(Fp::rand(&mut rng))
+                    .into_affine()
+            }),
+            challenges: std::array::from_fn(|_| Fp::rand(&mut rng)),
+            alphas: Alphas::new(Fp::rand(&mut rng)),
+        };
+        let to_macro_instance = |instance: &TestInstance| MacroInstance {
+            commitments: instance.commitments,
+            challenges: instance.challenges,
+            alphas: instance.alphas.clone(),
+        };
+        let (a, b) = (random_instance(), random_instance());
+        let challenge = Fp::rand(&mut thread_rng());
+
+        let expected = Instance::combine(a.clone(), b.clone(), challenge);
+        let combined = Instance::combine(to_macro_instance(&a), to_macro_instance(&b), challenge);
+        assert_eq!(combined, to_macro_instance(&expected));
+        assert_eq!(combined.alphas(), expected.alphas());
+    }
+
+    #[test]
+    fn test_commit_all() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let domain = D::<Fp>::new(8).unwrap();
+        let mut srs = SRS::<Curve>::create(8);
+        srs.add_lagrange_basis(domain);
+        let mut witness: Vec<Evaluations<Fp, D<Fp>>> = (0..16)
+            .map(|_| {
+                let evals = (0..8).map(|_| Fp::rand(&mut rng)).collect();
+                Evaluations::from_vec_and_domain(evals, domain)
+            })
+            .collect();
+        // the commitment to zero is the point at infinity
+        witness.push(Evaluations::from_vec_and_domain(
+            vec![Fp::zero(); 8],
+            domain,
+        ));
+
+        let expected: Vec<Curve> = witness
+            .iter()
+            .map(|w| srs.commit_evaluations_non_hiding(domain, w).elems[0])
+            .collect();
+        assert_eq!(crate::commit_all(&srs, domain, &witness), expected);
+    }
+
+    #[test]
+    fn test_make_srs() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let domain = D::<Fp>::new(16).unwrap();
+        let srs = crate::make_srs::<Curve>(domain);
+        assert_eq!(srs.g.len(), domain.size());
+
+        let witness: TestWitness = std::array::from_fn(|_| {
+            let evals = (0..16).map(|_| Fp::rand(&mut rng)).collect();
+            Evaluations::from_vec_and_domain(evals, domain)
+        });
+        let commitments = crate::commit_all(&srs, domain, &witness);
+        assert_eq!(commitments.len(), witness.len());
+        for (commitment, column) in commitments.iter().zip(&witness) {
+            let expected = srs.commit_evaluations_non_hiding(domain, column);
+            assert_eq!(expected.elems, vec![*commitment]);
+        }
+    }
+
+    #[test]
+    fn test_combine_slices_variable_columns() {
+        check_combine_columns::<3>();
+        check_combine_columns::<7>();
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_combine_par() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let domain = D::<Fp>::new(1 << 10).unwrap();
+        let mut random_witness = || -> TestWitness {
+            std::array::from_fn(|_| {
+                let evals = (0..domain.size()).map(|_| Fp::rand(&mut rng)).collect();
+                Evaluations::from_vec_and_domain(evals, domain)
+            })
+        };
+        let a = random_witness();
+        let b = random_witness();
+        let challenge = Fp::rand(&mut rng);
+
+        let sequential = combine_slices(&a, &b, challenge);
+        let parallel = crate::combine_par(&a, &b, challenge);
+        assert_eq!(parallel, sequential);
+        assert_eq!(TestWitness::combine(a, b, challenge).to_vec(), sequential);
+    }
+
+    #[test]
+    fn test_combine_slices_deduplicated() {
+        let mut rng = thread_rng();
+        let mut random_commitment = || {
+            Curve::prime_subgroup_generator()
+                .mul(Fp::rand(&mut rng))
+                .into_affine()
+        };
+        // the first two columns are shared by both instances
+        let shared = [random_commitment(), random_commitment()];
+        let a: Vec<Curve> = shared
+            .iter()
+            .copied()
+            .chain([random_commitment(), random_commitment()])
+            .collect();
+        let b: Vec<Curve> = shared
+            .iter()
+            .copied()
+            .chain([random_commitment(), random_commitment()])
+            .collect();
+        let challenge = Fp::rand(&mut rng);
+        assert_eq!(
+            combine_slices_deduplicated(&a, &b, challenge),
+            combine_slices(&a, &b, challenge)
+        );
+
+        // as well as for commitments of several chunks
+        let chunks = |commitments: &[Curve]| PolyComm {
+            elems: commitments.to_vec(),
+        };
+        let a = [chunks(&a), chunks(&shared)];
+        let b = [chunks(&b), chunks(&shared)];
+        assert_eq!(
+            combine_slices_deduplicated(&a, &b, challenge),
+            combine_slices(&a, &b, challenge)
+        );
+    }
 }
@@ -0,0 +1,252 @@
+//! This file shows that the folding scheme is not tied to the curve of the
+//! other examples, by folding instances committed over Pallas, whose scalar
+//! field is the base field of Vesta. The configuration only differs from the
+//! other examples by its [Curve](FoldingConfig::Curve) and its
+//! [Srs](FoldingConfig::Srs), the rest of the machinery being generic.
+//! The circuit has a single constraint `a(X) b(X) - c(X) = 0` over three
+//! witness columns.
+use crate::{
+    checker::{Checker, ExtendedProvider, Provider},
+    error_term::Side,
+    expressions::{cell, ColumnKind, FoldingColumnTrait},
+    Alphas, FoldingCompatibleExpr, FoldingConfig, FoldingEnv, Instance, Witness,
+};
+use ark_ff::{UniformRand, Zero};
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
+use kimchi::circuits::gate::CurrOrNext;
+use mina_curves::pasta::{Fq, Pallas};
+use poly_commitment::srs::SRS;
+use rand::Rng;
+use std::ops::Index;
+
+type Evals = Evaluations<Fq, Radix2EvaluationDomain<Fq>>;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum TestColumn {
+    A,
+    B,
+    C,
+}
+
+impl FoldingColumnTrait for TestColumn {
+    fn kind(&self) -> ColumnKind {
+        ColumnKind::Witness
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum TestChallenge {}
+
+/// The instance is the commitments to the three columns, on Pallas
+#[derive(Debug, Clone)]
+pub struct TestInstance {
+    commitments: [Pallas; 3],
+    alphas: Alphas<Fq>,
+}
+
+impl Instance<Pallas> for TestInstance {
+    fn combine(a: Self, b: Self, challenge: Fq) -> Self {
+        TestInstance {
+            commitments: crate::combine_slices(&a.commitments, &b.commitments, challenge)
+                .try_into()
+                .unwrap(),
+            alphas: Alphas::combine(a.alphas, b.alphas, challenge),
+        }
+    }
+
+    fn alphas(&self) -> &Alphas<Fq> {
+        &self.alphas
+    }
+}
+
+/// The evaluations of the columns `a`, `b` and `c` over the domain
+#[derive(Debug, Clone)]
+pub struct TestWitness([Evals; 3]);
+
+impl Witness<Pallas> for TestWitness {
+    fn combine(a: Self, b: Self, challenge: Fq) -> Self {
+        TestWitness(
+            crate::combine_slices(&a.0, &b.0, challenge)
+                .try_into()
+                .unwrap(),
+        )
+    }
+
+    fn rows(&self) -> usize {
+        self.0[0].evals.len()
+    }
+}
+
+pub struct TestFoldingEnv {
+    instances: [TestInstance; 2],
+    // Corresponds to the omega evaluations, for both sides
+    curr_witnesses: [TestWitness; 2],
+    // Corresponds to the zeta*omega evaluations, for both sides
+    next_witnesses: [TestWitness; 2],
+}
+
+impl FoldingEnv<Fq, TestInstance, TestWitness, TestColumn, TestChallenge, ()> for TestFoldingEnv {
+    type Structure = ();
+
+    fn new(
+        _structure: &Self::Structure,
+        instances: [&TestInstance; 2],
+        witnesses: [&TestWitness; 2],
+    ) -> Self {
+        let curr_witnesses = [witnesses[0].clone(), witnesses[1].clone()];
+        let mut next_witnesses = curr_witnesses.clone();
+        for side in next_witnesses.iter_mut() {
+            for col in side.0.iter_mut() {
+                col.evals.rotate_left(1);
+            }
+        }
+        TestFoldingEnv {
+            instances: [instances[0].clone(), instances[1].clone()],
+            curr_witnesses,
+            next_witnesses,
+        }
+    }
+
+    fn domain_size(&self) -> usize {
+        self.curr_witnesses[0].rows()
+    }
+
+    fn col(&self, col: TestColumn, curr_or_next: CurrOrNext, side: Side) -> &Vec<Fq> {
+        let wit = match curr_or_next {
+            CurrOrNext::Curr => &self.curr_witnesses[side as usize],
+            CurrOrNext::Next => &self.next_witnesses[side as usize],
+        };
+        &wit[col].evals
+    }
+
+    fn challenge(&self, challenge: TestChallenge, _side: Side) -> Fq {
+        match challenge {}
+    }
+
+    fn alpha(&self, i: usize, side: Side) -> Fq {
+        let instance = &self.instances[side as usize];
+        instance.alphas.get(i).unwrap()
+    }
+
+    fn selector(&self, _s: &(), _side: Side) -> &Vec<Fq> {
+        unreachable!("this example does not use selectors")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TestFoldingConfig;
+
+impl FoldingConfig for TestFoldingConfig {
+    type Structure = ();
+    type Column = TestColumn;
+    type Selector = ();
+    type Challenge = TestChallenge;
+    type Curve = Pallas;
+    type Srs = SRS<Pallas>;
+    type Instance = TestInstance;
+    type Witness = TestWitness;
+    type Env = TestFoldingEnv;
+
+    fn identity_instance(
+        _structure: &Self::Structure,
+        _srs: &Self::Srs,
+        domain: Radix2EvaluationDomain<Fq>,
+        alphas: Alphas<Fq>,
+    ) -> (TestInstance, TestWitness) {
+        let instance = TestInstance {
+            commitments: [Pallas::zero(); 3],
+            alphas,
+        };
+        let witness = TestWitness(std::array::from_fn(|_| {
+            Evaluations::from_vec_and_domain(vec![Fq::zero(); domain.size()], domain)
+        }));
+        (instance, witness)
+    }
+}
+
+fn constraints() -> Vec<FoldingCompatibleExpr<TestFoldingConfig>> {
+    let [a, b, c] = [TestColumn::A, TestColumn::B, TestColumn::C].map(cell);
+    vec![a * b - c]
+}
+
+impl Index<TestChallenge> for TestInstance {
+    type Output = Fq;
+
+    fn index(&self, index: TestChallenge) -> &Self::Output {
+        match index {}
+    }
+}
+
+impl Index<TestColumn> for TestWitness {
+    type Output = Evals;
+
+    fn index(&self, index: TestColumn) -> &Self::Output {
+        &self.0[index as usize]
+    }
+}
+
+impl Index<()> for TestWitness {
+    type Output = Evals;
+
+    fn index(&self, _index: ()) -> &Self::Output {
+        unreachable!("this example does not use selectors")
+    }
+}
+
+impl Checker<TestFoldingConfig> for Provider<TestFoldingConfig> {}
+impl Checker<TestFoldingConfig> for ExtendedProvider<TestFoldingConfig> {}
+
+/// Creates a trace with random values of `a` and `b`, and `c = a b`, along
+/// with its instance
+fn random_trace<RNG: Rng>(
+    rng: &mut RNG,
+    srs: &<TestFoldingConfig as FoldingConfig>::Srs,
+    domain: Radix2EvaluationDomain<Fq>,
+) -> (TestInstance, TestWitness) {
+    let a: Vec<Fq> = (0..domain.size()).map(|_| Fq::rand(rng)).collect();
+    let b: Vec<Fq> = (0..domain.size()).map(|_| Fq::rand(rng)).collect();
+    let c = a.iter().zip(&b).map(|(a, b)| *a * b).collect();
+    let witness =
+        TestWitness([a, b, c].map(|evals| Evaluations::from_vec_and_domain(evals, domain)));
+    let instance = TestInstance {
+        commitments: crate::commit_all(srs, domain, &witness.0)
+            .try_into()
+            .unwrap(),
+        alphas: Alphas::new(Fq::rand(rng)),
+    };
+    (instance, witness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FoldingScheme;
+    use kimchi::curve::KimchiCurve;
+    use mina_curves::pasta::PallasParameters;
+    use mina_poseidon::{constants::PlonkSpongeConstantsKimchi, sponge::DefaultFqSponge, FqSponge};
+
+    type BaseSponge = DefaultFqSponge<PallasParameters, PlonkSpongeConstantsKimchi>;
+
+    #[test]
+    fn test_folding_over_pallas() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let domain = Radix2EvaluationDomain::<Fq>::new(4).unwrap();
+        let srs = crate::make_srs::<Pallas>(domain);
+        let mut fq_sponge = BaseSponge::new(Pallas::other_curve_sponge_params());
+
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &());
+
+        let left = random_trace(&mut rng, &srs, domain);
+        let right = random_trace(&mut rng, &srs, domain);
+        for (instance, witness) in [&left, &right] {
+            let checker = Provider::<TestFoldingConfig>::new(instance.clone(), witness.clone());
+            constraints().iter().for_each(|c| checker.check(c));
+        }
+
+        let (folded_instance, folded_witness, _) =
+            scheme.fold_instance_witness_pair(left, right, &mut fq_sponge);
+        let checker = ExtendedProvider::new(folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+}
@@ -0,0 +1,295 @@
+//! This file shows how to fold traces containing a running-product argument,
+//! the building block of permutation arguments, with a single constraint
+//! `z(ωX) (a(X) + γ) - z(X) (b(X) + γ) = 0` showing that the column `b` is a
+//! permutation of the column `a`.
+//! See [crate::expressions::running_product_constraint] for a description of
+//! the cross term.
+use crate::{
+    checker::{Checker, ExtendedProvider, Provider},
+    error_term::Side,
+    examples::{Curve, Fp},
+    expressions::{running_product_constraint, ColumnKind, FoldingColumnTrait},
+    Alphas, FoldingCompatibleExpr, FoldingConfig, FoldingEnv, Instance, Witness,
+};
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{Field, One, UniformRand, Zero};
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
+use itertools::Itertools;
+use kimchi::circuits::gate::CurrOrNext;
+use poly_commitment::{srs::SRS, SRS as _};
+use rand::{seq::SliceRandom, Rng};
+use std::ops::Index;
+
+const DOMAIN_SIZE: usize = 4;
+
+// The columns: the values `a`, their permutation `b` and the running product
+// `z`, all of them being witness columns
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum TestColumn {
+    A,
+    B,
+    Z,
+}
+
+impl FoldingColumnTrait for TestColumn {
+    fn kind(&self) -> ColumnKind {
+        match self {
+            TestColumn::A | TestColumn::B | TestColumn::Z => ColumnKind::Witness,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum TestChallenge {
+    Gamma,
+}
+
+/// The instance is the commitments to the columns and the challenge γ used by
+/// the running product
+#[derive(Debug, Clone)]
+pub struct TestInstance {
+    commitments: [Curve; 3],
+    challenges: [Fp; 1],
+    alphas: Alphas<Fp>,
+}
+
+impl Instance<Curve> for TestInstance {
+    fn combine(a: Self, b: Self, challenge: Fp) -> Self {
+        TestInstance {
+            commitments: std::array::from_fn(|i| {
+                a.commitments[i] + b.commitments[i].mul(challenge).into_affine()
+            }),
+            challenges: std::array::from_fn(|i| a.challenges[i] + challenge * b.challenges[i]),
+            alphas: Alphas::combine(a.alphas, b.alphas, challenge),
+        }
+    }
+
+    fn alphas(&self) -> &Alphas<Fp> {
+        &self.alphas
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestWitness {
+    cols: [Evaluations<Fp, Radix2EvaluationDomain<Fp>>; 3],
+}
+
+impl Witness<Curve> for TestWitness {
+    fn combine(mut a: Self, b: Self, challenge: Fp) -> Self {
+        for (a, b) in a.cols.iter_mut().zip(b.cols) {
+            for (a, b) in a.evals.iter_mut().zip(b.evals) {
+                *a += challenge * b;
+            }
+        }
+        a
+    }
+
+    fn rows(&self) -> usize {
+        self.cols[0].evals.len()
+    }
+}
+
+pub struct TestFoldingEnv {
+    instances: [TestInstance; 2],
+    // Corresponds to the omega evaluations, for both sides
+    curr_witnesses: [TestWitness; 2],
+    // Corresponds to the zeta*omega evaluations, for both sides
+    // This is curr_witness but left shifted by 1
+    next_witnesses: [TestWitness; 2],
+}
+
+impl FoldingEnv<Fp, TestInstance, TestWitness, TestColumn, TestChallenge, ()> for TestFoldingEnv {
+    type Structure = ();
+
+    fn new(
+        _structure: &Self::Structure,
+        instances: [&TestInstance; 2],
+        witnesses: [&TestWitness; 2],
+    ) -> Self {
+        let curr_witnesses = [witnesses[0].clone(), witnesses[1].clone()];
+        let mut next_witnesses = curr_witnesses.clone();
+        for side in next_witnesses.iter_mut() {
+            for col in side.cols.iter_mut() {
+                col.evals.rotate_left(1);
+            }
+        }
+        TestFoldingEnv {
+            instances: [instances[0].clone(), instances[1].clone()],
+            curr_witnesses,
+            next_witnesses,
+        }
+    }
+
+    fn domain_size(&self) -> usize {
+        DOMAIN_SIZE
+    }
+
+    fn col(&self, col: TestColumn, curr_or_next: CurrOrNext, side: Side) -> &Vec<Fp> {
+        let wit = match curr_or_next {
+            CurrOrNext::Curr => &self.curr_witnesses[side as usize],
+            CurrOrNext::Next => &self.next_witnesses[side as usize],
+        };
+        &wit[col].evals
+    }
+
+    fn challenge(&self, challenge: TestChallenge, side: Side) -> Fp {
+        self.instances[side as usize][challenge]
+    }
+
+    fn alpha(&self, i: usize, side: Side) -> Fp {
+        let instance = &self.instances[side as usize];
+        instance.alphas.get(i).unwrap()
+    }
+
+    fn selector(&self, _s: &(), _side: Side) -> &Vec<Fp> {
+        unreachable!("no dynamic selectors in this example")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TestFoldingConfig;
+
+impl FoldingConfig for TestFoldingConfig {
+    type Structure = ();
+    type Column = TestColumn;
+    type Selector = ();
+    type Challenge = TestChallenge;
+    type Curve = Curve;
+    type Srs = SRS<Curve>;
+    type Instance = TestInstance;
+    type Witness = TestWitness;
+    type Env = TestFoldingEnv;
+
+    fn identity_instance(
+        _structure: &Self::Structure,
+        _srs: &Self::Srs,
+        domain: Radix2EvaluationDomain<Fp>,
+        alphas: Alphas<Fp>,
+    ) -> (TestInstance, TestWitness) {
+        let instance = TestInstance {
+            commitments: [Curve::zero(); 3],
+            challenges: [Fp::zero(); 1],
+            alphas,
+        };
+        let zero_col = || Evaluations::from_vec_and_domain(vec![Fp::zero(); domain.size()], domain);
+        (
+            instance,
+            TestWitness {
+                cols: std::array::from_fn(|_| zero_col()),
+            },
+        )
+    }
+}
+
+fn constraints() -> Vec<FoldingCompatibleExpr<TestFoldingConfig>> {
+    vec![running_product_constraint(
+        TestColumn::Z,
+        TestColumn::A,
+        TestColumn::B,
+        TestChallenge::Gamma,
+    )]
+}
+
+impl Index<TestChallenge> for TestInstance {
+    type Output = Fp;
+
+    fn index(&self, index: TestChallenge) -> &Self::Output {
+        match index {
+            TestChallenge::Gamma => &self.challenges[0],
+        }
+    }
+}
+
+impl Index<TestColumn> for TestWitness {
+    type Output = Evaluations<Fp, Radix2EvaluationDomain<Fp>>;
+
+    fn index(&self, index: TestColumn) -> &Self::Output {
+        match index {
+            TestColumn::A => &self.cols[0],
+            TestColumn::B => &self.cols[1],
+            TestColumn::Z => &self.cols[2],
+        }
+    }
+}
+
+impl Index<()> for TestWitness {
+    type Output = Evaluations<Fp, Radix2EvaluationDomain<Fp>>;
+
+    fn index(&self, _index: ()) -> &Self::Output {
+        unreachable!("no dynamic selectors in this example")
+    }
+}
+
+impl Checker<TestFoldingConfig> for Provider<TestFoldingConfig> {}
+impl Checker<TestFoldingConfig> for ExtendedProvider<TestFoldingConfig> {}
+
+/// Creates a trace where `b` is a random permutation of `a`, together with
+/// its instance. As the running product depends on γ, the challenge is
+/// sampled before computing `z`.
+fn permutation_trace<RNG: Rng>(
+    rng: &mut RNG,
+    srs: &<TestFoldingConfig as FoldingConfig>::Srs,
+    domain: Radix2EvaluationDomain<Fp>,
+) -> (TestInstance, TestWitness) {
+    let a: Vec<Fp> = (0..DOMAIN_SIZE).map(|_| Fp::rand(rng)).collect();
+    let mut b = a.clone();
+    b.shuffle(rng);
+
+    let gamma = Fp::rand(rng);
+    let mut z = vec![Fp::one()];
+    for i in 0..DOMAIN_SIZE - 1 {
+        let ratio = (b[i] + gamma) * (a[i] + gamma).inverse().unwrap();
+        z.push(z[i] * ratio);
+    }
+
+    let witness = TestWitness {
+        cols: [a, b, z].map(|evals| Evaluations::from_vec_and_domain(evals, domain)),
+    };
+    let commitments = witness
+        .cols
+        .iter()
+        .map(|w| srs.commit_evaluations_non_hiding(domain, w))
+        .map(|c| c.elems[0])
+        .collect_vec();
+    let instance = TestInstance {
+        commitments: commitments.try_into().unwrap(),
+        challenges: [gamma],
+        alphas: Alphas::new(Fp::rand(rng)),
+    };
+    (instance, witness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{examples::BaseSponge, CrossTerms, FoldingScheme};
+    use kimchi::curve::KimchiCurve;
+    use mina_poseidon::FqSponge;
+    use rand::thread_rng;
+
+    #[test]
+    fn test_folding_permutation_argument() {
+        let mut rng = thread_rng();
+        let domain = Radix2EvaluationDomain::<Fp>::new(DOMAIN_SIZE).unwrap();
+        let mut srs = SRS::<Curve>::create(DOMAIN_SIZE);
+        srs.add_lagrange_basis(domain);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+
+        let (scheme, final_constraint) =
+            FoldingScheme::<TestFoldingConfig>::new(constraints(), &srs, domain, &());
+
+        let left = permutation_trace(&mut rng, &srs, domain);
+        let right = permutation_trace(&mut rng, &srs, domain);
+
+        // both traces satisfy the running product on their own
+        for (instance, witness) in [&left, &right] {
+            let checker = Provider::<TestFoldingConfig>::new(instance.clone(), witness.clone());
+            constraints().iter().for_each(|c| checker.check(c));
+        }
+
+        let (folded_instance, folded_witness, CrossTerms { t0: _, t1: _ }) =
+            scheme.fold_instance_witness_pair(left, right, &mut fq_sponge);
+        let checker = ExtendedProvider::new(folded_instance, folded_witness);
+        checker.check(&final_constraint);
+    }
+}
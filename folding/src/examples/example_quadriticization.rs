@@ -4,12 +4,12 @@ use crate::{
     checker::{Checker, ExtendedProvider},
     error_term::Side,
     examples::{example_decomposable_folding::TestWitness, BaseSponge, Curve, Fp},
-    expressions::{FoldingColumnTrait, FoldingCompatibleExprInner},
-    Alphas, FoldingCompatibleExpr, FoldingConfig, FoldingEnv, Instance,
+    expressions::{ColumnKind, FoldingColumnTrait, FoldingCompatibleExprInner},
+    Alphas, FoldingCompatibleExpr, FoldingConfig, FoldingEnv, Instance, Witness,
 };
 use ark_ec::{AffineCurve, ProjectiveCurve};
-use ark_ff::UniformRand;
-use ark_poly::{Evaluations, Radix2EvaluationDomain};
+use ark_ff::{UniformRand, Zero};
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
 use itertools::Itertools;
 use kimchi::circuits::{expr::Variable, gate::CurrOrNext};
 use poly_commitment::{srs::SRS, SRS as _};
@@ -34,9 +34,9 @@ pub enum DynamicSelector {
 
 impl FoldingColumnTrait for TestColumn {
     //in this case we have only witness, the other example shows non-witness columns
-    fn is_witness(&self) -> bool {
+    fn kind(&self) -> ColumnKind {
         match self {
-            TestColumn::A | TestColumn::B | TestColumn::C => true,
+            TestColumn::A | TestColumn::B | TestColumn::C => ColumnKind::Witness,
         }
     }
 }
@@ -113,9 +113,7 @@ impl FoldingEnv<Fp, TestInstance, TestWitness, TestColumn, TestChallenge, Dynami
     }
 
     fn domain_size(&self) -> usize {
-        // this works in the example but is not the best way as the envionment
-        // could get circuits of any size
-        2
+        self.curr_witnesses[0].rows()
     }
 
     // provide access to columns, here side refers to one of the two pairs you
@@ -197,7 +195,7 @@ pub struct TestFoldingConfig;
 
 #[allow(dead_code)]
 // Does not contain alpha because it should be added to the expressions by folding
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub enum TestChallenge {
     Beta,
     Gamma,
@@ -214,6 +212,24 @@ impl FoldingConfig for TestFoldingConfig {
     type Instance = TestInstance;
     type Witness = TestWitness;
     type Env = TestFoldingEnv;
+
+    // the multiplication gate is of degree 3 and quadraticized
+    const NUM_CROSS_TERMS: usize = 2;
+
+    fn identity_instance(
+        _structure: &Self::Structure,
+        _srs: &Self::Srs,
+        domain: Radix2EvaluationDomain<Fp>,
+        alphas: Alphas<Fp>,
+    ) -> (TestInstance, TestWitness) {
+        let instance = TestInstance {
+            commitments: [Curve::zero(); 5],
+            challenges: [Fp::zero(); 3],
+            alphas,
+        };
+        let zero_col = || Evaluations::from_vec_and_domain(vec![Fp::zero(); domain.size()], domain);
+        (instance, std::array::from_fn(|_| zero_col()))
+    }
 }
 
 //creates an instance from its witness
@@ -323,13 +339,14 @@ mod tests {
 
         // initiallize the scheme, also getting the final single expression for
         // the entire constraint system
-        let (scheme, final_constraint) = DecomposableFoldingScheme::<TestFoldingConfig>::new(
-            constraints.clone(),
-            vec![],
-            &srs,
-            domain,
-            &(),
-        );
+        let (scheme, final_constraint) =
+            DecomposableFoldingScheme::<TestFoldingConfig>::new_quadraticized(
+                constraints.clone(),
+                vec![],
+                &srs,
+                domain,
+                &(),
+            );
 
         // some inputs to be used by both add and mul
         let inputs1 = [[4u32, 2u32], [2u32, 1u32]];
@@ -365,7 +382,7 @@ mod tests {
                 Some(DynamicSelector::SelecAdd),
                 &mut fq_sponge,
             );
-            let (folded_instance, folded_witness, [_t0, _t1]) = folded;
+            let (folded_instance, folded_witness, _) = folded;
             let checker = ExtendedProvider::new(folded_instance, folded_witness);
             checker.check(&final_constraint);
             let ExtendedProvider {
@@ -393,7 +410,7 @@ mod tests {
                 Some(DynamicSelector::SelecMul),
                 &mut fq_sponge,
             );
-            let (folded_instance, folded_witness, [_t0, _t1]) = folded;
+            let (folded_instance, folded_witness, _) = folded;
 
             let checker = ExtendedProvider::new(folded_instance, folded_witness);
 
@@ -409,11 +426,48 @@ mod tests {
         {
             // here we use already relaxed pairs, which have a trival x -> x implementation
             let folded = scheme.fold_instance_witness_pair(left, right, None, &mut fq_sponge);
-            let (folded_instance, folded_witness, [_t0, _t1]) = folded;
+            let (folded_instance, folded_witness, _) = folded;
 
             let checker = ExtendedProvider::new(folded_instance, folded_witness);
 
             checker.check(&final_constraint);
         };
     }
+
+    #[test]
+    fn test_cross_terms_array() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+
+        let (scheme, final_constraint) =
+            DecomposableFoldingScheme::<TestFoldingConfig>::new_quadraticized(
+                constraints(),
+                vec![],
+                &srs,
+                domain,
+                &(),
+            );
+        assert_eq!(scheme.degree(), 3);
+        assert_eq!(
+            scheme.cross_terms_count(),
+            TestFoldingConfig::NUM_CROSS_TERMS
+        );
+
+        let make_pair = |a, b| {
+            let witness = int_to_witness(mul_witness(a, b), domain);
+            (instance_from_witness(&witness, &srs, domain), witness)
+        };
+        let (instance, witness, cross_terms): (_, _, [_; TestFoldingConfig::NUM_CROSS_TERMS]) =
+            scheme.fold_instance_witness_pair_array(
+                make_pair([4, 2], [2, 1]),
+                make_pair([5, 6], [4, 3]),
+                Some(DynamicSelector::SelecMul),
+                &mut fq_sponge,
+            );
+        assert_eq!(cross_terms.len(), 2);
+        let checker = ExtendedProvider::new(instance, witness);
+        checker.check(&final_constraint);
+    }
 }
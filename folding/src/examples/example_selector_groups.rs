@@ -0,0 +1,402 @@
+//! This file shows how to fold circuits using several groups of dynamic
+//! selectors, with [DecomposableFoldingScheme::new_with_groups].
+//! Each row is either an addition or a subtraction, and is either enabled, or
+//! disabled in which case every column is zero, e.g. for padding. The two
+//! dimensions are independent, and exactly one selector of each group is
+//! enabled in each row.
+//! The columns and challenges are the ones of
+//! [example_decomposable_folding](super::example_decomposable_folding).
+use crate::{
+    checker::{Checker, ExtendedProvider},
+    decomposable_folding::DecomposableFoldingScheme,
+    error_term::Side,
+    examples::{
+        example_decomposable_folding::{TestChallenge, TestColumn},
+        Curve, Fp,
+    },
+    expressions::{cell, FoldingCompatibleExpr},
+    transcript::DeterministicTranscript,
+    Alphas, FoldingConfig, FoldingEnv, Witness,
+};
+use ark_ff::Zero;
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
+use kimchi::circuits::gate::CurrOrNext;
+use mina_poseidon::FqSponge;
+use poly_commitment::{srs::SRS, SRS as _};
+use std::{collections::BTreeMap, ops::Index};
+
+/// The dynamic selectors, the first two describing the operation of the row
+/// and the last two whether the row is enabled
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum GroupSelector {
+    Add,
+    Sub,
+    Enabled,
+    Disabled,
+}
+
+/// The commitments to the 3 columns and the 4 dynamic selectors, and the
+/// challenges
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupInstance {
+    commitments: [Curve; 7],
+    challenges: [Fp; 3],
+    alphas: Alphas<Fp>,
+}
+
+crate::impl_instance!(
+    Curve,
+    GroupInstance {
+        commitments: [commitments],
+        scalars: [challenges],
+        alphas: alphas,
+    }
+);
+
+/// The 3 columns followed by the 4 dynamic selectors
+pub type GroupWitness = [Evaluations<Fp, Radix2EvaluationDomain<Fp>>; 7];
+
+impl Witness<Curve> for GroupWitness {
+    fn combine(mut a: Self, b: Self, challenge: Fp) -> Self {
+        for (a, b) in a.iter_mut().zip(b) {
+            for (a, b) in a.evals.iter_mut().zip(b.evals) {
+                *a += challenge * b;
+            }
+        }
+        a
+    }
+
+    fn rows(&self) -> usize {
+        self[0].evals.len()
+    }
+}
+
+pub struct GroupFoldingEnv {
+    instances: [GroupInstance; 2],
+    // Corresponds to the omega evaluations, for both sides
+    curr_witnesses: [GroupWitness; 2],
+    // Corresponds to the zeta*omega evaluations, for both sides
+    next_witnesses: [GroupWitness; 2],
+}
+
+impl FoldingEnv<Fp, GroupInstance, GroupWitness, TestColumn, TestChallenge, GroupSelector>
+    for GroupFoldingEnv
+{
+    type Structure = ();
+
+    fn new(
+        _structure: &Self::Structure,
+        instances: [&GroupInstance; 2],
+        witnesses: [&GroupWitness; 2],
+    ) -> Self {
+        let curr_witnesses = [witnesses[0].clone(), witnesses[1].clone()];
+        let mut next_witnesses = curr_witnesses.clone();
+        for side in next_witnesses.iter_mut() {
+            for col in side.iter_mut() {
+                col.evals.rotate_left(1);
+            }
+        }
+        GroupFoldingEnv {
+            instances: [instances[0].clone(), instances[1].clone()],
+            curr_witnesses,
+            next_witnesses,
+        }
+    }
+
+    fn domain_size(&self) -> usize {
+        self.curr_witnesses[0].rows()
+    }
+
+    fn col(&self, col: TestColumn, curr_or_next: CurrOrNext, side: Side) -> &Vec<Fp> {
+        let wit = match curr_or_next {
+            CurrOrNext::Curr => &self.curr_witnesses[side as usize],
+            CurrOrNext::Next => &self.next_witnesses[side as usize],
+        };
+        &wit[col].evals
+    }
+
+    fn challenge(&self, challenge: TestChallenge, side: Side) -> Fp {
+        self.instances[side as usize][challenge]
+    }
+
+    fn alpha(&self, i: usize, side: Side) -> Fp {
+        let instance = &self.instances[side as usize];
+        instance.alphas.get(i).unwrap()
+    }
+
+    fn selector(&self, s: &GroupSelector, side: Side) -> &Vec<Fp> {
+        &self.curr_witnesses[side as usize][*s].evals
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GroupFoldingConfig;
+
+impl FoldingConfig for GroupFoldingConfig {
+    type Structure = ();
+    type Column = TestColumn;
+    type Selector = GroupSelector;
+    type Challenge = TestChallenge;
+    type Curve = Curve;
+    type Srs = SRS<Curve>;
+    type Instance = GroupInstance;
+    type Witness = GroupWitness;
+    type Env = GroupFoldingEnv;
+
+    fn identity_instance(
+        _structure: &Self::Structure,
+        _srs: &Self::Srs,
+        domain: Radix2EvaluationDomain<Fp>,
+        alphas: Alphas<Fp>,
+    ) -> (GroupInstance, GroupWitness) {
+        let instance = GroupInstance {
+            commitments: [Curve::zero(); 7],
+            challenges: [Fp::zero(); 3],
+            alphas,
+        };
+        let zero_col = || Evaluations::from_vec_and_domain(vec![Fp::zero(); domain.size()], domain);
+        (instance, std::array::from_fn(|_| zero_col()))
+    }
+}
+
+// the addition and subtraction gates, and the enabled and disabled rows, the
+// columns of the latter being zero
+fn constraints() -> Vec<BTreeMap<GroupSelector, Vec<FoldingCompatibleExpr<GroupFoldingConfig>>>> {
+    let [a, b, c] = [TestColumn::A, TestColumn::B, TestColumn::C].map(cell);
+
+    let add = &a + &b - c.clone();
+    let sub = a.clone() - b.clone() - c.clone();
+    let operations = [
+        (GroupSelector::Add, vec![add]),
+        (GroupSelector::Sub, vec![sub]),
+    ];
+    let flags = [
+        (GroupSelector::Enabled, vec![]),
+        (GroupSelector::Disabled, vec![a, b, c]),
+    ];
+    vec![
+        operations.into_iter().collect(),
+        flags.into_iter().collect(),
+    ]
+}
+
+// creates an instance from its witness, with reproducible challenges
+fn instance_from_witness(
+    witness: &GroupWitness,
+    srs: &SRS<Curve>,
+    domain: Radix2EvaluationDomain<Fp>,
+) -> GroupInstance {
+    let commitments = witness
+        .iter()
+        .map(|w| srs.commit_evaluations_non_hiding(domain, w).elems[0]);
+    let commitments: Vec<_> = commitments.collect();
+    let mut transcript = DeterministicTranscript::<Curve>::new(0);
+    let challenges = [(); 3].map(|_| transcript.challenge());
+    let alphas = Alphas::new(transcript.challenge());
+    GroupInstance {
+        commitments: commitments.try_into().unwrap(),
+        challenges,
+        alphas,
+    }
+}
+
+impl Checker<GroupFoldingConfig> for ExtendedProvider<GroupFoldingConfig> {}
+
+impl Index<TestChallenge> for GroupInstance {
+    type Output = Fp;
+
+    fn index(&self, index: TestChallenge) -> &Self::Output {
+        match index {
+            TestChallenge::Beta => &self.challenges[0],
+            TestChallenge::Gamma => &self.challenges[1],
+            TestChallenge::JointCombiner => &self.challenges[2],
+        }
+    }
+}
+
+impl Index<TestColumn> for GroupWitness {
+    type Output = Evaluations<Fp, Radix2EvaluationDomain<Fp>>;
+
+    fn index(&self, index: TestColumn) -> &Self::Output {
+        match index {
+            TestColumn::A => &self[0],
+            TestColumn::B => &self[1],
+            TestColumn::C => &self[2],
+        }
+    }
+}
+
+impl Index<GroupSelector> for GroupWitness {
+    type Output = Evaluations<Fp, Radix2EvaluationDomain<Fp>>;
+
+    fn index(&self, index: GroupSelector) -> &Self::Output {
+        match index {
+            GroupSelector::Add => &self[3],
+            GroupSelector::Sub => &self[4],
+            GroupSelector::Enabled => &self[5],
+            GroupSelector::Disabled => &self[6],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{examples::BaseSponge, RelaxedInstance, RelaxedWitness};
+    use ark_poly::Radix2EvaluationDomain as D;
+    use kimchi::curve::KimchiCurve;
+    use GroupSelector::*;
+
+    // a witness of 2 rows with the given selectors, the output being computed
+    // by the operation, and zero when the rows are disabled
+    fn witness(
+        [operation, flag]: [GroupSelector; 2],
+        a: [u32; 2],
+        b: [u32; 2],
+        domain: Radix2EvaluationDomain<Fp>,
+    ) -> GroupWitness {
+        let [a, b] = match flag {
+            Disabled => [[0, 0], [0, 0]],
+            _ => [a, b],
+        };
+        let c = match operation {
+            Add => [a[0] + b[0], a[1] + b[1]],
+            _ => [a[0] - b[0], a[1] - b[1]],
+        };
+        let enabled = |s| {
+            if [operation, flag].contains(&s) {
+                [1, 1]
+            } else {
+                [0, 0]
+            }
+        };
+        let columns = [
+            a,
+            b,
+            c,
+            enabled(Add),
+            enabled(Sub),
+            enabled(Enabled),
+            enabled(Disabled),
+        ];
+        columns.map(|col| Evaluations::from_vec_and_domain(col.map(Fp::from).to_vec(), domain))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn relaxed_pair(
+        scheme: &DecomposableFoldingScheme<GroupFoldingConfig>,
+        witness: GroupWitness,
+        srs: &SRS<Curve>,
+        domain: Radix2EvaluationDomain<Fp>,
+    ) -> (
+        RelaxedInstance<Curve, GroupInstance>,
+        RelaxedWitness<Curve, GroupWitness>,
+    ) {
+        scheme.relax(instance_from_witness(&witness, srs, domain), witness)
+    }
+
+    #[test]
+    fn test_fold_with_selector_groups() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let (scheme, final_constraint) =
+            DecomposableFoldingScheme::<GroupFoldingConfig>::new_with_groups(
+                constraints(),
+                vec![],
+                &srs,
+                domain,
+                &(),
+            );
+        // each constraint is multiplied by a single selector
+        assert_eq!(scheme.degree(), 2);
+
+        let pair = |selectors, a, b| {
+            let witness = witness(selectors, a, b, domain);
+            (instance_from_witness(&witness, &srs, domain), witness)
+        };
+
+        // folding enabled additions, assuming both selectors
+        let mut fq_sponge = BaseSponge::new(Curve::other_curve_sponge_params());
+        let (instance, witness, _) = scheme.fold_instance_witness_pair_with_selectors(
+            pair([Add, Enabled], [4, 2], [2, 1]),
+            pair([Add, Enabled], [5, 6], [4, 3]),
+            &[Add, Enabled],
+            &mut fq_sponge,
+        );
+        ExtendedProvider::new(instance, witness).check(&final_constraint);
+
+        // folding an enabled subtraction with a disabled row, assuming only
+        // the operation
+        let (instance, witness, _) = scheme.fold_instance_witness_pair_with_selectors(
+            pair([Sub, Enabled], [9, 8], [3, 1]),
+            pair([Sub, Disabled], [5, 6], [4, 3]),
+            &[Sub],
+            &mut fq_sponge,
+        );
+        ExtendedProvider::new(instance, witness).check(&final_constraint);
+
+        // the selectors of both groups are folded without assumptions
+        let (instance, witness, _) = scheme.fold_instance_witness_pair(
+            pair([Add, Disabled], [1, 2], [3, 4]),
+            pair([Sub, Enabled], [5, 6], [4, 3]),
+            None,
+            &mut fq_sponge,
+        );
+        ExtendedProvider::new(instance, witness).check(&final_constraint);
+    }
+
+    #[test]
+    fn test_selector_groups_wellformedness() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let mut srs = SRS::<Curve>::create(2);
+        srs.add_lagrange_basis(domain);
+
+        let (scheme, final_constraint) =
+            DecomposableFoldingScheme::<GroupFoldingConfig>::new_with_groups(
+                constraints(),
+                vec![],
+                &srs,
+                domain,
+                &(),
+            );
+        let check = |witness| {
+            let (instance, witness) = relaxed_pair(&scheme, witness, &srs, domain);
+            ExtendedProvider::new(instance, witness).check_detailed(&final_constraint)
+        };
+
+        assert!(check(witness([Sub, Disabled], [1, 2], [3, 4], domain)).is_ok());
+
+        // a disabled row must be zero
+        let mut not_zero = witness([Add, Disabled], [1, 2], [3, 4], domain);
+        not_zero[0].evals[1] = Fp::from(1u32);
+        not_zero[2].evals[1] = Fp::from(1u32);
+        assert!(check(not_zero).is_err());
+
+        // exactly one selector of each group is enabled, independently of the
+        // other group
+        let mut both_flags = witness([Add, Enabled], [1, 2], [3, 4], domain);
+        both_flags[6].evals[0] = Fp::from(1u32);
+        assert!(check(both_flags).is_err());
+        let mut no_operation = witness([Add, Enabled], [1, 2], [3, 4], domain);
+        no_operation[3].evals[0] = Fp::zero();
+        assert!(check(no_operation).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "the groups of selectors must be disjoint")]
+    fn test_overlapping_selector_groups() {
+        let domain = D::<Fp>::new(2).unwrap();
+        let srs = SRS::<Curve>::create(2);
+        let mut groups = constraints();
+        groups[1].insert(Add, vec![]);
+        DecomposableFoldingScheme::<GroupFoldingConfig>::new_with_groups(
+            groups,
+            vec![],
+            &srs,
+            domain,
+            &(),
+        );
+    }
+}
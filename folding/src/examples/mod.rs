@@ -20,5 +20,9 @@ pub type SpongeParams = PlonkSpongeConstantsKimchi;
 pub type BaseSponge = DefaultFqSponge<ark_bn254::g1::Parameters, SpongeParams>;
 
 pub mod example;
+pub mod example_column_kinds;
 pub mod example_decomposable_folding;
+pub mod example_pallas;
+pub mod example_permutation;
 pub mod example_quadriticization;
+pub mod example_selector_groups;
@@ -12,13 +12,15 @@
 //! - [FoldingCompatibleExpr]: an expression that can be used with folding. It
 //! aims to be an intermediate representation from
 //! [kimchi::circuits::expr::Expr]. It can be printed in a human-readable way
-//! using the trait [ToString].
+//! using the trait [std::fmt::Display], or [FoldingCompatibleExpr::pretty].
 //! - [FoldingExp]: an internal representation of a folded expression.
 //! - [IntegratedFoldingExpr]: a simplified expression with all terms separated
 //!
 //! When using the library, the user should:
 //! - Convert an expression from [kimchi::circuits::expr::Expr] into a
-//! [FoldingCompatibleExpr] using the trait [From].
+//! [FoldingCompatibleExpr] using the trait [From], or
+//! [FoldingCompatibleExpr::try_from_expr] to get an error instead of a panic
+//! when the expression cannot be folded.
 //! - Convert a list of [FoldingCompatibleExpr] into a [IntegratedFoldingExpr]
 //! using the function [folding_expression].
 //!
@@ -31,13 +33,18 @@ use crate::{
     FoldingConfig, ScalarField,
 };
 use ark_ec::AffineCurve;
-use ark_ff::One;
+use ark_ff::{Field, One};
 use itertools::Itertools;
 use kimchi::circuits::{
-    expr::{ChallengeTerm, ConstantExprInner, ConstantTerm, ExprInner, Operations, Variable},
+    expr::{
+        ChallengeTerm, ConstantExpr, ConstantExprInner, ConstantTerm, Expr, ExprInner, Operations,
+        Variable,
+    },
     gate::CurrOrNext,
 };
 use num_traits::Zero;
+use std::collections::{BTreeSet, HashSet};
+use thiserror::Error;
 
 /// Describe the degree of a constraint.
 /// Only degree up to `2` is supported.
@@ -74,20 +81,36 @@ impl std::ops::Mul for &Degree {
     }
 }
 
+/// How the values of a column are folded, see [FoldingColumnTrait::kind]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ColumnKind {
+    /// A column of the witness, committed to and folded
+    Witness,
+    /// A fixed (structure) column, shared by the instances and not folded
+    Fixed,
+    /// A dynamic selector stored along the witness, folded like the witness
+    /// columns but not counted in the degree of the constraints
+    DynamicSelector,
+}
+
 pub trait FoldingColumnTrait: Copy + Clone {
-    fn is_witness(&self) -> bool;
+    fn kind(&self) -> ColumnKind;
+
+    fn is_witness(&self) -> bool {
+        self.kind() == ColumnKind::Witness
+    }
 
     // TODO: why witnesses are degree 1, otherwise 0?
     fn degree(&self) -> Degree {
-        match self.is_witness() {
-            true => Degree::One,
-            false => Degree::Zero,
+        match self.kind() {
+            ColumnKind::Witness | ColumnKind::DynamicSelector => Degree::One,
+            ColumnKind::Fixed => Degree::Zero,
         }
     }
 }
 
 /// Extra expressions that can be created by folding
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ExpExtension<C: FoldingConfig> {
     U,
     Error,
@@ -100,7 +123,7 @@ pub enum ExpExtension<C: FoldingConfig> {
 
 /// Components to be used to convert multivariate polynomials into "compatible"
 /// multivariate polynomials that will be translated to folding expressions.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum FoldingCompatibleExprInner<C: FoldingConfig> {
     Constant(<C::Curve as AffineCurve>::ScalarField),
     Challenge(C::Challenge),
@@ -114,7 +137,7 @@ pub enum FoldingCompatibleExprInner<C: FoldingConfig> {
 /// [FoldingCompatibleExpr] using the trait [From].
 /// From there, an expression of type [IntegratedFoldingExpr] can be created
 /// using the function [folding_expression].
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum FoldingCompatibleExpr<C: FoldingConfig> {
     Atom(FoldingCompatibleExprInner<C>),
     Pow(Box<Self>, u64),
@@ -125,60 +148,130 @@ pub enum FoldingCompatibleExpr<C: FoldingConfig> {
     Square(Box<Self>),
 }
 
-/// Implement a human-readable version of a folding compatible expression.
-// FIXME: use Display instead, to follow the recommandation of the trait.
-impl<C: FoldingConfig> ToString for FoldingCompatibleExpr<C> {
-    fn to_string(&self) -> String {
-        match self {
-            FoldingCompatibleExpr::Atom(c) => match c {
-                FoldingCompatibleExprInner::Constant(c) => {
-                    if c.is_zero() {
-                        "0".to_string()
-                    } else {
-                        c.to_string()
-                    }
-                }
-                FoldingCompatibleExprInner::Challenge(c) => {
-                    format!("{:?}", c)
-                }
-                FoldingCompatibleExprInner::Cell(cell) => {
-                    let Variable { col, row } = cell;
-                    let next = match row {
-                        CurrOrNext::Curr => "",
-                        CurrOrNext::Next => " * ω",
-                    };
-                    format!("Col({:?}){}", col, next)
-                }
-                FoldingCompatibleExprInner::Extensions(e) => match e {
-                    ExpExtension::U => "U".to_string(),
-                    ExpExtension::Error => "E".to_string(),
-                    ExpExtension::ExtendedWitness(i) => {
-                        format!("ExWit({})", i)
-                    }
-                    ExpExtension::Alpha(i) => format!("α_{i}"),
-                    ExpExtension::Selector(s) => format!("Selec({:?})", s),
-                },
-            },
-            FoldingCompatibleExpr::Double(e) => {
-                format!("2 {}", e.to_string())
-            }
-            FoldingCompatibleExpr::Square(e) => {
-                format!("{} ^ 2", e.to_string())
+/// A reference to the column `col` at the current row, to be combined with
+/// the arithmetic operators, e.g. `cell(A) + cell(B) - cell(C)`.
+pub fn cell<C: FoldingConfig>(col: C::Column) -> FoldingCompatibleExpr<C> {
+    FoldingCompatibleExpr::Atom(FoldingCompatibleExprInner::Cell(Variable {
+        col,
+        row: CurrOrNext::Curr,
+    }))
+}
+
+macro_rules! impl_binary_op {
+    ($trait:ident, $fn:ident, $variant:ident) => {
+        impl<C: FoldingConfig> std::ops::$trait for FoldingCompatibleExpr<C> {
+            type Output = Self;
+
+            fn $fn(self, rhs: Self) -> Self {
+                Self::$variant(Box::new(self), Box::new(rhs))
             }
-            FoldingCompatibleExpr::Add(e1, e2) => {
-                format!("{} + {}", e1.to_string(), e2.to_string())
+        }
+
+        impl<C: FoldingConfig> std::ops::$trait for &FoldingCompatibleExpr<C> {
+            type Output = FoldingCompatibleExpr<C>;
+
+            fn $fn(self, rhs: Self) -> FoldingCompatibleExpr<C> {
+                FoldingCompatibleExpr::$variant(Box::new(self.clone()), Box::new(rhs.clone()))
             }
-            FoldingCompatibleExpr::Sub(e1, e2) => {
-                format!("{} - {}", e1.to_string(), e2.to_string())
+        }
+    };
+}
+
+impl_binary_op!(Add, add, Add);
+impl_binary_op!(Sub, sub, Sub);
+impl_binary_op!(Mul, mul, Mul);
+
+/// What a [FoldingCompatibleExpr] is made of, see
+/// [FoldingCompatibleExpr::summary]
+#[derive(Clone, Debug)]
+pub struct ExprSummary<C: FoldingConfig> {
+    /// The degree in the witness columns, extended ones included. Selectors,
+    /// challenges, alphas and `u` are not counted.
+    pub degree: usize,
+    /// The columns referenced, witness or not
+    pub columns: HashSet<C::Column>,
+    /// The number of alphas, i.e. one more than the highest alpha used
+    pub alphas: usize,
+}
+
+// Precedences of the operators, used to parenthesize the rendered expressions
+const SUM: u8 = 1;
+const PRODUCT: u8 = 2;
+const POWER: u8 = 3;
+const ATOM: u8 = 4;
+
+impl<C: FoldingConfig> FoldingCompatibleExpr<C> {
+    /// Renders the expression in infix notation, naming the columns with
+    /// `name`. Sub-expressions are only parenthesized when the precedence of
+    /// the operators requires it, e.g. `a * (b + c) - (d - e)`.
+    pub fn pretty(&self, name: &impl Fn(C::Column) -> String) -> String {
+        self.render(name).0
+    }
+
+    /// Renders the expression, returning the precedence of its outermost
+    /// operator along with it
+    fn render(&self, name: &impl Fn(C::Column) -> String) -> (String, u8) {
+        use FoldingCompatibleExpr::*;
+        // renders `e`, parenthesized if its operator binds less than `min`
+        let operand = |e: &Self, min: u8| {
+            let (s, prec) = e.render(name);
+            if prec < min {
+                format!("({s})")
+            } else {
+                s
             }
-            FoldingCompatibleExpr::Mul(e1, e2) => {
-                format!("({}) ({})", e1.to_string(), e2.to_string())
+        };
+        match self {
+            Atom(c) => {
+                let s = match c {
+                    FoldingCompatibleExprInner::Constant(c) => {
+                        if c.is_zero() {
+                            "0".to_string()
+                        } else {
+                            c.to_string()
+                        }
+                    }
+                    FoldingCompatibleExprInner::Challenge(c) => format!("{:?}", c),
+                    FoldingCompatibleExprInner::Cell(Variable { col, row }) => match row {
+                        CurrOrNext::Curr => name(*col),
+                        CurrOrNext::Next => format!("next({})", name(*col)),
+                    },
+                    FoldingCompatibleExprInner::Extensions(e) => match e {
+                        ExpExtension::U => "U".to_string(),
+                        ExpExtension::Error => "E".to_string(),
+                        ExpExtension::ExtendedWitness(i) => format!("ExWit({})", i),
+                        ExpExtension::Alpha(i) => format!("α_{i}"),
+                        ExpExtension::Selector(s) => format!("Selec({:?})", s),
+                    },
+                };
+                (s, ATOM)
             }
-            FoldingCompatibleExpr::Pow(_, _) => todo!(),
+            Add(e1, e2) => (format!("{} + {}", operand(e1, SUM), operand(e2, SUM)), SUM),
+            // the subtrahend is parenthesized when it is itself a sum
+            Sub(e1, e2) => (
+                format!("{} - {}", operand(e1, SUM), operand(e2, PRODUCT)),
+                SUM,
+            ),
+            Mul(e1, e2) => (
+                format!("{} * {}", operand(e1, PRODUCT), operand(e2, PRODUCT)),
+                PRODUCT,
+            ),
+            Double(e) => (format!("2 * {}", operand(e, PRODUCT)), PRODUCT),
+            Square(e) => (format!("{}^2", operand(e, ATOM)), POWER),
+            Pow(e, p) => (format!("{}^{}", operand(e, ATOM), p), POWER),
         }
     }
 }
 
+/// Implement a human-readable version of a folding compatible expression,
+/// naming the columns by their [Debug] representation. See
+/// [FoldingCompatibleExpr::pretty] to name them differently.
+impl<C: FoldingConfig> std::fmt::Display for FoldingCompatibleExpr<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pretty(&|col| format!("{:?}", col)))
+    }
+}
+
 /// Internal expression used for folding.
 /// A "folding" expression is a multivariate polynomial like defined in
 /// [kimchi::circuits::expr] with the following differences.
@@ -235,7 +328,7 @@ impl<C: FoldingConfig> FoldingExp<C> {
 // FIXME: test independently the behavior of pow_to_mul, and explain only why 8
 // maximum
 impl<C: FoldingConfig> FoldingCompatibleExpr<C> {
-    pub(crate) fn simplify(self) -> FoldingExp<C> {
+    pub(crate) fn into_folding_exp(self) -> FoldingExp<C> {
         use FoldingExp::*;
         match self {
             FoldingCompatibleExpr::Atom(atom) => match atom {
@@ -256,24 +349,205 @@ impl<C: FoldingConfig> FoldingCompatibleExpr<C> {
                     }
                 }
             },
-            FoldingCompatibleExpr::Double(exp) => Double(Box::new((*exp).simplify())),
-            FoldingCompatibleExpr::Square(exp) => Square(Box::new((*exp).simplify())),
+            FoldingCompatibleExpr::Double(exp) => Double(Box::new((*exp).into_folding_exp())),
+            FoldingCompatibleExpr::Square(exp) => Square(Box::new((*exp).into_folding_exp())),
             FoldingCompatibleExpr::Add(e1, e2) => {
-                let e1 = Box::new(e1.simplify());
-                let e2 = Box::new(e2.simplify());
+                let e1 = Box::new(e1.into_folding_exp());
+                let e2 = Box::new(e2.into_folding_exp());
                 Add(e1, e2)
             }
             FoldingCompatibleExpr::Sub(e1, e2) => {
-                let e1 = Box::new(e1.simplify());
-                let e2 = Box::new(e2.simplify());
+                let e1 = Box::new(e1.into_folding_exp());
+                let e2 = Box::new(e2.into_folding_exp());
                 Sub(e1, e2)
             }
             FoldingCompatibleExpr::Mul(e1, e2) => {
-                let e1 = Box::new(e1.simplify());
-                let e2 = Box::new(e2.simplify());
+                let e1 = Box::new(e1.into_folding_exp());
+                let e2 = Box::new(e2.into_folding_exp());
                 Mul(e1, e2)
             }
-            FoldingCompatibleExpr::Pow(e, p) => Self::pow_to_mul(e.simplify(), p),
+            FoldingCompatibleExpr::Pow(e, p) => Self::pow_to_mul(e.into_folding_exp(), p),
+        }
+    }
+
+    /// Returns the set of challenges referenced by the expression.
+    /// It can be used to check that the environment is able to resolve all of
+    /// them, or to detect challenges that are never used by the constraints.
+    pub fn referenced_challenges(&self) -> BTreeSet<C::Challenge> {
+        let mut challenges = BTreeSet::new();
+        self.collect_challenges(&mut challenges);
+        challenges
+    }
+
+    fn collect_challenges(&self, challenges: &mut BTreeSet<C::Challenge>) {
+        match self {
+            FoldingCompatibleExpr::Atom(FoldingCompatibleExprInner::Challenge(c)) => {
+                challenges.insert(*c);
+            }
+            FoldingCompatibleExpr::Atom(_) => {}
+            FoldingCompatibleExpr::Pow(e, _)
+            | FoldingCompatibleExpr::Double(e)
+            | FoldingCompatibleExpr::Square(e) => e.collect_challenges(challenges),
+            FoldingCompatibleExpr::Add(e1, e2)
+            | FoldingCompatibleExpr::Sub(e1, e2)
+            | FoldingCompatibleExpr::Mul(e1, e2) => {
+                e1.collect_challenges(challenges);
+                e2.collect_challenges(challenges);
+            }
+        }
+    }
+
+    /// Returns the degree of the expression in the witness columns, the
+    /// columns it references and the number of alphas it uses, in a single
+    /// walk of the expression.
+    pub fn summary(&self) -> ExprSummary<C> {
+        let mut summary = ExprSummary {
+            degree: 0,
+            columns: HashSet::new(),
+            alphas: 0,
+        };
+        let degree = self.summarize(&mut summary);
+        ExprSummary { degree, ..summary }
+    }
+
+    /// Collects the columns and the alphas of the expression in `summary`,
+    /// returning its degree
+    fn summarize(&self, summary: &mut ExprSummary<C>) -> usize {
+        use FoldingCompatibleExpr::*;
+        match self {
+            Atom(FoldingCompatibleExprInner::Cell(Variable { col, .. })) => {
+                summary.columns.insert(*col);
+                usize::from(col.is_witness())
+            }
+            Atom(FoldingCompatibleExprInner::Extensions(ExpExtension::ExtendedWitness(_))) => 1,
+            Atom(FoldingCompatibleExprInner::Extensions(ExpExtension::Alpha(i))) => {
+                summary.alphas = summary.alphas.max(i + 1);
+                0
+            }
+            Atom(_) => 0,
+            Pow(e, p) => e.summarize(summary) * *p as usize,
+            Double(e) => e.summarize(summary),
+            Square(e) => e.summarize(summary) * 2,
+            Add(e1, e2) | Sub(e1, e2) => {
+                let d1 = e1.summarize(summary);
+                let d2 = e2.summarize(summary);
+                d1.max(d2)
+            }
+            Mul(e1, e2) => e1.summarize(summary) + e2.summarize(summary),
+        }
+    }
+
+    /// Returns the number of nodes of the expression tree, atoms included
+    pub fn node_count(&self) -> usize {
+        use FoldingCompatibleExpr::*;
+        match self {
+            Atom(_) => 1,
+            Pow(e, _) | Double(e) | Square(e) => 1 + e.node_count(),
+            Add(e1, e2) | Sub(e1, e2) | Mul(e1, e2) => 1 + e1.node_count() + e2.node_count(),
+        }
+    }
+
+    /// Merges the structurally equal operands of a node, bottom-up: `e + e`
+    /// becomes `2 e`, `e * e` becomes `e^2` and `e - e` becomes `0`, so that
+    /// the repeated subtree is only stored and walked once. The resulting
+    /// expression evaluates to the same values, see [Self::node_count] to
+    /// measure the reduction.
+    pub fn dedup(self) -> Self {
+        use FoldingCompatibleExpr::*;
+        match self {
+            Atom(_) => self,
+            Pow(e, p) => Pow(Box::new(e.dedup()), p),
+            Double(e) => Double(Box::new(e.dedup())),
+            Square(e) => Square(Box::new(e.dedup())),
+            Add(e1, e2) => {
+                let (e1, e2) = (e1.dedup(), e2.dedup());
+                if e1 == e2 {
+                    Double(Box::new(e1))
+                } else {
+                    Add(Box::new(e1), Box::new(e2))
+                }
+            }
+            Sub(e1, e2) => {
+                let (e1, e2) = (e1.dedup(), e2.dedup());
+                if e1 == e2 {
+                    Atom(FoldingCompatibleExprInner::Constant(
+                        ScalarField::<C>::zero(),
+                    ))
+                } else {
+                    Sub(Box::new(e1), Box::new(e2))
+                }
+            }
+            Mul(e1, e2) => {
+                let (e1, e2) = (e1.dedup(), e2.dedup());
+                if e1 == e2 {
+                    Square(Box::new(e1))
+                } else {
+                    Mul(Box::new(e1), Box::new(e2))
+                }
+            }
+        }
+    }
+
+    /// Folds the arithmetic between constants and drops the neutral operands,
+    /// bottom-up: `x + 0` and `0 + x` become `x`, `x * 1` and `1 * x` become
+    /// `x`, and `x - 0` becomes `x`. Expressions which are already minimal are
+    /// returned unchanged, and the result evaluates to the same values.
+    pub fn simplify(self) -> Self {
+        use FoldingCompatibleExpr::*;
+        use FoldingCompatibleExprInner::Constant;
+        let constant = |e: &Self| match e {
+            Atom(Constant(c)) => Some(*c),
+            _ => None,
+        };
+        match self {
+            Atom(_) => self,
+            Pow(e, p) => {
+                let e = e.simplify();
+                match constant(&e) {
+                    Some(c) => Atom(Constant(c.pow([p]))),
+                    None => Pow(Box::new(e), p),
+                }
+            }
+            Double(e) => {
+                let e = e.simplify();
+                match constant(&e) {
+                    Some(c) => Atom(Constant(c.double())),
+                    None => Double(Box::new(e)),
+                }
+            }
+            Square(e) => {
+                let e = e.simplify();
+                match constant(&e) {
+                    Some(c) => Atom(Constant(c.square())),
+                    None => Square(Box::new(e)),
+                }
+            }
+            Add(e1, e2) => {
+                let (e1, e2) = (e1.simplify(), e2.simplify());
+                match (constant(&e1), constant(&e2)) {
+                    (Some(c1), Some(c2)) => Atom(Constant(c1 + c2)),
+                    (Some(c), _) if c.is_zero() => e2,
+                    (_, Some(c)) if c.is_zero() => e1,
+                    _ => Add(Box::new(e1), Box::new(e2)),
+                }
+            }
+            Sub(e1, e2) => {
+                let (e1, e2) = (e1.simplify(), e2.simplify());
+                match (constant(&e1), constant(&e2)) {
+                    (Some(c1), Some(c2)) => Atom(Constant(c1 - c2)),
+                    (_, Some(c)) if c.is_zero() => e1,
+                    _ => Sub(Box::new(e1), Box::new(e2)),
+                }
+            }
+            Mul(e1, e2) => {
+                let (e1, e2) = (e1.simplify(), e2.simplify());
+                match (constant(&e1), constant(&e2)) {
+                    (Some(c1), Some(c2)) => Atom(Constant(c1 * c2)),
+                    (Some(c), _) if c.is_one() => e2,
+                    (_, Some(c)) if c.is_one() => e1,
+                    _ => Mul(Box::new(e1), Box::new(e2)),
+                }
+            }
         }
     }
 
@@ -336,11 +610,30 @@ impl<C: FoldingConfig> FoldingExp<C> {
         }
     }
 
+    /// Returns the degree of the expression in the values that are folded,
+    /// i.e. the columns of degree `1` for [Self::folding_degree]. Unlike the
+    /// latter, it is not bounded by `2`.
+    pub(crate) fn total_degree(&self) -> usize {
+        match self {
+            FoldingExp::Atom(_) => match self.folding_degree() {
+                Degree::Zero => 0,
+                _ => 1,
+            },
+            FoldingExp::Double(e) => e.total_degree(),
+            FoldingExp::Square(e) => 2 * e.total_degree(),
+            FoldingExp::Mul(e1, e2) => e1.total_degree() + e2.total_degree(),
+            FoldingExp::Add(e1, e2) | FoldingExp::Sub(e1, e2) => {
+                e1.total_degree().max(e2.total_degree())
+            }
+            FoldingExp::Pow(e, i) => *i as usize * e.total_degree(),
+        }
+    }
+
     /// Convert a folding expression into a compatible one.
     // TODO: explain why do we need it. It is transformations between the two
     // categories FoldingCompatibleExpr and FoldingExpr. Is there a one-to-one
     // conversion?
-    fn into_compatible(self) -> FoldingCompatibleExpr<C> {
+    pub(crate) fn into_compatible(self) -> FoldingCompatibleExpr<C> {
         use FoldingCompatibleExpr::*;
         use FoldingCompatibleExprInner::*;
         match self {
@@ -387,6 +680,50 @@ impl<C: FoldingConfig> FoldingExp<C> {
     }
 }
 
+/// Build the constraint of a running-product argument showing that the values
+/// of the column `b` are a permutation of the values of the column `a`:
+/// ```text
+/// z(ωX) (a(X) + γ) - z(X) (b(X) + γ) = 0
+/// ```
+/// As the next row of the last row is the first one, the constraint holds on
+/// the whole domain if and only if `∏ (a_i + γ) = ∏ (b_i + γ)`, as long as
+/// `z` is not zero. The latter must be enforced by the caller, for instance
+/// with a boundary constraint `z_0 = 1`.
+///
+/// The constraint is of degree `2`, the challenge `γ` being part of the
+/// instance and folded like the witness. Folding two traces with the
+/// challenge `r` gives, before multiplying by the alphas, the cross term
+/// ```text
+/// T = z'_1 (a_2 + γ_2) + z'_2 (a_1 + γ_1) - z_1 (b_2 + γ_2) - z_2 (b_1 + γ_1)
+/// ```
+/// where `z'` is the column `z` on the next row. No special treatment is
+/// required from the scheme, which computes this term as for any other degree
+/// `2` constraint.
+pub fn running_product_constraint<C: FoldingConfig>(
+    z: C::Column,
+    a: C::Column,
+    b: C::Column,
+    gamma: C::Challenge,
+) -> FoldingCompatibleExpr<C> {
+    use FoldingCompatibleExpr::*;
+    let cell = |col, row| {
+        Box::new(Atom(FoldingCompatibleExprInner::Cell(Variable {
+            col,
+            row,
+        })))
+    };
+    let gamma = Box::new(Atom(FoldingCompatibleExprInner::Challenge(gamma)));
+    let lhs = Mul(
+        cell(z, CurrOrNext::Next),
+        Box::new(Add(cell(a, CurrOrNext::Curr), gamma.clone())),
+    );
+    let rhs = Mul(
+        cell(z, CurrOrNext::Curr),
+        Box::new(Add(cell(b, CurrOrNext::Curr), gamma)),
+    );
+    Sub(Box::new(lhs), Box::new(rhs))
+}
+
 // TODO: doc - what is the sign?
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Sign {
@@ -465,6 +802,28 @@ impl<C: FoldingConfig> Default for IntegratedFoldingExpr<C> {
 }
 
 impl<C: FoldingConfig> IntegratedFoldingExpr<C> {
+    /// Returns the highest degree of the terms of the expression
+    pub fn degree(&self) -> Degree {
+        if !self.degree_2.is_empty() {
+            Degree::Two
+        } else if !self.degree_1.is_empty() {
+            Degree::One
+        } else {
+            Degree::Zero
+        }
+    }
+
+    /// Returns the number of alphas used to combine the constraints
+    pub fn alphas_count(&self) -> usize {
+        self.degree_0
+            .iter()
+            .chain(self.degree_1.iter())
+            .chain(self.degree_2.iter())
+            .map(|(_, _, alpha)| alpha + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
     /// Combines constraints into single expression
     pub fn final_expression(self) -> FoldingCompatibleExpr<C> {
         use FoldingCompatibleExpr::*;
@@ -581,7 +940,10 @@ pub fn extract_terms<C: FoldingConfig>(exp: FoldingExp<C>) -> Box<dyn Iterator<I
 pub fn folding_expression<C: FoldingConfig>(
     exps: Vec<FoldingCompatibleExpr<C>>,
 ) -> (IntegratedFoldingExpr<C>, ExtendedWitnessGenerator<C>) {
-    let simplified_expressions = exps.into_iter().map(|exp| exp.simplify()).collect_vec();
+    let simplified_expressions = exps
+        .into_iter()
+        .map(|exp| exp.into_folding_exp())
+        .collect_vec();
     let Quadraticized {
         original_constraints: expressions,
         extra_constraints: extra_expressions,
@@ -759,3 +1121,89 @@ where
         }
     }
 }
+
+/// Errors raised when converting a kimchi expression into a
+/// [FoldingCompatibleExpr]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ExprConversionError {
+    #[error("special constants must be simplified before folding")]
+    SpecialConstant,
+    #[error("the unnormalized Lagrange basis cannot be used in folding expressions")]
+    UnnormalizedLagrangeBasis,
+    #[error("the zero-knowledge vanishing polynomial cannot be used in folding expressions")]
+    VanishesOnZeroKnowledgeAndPreviousRows,
+    #[error("the operation {0} is not supported in folding expressions")]
+    UnsupportedOperation(&'static str),
+}
+
+impl<C: FoldingConfig> FoldingCompatibleExpr<C>
+where
+    C::Challenge: From<ChallengeTerm>,
+{
+    /// Convert a kimchi expression, like the constraints of the zkVM
+    /// circuits, returning an error instead of panicking when it contains a
+    /// term that cannot be folded.
+    pub fn try_from_expr(
+        expr: Expr<ConstantExpr<ScalarField<C>>, C::Column>,
+    ) -> Result<Self, ExprConversionError> {
+        use FoldingCompatibleExpr::*;
+        let rec = |x: Box<Expr<ConstantExpr<ScalarField<C>>, C::Column>>| {
+            Self::try_from_expr(*x).map(Box::new)
+        };
+        let res = match expr {
+            Operations::Atom(ExprInner::Constant(c)) => Self::try_from_constant_expr(c)?,
+            Operations::Atom(ExprInner::Cell(col)) => Atom(FoldingCompatibleExprInner::Cell(col)),
+            Operations::Atom(ExprInner::UnnormalizedLagrangeBasis(_)) => {
+                return Err(ExprConversionError::UnnormalizedLagrangeBasis)
+            }
+            Operations::Atom(ExprInner::VanishesOnZeroKnowledgeAndPreviousRows) => {
+                return Err(ExprConversionError::VanishesOnZeroKnowledgeAndPreviousRows)
+            }
+            Operations::Add(x, y) => Add(rec(x)?, rec(y)?),
+            Operations::Mul(x, y) => Mul(rec(x)?, rec(y)?),
+            Operations::Sub(x, y) => Sub(rec(x)?, rec(y)?),
+            Operations::Double(x) => Double(rec(x)?),
+            Operations::Square(x) => Square(rec(x)?),
+            Operations::Pow(e, p) => Pow(rec(e)?, p),
+            Operations::Cache(_, _) => {
+                return Err(ExprConversionError::UnsupportedOperation("Cache"))
+            }
+            Operations::IfFeature(_, _, _) => {
+                return Err(ExprConversionError::UnsupportedOperation("IfFeature"))
+            }
+        };
+        Ok(res)
+    }
+
+    fn try_from_constant_expr(
+        expr: ConstantExpr<ScalarField<C>>,
+    ) -> Result<Self, ExprConversionError> {
+        use FoldingCompatibleExpr::*;
+        let rec =
+            |x: Box<ConstantExpr<ScalarField<C>>>| Self::try_from_constant_expr(*x).map(Box::new);
+        let res = match expr {
+            Operations::Atom(ConstantExprInner::Challenge(chal)) => {
+                Atom(FoldingCompatibleExprInner::Challenge(chal.into()))
+            }
+            Operations::Atom(ConstantExprInner::Constant(ConstantTerm::Literal(f))) => {
+                Atom(FoldingCompatibleExprInner::Constant(f))
+            }
+            Operations::Atom(ConstantExprInner::Constant(_)) => {
+                return Err(ExprConversionError::SpecialConstant)
+            }
+            Operations::Add(x, y) => Add(rec(x)?, rec(y)?),
+            Operations::Mul(x, y) => Mul(rec(x)?, rec(y)?),
+            Operations::Sub(x, y) => Sub(rec(x)?, rec(y)?),
+            Operations::Double(x) => Double(rec(x)?),
+            Operations::Square(x) => Square(rec(x)?),
+            Operations::Pow(e, p) => Pow(rec(e)?, p),
+            Operations::Cache(_, _) => {
+                return Err(ExprConversionError::UnsupportedOperation("Cache"))
+            }
+            Operations::IfFeature(_, _, _) => {
+                return Err(ExprConversionError::UnsupportedOperation("IfFeature"))
+            }
+        };
+        Ok(res)
+    }
+}
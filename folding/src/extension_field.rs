@@ -0,0 +1,227 @@
+//! Folding with a challenge sampled from the quadratic extension
+//! `F[X] / (X^2 - n)` of the scalar field `F`, `n` being a quadratic
+//! non-residue.
+//!
+//! The soundness error of a fold is `d / |F|`, where `d` is the degree of the
+//! relaxed relation in the challenge, see [FoldingScheme::soundness_bits].
+//! Sampling the challenge from the extension brings it down to `d / |F|^2`,
+//! without changing the curve.
+//!
+//! A value over the extension is represented by its two [Components] over
+//! `F`, for instance the folded witness is a pair of witnesses over `F`. As
+//! the commitments are linear, the commitments to the components are obtained
+//! from the commitments of the folded instances, by multiplying them by the
+//! components of the challenge.
+//!
+//! Only fresh instance-witness pairs can be folded for now: folding
+//! accumulators over the extension requires evaluating the constraints over
+//! the extension to compute the cross terms.
+
+use crate::{
+    error_term::{compute_error, ExtendedEnv},
+    instance_witness::{ExtendedInstance, ExtendedWitness, RelaxablePair},
+    BaseField, FoldingConfig, FoldingScheme, Instance, ScalarField, Witness,
+};
+use ark_ff::{One, PrimeField, SquareRootField};
+use ark_poly::{Evaluations, Radix2EvaluationDomain};
+use mina_poseidon::FqSponge;
+use poly_commitment::{commitment::CommitmentCurve, PolyComm, SRS};
+use std::{
+    collections::BTreeSet,
+    ops::{Add, Mul, Sub},
+};
+
+/// Returns the smallest quadratic non-residue `n` of `F`, used to define the
+/// extension `F[X] / (X^2 - n)`
+pub fn non_residue<F: PrimeField + SquareRootField>() -> F {
+    let mut n = F::one() + F::one();
+    while !n.legendre().is_qnr() {
+        n += F::one();
+    }
+    n
+}
+
+/// An element `c0 + c1 X` of the extension `F[X] / (X^2 - n)`, where `n` is
+/// given by [non_residue]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuadraticExtension<F> {
+    pub c0: F,
+    pub c1: F,
+}
+
+impl<F: PrimeField + SquareRootField> QuadraticExtension<F> {
+    pub fn new(c0: F, c1: F) -> Self {
+        Self { c0, c1 }
+    }
+
+    /// Embeds an element of the base field
+    pub fn from_base(c0: F) -> Self {
+        Self::new(c0, F::zero())
+    }
+
+    /// Samples an element of the extension, squeezing its two components from
+    /// the sponge
+    pub fn challenge<G, Sponge>(fq_sponge: &mut Sponge) -> Self
+    where
+        G: CommitmentCurve<ScalarField = F>,
+        Sponge: FqSponge<G::BaseField, G, F>,
+    {
+        let c0 = fq_sponge.challenge();
+        let c1 = fq_sponge.challenge();
+        Self::new(c0, c1)
+    }
+
+    pub fn square(self) -> Self {
+        self * self
+    }
+}
+
+impl<F: PrimeField + SquareRootField> Add for QuadraticExtension<F> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.c0 + other.c0, self.c1 + other.c1)
+    }
+}
+
+impl<F: PrimeField + SquareRootField> Sub for QuadraticExtension<F> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.c0 - other.c0, self.c1 - other.c1)
+    }
+}
+
+impl<F: PrimeField + SquareRootField> Mul for QuadraticExtension<F> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let c0 = self.c0 * other.c0 + non_residue::<F>() * self.c1 * other.c1;
+        let c1 = self.c0 * other.c1 + self.c1 * other.c0;
+        Self::new(c0, c1)
+    }
+}
+
+/// The two components over the base field of a value over the extension
+#[derive(Clone, Debug, PartialEq)]
+pub struct Components<T> {
+    pub c0: T,
+    pub c1: T,
+}
+
+/// The result of [FoldingScheme::fold_over_extension], the relaxed relation
+/// holding over the extension for `u`, the error and the folded instance and
+/// witness.
+pub struct ExtensionFold<CF: FoldingConfig> {
+    pub instance: Components<ExtendedInstance<CF::Curve, CF::Instance>>,
+    pub witness: Components<ExtendedWitness<CF::Curve, CF::Witness>>,
+    pub u: QuadraticExtension<ScalarField<CF>>,
+    pub error: Components<Evaluations<ScalarField<CF>, Radix2EvaluationDomain<ScalarField<CF>>>>,
+    pub error_commitment: Components<PolyComm<CF::Curve>>,
+    /// Commitments to the cross terms `T_0` and `T_1`, which are over the
+    /// base field as the folded pairs are
+    pub cross_terms: [PolyComm<CF::Curve>; 2],
+    pub challenge: QuadraticExtension<ScalarField<CF>>,
+}
+
+impl<'a, CF: FoldingConfig> FoldingScheme<'a, CF>
+where
+    ScalarField<CF>: SquareRootField,
+{
+    /// Fold two fresh instance-witness pairs with a challenge `r` sampled from
+    /// the quadratic extension of the scalar field.
+    /// The cross terms are computed over the base field as usual, and the
+    /// folded pair is `(a + r_0 b, r_1 b)`, with `u = 1 + r` and
+    /// `E = - r T_0 - r^2 T_1`.
+    pub fn fold_over_extension<Sponge>(
+        &self,
+        a: (CF::Instance, CF::Witness),
+        b: (CF::Instance, CF::Witness),
+        fq_sponge: &mut Sponge,
+    ) -> ExtensionFold<CF>
+    where
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        let relax = |pair: (CF::Instance, CF::Witness)| {
+            RelaxablePair::<CF::Curve, CF::Instance, CF::Witness>::relax(
+                pair,
+                &self.zero_vec,
+                self.zero_commitment.clone(),
+            )
+        };
+        let (a, b) = (relax(a), relax(b));
+        let u = (a.0.u, b.0.u);
+        let env = ExtendedEnv::new(
+            &self.structure,
+            [a.0, b.0],
+            [a.1, b.1],
+            self.domain,
+            BTreeSet::new(),
+        );
+        let env: ExtendedEnv<CF> =
+            env.compute_extension(&self.extended_witness_generator, self.srs);
+        let [t0, t1]: [Vec<ScalarField<CF>>; 2] = compute_error(&self.expression, &env, u);
+        let cross_terms = [&t0, &t1].map(|t| {
+            let evals = Evaluations::from_vec_and_domain(t.clone(), self.domain);
+            self.srs.commit_evaluations_non_hiding(self.domain, &evals)
+        });
+
+        self.absorb_structure(fq_sponge);
+        fq_sponge.absorb_g(&cross_terms[0].elems);
+        fq_sponge.absorb_g(&cross_terms[1].elems);
+        let challenge = QuadraticExtension::challenge::<CF::Curve, _>(fq_sponge);
+        let challenge_square = challenge.square();
+
+        let ([ins1, ins2], [wit1, wit2]) = env.unwrap();
+        let ins1 = ins1.inner_instance().clone();
+        let ins2 = ins2.inner_instance().clone();
+        let clone_witness = |w: &ExtendedWitness<CF::Curve, CF::Witness>| ExtendedWitness {
+            inner: w.inner.clone(),
+            extended: w.extended.clone(),
+        };
+        let wit2_copy = clone_witness(&wit2.witness);
+        let (wit1, wit2) = (wit1.witness, wit2.witness);
+
+        // `r_1 b` is computed as `b + (r_1 - 1) b` to keep the shape of `b`,
+        // e.g. its alphas and extended columns
+        let scale = challenge.c1 - ScalarField::<CF>::one();
+        let instance = Components {
+            c0: Instance::combine(ins1, ins2.clone(), challenge.c0),
+            c1: Instance::combine(ins2.clone(), ins2, scale),
+        };
+        let witness = Components {
+            c0: Witness::combine(wit1, clone_witness(&wit2), challenge.c0),
+            c1: Witness::combine(wit2, wit2_copy, scale),
+        };
+
+        let error_component = |r: ScalarField<CF>, r2: ScalarField<CF>| {
+            let evals = t0
+                .iter()
+                .zip(t1.iter())
+                .map(|(t0, t1)| -(r * t0 + r2 * t1))
+                .collect();
+            Evaluations::from_vec_and_domain(evals, self.domain)
+        };
+        let error = Components {
+            c0: error_component(challenge.c0, challenge_square.c0),
+            c1: error_component(challenge.c1, challenge_square.c1),
+        };
+        let commitment_component = |r: ScalarField<CF>, r2: ScalarField<CF>| {
+            &cross_terms[0].scale(-r) + &cross_terms[1].scale(-r2)
+        };
+        let error_commitment = Components {
+            c0: commitment_component(challenge.c0, challenge_square.c0),
+            c1: commitment_component(challenge.c1, challenge_square.c1),
+        };
+
+        ExtensionFold {
+            instance,
+            witness,
+            u: QuadraticExtension::from_base(ScalarField::<CF>::one()) + challenge,
+            error,
+            error_commitment,
+            cross_terms,
+            challenge,
+        }
+    }
+}
@@ -13,16 +13,41 @@
 //! implemented for [Instance] and [RelaxedInstance] so methods that require a
 //! relaxed instance can also be called on a normal instance
 //! - [RelaxableWitness]: same than [RelaxableInstance] but for witnesses.
+//! - [ProjectiveCommitments]: an accumulator to combine commitments over
+//! several folds without converting them to affine coordinates at each step.
+//! - [commit_all]: commits to all the columns of a witness at once.
+//! - [make_srs]: creates an SRS sized for a domain, with its Lagrange basis.
+//! - [pad_witness]: zero-pads witness columns to the size of the domain.
+//! - [Witness::from_columns]: builds a witness from columns of the size of
+//! the domain, failing with a [WitnessError] otherwise.
+//! - [Foldable]: a value that can be linearly combined, like the scalars,
+//! commitments and evaluations instances and witnesses are made of, used by
+//! [combine_slices] to combine a variable number of columns, or
+//! [combine_slices_deduplicated] when some columns may be shared by the
+//! instances. With the `rayon` feature, witnesses made of evaluations can be
+//! combined in parallel with `combine_par`.
 
 use crate::{Alphas, Evals};
-use ark_ff::Field;
+use ark_ec::{
+    msm::VariableBaseMSM, short_weierstrass_jacobian::GroupAffine as SWJAffine, AffineCurve,
+    ModelParameters, ProjectiveCurve, SWModelParameters,
+};
+use ark_ff::{FftField, Field, Fp256, Fp256Parameters, PrimeField};
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
 use num_traits::One;
-use poly_commitment::commitment::{CommitmentCurve, PolyComm};
+use poly_commitment::{
+    commitment::{CommitmentCurve, PolyComm},
+    srs::SRS,
+    SRS as SRSTrait,
+};
 use std::collections::BTreeMap;
+use thiserror::Error;
 
 pub trait Instance<G: CommitmentCurve>: Sized {
     /// Combine two instances 'a' and 'b' into a new instance.
     /// See page 15.
+    /// Instances storing their commitments and challenges in slices can
+    /// delegate to [combine_slices].
     fn combine(a: Self, b: Self, challenge: G::ScalarField) -> Self;
 
     /// This method takes an Instance and a commitment to zero and extends the instance,
@@ -43,11 +68,42 @@ pub trait Instance<G: CommitmentCurve>: Sized {
 
 pub trait Witness<G: CommitmentCurve>: Sized {
     /// Returns a new witness which is a linear combination using the challenge of the two witnesses `a` and `b`.
+    /// Witnesses storing their columns in slices can delegate to
+    /// [combine_slices].
     fn combine(a: Self, b: Self, challenge: G::ScalarField) -> Self;
 
     /// Returns the number of rows in the witness
     fn rows(&self) -> usize;
 
+    /// Builds a witness from its columns, given as the evaluations over
+    /// `domain`. Every column must have exactly `domain.size()` rows, as a
+    /// column over a different domain would silently give wrong folds; use
+    /// [pad_witness] first for shorter columns.
+    fn from_columns(
+        columns: Vec<Vec<G::ScalarField>>,
+        domain: Radix2EvaluationDomain<G::ScalarField>,
+    ) -> Result<Self, WitnessError>
+    where
+        Self: TryFrom<Vec<Evals<G::ScalarField>>>,
+    {
+        let count = columns.len();
+        let columns = columns
+            .into_iter()
+            .enumerate()
+            .map(|(column, evals)| {
+                if evals.len() != domain.size() {
+                    return Err(WitnessError::WrongColumnLength {
+                        column,
+                        length: evals.len(),
+                        domain_size: domain.size(),
+                    });
+                }
+                Ok(Evaluations::from_vec_and_domain(evals, domain))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::try_from(columns).map_err(|_| WitnessError::WrongColumnCount(count))
+    }
+
     /// This method takes a witness and a vector of evaluations to the zero polynomial,
     /// returning a relaxed witness which is composed by the extended witness and the error vector
     /// that is set to the zero polynomial.
@@ -60,6 +116,177 @@ pub trait Witness<G: CommitmentCurve>: Sized {
     }
 }
 
+/// Ways in which the columns given to [Witness::from_columns] can be
+/// malformed
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum WitnessError {
+    #[error("column {column} has {length} rows, expected the domain size {domain_size}")]
+    WrongColumnLength {
+        column: usize,
+        length: usize,
+        domain_size: usize,
+    },
+    #[error("{0} columns do not make a witness")]
+    WrongColumnCount(usize),
+}
+
+/// A value that can be folded, i.e. combined into `a + challenge * b`
+pub trait Foldable<F>: Sized {
+    fn fold(a: Self, b: Self, challenge: F) -> Self;
+
+    /// Folds a value with itself, i.e. computes `(1 + challenge) * a`.
+    /// Commitments override it to save the addition of the two sides, see
+    /// [combine_slices_deduplicated].
+    fn fold_with_itself(a: Self, challenge: F) -> Self
+    where
+        Self: Clone,
+    {
+        Self::fold(a.clone(), a, challenge)
+    }
+}
+
+impl<P: Fp256Parameters> Foldable<Fp256<P>> for Fp256<P> {
+    fn fold(a: Self, b: Self, challenge: Self) -> Self {
+        a + challenge * b
+    }
+}
+
+impl<P: SWModelParameters> Foldable<<P as ModelParameters>::ScalarField> for SWJAffine<P> {
+    fn fold(a: Self, b: Self, challenge: <P as ModelParameters>::ScalarField) -> Self {
+        a + b.mul(challenge).into_affine()
+    }
+
+    fn fold_with_itself(a: Self, challenge: <P as ModelParameters>::ScalarField) -> Self {
+        a.mul(challenge + P::ScalarField::one()).into_affine()
+    }
+}
+
+impl<G: CommitmentCurve> Foldable<G::ScalarField> for PolyComm<G> {
+    fn fold(a: Self, b: Self, challenge: G::ScalarField) -> Self {
+        &a + &b.scale(challenge)
+    }
+
+    fn fold_with_itself(a: Self, challenge: G::ScalarField) -> Self {
+        a.scale(challenge + G::ScalarField::one())
+    }
+}
+
+impl<F: FftField> Foldable<F> for Evaluations<F, Radix2EvaluationDomain<F>> {
+    fn fold(mut a: Self, b: Self, challenge: F) -> Self {
+        for (a, b) in a.evals.iter_mut().zip(b.evals) {
+            *a += challenge * b;
+        }
+        a
+    }
+}
+
+/// Combines two slices of values element-wise into `a[i] + challenge * b[i]`.
+/// Implementations of [Instance::combine] and [Witness::combine] can delegate
+/// to it for each of their fields, so that the number of columns is only
+/// declared in their types.
+pub fn combine_slices<F: Copy, T: Foldable<F> + Clone>(a: &[T], b: &[T], challenge: F) -> Vec<T> {
+    assert_eq!(a.len(), b.len());
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| T::fold(a.clone(), b.clone(), challenge))
+        .collect()
+}
+
+/// Same as [combine_slices], but detects the pairs of equal values, e.g. the
+/// commitments to a structure column shared by the two instances, and
+/// combines them with [Foldable::fold_with_itself], skipping the addition of
+/// the two sides. The result is the same as the one of [combine_slices].
+pub fn combine_slices_deduplicated<F: Copy, T: Foldable<F> + Clone + PartialEq>(
+    a: &[T],
+    b: &[T],
+    challenge: F,
+) -> Vec<T> {
+    assert_eq!(a.len(), b.len());
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| {
+            if a == b {
+                T::fold_with_itself(a.clone(), challenge)
+            } else {
+                T::fold(a.clone(), b.clone(), challenge)
+            }
+        })
+        .collect()
+}
+
+/// Implements [Instance] for a struct made of commitments, scalars and
+/// [Alphas]. The fields listed as `commitments` and `scalars` are arrays or
+/// vectors of [Foldable] values, combined with [combine_slices], and the
+/// `alphas` field is combined with [Alphas::combine]. Every field of the
+/// struct must be listed.
+///
+/// ```ignore
+/// impl_instance!(Curve, TestInstance {
+///     commitments: [commitments],
+///     scalars: [challenges],
+///     alphas: alphas,
+/// });
+/// ```
+#[macro_export]
+macro_rules! impl_instance {
+    (
+        $curve:ty,
+        $instance:ty {
+            commitments: [$($commitment:ident),* $(,)?],
+            scalars: [$($scalar:ident),* $(,)?],
+            alphas: $alphas:ident $(,)?
+        }
+    ) => {
+        impl $crate::Instance<$curve> for $instance {
+            fn combine(
+                a: Self,
+                b: Self,
+                challenge: <$curve as ::ark_ec::AffineCurve>::ScalarField,
+            ) -> Self {
+                Self {
+                    $($commitment: $crate::combine_slices(
+                        &a.$commitment,
+                        &b.$commitment,
+                        challenge,
+                    )
+                    .try_into()
+                    .unwrap(),)*
+                    $($scalar: $crate::combine_slices(&a.$scalar, &b.$scalar, challenge)
+                        .try_into()
+                        .unwrap(),)*
+                    $alphas: $crate::Alphas::combine(a.$alphas, b.$alphas, challenge),
+                }
+            }
+
+            fn alphas(&self) -> &$crate::Alphas<<$curve as ::ark_ec::AffineCurve>::ScalarField> {
+                &self.$alphas
+            }
+        }
+    };
+}
+
+/// Same as [combine_slices] for columns of evaluations, but combining the
+/// columns, and the rows of each column, in parallel. The result is the same
+/// as the one of [combine_slices].
+#[cfg(feature = "rayon")]
+pub fn combine_par<F: FftField>(a: &[Evals<F>], b: &[Evals<F>], challenge: F) -> Vec<Evals<F>> {
+    use rayon::prelude::*;
+
+    assert_eq!(a.len(), b.len());
+    a.par_iter()
+        .zip(b)
+        .map(|(a, b)| {
+            let evals = a
+                .evals
+                .par_iter()
+                .zip(&b.evals)
+                .map(|(a, b)| *a + challenge * b)
+                .collect();
+            Evaluations::from_vec_and_domain(evals, a.domain())
+        })
+        .collect()
+}
+
 impl<G: CommitmentCurve, W: Witness<G>> ExtendedWitness<G, W> {
     /// This method returns an extended witness which is defined as the witness itself,
     /// followed by an empty BTreeMap.
@@ -85,6 +312,7 @@ impl<G: CommitmentCurve, I: Instance<G>> ExtendedInstance<G, I> {
 }
 
 // -- Relaxed instances
+#[derive(Clone, Debug, PartialEq)]
 pub struct RelaxedInstance<G: CommitmentCurve, I: Instance<G>> {
     instance: ExtendedInstance<G, I>,
     pub u: G::ScalarField,
@@ -113,6 +341,7 @@ impl<G: CommitmentCurve, I: Instance<G>> RelaxedInstance<G, I> {
 }
 
 // -- Relaxed witnesses
+#[derive(Clone)]
 pub struct RelaxedWitness<G: CommitmentCurve, W: Witness<G>> {
     pub witness: ExtendedWitness<G, W>,
     pub error_vec: Evals<G::ScalarField>,
@@ -139,6 +368,7 @@ impl<G: CommitmentCurve, W: Witness<G>> RelaxedWitness<G, W> {
 }
 
 // -- Extended witness
+#[derive(Clone)]
 pub struct ExtendedWitness<G: CommitmentCurve, W: Witness<G>> {
     pub inner: W,
     //extra columns added by quadraticization to lower the degree of expressions to 2
@@ -192,6 +422,7 @@ impl<G: CommitmentCurve, W: Witness<G>> ExtendedWitness<G, W> {
 }
 
 // -- Extended instance
+#[derive(Clone, Debug, PartialEq)]
 pub struct ExtendedInstance<G: CommitmentCurve, I: Instance<G>> {
     pub inner: I,
     //commitments to extra columns
@@ -304,14 +535,17 @@ where
 }
 
 impl<G: CommitmentCurve, I: Instance<G>> RelaxedInstance<G, I> {
-    fn sub_errors(self, error_commitments: &[PolyComm<G>; 2], challenge: G::ScalarField) -> Self {
+    fn sub_errors(self, error_commitments: &[PolyComm<G>], challenge: G::ScalarField) -> Self {
         let RelaxedInstance {
             instance,
             u,
             error_commitment: error,
         } = self;
-        let [e0, e1] = error_commitments;
-        let error_commitment = &error - (&(&e0.scale(challenge) + &e1.scale(challenge.square())));
+        let mut power = G::ScalarField::one();
+        let error_commitment = error_commitments.iter().fold(error, |acc, t| {
+            power *= challenge;
+            &acc - &t.scale(power)
+        });
         RelaxedInstance {
             instance,
             u,
@@ -319,8 +553,11 @@ impl<G: CommitmentCurve, I: Instance<G>> RelaxedInstance<G, I> {
         }
     }
 
-    fn combine(a: Self, b: Self, challenge: <G>::ScalarField) -> Self {
-        let challenge_cube = challenge * challenge * challenge;
+    /// Combines the instances and their errors, the error of `b` being
+    /// multiplied by `challenge^degree`, the degree of the relaxed relation
+    /// in the challenge
+    fn combine(a: Self, b: Self, challenge: <G>::ScalarField, degree: usize) -> Self {
+        let challenge_power = challenge.pow([degree as u64]);
         let RelaxedInstance {
             instance: ins1,
             u: u1,
@@ -333,7 +570,7 @@ impl<G: CommitmentCurve, I: Instance<G>> RelaxedInstance<G, I> {
         } = b;
         let instance = <ExtendedInstance<G, I>>::combine(ins1, ins2, challenge);
         let u = u1 + u2 * challenge;
-        let error_commitment = &e1 + &e2.scale(challenge_cube);
+        let error_commitment = &e1 + &e2.scale(challenge_power);
         RelaxedInstance {
             instance,
             u,
@@ -341,33 +578,32 @@ impl<G: CommitmentCurve, I: Instance<G>> RelaxedInstance<G, I> {
         }
     }
 
+    /// Folds `a` and `b` with the commitments to the cross terms `T_0`, ...,
+    /// `T_{n-1}`, giving the error `E_1 - r T_0 - ... - r^n T_{n-1} + r^(n+1) E_2`
     pub(super) fn combine_and_sub_error(
         a: Self,
         b: Self,
         challenge: <G>::ScalarField,
-        error_commitments: &[PolyComm<G>; 2],
+        error_commitments: &[PolyComm<G>],
     ) -> Self {
-        Self::combine(a, b, challenge).sub_errors(error_commitments, challenge)
+        let degree = error_commitments.len() + 1;
+        Self::combine(a, b, challenge, degree).sub_errors(error_commitments, challenge)
     }
 }
 
 impl<G: CommitmentCurve, W: Witness<G>> RelaxedWitness<G, W> {
-    fn sub_error(mut self, errors: [Vec<G::ScalarField>; 2], challenge: G::ScalarField) -> Self {
-        let [e0, e1] = errors;
-
-        for (a, (e0, e1)) in self
-            .error_vec
-            .evals
-            .iter_mut()
-            .zip(e0.into_iter().zip(e1.into_iter()))
-        {
-            // should be the same as e0 * c + e1 * c^2
-            *a -= ((e1 * challenge) + e0) * challenge;
+    fn sub_error(mut self, errors: Vec<Vec<G::ScalarField>>, challenge: G::ScalarField) -> Self {
+        let mut power = G::ScalarField::one();
+        for error in errors {
+            power *= challenge;
+            for (a, e) in self.error_vec.evals.iter_mut().zip(error) {
+                *a -= e * power;
+            }
         }
         self
     }
 
-    fn combine(a: Self, b: Self, challenge: <G>::ScalarField) -> Self {
+    fn combine(a: Self, b: Self, challenge: <G>::ScalarField, degree: usize) -> Self {
         let RelaxedWitness {
             witness: a,
             error_vec: mut e1,
@@ -376,20 +612,134 @@ impl<G: CommitmentCurve, W: Witness<G>> RelaxedWitness<G, W> {
             witness: b,
             error_vec: e2,
         } = b;
-        let challenge_cube = (challenge * challenge) * challenge;
+        let challenge_power = challenge.pow([degree as u64]);
         let witness = <ExtendedWitness<G, W>>::combine(a, b, challenge);
         for (a, b) in e1.evals.iter_mut().zip(e2.evals.into_iter()) {
-            *a += b * challenge_cube;
+            *a += b * challenge_power;
         }
         let error_vec = e1;
         RelaxedWitness { witness, error_vec }
     }
+
+    /// The counterpart of [RelaxedInstance::combine_and_sub_error] for the
+    /// witnesses, given the evaluations of the cross terms
     pub(super) fn combine_and_sub_error(
         a: Self,
         b: Self,
         challenge: <G>::ScalarField,
-        error: [Vec<G::ScalarField>; 2],
+        error: Vec<Vec<G::ScalarField>>,
     ) -> Self {
-        Self::combine(a, b, challenge).sub_error(error, challenge)
+        let degree = error.len() + 1;
+        Self::combine(a, b, challenge, degree).sub_error(error, challenge)
     }
 }
+
+// -- Projective accumulation of commitments
+/// Accumulator of commitments kept in projective coordinates.
+/// Combining affine commitments requires a field inversion per commitment and
+/// per fold when converting the result back to affine. When folding many
+/// instances in a row, the commitments can instead be accumulated in
+/// projective form and normalized once, using batch normalization, at the
+/// end.
+#[derive(Clone, Debug)]
+pub struct ProjectiveCommitments<G: CommitmentCurve> {
+    commitments: Vec<G::Projective>,
+}
+
+impl<G: CommitmentCurve> ProjectiveCommitments<G> {
+    /// Starts an accumulation from the commitments of the first instance
+    pub fn new(commitments: &[G]) -> Self {
+        let commitments = commitments.iter().map(|c| c.into_projective()).collect();
+        Self { commitments }
+    }
+
+    /// Computes `acc + challenge * commitments`, the projective counterpart
+    /// of [Instance::combine] for the commitments of an instance
+    pub fn combine(&mut self, commitments: &[G], challenge: G::ScalarField) {
+        assert_eq!(self.commitments.len(), commitments.len());
+        self.commitments
+            .iter_mut()
+            .zip(commitments)
+            .for_each(|(acc, c)| *acc += c.mul(challenge));
+    }
+
+    /// Converts the accumulated commitments back to affine coordinates
+    pub fn into_affine(self) -> Vec<G> {
+        G::Projective::batch_normalization_into_affine(&self.commitments)
+    }
+}
+
+/// Zero-pads the columns of a witness, all of the same length, to the size of
+/// `domain`, returning them along with their original length, i.e. the number
+/// of rows actually used. The padding rows may not satisfy the constraints,
+/// e.g. when no dynamic selector is enabled in them, see
+/// [Checker::check_active](crate::checker::Checker::check_active) to ignore
+/// them when checking a witness.
+pub fn pad_witness<F: FftField>(
+    columns: Vec<Vec<F>>,
+    domain: Radix2EvaluationDomain<F>,
+) -> (Vec<Evals<F>>, usize) {
+    let rows = columns.first().map_or(0, Vec::len);
+    assert!(
+        rows <= domain.size(),
+        "{rows} rows do not fit in a domain of size {}",
+        domain.size()
+    );
+    let columns = columns
+        .into_iter()
+        .map(|mut column| {
+            assert_eq!(column.len(), rows, "the columns have different lengths");
+            column.resize(domain.size(), F::zero());
+            Evaluations::from_vec_and_domain(column, domain)
+        })
+        .collect();
+    (columns, rows)
+}
+
+/// Creates an SRS of the size of `domain`, with the Lagrange basis of the
+/// domain already added, as required by [commit_all] and by the folding
+/// schemes to commit to the columns of the witnesses.
+pub fn make_srs<G: CommitmentCurve>(domain: Radix2EvaluationDomain<G::ScalarField>) -> SRS<G> {
+    let mut srs = SRS::create(domain.size());
+    srs.add_lagrange_basis(domain);
+    srs
+}
+
+/// Returns the non-hiding commitments to the columns of `witness`, in order.
+/// It gives the same commitments as committing to each column with
+/// [SRS::commit_evaluations_non_hiding](poly_commitment::SRS::commit_evaluations_non_hiding),
+/// but looks up the Lagrange basis of the domain once for all the columns and
+/// converts the commitments to affine coordinates in a single batch.
+/// The Lagrange basis of `domain` must have been added to the SRS, and must
+/// not be chunked, i.e. the SRS must be at least as large as the domain.
+/// Any [SRS implementation](poly_commitment::SRS) can be used, e.g. the
+/// [FoldingConfig::Srs](crate::FoldingConfig::Srs) of a folding scheme.
+pub fn commit_all<G: CommitmentCurve, Srs: SRSTrait<G>>(
+    srs: &Srs,
+    domain: Radix2EvaluationDomain<G::ScalarField>,
+    witness: &[Evals<G::ScalarField>],
+) -> Vec<G> {
+    let basis = srs
+        .get_lagrange_basis(domain.size())
+        .unwrap_or_else(|| panic!("lagrange bases for size {} not found", domain.size()));
+    let basis: Vec<G> = basis
+        .iter()
+        .map(|comm| {
+            assert_eq!(
+                comm.elems.len(),
+                1,
+                "chunked lagrange bases are not supported"
+            );
+            comm.elems[0]
+        })
+        .collect();
+    let commitments: Vec<G::Projective> = witness
+        .iter()
+        .map(|column| {
+            assert_eq!(column.domain().size(), domain.size());
+            let scalars: Vec<_> = column.evals.iter().map(|x| x.into_repr()).collect();
+            VariableBaseMSM::multi_scalar_mul(&basis, &scalars)
+        })
+        .collect();
+    G::Projective::batch_normalization_into_affine(&commitments)
+}
@@ -6,6 +6,14 @@
 //! to degree `2`.
 //! - [decomposable_folding]: a submodule to "parallelize" folded
 //! computations.
+//! - [extension_field]: a submodule to fold with challenges sampled from a
+//! quadratic extension of the scalar field.
+//! - [accumulator]: a submodule to fold long chains while committing to the
+//! transcript of each step in a Merkle tree.
+//! - [r1cs]: a submodule to export a relaxed accumulator as a relaxed R1CS
+//! instance.
+//! - [transcript]: a Fiat-Shamir transcript shared by the prover and the
+//! verifier of a fold.
 //!
 //! Examples can be found in the directory `examples`.
 //!
@@ -17,31 +25,41 @@
 //! [expressions].
 // TODO: the documentation above might need more descriptions.
 
-use ark_ec::AffineCurve;
-use ark_ff::{Field, Zero};
+use ark_ec::{msm::VariableBaseMSM, AffineCurve};
+use ark_ff::{Field, FpParameters, One, PrimeField, Zero};
 use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
-use error_term::{compute_error, ExtendedEnv};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use error_term::{check_error_terms, compute_error, ExtendedEnv};
 use expressions::{
-    folding_expression, FoldingColumnTrait, FoldingCompatibleExpr, IntegratedFoldingExpr,
+    folding_expression, Degree, FoldingColumnTrait, FoldingCompatibleExpr, IntegratedFoldingExpr,
 };
 use instance_witness::{RelaxableInstance, RelaxablePair};
+use itertools::Either;
 use kimchi::circuits::gate::CurrOrNext;
 use mina_poseidon::FqSponge;
 use poly_commitment::{commitment::CommitmentCurve, PolyComm, SRS};
 use quadraticization::ExtendedWitnessGenerator;
 use std::{
+    collections::BTreeSet,
     fmt::Debug,
     hash::Hash,
     iter::successors,
     rc::Rc,
     sync::atomic::{AtomicUsize, Ordering},
 };
+use thiserror::Error;
 
 // Make available outside the crate to avoid code duplication
 pub use error_term::Side;
 pub use expressions::ExpExtension;
-pub use instance_witness::{Instance, RelaxedInstance, RelaxedWitness, Witness};
+#[cfg(feature = "rayon")]
+pub use instance_witness::combine_par;
+pub use instance_witness::{
+    combine_slices, combine_slices_deduplicated, commit_all, make_srs, pad_witness, Foldable,
+    Instance, ProjectiveCommitments, RelaxedInstance, RelaxedWitness, Witness, WitnessError,
+};
 
+pub mod accumulator;
 pub mod columns;
 pub mod decomposable_folding;
 
@@ -49,8 +67,13 @@ mod error_term;
 
 mod eval_leaf;
 pub mod expressions;
+pub mod extension_field;
 mod instance_witness;
+#[cfg(unix)]
+pub mod mmap_witness;
 pub mod quadraticization;
+pub mod r1cs;
+pub mod transcript;
 
 // Modules strictly related to tests
 // TODO: should we move them into an explicit subdirectory `test`?
@@ -78,7 +101,7 @@ pub trait FoldingConfig: Clone + Debug + Eq + Hash + 'static {
 
     /// The type of an abstract challenge that can be found in the expressions
     /// provided as constraints.
-    type Challenge: Clone + Copy + Debug + Eq + Hash;
+    type Challenge: Clone + Copy + Debug + Eq + Hash + Ord + PartialOrd;
 
     /// The target curve used by the polynomial commitment
     type Curve: CommitmentCurve;
@@ -104,6 +127,44 @@ pub trait FoldingConfig: Clone + Debug + Eq + Hash + 'static {
         Self::Selector,
         Structure = Self::Structure,
     >;
+
+    /// The number of cross terms of a fold, i.e. `2` for constraints of degree
+    /// at most `2` or quadraticized, and the degree of the constraints when
+    /// they are folded natively, see
+    /// [DecomposableFoldingScheme::cross_terms_count](decomposable_folding::DecomposableFoldingScheme::cross_terms_count).
+    /// Configurations can use it to size the arrays of cross terms, see
+    /// [DecomposableFoldingScheme::fold_instance_witness_pair_array](decomposable_folding::DecomposableFoldingScheme::fold_instance_witness_pair_array).
+    const NUM_CROSS_TERMS: usize = 2;
+
+    /// Returns the instance-witness pair in which every commitment, challenge
+    /// and column is zero, using the given `alphas`. Once relaxed with `u = 0`
+    /// and a zero error term, it is the identity of folding, see
+    /// [FoldingScheme::identity_accumulator].
+    fn identity_instance(
+        structure: &Self::Structure,
+        srs: &Self::Srs,
+        domain: Radix2EvaluationDomain<ScalarField<Self>>,
+        alphas: Alphas<ScalarField<Self>>,
+    ) -> (Self::Instance, Self::Witness);
+
+    /// Returns the commitments to the structure of the circuit, e.g. its
+    /// fixed selectors, absorbed before squeezing every folding challenge.
+    /// Defaults to none, which is only correct for circuits without
+    /// structure.
+    ///
+    /// The structure is shared by the folded instances and is not part of
+    /// them. If it is not bound to the transcript, the challenge only depends
+    /// on the instances and the cross terms, and a prover could compute the
+    /// cross terms for a structure other than the one the verifier expects.
+    /// This matters as soon as several circuits are folded with the same
+    /// transcript, or when the structure is chosen by the prover.
+    fn structure_commitments(
+        _structure: &Self::Structure,
+        _srs: &Self::Srs,
+        _domain: Radix2EvaluationDomain<ScalarField<Self>>,
+    ) -> Vec<PolyComm<Self::Curve>> {
+        vec![]
+    }
 }
 
 /// Describe a folding environment.
@@ -121,7 +182,8 @@ pub trait FoldingEnv<F: Zero + Clone, I, W, Col, Chal, Selector> {
     /// Creates a new environment storing the structure, instances and witnesses.
     fn new(structure: &Self::Structure, instances: [&I; 2], witnesses: [&W; 2]) -> Self;
 
-    /// Returns the domain size of the circuit.
+    /// Returns the domain size of the circuit, which should be read from the
+    /// stored witnesses, see [Witness::rows], rather than being hardcoded.
     fn domain_size(&self) -> usize;
 
     // TODO: move into `FoldingConfig`
@@ -134,8 +196,19 @@ pub trait FoldingEnv<F: Zero + Clone, I, W, Col, Chal, Selector> {
     }
 
     /// Returns the evaluations of a given column witness at omega or zeta*omega.
+    /// Only called for the folded columns, see [Self::fixed_col] for the
+    /// others.
     fn col(&self, col: Col, curr_or_next: CurrOrNext, side: Side) -> &Vec<F>;
 
+    /// Returns the evaluations of a fixed column, see
+    /// [ColumnKind::Fixed](crate::expressions::ColumnKind::Fixed), at omega
+    /// or zeta*omega. Fixed columns are part of the [Self::Structure] and are
+    /// not folded, so they are the same for both sides.
+    /// Environments without fixed columns do not need to implement it.
+    fn fixed_col(&self, _col: Col, _curr_or_next: CurrOrNext) -> &Vec<F> {
+        panic!("fixed_col must be implemented by the environments folding fixed columns")
+    }
+
     /// Obtains a given challenge from the expanded instance for one side.
     /// The challenges are stored inside the instances structs.
     fn challenge(&self, challenge: Chal, side: Side) -> F;
@@ -154,11 +227,20 @@ type Evals<F> = Evaluations<F, Radix2EvaluationDomain<F>>;
 pub struct FoldingScheme<'a, CF: FoldingConfig> {
     pub expression: IntegratedFoldingExpr<CF>,
     pub srs: &'a CF::Srs,
+    /// The evaluation domain of the folded circuit. It is built once by the
+    /// caller and reused, with its precomputed roots of unity, by every fold
+    /// performed with this scheme.
     pub domain: Radix2EvaluationDomain<ScalarField<CF>>,
     pub zero_commitment: PolyComm<CF::Curve>,
     pub zero_vec: Evals<ScalarField<CF>>,
     pub structure: CF::Structure,
+    /// The commitments returned by [FoldingConfig::structure_commitments],
+    /// absorbed before squeezing the folding challenges
+    pub structure_commitments: Vec<PolyComm<CF::Curve>>,
     pub extended_witness_generator: ExtendedWitnessGenerator<CF>,
+    /// Whether to reject a zero folding challenge, see
+    /// [Self::check_challenge]. Disabled by default.
+    pub reject_zero_challenge: bool,
 }
 
 impl<'a, CF: FoldingConfig> FoldingScheme<'a, CF> {
@@ -169,12 +251,17 @@ impl<'a, CF: FoldingConfig> FoldingScheme<'a, CF> {
         structure: &CF::Structure,
     ) -> (Self, FoldingCompatibleExpr<CF>) {
         let (expression, extended_witness_generator) = folding_expression(constraints);
+        debug_assert!(
+            check_error_terms(&expression),
+            "the error terms are not the cross terms of the relaxed relation"
+        );
         let zero = <ScalarField<CF>>::zero();
         let evals = std::iter::repeat(zero).take(domain.size()).collect();
         let zero_vec_evals = Evaluations::from_vec_and_domain(evals, domain);
         let zero_commitment = srs.commit_evaluations_non_hiding(domain, &zero_vec_evals);
         let zero_vec = zero_vec_evals;
         let final_expression = expression.clone().final_expression();
+        let structure_commitments = CF::structure_commitments(structure, srs, domain);
         let scheme = Self {
             expression,
             srs,
@@ -182,11 +269,85 @@ impl<'a, CF: FoldingConfig> FoldingScheme<'a, CF> {
             zero_commitment,
             zero_vec,
             structure: structure.clone(),
+            structure_commitments,
             extended_witness_generator,
+            reject_zero_challenge: false,
         };
         (scheme, final_expression)
     }
 
+    /// Checks symbolically that the error terms computed when folding are the
+    /// cross terms of the relaxed relation, i.e. the coefficients of `X` and
+    /// `X^2` in the expansion of the relation over the pair folded with a
+    /// formal challenge `X`. It is checked once by [Self::new] in debug builds,
+    /// instead of for each fold, and can be called by the users of the scheme
+    /// in release builds.
+    pub fn check_error_terms(&self) -> bool {
+        check_error_terms(&self.expression)
+    }
+
+    /// Checks a folding challenge before using it.
+    /// Folding with the challenge `0` gives back the left instance and
+    /// witness, as `a + 0 b = a`, silently dropping the right pair, and
+    /// folding with `1` gives `a + b`. Both follow from the arithmetic, but a
+    /// zero challenge, e.g. from a broken transcript, means that the fold
+    /// proves nothing about the right pair, so it is rejected when
+    /// [Self::reject_zero_challenge] is set.
+    pub fn check_challenge(&self, challenge: ScalarField<CF>) -> Result<(), FoldingError> {
+        if self.reject_zero_challenge && challenge.is_zero() {
+            Err(FoldingError::ZeroChallenge)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Absorb the [structure commitments](Self::structure_commitments), to be
+    /// called before squeezing a folding challenge.
+    pub(crate) fn absorb_structure<Sponge>(&self, fq_sponge: &mut Sponge)
+    where
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        for commitment in self.structure_commitments.iter() {
+            fq_sponge.absorb_g(&commitment.elems);
+        }
+    }
+
+    /// Returns the all-zero relaxed accumulator, with zero commitments and
+    /// columns, `u = 0` and a zero error term. It gives a starting point to
+    /// folding chains, as folding a pair `(U, W)` into it with the challenge
+    /// `r` gives `(r U, r W)` and `u = r`, the cross terms being zero.
+    #[allow(clippy::type_complexity)]
+    pub fn identity_accumulator(
+        &self,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+    ) {
+        let alphas = Alphas::zero(self.expression.alphas_count());
+        let (instance, witness) =
+            CF::identity_instance(&self.structure, self.srs, self.domain, alphas);
+        let mut instance = Instance::relax(instance, self.zero_commitment.clone());
+        instance.u = ScalarField::<CF>::zero();
+        let witness = Witness::relax(witness, &self.zero_vec);
+        (instance, witness)
+    }
+
+    /// Relaxes a fresh instance-witness pair, attaching zero error terms and
+    /// the scaling factor `u = 1`. Folding the relaxed pair gives the same
+    /// result as folding the fresh pair, which lets fresh pairs and folded
+    /// accumulators be handled uniformly.
+    #[allow(clippy::type_complexity)]
+    pub fn relax(
+        &self,
+        instance: CF::Instance,
+        witness: CF::Witness,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+    ) {
+        (instance, witness).relax(&self.zero_vec, self.zero_commitment.clone())
+    }
+
     #[allow(clippy::type_complexity)]
     pub fn fold_instance_witness_pair<A, B, Sponge>(
         &self,
@@ -196,8 +357,60 @@ impl<'a, CF: FoldingConfig> FoldingScheme<'a, CF> {
     ) -> (
         RelaxedInstance<CF::Curve, CF::Instance>,
         RelaxedWitness<CF::Curve, CF::Witness>,
-        [PolyComm<CF::Curve>; 2],
+        CrossTerms<CF::Curve>,
     )
+    where
+        A: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        B: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        let (instance, witness, proof) = self.fold_with_accumulation_proof(a, b, fq_sponge);
+        (instance, witness, proof.cross_terms.into())
+    }
+
+    /// Fold two instance-witness pairs like
+    /// [Self::fold_instance_witness_pair], additionally returning the
+    /// [AccumulationProof] that a verifier can check with
+    /// [Self::verify_accumulation].
+    /// Panics if the challenge is rejected by [Self::check_challenge], see
+    /// [Self::try_fold_with_accumulation_proof].
+    #[allow(clippy::type_complexity)]
+    pub fn fold_with_accumulation_proof<A, B, Sponge>(
+        &self,
+        a: A,
+        b: B,
+        fq_sponge: &mut Sponge,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+        AccumulationProof<CF::Curve>,
+    )
+    where
+        A: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        B: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        self.try_fold_with_accumulation_proof(a, b, fq_sponge)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Same as [Self::fold_with_accumulation_proof], but returns an error
+    /// instead of panicking when the challenge is rejected by
+    /// [Self::check_challenge]
+    #[allow(clippy::type_complexity)]
+    pub fn try_fold_with_accumulation_proof<A, B, Sponge>(
+        &self,
+        a: A,
+        b: B,
+        fq_sponge: &mut Sponge,
+    ) -> Result<
+        (
+            RelaxedInstance<CF::Curve, CF::Instance>,
+            RelaxedWitness<CF::Curve, CF::Witness>,
+            AccumulationProof<CF::Curve>,
+        ),
+        FoldingError,
+    >
     where
         A: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
         B: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
@@ -215,7 +428,7 @@ impl<'a, CF: FoldingConfig> FoldingScheme<'a, CF> {
             [ins1, ins2],
             [wit1, wit2],
             self.domain,
-            None,
+            BTreeSet::new(),
         );
         let env: ExtendedEnv<CF> =
             env.compute_extension(&self.extended_witness_generator, self.srs);
@@ -234,17 +447,155 @@ impl<'a, CF: FoldingConfig> FoldingScheme<'a, CF> {
         assert_eq!(error_commitments[0].elems.len(), 1);
         assert_eq!(error_commitments[1].elems.len(), 1);
 
+        self.absorb_structure(fq_sponge);
         fq_sponge.absorb_g(&error_commitments[0].elems);
         fq_sponge.absorb_g(&error_commitments[1].elems);
 
         let challenge = fq_sponge.challenge();
+        self.check_challenge(challenge)?;
 
-        let error = error_evals.map(|e| e.evals);
+        let error = Vec::from(error_evals.map(|e| e.evals));
         let ([ins1, ins2], [wit1, wit2]) = env.unwrap();
         let instance =
             RelaxedInstance::combine_and_sub_error(ins1, ins2, challenge, &error_commitments);
         let witness = RelaxedWitness::combine_and_sub_error(wit1, wit2, challenge, error);
-        (instance, witness, error_commitments)
+        let proof = AccumulationProof {
+            cross_terms: error_commitments,
+            challenge,
+        };
+        Ok((instance, witness, proof))
+    }
+
+    /// Fold two instance-witness pairs like
+    /// [Self::fold_with_accumulation_proof], the challenge being derived from
+    /// `transcript_digest` instead of the state of a running transcript, so
+    /// that the fold can be checked with [Self::verify_fold_from_digest].
+    /// `fq_sponge` must be a fresh sponge.
+    #[allow(clippy::type_complexity)]
+    pub fn fold_from_digest<A, B, Sponge>(
+        &self,
+        a: A,
+        b: B,
+        transcript_digest: ScalarField<CF>,
+        fq_sponge: &mut Sponge,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+        AccumulationProof<CF::Curve>,
+    )
+    where
+        A: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        B: RelaxablePair<CF::Curve, CF::Instance, CF::Witness>,
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        fq_sponge.absorb_fr(&[transcript_digest]);
+        self.fold_with_accumulation_proof(a, b, fq_sponge)
+    }
+
+    /// Check that `proof` is a fold made by [Self::fold_from_digest] from the
+    /// transcript summarized by `transcript_digest`, without the instances
+    /// being folded, for verifiers carrying only the digest of the transcript,
+    /// e.g. recursively.
+    /// `fq_sponge` must be a fresh sponge. The challenge is recomputed from
+    /// the digest, the structure and the cross terms, and compared to the one
+    /// of the proof.
+    ///
+    /// The digest must bind the two instances being folded, i.e. the
+    /// commitments of their inner instances, their extended columns, error
+    /// commitments and `u`, as well as everything preceding them in the
+    /// transcript. Otherwise, the challenge would not depend on the instances
+    /// and a prover could choose them after learning it. As with
+    /// [Self::fold_chains], the inner instances are opaque to this library, and
+    /// computing the digest is left to the caller.
+    /// Checking the folded instance itself requires the instances, see
+    /// [Self::verify_accumulation].
+    pub fn verify_fold_from_digest<Sponge>(
+        &self,
+        proof: &AccumulationProof<CF::Curve>,
+        transcript_digest: ScalarField<CF>,
+        fq_sponge: &mut Sponge,
+    ) -> bool
+    where
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        let [t0, t1] = &proof.cross_terms;
+        if t0.elems.len() != 1 || t1.elems.len() != 1 {
+            return false;
+        }
+        fq_sponge.absorb_fr(&[transcript_digest]);
+        self.absorb_structure(fq_sponge);
+        fq_sponge.absorb_g(&t0.elems);
+        fq_sponge.absorb_g(&t1.elems);
+        fq_sponge.challenge() == proof.challenge
+    }
+
+    /// Returns the number of bits of security of a single fold, i.e.
+    /// `-log2(d / |F|)` where `F` is the scalar field from which the challenge
+    /// is sampled and `d` is the degree of the relaxed relation in the
+    /// challenge.
+    /// As every constraint is multiplied by a power of alpha, which is folded
+    /// too, `d` is the degree of the constraints plus one. For instance, the
+    /// error term of degree `2` constraints is combined as
+    /// `E = E_1 - r T_0 - r^2 T_1 + r^3 E_2`.
+    pub fn soundness_bits(&self) -> f64 {
+        let degree = match self.expression.degree() {
+            Degree::Zero => 1,
+            Degree::One => 2,
+            Degree::Two => 3,
+        };
+        // The modulus does not fit in a f64, but its logarithm does
+        let modulus = <ScalarField<CF> as PrimeField>::Params::MODULUS;
+        let modulus = modulus
+            .as_ref()
+            .iter()
+            .rev()
+            .fold(0f64, |acc, limb| acc * 2f64.powi(64) + *limb as f64);
+        modulus.log2() - (degree as f64).log2()
+    }
+
+    /// Fold the accumulators of two independent folding chains, which may have
+    /// been obtained after a different number of folds.
+    ///
+    /// Each chain has absorbed its own sequence of error terms, so the states
+    /// of their sponges cannot be compared and neither can be reused to derive
+    /// the challenge. Instead, both accumulators are absorbed into
+    /// `fq_sponge` before folding them as usual.
+    ///
+    /// Soundness: the relaxed relation satisfied by an accumulator only
+    /// depends on its instance, i.e. the commitments, `u` and the error
+    /// commitment, and not on the number of folds that produced it. As long as
+    /// the challenge is derived after both instances are bound to the
+    /// transcript, the fold is as sound as any other fold. The caller is
+    /// responsible for absorbing the commitments of the inner instances, which
+    /// are opaque to this library, before calling this method.
+    #[allow(clippy::type_complexity)]
+    pub fn fold_chains<Sponge>(
+        &self,
+        a: (
+            RelaxedInstance<CF::Curve, CF::Instance>,
+            RelaxedWitness<CF::Curve, CF::Witness>,
+        ),
+        b: (
+            RelaxedInstance<CF::Curve, CF::Instance>,
+            RelaxedWitness<CF::Curve, CF::Witness>,
+        ),
+        fq_sponge: &mut Sponge,
+    ) -> (
+        RelaxedInstance<CF::Curve, CF::Instance>,
+        RelaxedWitness<CF::Curve, CF::Witness>,
+        CrossTerms<CF::Curve>,
+    )
+    where
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+    {
+        for (instance, _) in [&a, &b] {
+            for commitment in instance.inner_instance().extended.iter() {
+                fq_sponge.absorb_g(&commitment.elems);
+            }
+            fq_sponge.absorb_g(&instance.get_error_column_commitment().elems);
+            fq_sponge.absorb_fr(&[instance.u]);
+        }
+        self.fold_instance_witness_pair(a, b, fq_sponge)
     }
 
     /// Fold two relaxable instances into a relaxed instance.
@@ -263,20 +614,259 @@ impl<'a, CF: FoldingConfig> FoldingScheme<'a, CF> {
         B: RelaxableInstance<CF::Curve, CF::Instance>,
         Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
     {
-        let a: RelaxedInstance<CF::Curve, CF::Instance> = a.relax(self.zero_commitment.clone());
-        let b: RelaxedInstance<CF::Curve, CF::Instance> = b.relax(self.zero_commitment.clone());
-
         // sanity check to verify that we only have one commitment in polycomm
         // (i.e. domain = poly size)
         assert_eq!(error_commitments[0].elems.len(), 1);
         assert_eq!(error_commitments[1].elems.len(), 1);
 
+        self.absorb_structure(fq_sponge);
         fq_sponge.absorb_g(&error_commitments[0].elems);
         fq_sponge.absorb_g(&error_commitments[1].elems);
 
         let challenge = fq_sponge.challenge();
 
-        RelaxedInstance::combine_and_sub_error(a, b, challenge, &error_commitments)
+        self.fold_instance(a, b, &error_commitments, challenge)
+    }
+
+    /// Fold two relaxable instances with a known `challenge`, the instance
+    /// half of [Self::fold_instance_witness_pair]. The instances are combined
+    /// with [Instance::combine], the relaxation factors become `u_a + r u_b`,
+    /// and the commitments to the cross terms, e.g. taken from an
+    /// [AccumulationProof], are subtracted from the error commitment.
+    /// No witness nor SRS is needed, which lets a verifier keep track of the
+    /// running accumulator.
+    pub fn fold_instance<A, B>(
+        &self,
+        a: A,
+        b: B,
+        cross_terms: &[PolyComm<CF::Curve>; 2],
+        challenge: ScalarField<CF>,
+    ) -> RelaxedInstance<CF::Curve, CF::Instance>
+    where
+        A: RelaxableInstance<CF::Curve, CF::Instance>,
+        B: RelaxableInstance<CF::Curve, CF::Instance>,
+    {
+        let a: RelaxedInstance<CF::Curve, CF::Instance> = a.relax(self.zero_commitment.clone());
+        let b: RelaxedInstance<CF::Curve, CF::Instance> = b.relax(self.zero_commitment.clone());
+        // The powers of alpha of an instance are counted when computing the
+        // error terms, which the verifier does not do. They are requested
+        // here, so that they are combined like by the prover.
+        if let Some(last) = self.expression.alphas_count().checked_sub(1) {
+            for instance in [&a, &b] {
+                let _ = instance.inner_instance().alphas().get(last);
+            }
+        }
+        RelaxedInstance::combine_and_sub_error(a, b, challenge, cross_terms)
+    }
+
+    /// Check that `folded` is the accumulation of the instances `a` and `b`
+    /// described by `proof`.
+    /// The challenge `r` is recomputed from the cross-term commitments `T_0`
+    /// and `T_1` and the following relations are verified:
+    /// - `U = U_1 + r U_2` for the instances and the extended columns,
+    /// - `u = u_1 + r u_2`,
+    /// - `E = E_1 - r T_0 - r^2 T_1 + r^3 E_2`.
+    /// The error relation is the one of the Nova paper, generalized to the two
+    /// cross terms required by the homogenization of degree `2` expressions.
+    pub fn verify_accumulation<A, B, Sponge>(
+        &self,
+        a: A,
+        b: B,
+        folded: &RelaxedInstance<CF::Curve, CF::Instance>,
+        proof: &AccumulationProof<CF::Curve>,
+        fq_sponge: &mut Sponge,
+    ) -> bool
+    where
+        A: RelaxableInstance<CF::Curve, CF::Instance>,
+        B: RelaxableInstance<CF::Curve, CF::Instance>,
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+        CF::Instance: PartialEq,
+    {
+        let [t0, t1] = &proof.cross_terms;
+        if t0.elems.len() != 1 || t1.elems.len() != 1 {
+            return false;
+        }
+        self.absorb_structure(fq_sponge);
+        fq_sponge.absorb_g(&t0.elems);
+        fq_sponge.absorb_g(&t1.elems);
+        let challenge = fq_sponge.challenge();
+        if challenge != proof.challenge || self.check_challenge(challenge).is_err() {
+            return false;
+        }
+
+        let expected = self.fold_instance(a, b, &proof.cross_terms, challenge);
+        &expected == folded
+    }
+
+    /// Gathers the instances of a fold, relaxing them if needed, to check
+    /// them with [Self::verify_batch]
+    pub fn fold_record<A, B>(
+        &self,
+        a: A,
+        b: B,
+        folded: RelaxedInstance<CF::Curve, CF::Instance>,
+    ) -> FoldRecord<CF>
+    where
+        A: RelaxableInstance<CF::Curve, CF::Instance>,
+        B: RelaxableInstance<CF::Curve, CF::Instance>,
+    {
+        FoldRecord {
+            left: a.relax(self.zero_commitment.clone()),
+            right: b.relax(self.zero_commitment.clone()),
+            folded,
+        }
+    }
+
+    /// Check many folds at once, each of them given by its instances, the
+    /// commitments to its cross terms `T_0` and `T_1` and its challenge `r`.
+    /// The relations of [Self::verify_accumulation] on the commitments, i.e.
+    /// `E = E_1 - r T_0 - r^2 T_1 + r^3 E_2` and the combination of the
+    /// extended columns, are combined with the powers of a single challenge
+    /// `ρ` sampled from `fq_sponge` after absorbing every fold, and checked
+    /// with a single MSM. If any fold is invalid, the combination is nonzero
+    /// except with negligible probability. Every commitment entering the
+    /// MSM, along with `u` and `r`, is absorbed before `ρ` is sampled, so that
+    /// none of them can be chosen after `ρ` to cancel an invalid fold.
+    /// `u` and the inner instances, which are opaque to this library, are
+    /// checked fold by fold with [Instance::combine].
+    /// The challenges are taken as they are, see
+    /// [Self::verify_fold_from_digest] to check them against the transcript.
+    #[allow(clippy::type_complexity)]
+    pub fn verify_batch<Sponge>(
+        &self,
+        items: &[(FoldRecord<CF>, [CF::Curve; 2], ScalarField<CF>)],
+        fq_sponge: &mut Sponge,
+    ) -> bool
+    where
+        Sponge: FqSponge<BaseField<CF>, CF::Curve, ScalarField<CF>>,
+        CF::Instance: PartialEq,
+    {
+        let single = |comm: &PolyComm<CF::Curve>| match comm.elems.as_slice() {
+            [elem] => Some(*elem),
+            _ => None,
+        };
+        // The equations, as pairs of commitments and scalars summing to zero
+        let mut equations: Vec<Vec<(CF::Curve, ScalarField<CF>)>> = vec![];
+        for (record, [t0, t1], r) in items {
+            let FoldRecord {
+                left,
+                right,
+                folded,
+            } = record;
+            if folded.u != left.u + *r * right.u {
+                return false;
+            }
+            let inner = CF::Instance::combine(
+                left.inner_instance().inner.clone(),
+                right.inner_instance().inner.clone(),
+                *r,
+            );
+            if inner != folded.inner_instance().inner {
+                return false;
+            }
+
+            let errors = [left, right, folded].map(|i| single(i.get_error_column_commitment()));
+            let (e1, e2, e) = match errors {
+                [Some(e1), Some(e2), Some(e)] => (e1, e2, e),
+                _ => return false,
+            };
+            equations.push(vec![
+                (e1, ScalarField::<CF>::one()),
+                (*t0, -*r),
+                (*t1, -r.square()),
+                (e2, r.pow([3])),
+                (e, -ScalarField::<CF>::one()),
+            ]);
+
+            let extended = [left, right, folded].map(|i| &i.inner_instance().extended);
+            if extended[0].len() != extended[2].len() || extended[1].len() != extended[2].len() {
+                return false;
+            }
+            for ((a, b), f) in extended[0].iter().zip(extended[1]).zip(extended[2]) {
+                let (a, b, f) = match (single(a), single(b), single(f)) {
+                    (Some(a), Some(b), Some(f)) => (a, b, f),
+                    _ => return false,
+                };
+                equations.push(vec![
+                    (a, ScalarField::<CF>::one()),
+                    (b, *r),
+                    (f, -ScalarField::<CF>::one()),
+                ]);
+                fq_sponge.absorb_g(&[a, b, f]);
+            }
+
+            fq_sponge.absorb_g(&[e1, e2, e, *t0, *t1]);
+            fq_sponge.absorb_fr(&[left.u, right.u, folded.u, *r]);
+        }
+        let rho = fq_sponge.challenge();
+        let powers = successors(Some(ScalarField::<CF>::one()), |p| Some(*p * rho));
+
+        let (bases, scalars): (Vec<_>, Vec<_>) = equations
+            .into_iter()
+            .zip(powers)
+            .flat_map(|(equation, power)| {
+                equation
+                    .into_iter()
+                    .map(move |(base, scalar)| (base, (scalar * power).into_repr()))
+            })
+            .unzip();
+        VariableBaseMSM::multi_scalar_mul(&bases, &scalars).is_zero()
+    }
+}
+
+/// The instances of a fold: the two instances folded and the resulting one,
+/// see [FoldingScheme::verify_batch]
+#[derive(Clone)]
+pub struct FoldRecord<CF: FoldingConfig> {
+    pub left: RelaxedInstance<CF::Curve, CF::Instance>,
+    pub right: RelaxedInstance<CF::Curve, CF::Instance>,
+    pub folded: RelaxedInstance<CF::Curve, CF::Instance>,
+}
+
+/// The data sent by the prover for a single fold, in the form expected by
+/// Nova-style accumulation verifiers: the commitments to the cross terms and
+/// the folding challenge `r`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccumulationProof<G: CommitmentCurve> {
+    /// Commitments to the cross terms `T_0` and `T_1`
+    pub cross_terms: [PolyComm<G>; 2],
+    /// The challenge `r` used to combine the instances
+    pub challenge: G::ScalarField,
+}
+
+/// The commitments to the two cross terms of a fold of constraints of degree
+/// at most 2. The error term of the folded pair is the combination of the
+/// error terms of the inputs, minus `r T_0 + r^2 T_1`, `r` being the folding
+/// challenge.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrossTerms<G: CommitmentCurve> {
+    /// Commitment to the cross term `T_0`
+    pub t0: PolyComm<G>,
+    /// Commitment to the cross term `T_1`
+    pub t1: PolyComm<G>,
+}
+
+impl<G: CommitmentCurve> From<[PolyComm<G>; 2]> for CrossTerms<G> {
+    fn from([t0, t1]: [PolyComm<G>; 2]) -> Self {
+        Self { t0, t1 }
+    }
+}
+
+impl<G: CommitmentCurve> From<CrossTerms<G>> for [PolyComm<G>; 2] {
+    fn from(cross_terms: CrossTerms<G>) -> Self {
+        [cross_terms.t0, cross_terms.t1]
+    }
+}
+
+/// Converts the cross terms returned by
+/// [DecomposableFoldingScheme](decomposable_folding::DecomposableFoldingScheme),
+/// returning them back if there are not exactly 2 of them, e.g. when folding
+/// natively constraints of higher degree.
+impl<G: CommitmentCurve> TryFrom<Vec<PolyComm<G>>> for CrossTerms<G> {
+    type Error = Vec<PolyComm<G>>;
+
+    fn try_from(cross_terms: Vec<PolyComm<G>>) -> Result<Self, Self::Error> {
+        <[PolyComm<G>; 2]>::try_from(cross_terms).map(Self::from)
     }
 }
 
@@ -289,15 +879,38 @@ impl<'a, CF: FoldingConfig> FoldingScheme<'a, CF> {
 /// of other element. This type represents that, allowing to also recognize
 /// which case is present.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "SerializedAlphas<F>", into = "SerializedAlphas<F>")
+)]
 pub enum Alphas<F: Field> {
     Powers(F, Rc<AtomicUsize>),
     Combinations(Vec<F>),
 }
 
+/// Two sets of powers are equal when they have the same base, the number of
+/// powers requested so far not being taken into account. Powers and
+/// combinations, e.g. from a fold with the challenge `0`, are equal when the
+/// powers requested so far are the combined values.
+impl<F: Field> PartialEq for Alphas<F> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Alphas::Powers(a, _), Alphas::Powers(b, _)) => a == b,
+            (Alphas::Combinations(a), Alphas::Combinations(b)) => a == b,
+            _ => self.iter().eq(other.iter()),
+        }
+    }
+}
+
 impl<F: Field> Alphas<F> {
     pub fn new(alpha: F) -> Self {
         Self::Powers(alpha, Rc::new(AtomicUsize::from(0)))
     }
+    /// `n` combinations equal to zero, as used by the identity of folding
+    pub fn zero(n: usize) -> Self {
+        Self::Combinations(vec![F::zero(); n])
+    }
     pub fn get(&self, i: usize) -> Option<F> {
         match self {
             Alphas::Powers(alpha, count) => {
@@ -318,15 +931,155 @@ impl<F: Field> Alphas<F> {
             Alphas::Combinations(c) => c,
         }
     }
+    /// The number of alphas, that is the powers requested so far or the
+    /// combined values
+    pub fn len(&self) -> usize {
+        match self {
+            Alphas::Powers(_, count) => count.load(Ordering::Relaxed),
+            Alphas::Combinations(alphas) => alphas.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Iterates over the alphas in index order, without requesting new powers
+    pub fn iter(&self) -> impl Iterator<Item = F> + '_ {
+        match self {
+            Alphas::Powers(alpha, _) => {
+                let alphas = successors(Some(F::one()), move |last| Some(*last * alpha));
+                Either::Left(alphas.take(self.len()))
+            }
+            Alphas::Combinations(alphas) => Either::Right(alphas.iter().copied()),
+        }
+    }
+    /// Combines the alphas of two instances into `a + challenge * b`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` and `b` do not have the same number of alphas, see
+    /// [Alphas::try_combine].
     pub fn combine(a: Self, b: Self, challenge: F) -> Self {
+        Self::try_combine(a, b, challenge).unwrap_or_else(|e| panic!("{e}"))
+    }
+    /// Same as [Alphas::combine], but returns an error instead of panicking
+    /// when `a` and `b` do not have the same number of alphas.
+    pub fn try_combine(a: Self, b: Self, challenge: F) -> Result<Self, AlphasError> {
         let a = a.powers();
         let b = b.powers();
-        assert_eq!(a.len(), b.len());
+        if a.len() != b.len() {
+            return Err(AlphasError::LengthMismatch {
+                left: a.len(),
+                right: b.len(),
+            });
+        }
         let comb = a
             .into_iter()
             .zip(b)
             .map(|(a, b)| a + b * challenge)
             .collect();
-        Self::Combinations(comb)
+        Ok(Self::Combinations(comb))
+    }
+
+    /// Serializes the alphas with the encoding of [CanonicalSerialize]: a tag,
+    /// followed by the base alpha and the number of powers requested so far,
+    /// or by the combined values
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        match self {
+            Alphas::Powers(alpha, count) => {
+                ALPHAS_POWERS.serialize(&mut bytes).unwrap();
+                alpha.serialize(&mut bytes).unwrap();
+                (count.load(Ordering::Relaxed) as u64)
+                    .serialize(&mut bytes)
+                    .unwrap();
+            }
+            Alphas::Combinations(alphas) => {
+                ALPHAS_COMBINATIONS.serialize(&mut bytes).unwrap();
+                alphas.serialize(&mut bytes).unwrap();
+            }
+        }
+        bytes
+    }
+
+    /// Deserializes alphas serialized by [Alphas::to_bytes]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        let mut reader = bytes;
+        match u8::deserialize(&mut reader)? {
+            ALPHAS_POWERS => {
+                let alpha = F::deserialize(&mut reader)?;
+                let count = u64::deserialize(&mut reader)? as usize;
+                Ok(Alphas::Powers(alpha, Rc::new(AtomicUsize::from(count))))
+            }
+            ALPHAS_COMBINATIONS => Ok(Alphas::Combinations(Vec::deserialize(&mut reader)?)),
+            _ => Err(SerializationError::InvalidData),
+        }
+    }
+}
+
+/// Errors that can occur when folding with a [FoldingScheme]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FoldingError {
+    /// The folding challenge is zero, which would discard the right pair, see
+    /// [FoldingScheme::check_challenge]
+    #[error("the folding challenge is zero")]
+    ZeroChallenge,
+}
+
+/// Errors that can occur when combining [Alphas]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AlphasError {
+    /// The two alphas were built with a different number of powers, e.g.
+    /// because their instances come from different constraint systems
+    #[error("cannot combine {left} alphas with {right} alphas, the numbers of alphas must match")]
+    LengthMismatch { left: usize, right: usize },
+}
+
+impl<F: Field> IntoIterator for Alphas<F> {
+    type Item = F;
+    type IntoIter = std::vec::IntoIter<F>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.powers().into_iter()
+    }
+}
+
+const ALPHAS_POWERS: u8 = 0;
+const ALPHAS_COMBINATIONS: u8 = 1;
+
+/// The serde representation of [Alphas], the shared counter of powers being
+/// replaced by its value
+#[cfg(feature = "serde")]
+#[serde_with::serde_as]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(bound = "F: Field")]
+enum SerializedAlphas<F: Field> {
+    Powers(
+        #[serde_as(as = "o1_utils::serialization::SerdeAs")] F,
+        usize,
+    ),
+    Combinations(#[serde_as(as = "Vec<o1_utils::serialization::SerdeAs>")] Vec<F>),
+}
+
+#[cfg(feature = "serde")]
+impl<F: Field> From<Alphas<F>> for SerializedAlphas<F> {
+    fn from(alphas: Alphas<F>) -> Self {
+        match alphas {
+            Alphas::Powers(alpha, count) => {
+                SerializedAlphas::Powers(alpha, count.load(Ordering::Relaxed))
+            }
+            Alphas::Combinations(alphas) => SerializedAlphas::Combinations(alphas),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<F: Field> From<SerializedAlphas<F>> for Alphas<F> {
+    fn from(alphas: SerializedAlphas<F>) -> Self {
+        match alphas {
+            SerializedAlphas::Powers(alpha, count) => {
+                Alphas::Powers(alpha, Rc::new(AtomicUsize::from(count)))
+            }
+            SerializedAlphas::Combinations(alphas) => Alphas::Combinations(alphas),
+        }
     }
 }
@@ -0,0 +1,238 @@
+//! A witness whose columns are stored in a memory-mapped file, for traces too
+//! large to be held in memory.
+//!
+//! The evaluations are stored column after column, each serialized with
+//! [CanonicalSerialize]. Combining two witnesses streams over the files by
+//! chunks of [CHUNK_SIZE] rows, writing the result in place in the file of the
+//! first witness.
+
+use crate::{instance_witness::Witness, Evals};
+use ark_ff::PrimeField;
+use ark_poly::{Evaluations, Radix2EvaluationDomain};
+use poly_commitment::commitment::CommitmentCurve;
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    os::unix::io::AsRawFd,
+    path::Path,
+};
+
+/// The number of rows of a column read or written at once
+pub const CHUNK_SIZE: usize = 1 << 12;
+
+/// A read-write shared mapping of a whole file, unmapped on drop
+struct Mmap {
+    ptr: *mut u8,
+    len: usize,
+    // The file is kept open as long as it is mapped
+    _file: File,
+}
+
+impl Mmap {
+    fn new(file: File, len: usize) -> io::Result<Self> {
+        if len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot map an empty witness",
+            ));
+        }
+        // SAFETY: the mapping is created at an address chosen by the kernel, so
+        // it cannot alias any existing memory. The result is checked below.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: ptr as *mut u8,
+            len,
+            _file: file,
+        })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        // SAFETY: `ptr` points to `len` mapped bytes, which stay mapped until
+        // `self` is dropped, and the borrow of `self` prevents any mutable
+        // access through [Self::as_mut_slice] while the slice is alive.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: as for [Self::as_slice], the mutable borrow of `self` makes
+        // this slice the only access to the mapping while it is alive.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` and `len` describe the mapping created in [Mmap::new],
+        // and no slice borrowing it can outlive `self`.
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+}
+
+/// A witness of `columns` columns of `rows` evaluations, backed by a
+/// memory-mapped file
+pub struct MmapWitness<F: PrimeField> {
+    map: Mmap,
+    columns: usize,
+    rows: usize,
+    domain: Radix2EvaluationDomain<F>,
+}
+
+impl<F: PrimeField> MmapWitness<F> {
+    fn element_size() -> usize {
+        F::zero().serialized_size()
+    }
+
+    /// The size in bytes of the file of a witness of `columns` columns over
+    /// `domain`
+    fn file_len(columns: usize, domain: Radix2EvaluationDomain<F>) -> usize {
+        columns * domain.size as usize * Self::element_size()
+    }
+
+    /// Maps `file`, which must already have the size of a witness of
+    /// `columns` columns over `domain`
+    fn open_file(
+        file: File,
+        columns: usize,
+        domain: Radix2EvaluationDomain<F>,
+    ) -> io::Result<Self> {
+        let rows = domain.size as usize;
+        let len = Self::file_len(columns, domain);
+        let file_len = file.metadata()?.len();
+        if file_len != len as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the witness file has {file_len} bytes, expected {len} for {columns} columns of {rows} rows"
+                ),
+            ));
+        }
+        let map = Mmap::new(file, len)?;
+        Ok(Self {
+            map,
+            columns,
+            rows,
+            domain,
+        })
+    }
+
+    /// Writes the given columns into a new file at `path`, overwriting it if
+    /// it exists, and maps it
+    pub fn create(path: &Path, columns: &[Evals<F>]) -> io::Result<Self> {
+        let domain = columns.first().map(|c| c.domain()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a witness needs at least one column",
+            )
+        })?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(Self::file_len(columns.len(), domain) as u64)?;
+        let mut witness = Self::open_file(file, columns.len(), domain)?;
+        for (i, column) in columns.iter().enumerate() {
+            assert_eq!(column.evals.len(), witness.rows);
+            witness.write_rows(i, 0, &column.evals)?;
+        }
+        Ok(witness)
+    }
+
+    /// Maps an existing file, written by [Self::create], of `columns` columns
+    /// over `domain`. Fails with [io::ErrorKind::InvalidInput] if the size of
+    /// the file does not match.
+    pub fn open(
+        path: &Path,
+        columns: usize,
+        domain: Radix2EvaluationDomain<F>,
+    ) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Self::open_file(file, columns, domain)
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    fn offset(&self, column: usize, row: usize) -> usize {
+        (column * self.rows + row) * Self::element_size()
+    }
+
+    /// Reads the rows `start..start + len` of a column. Fails with
+    /// [io::ErrorKind::InvalidData] if the file does not hold valid field
+    /// elements.
+    fn read_rows(&self, column: usize, start: usize, len: usize) -> io::Result<Vec<F>> {
+        let size = Self::element_size();
+        let offset = self.offset(column, start);
+        self.map.as_slice()[offset..offset + len * size]
+            .chunks(size)
+            .map(|bytes| {
+                F::deserialize(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            })
+            .collect()
+    }
+
+    /// Writes `values` in a column, starting at the row `start`
+    fn write_rows(&mut self, column: usize, start: usize, values: &[F]) -> io::Result<()> {
+        let size = Self::element_size();
+        let offset = self.offset(column, start);
+        let bytes = &mut self.map.as_mut_slice()[offset..offset + values.len() * size];
+        for (x, bytes) in values.iter().zip(bytes.chunks_mut(size)) {
+            x.serialize(bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        Ok(())
+    }
+
+    /// Loads a column in memory
+    pub fn column(&self, i: usize) -> io::Result<Evals<F>> {
+        assert!(i < self.columns);
+        let evals = self.read_rows(i, 0, self.rows)?;
+        Ok(Evaluations::from_vec_and_domain(evals, self.domain))
+    }
+}
+
+impl<G: CommitmentCurve> Witness<G> for MmapWitness<G::ScalarField> {
+    fn combine(mut a: Self, b: Self, challenge: G::ScalarField) -> Self {
+        assert_eq!(a.columns, b.columns);
+        assert_eq!(a.rows, b.rows);
+        for column in 0..a.columns {
+            for start in (0..a.rows).step_by(CHUNK_SIZE) {
+                let len = CHUNK_SIZE.min(a.rows - start);
+                // The trait cannot report errors, the files are expected to
+                // be the ones written by [MmapWitness::create]
+                let mut chunk = a
+                    .read_rows(column, start, len)
+                    .expect("invalid left witness file");
+                let other = b
+                    .read_rows(column, start, len)
+                    .expect("invalid right witness file");
+                for (x, y) in chunk.iter_mut().zip(other) {
+                    *x += challenge * y;
+                }
+                a.write_rows(column, start, &chunk)
+                    .expect("failed to write the folded witness");
+            }
+        }
+        a
+    }
+
+    fn rows(&self) -> usize {
+        self.rows
+    }
+}
@@ -0,0 +1,354 @@
+//! Export of a relaxed accumulator as a relaxed R1CS instance, to hand it to
+//! deciders working over R1CS.
+//!
+//! A relaxed R1CS is given by three matrices `A`, `B` and `C`, a scalar `u`,
+//! an error vector `E` and a vector `z`, and holds when
+//! `Az ∘ Bz = u Cz + E`.
+//! The final constraint of a [FoldingScheme](crate::FoldingScheme) evaluates
+//! in each row to `d_2 + u d_1 + u^2 d_0 + E`, where `d_i` is the sum of the
+//! terms of degree `i` in the witness. Taking `z = (u, w)`, where `w` are the
+//! witness cells referenced by the constraint, every term but the error is
+//! of degree 2 in `z`, and each row of the constraint gives one row of the
+//! R1CS:
+//! - the terms without `u` give `Az ∘ Bz`,
+//! - the terms `u l(z)` give `-Cz`,
+//! - the error gives `-E`.
+//!
+//! The translation has the following limits:
+//! - it only supports constraints of degree 2. Higher degree constraints must
+//! be reduced first, see [quadraticization](crate::quadraticization);
+//! - in each row, the terms without `u` must share a common variable `x`, so
+//! that they factor as `x l(z)`, for instance a single product of two cells;
+//! - the challenges, the alphas and the non-witness columns of the accumulator
+//! are evaluated and end up in the coefficients of the matrices, which are
+//! therefore specific to the exported accumulator.
+
+use crate::{
+    checker::Provide,
+    expressions::{Degree, FoldingColumnTrait, FoldingCompatibleExpr, FoldingCompatibleExprInner},
+    ExpExtension, FoldingConfig, ScalarField,
+};
+use ark_ff::{Field, One, Zero};
+use kimchi::circuits::{expr::Variable, gate::CurrOrNext};
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+/// A sparse matrix, given by the `(column, coefficient)` entries of each row
+pub type SparseMatrix<F> = Vec<Vec<(usize, F)>>;
+
+/// A relaxed R1CS instance with its witness `z`, whose first entry is `u`
+#[derive(Clone, Debug, PartialEq)]
+pub struct RelaxedR1CS<F> {
+    pub a: SparseMatrix<F>,
+    pub b: SparseMatrix<F>,
+    pub c: SparseMatrix<F>,
+    pub u: F,
+    pub e: Vec<F>,
+    pub z: Vec<F>,
+}
+
+impl<F: Field> RelaxedR1CS<F> {
+    fn mul_vec(&self, m: &SparseMatrix<F>) -> Vec<F> {
+        m.iter()
+            .map(|row| row.iter().map(|(i, c)| *c * self.z[*i]).sum())
+            .collect()
+    }
+
+    /// Checks that `Az ∘ Bz = u Cz + E`
+    pub fn is_satisfied(&self) -> bool {
+        let [az, bz, cz] = [&self.a, &self.b, &self.c].map(|m| self.mul_vec(m));
+        self.z.first() == Some(&self.u)
+            && self.e.len() == az.len()
+            && az
+                .iter()
+                .zip(bz.iter())
+                .zip(cz.iter())
+                .zip(self.e.iter())
+                .all(|(((a, b), c), e)| *a * b == self.u * c + e)
+    }
+}
+
+/// Ways in which a constraint can fail to be translated to R1CS
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum R1CSExportError {
+    #[error("the constraint has a degree higher than 2")]
+    DegreeTooHigh,
+    #[error("row {row} has a term of degree {degree} in z, expected 2")]
+    NotHomogeneous { row: usize, degree: usize },
+    #[error("the terms without u of row {row} do not share a common variable")]
+    NotFactorable { row: usize },
+}
+
+/// A polynomial of degree at most 2 in the entries of `z`
+#[derive(Clone, Debug)]
+struct Quadratic<F> {
+    constant: F,
+    linear: BTreeMap<usize, F>,
+    quadratic: BTreeMap<(usize, usize), F>,
+}
+
+impl<F: Field> Quadratic<F> {
+    fn constant(constant: F) -> Self {
+        Self {
+            constant,
+            linear: BTreeMap::new(),
+            quadratic: BTreeMap::new(),
+        }
+    }
+
+    fn variable(i: usize) -> Self {
+        let mut x = Self::constant(F::zero());
+        x.linear.insert(i, F::one());
+        x
+    }
+
+    fn is_constant(&self) -> bool {
+        self.linear.values().all(|c| c.is_zero()) && self.quadratic.values().all(|c| c.is_zero())
+    }
+
+    fn scale(mut self, s: F) -> Self {
+        self.constant *= s;
+        self.linear.values_mut().for_each(|c| *c *= s);
+        self.quadratic.values_mut().for_each(|c| *c *= s);
+        self
+    }
+
+    fn add(mut self, other: Self) -> Self {
+        self.constant += other.constant;
+        for (i, c) in other.linear {
+            *self.linear.entry(i).or_insert_with(F::zero) += c;
+        }
+        for (ij, c) in other.quadratic {
+            *self.quadratic.entry(ij).or_insert_with(F::zero) += c;
+        }
+        self
+    }
+
+    fn mul(self, other: Self) -> Result<Self, R1CSExportError> {
+        if self.is_constant() {
+            return Ok(other.scale(self.constant));
+        }
+        if other.is_constant() {
+            return Ok(self.scale(other.constant));
+        }
+        if !self.quadratic.values().all(|c| c.is_zero())
+            || !other.quadratic.values().all(|c| c.is_zero())
+        {
+            return Err(R1CSExportError::DegreeTooHigh);
+        }
+        // (c1 + l1)(c2 + l2) = c1 c2 + c2 l1 + c1 l2 + l1 l2
+        let mut res = Self::constant(self.constant * other.constant);
+        for (i, c) in self.linear.iter() {
+            *res.linear.entry(*i).or_insert_with(F::zero) += *c * other.constant;
+        }
+        for (i, c) in other.linear.iter() {
+            *res.linear.entry(*i).or_insert_with(F::zero) += *c * self.constant;
+        }
+        for (i, c1) in self.linear.iter() {
+            for (j, c2) in other.linear.iter() {
+                let key = if i <= j { (*i, *j) } else { (*j, *i) };
+                *res.quadratic.entry(key).or_insert_with(F::zero) += *c1 * c2;
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// A variable of `z`, other than `u`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum R1CSVariable<C: FoldingConfig> {
+    Cell(C::Column, usize),
+    Extended(usize, usize),
+    Selector(C::Selector, usize),
+}
+
+struct Exporter<'a, C: FoldingConfig, P> {
+    provider: &'a P,
+    rows: usize,
+    indices: HashMap<R1CSVariable<C>, usize>,
+    z: Vec<ScalarField<C>>,
+}
+
+impl<'a, C: FoldingConfig, P: Provide<C>> Exporter<'a, C, P> {
+    /// Returns the entries of `z` for the rows of a column, adding them to `z`
+    /// the first time they are seen
+    fn variables(
+        &mut self,
+        column: FoldingCompatibleExprInner<C>,
+        variable: impl Fn(usize) -> R1CSVariable<C>,
+        rotation: usize,
+    ) -> Vec<Quadratic<ScalarField<C>>> {
+        let values = self.provider.resolve(column);
+        (0..self.rows)
+            .map(|row| {
+                let row = (row + rotation) % self.rows;
+                let z = &mut self.z;
+                let i = *self.indices.entry(variable(row)).or_insert_with(|| {
+                    z.push(values[row]);
+                    z.len() - 1
+                });
+                Quadratic::variable(i)
+            })
+            .collect()
+    }
+
+    fn constants(&self, inner: FoldingCompatibleExprInner<C>) -> Vec<Quadratic<ScalarField<C>>> {
+        self.provider
+            .resolve(inner)
+            .into_iter()
+            .map(Quadratic::constant)
+            .collect()
+    }
+
+    fn eval(
+        &mut self,
+        exp: &FoldingCompatibleExpr<C>,
+    ) -> Result<Vec<Quadratic<ScalarField<C>>>, R1CSExportError> {
+        use FoldingCompatibleExpr::*;
+        use FoldingCompatibleExprInner::*;
+        let res = match exp {
+            Atom(Cell(Variable { col, row })) if col.degree() == Degree::One => {
+                let col = *col;
+                let rotation = match row {
+                    CurrOrNext::Curr => 0,
+                    CurrOrNext::Next => 1,
+                };
+                let column = Cell(Variable {
+                    col,
+                    row: CurrOrNext::Curr,
+                });
+                self.variables(column, |row| R1CSVariable::Cell(col, row), rotation)
+            }
+            Atom(Extensions(ExpExtension::U)) => vec![Quadratic::variable(0); self.rows],
+            Atom(Extensions(ExpExtension::ExtendedWitness(i))) => {
+                let i = *i;
+                let column = Extensions(ExpExtension::ExtendedWitness(i));
+                self.variables(column, |row| R1CSVariable::Extended(i, row), 0)
+            }
+            Atom(Extensions(ExpExtension::Selector(s))) => {
+                let s = *s;
+                let column = Extensions(ExpExtension::Selector(s));
+                self.variables(column, |row| R1CSVariable::Selector(s, row), 0)
+            }
+            Atom(inner) => self.constants(inner.clone()),
+            Double(e) => {
+                let two = ScalarField::<C>::one().double();
+                self.eval(e)?.into_iter().map(|x| x.scale(two)).collect()
+            }
+            Square(e) => self
+                .eval(e)?
+                .into_iter()
+                .map(|x| x.clone().mul(x))
+                .collect::<Result<_, _>>()?,
+            Add(e1, e2) => {
+                let v1 = self.eval(e1)?;
+                let v2 = self.eval(e2)?;
+                v1.into_iter().zip(v2).map(|(a, b)| a.add(b)).collect()
+            }
+            Sub(e1, e2) => {
+                let v1 = self.eval(e1)?;
+                let v2 = self.eval(e2)?;
+                let minus_one = -ScalarField::<C>::one();
+                v1.into_iter()
+                    .zip(v2)
+                    .map(|(a, b)| a.add(b.scale(minus_one)))
+                    .collect()
+            }
+            Mul(e1, e2) => {
+                let v1 = self.eval(e1)?;
+                let v2 = self.eval(e2)?;
+                v1.into_iter()
+                    .zip(v2)
+                    .map(|(a, b)| a.mul(b))
+                    .collect::<Result<_, _>>()?
+            }
+            Pow(e, p) => self
+                .eval(e)?
+                .into_iter()
+                .map(|x| {
+                    (0..*p).try_fold(Quadratic::constant(ScalarField::<C>::one()), |acc, _| {
+                        acc.mul(x.clone())
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        Ok(res)
+    }
+}
+
+/// Exports the relaxed accumulator resolved by `provider` as a relaxed R1CS,
+/// following the translation described in the [module documentation](self).
+/// `exp` is the final constraint returned by
+/// [FoldingScheme::new](crate::FoldingScheme::new).
+/// The R1CS is satisfied if and only if the final constraint is zero in every
+/// row.
+pub fn export_relaxed_r1cs<C, P>(
+    provider: &P,
+    exp: &FoldingCompatibleExpr<C>,
+) -> Result<RelaxedR1CS<ScalarField<C>>, R1CSExportError>
+where
+    C: FoldingConfig,
+    P: Provide<C>,
+{
+    let u = provider.resolve(FoldingCompatibleExprInner::Extensions(ExpExtension::U));
+    let mut exporter = Exporter {
+        provider,
+        rows: u.len(),
+        indices: HashMap::new(),
+        z: vec![u[0]],
+    };
+    let rows = exporter.eval(exp)?;
+
+    let (mut a, mut b, mut c, mut e) = (vec![], vec![], vec![], vec![]);
+    for (row, q) in rows.into_iter().enumerate() {
+        if q.linear.values().any(|c| !c.is_zero()) {
+            return Err(R1CSExportError::NotHomogeneous { row, degree: 1 });
+        }
+        let terms: Vec<_> = q
+            .quadratic
+            .into_iter()
+            .filter(|(_, c)| !c.is_zero())
+            .collect();
+        let (with_u, without_u): (Vec<_>, Vec<_>) =
+            terms.into_iter().partition(|((i, _), _)| *i == 0);
+
+        // u c z_j contributes to u l(z), that is -C
+        c.push(
+            with_u
+                .into_iter()
+                .map(|((_, j), coeff)| (j, -coeff))
+                .collect(),
+        );
+
+        // the terms without u are factored as x l(z)
+        match without_u.first() {
+            None => {
+                a.push(vec![]);
+                b.push(vec![]);
+            }
+            Some(((i, j), _)) => {
+                let common = [*i, *j]
+                    .into_iter()
+                    .find(|x| without_u.iter().all(|((i, j), _)| i == x || j == x))
+                    .ok_or(R1CSExportError::NotFactorable { row })?;
+                a.push(vec![(common, ScalarField::<C>::one())]);
+                b.push(
+                    without_u
+                        .into_iter()
+                        .map(|((i, j), coeff)| (if i == common { j } else { i }, coeff))
+                        .collect(),
+                );
+            }
+        }
+        e.push(-q.constant);
+    }
+
+    Ok(RelaxedR1CS {
+        a,
+        b,
+        c,
+        u: u[0],
+        e,
+        z: exporter.z,
+    })
+}
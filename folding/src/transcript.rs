@@ -0,0 +1,170 @@
+//! A Fiat-Shamir transcript for folding, wrapping an [FqSponge].
+//!
+//! The prover and the verifier of a fold must absorb the same values in the
+//! same order to derive the same challenges. [FoldingTranscript] gives names
+//! to the operations used by the folding scheme, and implements [FqSponge]
+//! itself, so that it can be given to any method of [crate::FoldingScheme]
+//! taking a sponge, e.g. [crate::FoldingScheme::fold_with_accumulation_proof]
+//! on the prover side and [crate::FoldingScheme::verify_accumulation] on the
+//! verifier side.
+//!
+//! [DeterministicTranscript] is a stand-in for tests, deriving its challenges
+//! from a seed only, so that they are the same across runs.
+
+#[cfg(any(test, feature = "test-utils"))]
+use ark_ff::PrimeField;
+#[cfg(any(test, feature = "test-utils"))]
+use blake2::{Blake2b512, Digest};
+use mina_poseidon::{poseidon::ArithmeticSpongeParams, FqSponge};
+use poly_commitment::commitment::CommitmentCurve;
+use std::marker::PhantomData;
+
+/// A transcript over the curve `G`, backed by the sponge `Sponge`
+pub struct FoldingTranscript<G, Sponge> {
+    sponge: Sponge,
+    _curve: PhantomData<G>,
+}
+
+impl<G, Sponge> FoldingTranscript<G, Sponge>
+where
+    G: CommitmentCurve,
+    Sponge: FqSponge<G::BaseField, G, G::ScalarField>,
+{
+    /// Starts a transcript from the current state of `sponge`
+    pub fn new(sponge: Sponge) -> Self {
+        Self {
+            sponge,
+            _curve: PhantomData,
+        }
+    }
+
+    /// Absorbs commitments, for instance the columns of an instance or the
+    /// cross terms of a fold
+    pub fn absorb_commitments(&mut self, commitments: &[G]) {
+        self.sponge.absorb_g(commitments);
+    }
+
+    /// Absorbs elements of the scalar field
+    pub fn absorb_scalars(&mut self, scalars: &[G::ScalarField]) {
+        self.sponge.absorb_fr(scalars);
+    }
+
+    /// Squeezes a challenge in the scalar field from everything absorbed so
+    /// far
+    pub fn challenge(&mut self) -> G::ScalarField {
+        self.sponge.challenge()
+    }
+
+    /// Returns the underlying sponge
+    pub fn into_sponge(self) -> Sponge {
+        self.sponge
+    }
+}
+
+impl<G, Sponge> FqSponge<G::BaseField, G, G::ScalarField> for FoldingTranscript<G, Sponge>
+where
+    G: CommitmentCurve,
+    Sponge: FqSponge<G::BaseField, G, G::ScalarField>,
+{
+    fn new(params: &'static ArithmeticSpongeParams<G::BaseField>) -> Self {
+        Self::new(Sponge::new(params))
+    }
+
+    fn absorb_fq(&mut self, x: &[G::BaseField]) {
+        self.sponge.absorb_fq(x)
+    }
+
+    fn absorb_g(&mut self, g: &[G]) {
+        self.absorb_commitments(g)
+    }
+
+    fn absorb_fr(&mut self, x: &[G::ScalarField]) {
+        self.absorb_scalars(x)
+    }
+
+    fn challenge_fq(&mut self) -> G::BaseField {
+        self.sponge.challenge_fq()
+    }
+
+    fn challenge(&mut self) -> G::ScalarField {
+        self.sponge.challenge()
+    }
+
+    fn digest_fq(self) -> G::BaseField {
+        self.sponge.digest_fq()
+    }
+
+    fn digest(self) -> G::ScalarField {
+        self.sponge.digest()
+    }
+}
+
+/// A transcript for tests, whose challenges only depend on a seed and on the
+/// number of challenges squeezed so far: the `i`-th challenge is the Blake2b
+/// hash of the seed and of `i`, both as little-endian `u64`, reduced modulo
+/// the field order. Absorbed values are ignored, which makes the challenges
+/// easy to reproduce for regression fixtures, but the transcript must never be
+/// used outside of tests.
+#[cfg(any(test, feature = "test-utils"))]
+pub struct DeterministicTranscript<G> {
+    seed: u64,
+    counter: u64,
+    _curve: PhantomData<G>,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl<G: CommitmentCurve> DeterministicTranscript<G> {
+    /// Starts a transcript from `seed`
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            counter: 0,
+            _curve: PhantomData,
+        }
+    }
+
+    fn next_bytes(&mut self) -> Vec<u8> {
+        let mut hasher = Blake2b512::new();
+        hasher.update(self.seed.to_le_bytes());
+        hasher.update(self.counter.to_le_bytes());
+        self.counter += 1;
+        hasher.finalize().to_vec()
+    }
+
+    /// Returns the next challenge in the scalar field
+    pub fn challenge(&mut self) -> G::ScalarField {
+        G::ScalarField::from_le_bytes_mod_order(&self.next_bytes())
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl<G: CommitmentCurve> FqSponge<G::BaseField, G, G::ScalarField> for DeterministicTranscript<G>
+where
+    G::BaseField: PrimeField,
+{
+    fn new(_params: &'static ArithmeticSpongeParams<G::BaseField>) -> Self {
+        Self::new(0)
+    }
+
+    fn absorb_fq(&mut self, _x: &[G::BaseField]) {}
+
+    fn absorb_g(&mut self, _g: &[G]) {}
+
+    fn absorb_fr(&mut self, _x: &[G::ScalarField]) {}
+
+    fn challenge_fq(&mut self) -> G::BaseField {
+        G::BaseField::from_le_bytes_mod_order(&self.next_bytes())
+    }
+
+    fn challenge(&mut self) -> G::ScalarField {
+        self.challenge()
+    }
+
+    fn digest_fq(mut self) -> G::BaseField {
+        self.challenge_fq()
+    }
+
+    fn digest(mut self) -> G::ScalarField {
+        self.challenge()
+    }
+}
@@ -347,6 +347,12 @@ impl<Field: PrimeField> SnarkyConstraintSystem<Field> {
         self.public_input_size = Some(num_pub_inputs);
     }
 
+    /// Returns the row of the circuit, public input rows included, at which
+    /// the next gate will be added.
+    pub fn current_row(&self) -> usize {
+        self.public_input_size.unwrap_or_default() + self.next_row
+    }
+
     pub fn set_prev_challenges(&mut self, prev_challenges: usize) {
         if self.prev_challenges.is_some() {
             panic!("set_prev_challenges can only be called once");
@@ -1707,10 +1713,12 @@ where
             BasicSnarkyConstraint::Boolean(v) => {
                 let v = env.read_var(v);
                 if !(v.is_one() || v.is_zero()) {
-                    Err(SnarkyRuntimeError::UnsatisfiedBooleanConstraint(
-                        env.constraints_counter(),
-                        v.to_string(),
-                    ))
+                    Err(SnarkyRuntimeError::UnsatisfiedBooleanConstraint {
+                        constraint: env.constraints_counter(),
+                        value: v.to_string(),
+                        row: env.constraint_row(),
+                        label: env.constraint_label(),
+                    })
                 } else {
                     Ok(())
                 }
@@ -1719,11 +1727,13 @@ where
                 let v1 = env.read_var(v1);
                 let v2 = env.read_var(v2);
                 if v1 != v2 {
-                    Err(SnarkyRuntimeError::UnsatisfiedEqualConstraint(
-                        env.constraints_counter(),
-                        v1.to_string(),
-                        v2.to_string(),
-                    ))
+                    Err(SnarkyRuntimeError::UnsatisfiedEqualConstraint {
+                        constraint: env.constraints_counter(),
+                        lhs: v1.to_string(),
+                        rhs: v2.to_string(),
+                        row: env.constraint_row(),
+                        label: env.constraint_label(),
+                    })
                 } else {
                     Ok(())
                 }
@@ -1733,11 +1743,13 @@ where
                 let v2 = env.read_var(v2);
                 let square = v1.square();
                 if square != v2 {
-                    Err(SnarkyRuntimeError::UnsatisfiedSquareConstraint(
-                        env.constraints_counter(),
-                        v1.to_string(),
-                        v2.to_string(),
-                    ))
+                    Err(SnarkyRuntimeError::UnsatisfiedSquareConstraint {
+                        constraint: env.constraints_counter(),
+                        value: v1.to_string(),
+                        square: v2.to_string(),
+                        row: env.constraint_row(),
+                        label: env.constraint_label(),
+                    })
                 } else {
                     Ok(())
                 }
@@ -1748,12 +1760,14 @@ where
                 let v3 = env.read_var(v3);
                 let mul = v1 * v2;
                 if mul != v3 {
-                    Err(SnarkyRuntimeError::UnsatisfiedR1CSConstraint(
-                        env.constraints_counter(),
-                        v1.to_string(),
-                        v2.to_string(),
-                        v3.to_string(),
-                    ))
+                    Err(SnarkyRuntimeError::UnsatisfiedR1CSConstraint {
+                        constraint: env.constraints_counter(),
+                        a: v1.to_string(),
+                        b: v2.to_string(),
+                        c: v3.to_string(),
+                        row: env.constraint_row(),
+                        label: env.constraint_label(),
+                    })
                 } else {
                     Ok(())
                 }
@@ -1787,17 +1801,19 @@ where
                 let res = *c0 * l + *c1 * r + *c2 * o + l * r * c3 + c4;
                 if !res.is_zero() {
                     // TODO: return different errors depending on the type of generic gate (e.g. addition, cst, mul, etc.)
-                    return Err(Box::new(SnarkyRuntimeError::UnsatisfiedGenericConstraint(
-                        c0.to_string(),
-                        l.to_string(),
-                        c1.to_string(),
-                        r.to_string(),
-                        c2.to_string(),
-                        o.to_string(),
-                        c3.to_string(),
-                        c4.to_string(),
-                        env.constraints_counter(),
-                    )));
+                    return Err(Box::new(SnarkyRuntimeError::UnsatisfiedGenericConstraint {
+                        c0: c0.to_string(),
+                        l: l.to_string(),
+                        c1: c1.to_string(),
+                        r: r.to_string(),
+                        c2: c2.to_string(),
+                        o: o.to_string(),
+                        c3: c3.to_string(),
+                        c4: c4.to_string(),
+                        constraint: env.constraints_counter(),
+                        row: env.constraint_row(),
+                        label: env.constraint_label(),
+                    }));
                 }
             }
 
@@ -75,42 +75,155 @@ pub enum SnarkyError {
     RuntimeError(SnarkyRuntimeError),
 }
 
+impl From<SnarkyCompilationError> for SnarkyError {
+    fn from(error: SnarkyCompilationError) -> Self {
+        SnarkyError::CompilationError(error)
+    }
+}
+
+impl From<SnarkyRuntimeError> for SnarkyError {
+    fn from(error: SnarkyRuntimeError) -> Self {
+        SnarkyError::RuntimeError(error)
+    }
+}
+
+// The conversions below allow using `?` on the compilation and runtime
+// results within a function returning a [SnarkyResult]. Note that they do not
+// record the location nor the labels of the error, use the helpers of
+// [crate::snarky::runner::RunState] when those are known.
+
+impl From<SnarkyError> for Box<RealSnarkyError> {
+    fn from(error: SnarkyError) -> Self {
+        Box::new(RealSnarkyError::new(error))
+    }
+}
+
+impl From<SnarkyCompilationError> for Box<RealSnarkyError> {
+    fn from(error: SnarkyCompilationError) -> Self {
+        SnarkyError::from(error).into()
+    }
+}
+
+impl From<SnarkyRuntimeError> for Box<RealSnarkyError> {
+    fn from(error: SnarkyRuntimeError) -> Self {
+        SnarkyError::from(error).into()
+    }
+}
+
+impl From<Box<SnarkyRuntimeError>> for Box<RealSnarkyError> {
+    fn from(error: Box<SnarkyRuntimeError>) -> Self {
+        (*error).into()
+    }
+}
+
+/// Helpers to narrow a [SnarkyResult] down to one kind of error.
+pub trait SnarkyResultExt<T> {
+    /// Returns the compilation result, or the original error if it is not a
+    /// compilation error.
+    fn into_compile_result(self) -> Result<SnarkyCompileResult<T>, Box<RealSnarkyError>>;
+
+    /// Returns the runtime result, or the original error if it is not a
+    /// runtime error.
+    fn into_runtime_result(self) -> Result<SnarkyRuntimeResult<T>, Box<RealSnarkyError>>;
+}
+
+impl<T> SnarkyResultExt<T> for SnarkyResult<T> {
+    fn into_compile_result(self) -> Result<SnarkyCompileResult<T>, Box<RealSnarkyError>> {
+        match self {
+            Ok(value) => Ok(Ok(value)),
+            Err(error) => match error.source {
+                SnarkyError::CompilationError(error) => Ok(Err(error)),
+                SnarkyError::RuntimeError(_) => Err(error),
+            },
+        }
+    }
+
+    fn into_runtime_result(self) -> Result<SnarkyRuntimeResult<T>, Box<RealSnarkyError>> {
+        match self {
+            Ok(value) => Ok(Ok(value)),
+            Err(error) => match error.source {
+                SnarkyError::RuntimeError(error) => Ok(Err(Box::new(error))),
+                SnarkyError::CompilationError(_) => Err(error),
+            },
+        }
+    }
+}
+
 /// Errors that can occur during compilation of a circuit.
 #[derive(Debug, Clone, Error)]
 pub enum SnarkyCompilationError {
     #[error("the two values were not equal: {0} != {1}")]
     ConstantAssertEquals(String, String),
+
+    #[error("the variable #{var} was used without being assigned to a cell")]
+    UnassignedCell { var: usize },
+
+    #[error("the circuit has {rows} rows, but at most {max} rows are supported")]
+    CircuitTooLarge { rows: usize, max: usize },
+
+    #[error("the constraint system does not match: expected {expected}, found {found}")]
+    ConstraintSystemMismatch { expected: String, found: String },
 }
 
 /// Errors that can occur during runtime (proving).
 #[derive(Debug, Clone, Error)]
 pub enum SnarkyRuntimeError {
     #[error(
-        "unsatisfied constraint #{8}: `{0} * {1} + {2} * {3} + {4} * {5} + {6} * {1} * {3} + {7} != 0`"
+        "unsatisfied constraint #{constraint} at row {row}: `{c0} * {l} + {c1} * {r} + {c2} * {o} + {c3} * {l} * {r} + {c4} != 0`"
+    )]
+    UnsatisfiedGenericConstraint {
+        c0: String,
+        l: String,
+        c1: String,
+        r: String,
+        c2: String,
+        o: String,
+        c3: String,
+        c4: String,
+        constraint: usize,
+        row: usize,
+        label: Option<String>,
+    },
+
+    #[error(
+        "unsatisfied constraint #{constraint} at row {row}: {value} is not a boolean (0 or 1)"
+    )]
+    UnsatisfiedBooleanConstraint {
+        constraint: usize,
+        value: String,
+        row: usize,
+        label: Option<String>,
+    },
+
+    #[error("unsatisfied constraint #{constraint} at row {row}: {lhs} is not equal to {rhs}")]
+    UnsatisfiedEqualConstraint {
+        constraint: usize,
+        lhs: String,
+        rhs: String,
+        row: usize,
+        label: Option<String>,
+    },
+
+    #[error(
+        "unsatisfied constraint #{constraint} at row {row}: {value}^2 is not equal to {square}"
     )]
-    UnsatisfiedGenericConstraint(
-        String,
-        String,
-        String,
-        String,
-        String,
-        String,
-        String,
-        String,
-        usize,
-    ),
-
-    #[error("unsatisfied constraint #{0}: {1} is not a boolean (0 or 1)")]
-    UnsatisfiedBooleanConstraint(usize, String),
-
-    #[error("unsatisfied constraint #{0}: {1} is not equal to {2}")]
-    UnsatisfiedEqualConstraint(usize, String, String),
-
-    #[error("unsatisfied constraint #{0}: {1}^2 is not equal to {2}")]
-    UnsatisfiedSquareConstraint(usize, String, String),
-
-    #[error("unsatisfied constraint #{0}: {1} * {2} is not equal to {3}")]
-    UnsatisfiedR1CSConstraint(usize, String, String, String),
+    UnsatisfiedSquareConstraint {
+        constraint: usize,
+        value: String,
+        square: String,
+        row: usize,
+        label: Option<String>,
+    },
+
+    #[error("unsatisfied constraint #{constraint} at row {row}: {a} * {b} is not equal to {c}")]
+    UnsatisfiedR1CSConstraint {
+        constraint: usize,
+        a: String,
+        b: String,
+        c: String,
+        row: usize,
+        label: Option<String>,
+    },
 
     #[error("the number of public inputs passed ({0}) does not match the number of public inputs expected ({1})")]
     PubInputMismatch(usize, usize),
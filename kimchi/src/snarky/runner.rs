@@ -100,6 +100,10 @@ where
     /// A map from a constraint index to a source location
     /// (usually a file name and line number).
     constraints_locations: Vec<Cow<'static, str>>,
+
+    /// A map from a constraint index to the row of the circuit it was added at,
+    /// filled when compiling the circuit.
+    constraints_rows: Vec<usize>,
 }
 
 //
@@ -116,6 +120,13 @@ where
     fn read_var(&self, var: &FieldVar<F>) -> F;
 
     fn constraints_counter(&self) -> usize;
+
+    /// The row of the circuit at which the current constraint was added during
+    /// compilation, or 0 if the circuit has not been compiled.
+    fn constraint_row(&self) -> usize;
+
+    /// The innermost label attached to the current constraint, if any.
+    fn constraint_label(&self) -> Option<String>;
 }
 
 impl<F: PrimeField, G: WitnessGeneration<F>> WitnessGeneration<F> for &G {
@@ -126,6 +137,14 @@ impl<F: PrimeField, G: WitnessGeneration<F>> WitnessGeneration<F> for &G {
     fn constraints_counter(&self) -> usize {
         G::constraints_counter(*self)
     }
+
+    fn constraint_row(&self) -> usize {
+        G::constraint_row(*self)
+    }
+
+    fn constraint_label(&self) -> Option<String> {
+        G::constraint_label(*self)
+    }
 }
 
 impl<F: PrimeField> WitnessGeneration<F> for &dyn WitnessGeneration<F> {
@@ -136,6 +155,14 @@ impl<F: PrimeField> WitnessGeneration<F> for &dyn WitnessGeneration<F> {
     fn constraints_counter(&self) -> usize {
         (**self).constraints_counter()
     }
+
+    fn constraint_row(&self) -> usize {
+        (**self).constraint_row()
+    }
+
+    fn constraint_label(&self) -> Option<String> {
+        (**self).constraint_label()
+    }
 }
 
 impl<F> WitnessGeneration<F> for RunState<F>
@@ -149,6 +176,18 @@ where
     fn constraints_counter(&self) -> usize {
         self.constraints_counter
     }
+
+    fn constraint_row(&self) -> usize {
+        self.constraints_counter
+            .checked_sub(1)
+            .and_then(|idx| self.constraints_rows.get(idx))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn constraint_label(&self) -> Option<String> {
+        self.labels_stack.last().map(|label| label.to_string())
+    }
 }
 
 //
@@ -195,6 +234,7 @@ where
             labels_stack: vec![],
             constraints_counter: 0,
             constraints_locations: vec![],
+            constraints_rows: vec![],
         };
 
         // allocate the public inputs
@@ -410,6 +450,8 @@ where
                     None => return Ok(()),
                 };
 
+                env.constraints_rows.push(cs.current_row());
+
                 match constraint {
                     Constraint::BasicSnarkyConstraint(c) => {
                         cs.add_basic_snarky_constraint(&env.labels_stack, &loc, c);
@@ -4,7 +4,10 @@ use crate::{
         api::SnarkyCircuit,
         boolean::Boolean,
         cvar::FieldVar,
-        errors::{SnarkyError, SnarkyRuntimeError},
+        errors::{
+            SnarkyCompilationError, SnarkyCompileResult, SnarkyError, SnarkyResultExt,
+            SnarkyRuntimeError, SnarkyRuntimeResult,
+        },
         runner::RunState,
     },
 };
@@ -127,17 +130,101 @@ fn test_simple_circuit() {
             prover_index.prove::<BaseSponge, ScalarSponge>(public_input, private_input, debug);
 
         match res.unwrap_err().source {
-            SnarkyError::RuntimeError(SnarkyRuntimeError::UnsatisfiedR1CSConstraint(
-                _,
-                x,
-                y,
-                z,
-            )) => {
-                assert_eq!(x, Fp::one().to_string());
-                assert_eq!(y, Fp::from(3).to_string());
-                assert_eq!(z, Fp::from(2).to_string());
+            SnarkyError::RuntimeError(SnarkyRuntimeError::UnsatisfiedR1CSConstraint {
+                constraint,
+                a,
+                b,
+                c,
+                row,
+                label,
+            }) => {
+                assert_eq!(a, Fp::one().to_string());
+                assert_eq!(b, Fp::from(3).to_string());
+                assert_eq!(c, Fp::from(2).to_string());
+                // the assertion is the first constraint of the circuit, added
+                // right after the 3 rows of public input and output
+                assert_eq!(constraint, 1);
+                assert_eq!(row, 3);
+                assert_eq!(label.as_deref(), Some("x * y = z"));
             }
             err => panic!("not the err expected: {err}"),
         }
     }
 }
+
+#[test]
+fn test_compilation_error_messages() {
+    let err = SnarkyCompilationError::UnassignedCell { var: 3 };
+    assert_eq!(
+        err.to_string(),
+        "the variable #3 was used without being assigned to a cell"
+    );
+
+    let err = SnarkyCompilationError::CircuitTooLarge {
+        rows: 1 << 17,
+        max: 1 << 16,
+    };
+    assert_eq!(
+        err.to_string(),
+        "the circuit has 131072 rows, but at most 65536 rows are supported"
+    );
+
+    let err = SnarkyCompilationError::ConstraintSystemMismatch {
+        expected: "10 gates".to_string(),
+        found: "12 gates".to_string(),
+    };
+    assert_eq!(
+        err.to_string(),
+        "the constraint system does not match: expected 10 gates, found 12 gates"
+    );
+}
+
+#[test]
+fn test_error_conversions() {
+    fn compile(var: usize) -> SnarkyCompileResult<usize> {
+        Err(SnarkyCompilationError::UnassignedCell { var })
+    }
+
+    fn run() -> SnarkyRuntimeResult<()> {
+        Err(Box::new(SnarkyRuntimeError::PubInputMismatch(1, 2)))
+    }
+
+    fn compile_then_run() -> SnarkyResult<usize> {
+        let var = compile(3)?;
+        run()?;
+        Ok(var)
+    }
+
+    fn run_only() -> SnarkyResult<()> {
+        run()?;
+        Ok(())
+    }
+
+    // `?` preserves the variant of the error
+    let err = compile_then_run().unwrap_err();
+    assert!(matches!(
+        err.source,
+        SnarkyError::CompilationError(SnarkyCompilationError::UnassignedCell { var: 3 })
+    ));
+    let err = run_only().unwrap_err();
+    assert!(matches!(
+        err.source,
+        SnarkyError::RuntimeError(SnarkyRuntimeError::PubInputMismatch(1, 2))
+    ));
+
+    // the errors can be narrowed down to their kind
+    assert!(matches!(
+        compile_then_run().into_compile_result(),
+        Ok(Err(SnarkyCompilationError::UnassignedCell { var: 3 }))
+    ));
+    assert!(compile_then_run().into_runtime_result().is_err());
+    assert!(matches!(
+        run_only().into_runtime_result(),
+        Ok(Err(err)) if matches!(*err, SnarkyRuntimeError::PubInputMismatch(1, 2))
+    ));
+    assert!(run_only().into_compile_result().is_err());
+    assert!(matches!(
+        SnarkyResult::Ok(1).into_compile_result(),
+        Ok(Ok(1))
+    ));
+}
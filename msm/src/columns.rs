@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use folding::expressions::FoldingColumnTrait;
+use folding::expressions::{ColumnKind, FoldingColumnTrait};
 use kimchi::circuits::expr::{CacheId, FormattedOutput};
 
 /// Describe a generic indexed variable X_{i}.
@@ -68,8 +68,8 @@ pub trait ColumnIndexer {
 
 // Implementation to be compatible with folding if we use generic column constraints
 impl FoldingColumnTrait for Column {
-    fn is_witness(&self) -> bool {
+    fn kind(&self) -> ColumnKind {
         // TODO: check if we want to treat lookups differently
-        true
+        ColumnKind::Witness
     }
 }
@@ -214,6 +214,8 @@ pub struct VmConfiguration {
     pub proof_fmt: String,
     pub snapshot_fmt: String,
     pub pprof_cpu: bool,
+    /// The number of steps the program may execute
+    pub step_budget: u64,
     pub host: Option<HostProgram>,
 }
 
@@ -1,4 +1,4 @@
-use crate::cannon::*;
+use crate::{cannon::*, mips::witness::MAX_STEP_BUDGET};
 use clap::{arg, value_parser, Arg, ArgAction};
 
 pub fn main_cli() -> clap::Command {
@@ -44,6 +44,12 @@ pub fn main_cli() -> clap::Command {
                 .default_value("never")
                 .value_parser(step_frequency_parser),
         )
+        .arg(
+            Arg::new("step-budget")
+                .long("step-budget")
+                .value_name("STEPS")
+                .value_parser(value_parser!(u64).range(..=MAX_STEP_BUDGET)),
+        )
         .arg(
             Arg::new("pprof-cpu")
                 .long("pprof.cpu")
@@ -79,6 +85,10 @@ pub fn read_configuration(cli: &clap::ArgMatches) -> VmConfiguration {
     let proof_fmt = cli.get_one::<String>("proof-fmt").unwrap();
     let snapshot_fmt = cli.get_one::<String>("snapshot-fmt").unwrap();
     let pprof_cpu = cli.get_one::<bool>("pprof-cpu").unwrap();
+    let step_budget = cli
+        .get_one::<u64>("step-budget")
+        .copied()
+        .unwrap_or(MAX_STEP_BUDGET);
 
     let host_spec = cli
         .get_many::<String>("host")
@@ -109,6 +119,7 @@ pub fn read_configuration(cli: &clap::ArgMatches) -> VmConfiguration {
         proof_fmt: proof_fmt.to_string(),
         snapshot_fmt: snapshot_fmt.to_string(),
         pprof_cpu: *pprof_cpu,
+        step_budget,
         host,
     }
 }
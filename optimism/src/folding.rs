@@ -1,7 +1,7 @@
 use crate::trace::Trace;
 use ark_ec::{AffineCurve, ProjectiveCurve};
 use ark_ff::{FftField, Zero};
-use ark_poly::{Evaluations, Radix2EvaluationDomain};
+use ark_poly::{EvaluationDomain, Evaluations, Radix2EvaluationDomain};
 use folding::{Alphas, FoldingConfig, FoldingEnv, Instance, Side, Witness};
 use kimchi::circuits::{expr::ChallengeTerm, gate::CurrOrNext};
 use kimchi_msm::witness::Witness as GenericWitness;
@@ -18,7 +18,7 @@ pub(crate) type ScalarField<C> = <<C as FoldingConfig>::Curve as AffineCurve>::S
 pub(crate) type BaseField<C> = <<C as FoldingConfig>::Curve as AffineCurve>::BaseField;
 
 // Does not contain alpha because this one should be provided by folding itself
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, EnumIter, EnumCountMacro)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, EnumIter, EnumCountMacro)]
 pub enum Challenge {
     Beta,
     Gamma,
@@ -69,6 +69,18 @@ impl<const N: usize, G: CommitmentCurve> Instance<G> for FoldingInstance<N, G> {
     }
 }
 
+impl<const N: usize, G: CommitmentCurve> FoldingInstance<N, G> {
+    /// The instance with zero commitments and challenges, used to build the
+    /// identity of folding
+    pub fn zero(alphas: Alphas<G::ScalarField>) -> Self {
+        FoldingInstance {
+            commitments: [G::zero(); N],
+            challenges: [G::ScalarField::zero(); Challenge::COUNT],
+            alphas,
+        }
+    }
+}
+
 impl<const N: usize, G: CommitmentCurve> Index<Challenge> for FoldingInstance<N, G> {
     type Output = G::ScalarField;
 
@@ -87,6 +99,18 @@ pub struct FoldingWitness<const N: usize, F: FftField> {
     pub witness: GenericWitness<N, Evaluations<F, Radix2EvaluationDomain<F>>>,
 }
 
+impl<const N: usize, F: FftField> FoldingWitness<N, F> {
+    /// The witness with all columns set to zero over the domain
+    pub fn zero(domain: Radix2EvaluationDomain<F>) -> Self {
+        let zero_col = || Evaluations::from_vec_and_domain(vec![F::zero(); domain.size()], domain);
+        FoldingWitness {
+            witness: GenericWitness {
+                cols: Box::new(array::from_fn(|_| zero_col())),
+            },
+        }
+    }
+}
+
 impl<const N: usize, G: CommitmentCurve> Witness<G> for FoldingWitness<N, G::ScalarField> {
     fn combine(mut a: Self, b: Self, challenge: G::ScalarField) -> Self {
         for (a, b) in (*a.witness.cols).iter_mut().zip(*(b.witness.cols)) {
@@ -10,8 +10,8 @@ use crate::{
 use ark_poly::{Evaluations, Radix2EvaluationDomain};
 use folding::{
     checker::{Checker, ExtendedProvider},
-    expressions::FoldingColumnTrait,
-    FoldingConfig,
+    expressions::{ColumnKind, FoldingColumnTrait},
+    Alphas, FoldingConfig,
 };
 use kimchi_msm::columns::Column;
 use poly_commitment::srs::SRS;
@@ -59,9 +59,9 @@ impl Index<Column> for KeccakFoldingWitness {
 pub struct KeccakConfig;
 
 impl FoldingColumnTrait for KeccakColumn {
-    fn is_witness(&self) -> bool {
+    fn kind(&self) -> ColumnKind {
         // dynamic selectors KeccakColumn::Selector() count as witnesses
-        true
+        ColumnKind::Witness
     }
 }
 
@@ -75,6 +75,18 @@ impl FoldingConfig for KeccakConfig {
     type Witness = KeccakFoldingWitness;
     type Structure = Trace<ZKVM_KECCAK_COLS, ZKVM_KECCAK_REL, ZKVM_KECCAK_SEL, KeccakConfig>;
     type Env = KeccakFoldingEnvironment;
+
+    fn identity_instance(
+        _structure: &Self::Structure,
+        _srs: &Self::Srs,
+        domain: Radix2EvaluationDomain<Fp>,
+        alphas: Alphas<Fp>,
+    ) -> (Self::Instance, Self::Witness) {
+        (
+            KeccakFoldingInstance::zero(alphas),
+            KeccakFoldingWitness::zero(domain),
+        )
+    }
 }
 
 // IMPLEMENT CHECKER TRAITS
@@ -483,7 +483,10 @@ where
         let bytes31 = (1..32).fold(Self::zero(), |acc, i| {
             acc * Self::two_pow(8) + self.sponge_byte(i)
         });
-        self.write_syscall(self.is_squeeze(step), vec![self.hash_index(), bytes31]);
+        self.write_syscall(
+            self.is_squeeze(step),
+            vec![self.hash_index(), bytes31, Self::zero()],
+        );
     }
 
     /// Reads a Lookup containing the input of a step
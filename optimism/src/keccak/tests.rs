@@ -619,18 +619,19 @@ fn test_keccak_decomposable_folding() {
             })
             .collect();
 
-        let (scheme, final_constraint) = DecomposableFoldingScheme::<KeccakConfig>::new(
-            constraints,
-            vec![],
-            &srs,
-            domain,
-            &default_trace,
-        );
+        let (scheme, final_constraint) =
+            DecomposableFoldingScheme::<KeccakConfig>::new_quadraticized(
+                constraints,
+                vec![],
+                &srs,
+                domain,
+                &default_trace,
+            );
 
         // Fold Sponge(Absorb(Only))
         let left = keccak_trace[0].to_folding_pair(Sponge(Absorb(Only)), &srs, &mut fq_sponge);
         let right = keccak_trace[1].to_folding_pair(Sponge(Absorb(Only)), &srs, &mut fq_sponge);
-        let (folded_instance, folded_witness, [_t0, _t1]) = scheme.fold_instance_witness_pair(
+        let (folded_instance, folded_witness, _) = scheme.fold_instance_witness_pair(
             left,
             right,
             Some(Sponge(Absorb(Only))),
@@ -1,16 +1,20 @@
 //! Instantiation of the lookups for the VM project.
 
 use self::LookupTableIDs::*;
-use crate::{keccak::pad_blocks, ramlookup::RAMLookup};
-use ark_ff::{Field, PrimeField};
+use crate::{
+    keccak::pad_blocks,
+    ramlookup::{LookupMode, RAMLookup},
+};
+use ark_ff::{Field, One, PrimeField, Zero};
 use kimchi::{
     circuits::polynomials::keccak::{
-        constants::{RATE_IN_BYTES, ROUNDS},
+        constants::{RATE_IN_BYTES, ROUNDS, STATE_LEN},
         Keccak, RC,
     },
     o1_utils::{FieldHelpers, Two},
 };
 use kimchi_msm::{LogupTable, LogupWitness, LookupTableID};
+use std::collections::BTreeMap;
 
 /// The lookups struct based on RAMLookups for the VM table IDs
 pub(crate) type Lookup<F> = RAMLookup<F, LookupTableIDs>;
@@ -49,6 +53,8 @@ pub enum LookupTableIDs {
     SyscallLookup = 8,
     /// Input/Output of Keccak steps
     KeccakStepLookup = 9,
+    /// Hints written to the host
+    HintLookup = 10,
 }
 
 impl LookupTableID for LookupTableIDs {
@@ -68,6 +74,7 @@ impl LookupTableID for LookupTableIDs {
             7 => RegisterLookup,
             8 => SyscallLookup,
             9 => KeccakStepLookup,
+            10 => HintLookup,
             _ => panic!("Invalid table ID"),
         }
     }
@@ -78,7 +85,7 @@ impl LookupTableID for LookupTableIDs {
             RoundConstantsLookup => ROUNDS,
             ByteLookup => 1 << 8,
             RangeCheck16Lookup | SparseLookup | ResetLookup => 1 << 16,
-            MemoryLookup | RegisterLookup | SyscallLookup | KeccakStepLookup => {
+            MemoryLookup | RegisterLookup | SyscallLookup | KeccakStepLookup | HintLookup => {
                 panic!("RAM Tables do not have a fixed length")
             }
         }
@@ -88,7 +95,7 @@ impl LookupTableID for LookupTableIDs {
         match self {
             PadLookup | RoundConstantsLookup | ByteLookup | RangeCheck16Lookup | SparseLookup
             | ResetLookup => true,
-            MemoryLookup | RegisterLookup | SyscallLookup | KeccakStepLookup => false,
+            MemoryLookup | RegisterLookup | SyscallLookup | KeccakStepLookup | HintLookup => false,
         }
     }
 
@@ -108,10 +115,91 @@ impl LookupTableID for LookupTableIDs {
             Self::RegisterLookup,
             Self::SyscallLookup,
             Self::KeccakStepLookup,
+            Self::HintLookup,
         ]
     }
 }
 
+impl LookupTableIDs {
+    /// Returns the number of values in each entry of the table
+    pub fn arity(&self) -> usize {
+        match self {
+            // The length, 2^length and the 5 pad suffixes
+            PadLookup => 7,
+            // The round and the 4 limbs of the expanded round constant
+            RoundConstantsLookup => 5,
+            ByteLookup | RangeCheck16Lookup | SparseLookup => 1,
+            // The dense and sparse representations
+            ResetLookup => 2,
+            // The address or register index, the last access and the value
+            MemoryLookup | RegisterLookup => 3,
+            // The hash index, the byte index and the byte of the preimage. The
+            // hash output is also sent through this table, as the triple
+            // (hash index, hash, 0)
+            SyscallLookup => 3,
+            // The hash index, the step index and the state
+            KeccakStepLookup => STATE_LEN + 2,
+            // The hint index, the address and the length of the hint
+            HintLookup => 3,
+        }
+    }
+}
+
+/// Errors that can occur when building a lookup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LookupError {
+    /// The number of values does not match the arity of the table
+    InvalidArity {
+        table_id: LookupTableIDs,
+        expected: usize,
+        got: usize,
+    },
+}
+
+impl<T> RAMLookup<T, LookupTableIDs>
+where
+    T: Clone
+        + std::ops::Add<T, Output = T>
+        + std::ops::Sub<T, Output = T>
+        + std::ops::Mul<T, Output = T>
+        + std::fmt::Debug
+        + One
+        + Zero,
+{
+    /// Creates a new lookup like [RAMLookup::new], checking that the number of
+    /// values matches the arity of the table
+    pub fn try_new(
+        mode: LookupMode,
+        table_id: LookupTableIDs,
+        magnitude: T,
+        value: &[T],
+    ) -> Result<Self, LookupError> {
+        if value.len() != table_id.arity() {
+            return Err(LookupError::InvalidArity {
+                table_id,
+                expected: table_id.arity(),
+                got: value.len(),
+            });
+        }
+        Ok(Self::new(mode, table_id, magnitude, value))
+    }
+}
+
+/// Groups `lookups` by table, keeping the order of the lookups into each table,
+/// so that the lookup argument of each table can be built separately
+pub fn partition_by_table<T>(
+    lookups: Vec<Lookup<T>>,
+) -> BTreeMap<LookupTableIDs, Vec<Lookup<T>>> {
+    let mut tables = BTreeMap::new();
+    for lookup in lookups {
+        tables
+            .entry(lookup.table_id)
+            .or_insert_with(Vec::new)
+            .push(lookup);
+    }
+    tables
+}
+
 /// Trait that creates all the fixed lookup tables used in the VM
 pub(crate) trait FixedLookupTables<F> {
     /// Checks whether a value is in a table and returns the position if it is or None otherwise.
@@ -169,7 +257,7 @@ impl<F: Field> FixedLookupTables<F> for LookupTable<F> {
                     None
                 }
             }
-            MemoryLookup | RegisterLookup | SyscallLookup | KeccakStepLookup => None,
+            MemoryLookup | RegisterLookup | SyscallLookup | KeccakStepLookup | HintLookup => None,
         }
     }
 
@@ -255,3 +343,94 @@ impl<F: Field> FixedLookupTables<F> for LookupTable<F> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Fp;
+
+    #[test]
+    fn test_fixed_tables_arity() {
+        assert_eq!(
+            LookupTable::<Fp>::table_pad().entries[0].len(),
+            PadLookup.arity()
+        );
+        assert_eq!(
+            LookupTable::<Fp>::table_round_constants().entries[0].len(),
+            RoundConstantsLookup.arity()
+        );
+        assert_eq!(
+            LookupTable::<Fp>::table_byte().entries[0].len(),
+            ByteLookup.arity()
+        );
+        assert_eq!(
+            LookupTable::<Fp>::table_reset().entries[0].len(),
+            ResetLookup.arity()
+        );
+    }
+
+    #[test]
+    fn test_lookup_rejects_invalid_arity() {
+        let value = [Fp::from(1u32), Fp::from(2u32)];
+        let lookup = Lookup::try_new(LookupMode::Read, MemoryLookup, Fp::one(), &value);
+        assert_eq!(
+            lookup.unwrap_err(),
+            LookupError::InvalidArity {
+                table_id: MemoryLookup,
+                expected: 3,
+                got: 2
+            }
+        );
+        let value = [Fp::from(1u32), Fp::from(2u32), Fp::from(3u32)];
+        assert!(Lookup::try_new(LookupMode::Read, MemoryLookup, Fp::one(), &value).is_ok());
+    }
+
+    #[test]
+    fn test_lookup_map_expr() {
+        let value = vec![Fp::from(1u32), Fp::from(2u32), Fp::from(3u32)];
+        let lookup = Lookup::write_if(Fp::from(5u32), SyscallLookup, value.clone());
+
+        let same = lookup.clone().map_expr(|x| x);
+        assert_eq!(same.table_id, lookup.table_id);
+        assert!(matches!(same.mode, LookupMode::Write));
+        assert_eq!(same.magnitude, lookup.magnitude);
+        assert_eq!(same.value, lookup.value);
+
+        let doubled = lookup.map_expr(|x| x.double());
+        assert_eq!(doubled.magnitude, Fp::from(10u32));
+        assert_eq!(
+            doubled.value,
+            value.iter().map(|x| x.double()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_partition_by_table() {
+        let entry = |i: u32| vec![Fp::from(i), Fp::from(i + 1), Fp::zero()];
+        let lookups = vec![
+            Lookup::read_one(SyscallLookup, entry(0)),
+            Lookup::read_one(ByteLookup, vec![Fp::from(7u32)]),
+            Lookup::write_one(SyscallLookup, entry(1)),
+            Lookup::read_one(MemoryLookup, entry(2)),
+            Lookup::read_one(SyscallLookup, entry(3)),
+        ];
+        let tables = partition_by_table(lookups);
+        assert_eq!(
+            tables.keys().copied().collect::<Vec<_>>(),
+            vec![ByteLookup, MemoryLookup, SyscallLookup]
+        );
+        let syscalls = &tables[&SyscallLookup];
+        assert!(syscalls
+            .iter()
+            .all(|lookup| lookup.table_id == SyscallLookup));
+        assert_eq!(
+            syscalls
+                .iter()
+                .map(|lookup| lookup.value.clone())
+                .collect::<Vec<_>>(),
+            vec![entry(0), entry(1), entry(3)]
+        );
+        assert_eq!(tables[&ByteLookup].len(), 1);
+        assert_eq!(tables[&MemoryLookup].len(), 1);
+    }
+}
@@ -1,7 +1,5 @@
 use ark_ff::UniformRand;
-use folding::{
-    decomposable_folding::DecomposableFoldingScheme, expressions::FoldingCompatibleExpr,
-};
+use folding::decomposable_folding::DecomposableFoldingScheme;
 use kimchi::o1_utils;
 use kimchi_msm::{proof::ProofInputs, prover::prove, verifier::verify, witness::Witness};
 use kimchi_optimism::{
@@ -14,7 +12,7 @@ use kimchi_optimism::{
     },
     lookups::LookupTableIDs,
     mips::{
-        column::{MIPS_COLUMNS, MIPS_REL_COLS, MIPS_SEL_COLS},
+        column::{ColumnAlias as MIPSColumn, MIPS_COLUMNS, MIPS_REL_COLS, MIPS_SEL_COLS},
         constraints as mips_constraints,
         folding::MIPSFoldingConfig,
         interpreter::Instruction,
@@ -23,17 +21,11 @@ use kimchi_optimism::{
     },
     preimage_oracle::PreImageOracle,
     proof,
-    trace::Tracer,
+    trace::{Indexer, Tracer},
     BaseSponge, Fp, OpeningProof, ScalarSponge, DOMAIN_SIZE,
 };
 use log::debug;
-use std::{
-    cmp::Ordering,
-    collections::{BTreeMap, HashMap},
-    fs::File,
-    io::BufReader,
-    process::ExitCode,
-};
+use std::{cmp::Ordering, collections::HashMap, fs::File, io::BufReader, process::ExitCode};
 use strum::IntoEnumIterator;
 
 pub fn main() -> ExitCode {
@@ -86,6 +78,7 @@ pub fn main() -> ExitCode {
     // Initialize the environments
     // The Keccak environment is extracted inside the loop
     let mut mips_wit_env = mips_witness::Env::<Fp>::create(cannon::PAGE_SIZE as usize, state, po);
+    mips_wit_env.set_step_budget(configuration.step_budget);
     let mut mips_con_env = mips_constraints::Env::<Fp>::default();
     // The keccak environment is extracted inside the loop
 
@@ -93,29 +86,14 @@ pub fn main() -> ExitCode {
     let mut mips_trace = MIPSTrace::new(DOMAIN_SIZE, &mut mips_con_env);
     let mut keccak_trace = KeccakTrace::new(DOMAIN_SIZE, &mut KeccakEnv::<Fp>::default());
 
-    let _mips_folding = {
-        let constraints: BTreeMap<Instruction, Vec<FoldingCompatibleExpr<MIPSFoldingConfig>>> =
-            mips_trace
-                .constraints
-                .iter()
-                .map(|(k, constraints)| {
-                    (
-                        *k,
-                        constraints
-                            .iter()
-                            .map(|x| FoldingCompatibleExpr::from(x.clone()))
-                            .collect(),
-                    )
-                })
-                .collect();
-        DecomposableFoldingScheme::<MIPSFoldingConfig>::new(
-            constraints,
-            vec![],
-            &srs.full_srs,
-            domain.d1,
-            &mips_trace,
-        )
-    };
+    let _mips_folding = DecomposableFoldingScheme::<MIPSFoldingConfig>::from_kimchi(
+        mips_trace.constraints.clone(),
+        vec![],
+        &srs.full_srs,
+        domain.d1,
+        &mips_trace,
+    )
+    .expect("the MIPS constraints should be compatible with folding");
 
     // Initialize folded instances of the sub circuits
     let mut mips_folded_instance = HashMap::new();
@@ -171,6 +149,17 @@ pub fn main() -> ExitCode {
                     .push(mips_wit_env.scratch_state[i]),
                 Ordering::Equal => mips_trace.witness.get_mut(&instr).unwrap().cols[i]
                     .push(Fp::from(mips_wit_env.instruction_counter)),
+                Ordering::Greater if i == MIPSColumn::Halted.ix() => {
+                    mips_trace.witness.get_mut(&instr).unwrap().cols[i]
+                        .push(Fp::from(mips_wit_env.halt))
+                }
+                Ordering::Greater if i == MIPSColumn::ExitCode.ix() => {
+                    mips_trace.witness.get_mut(&instr).unwrap().cols[i].push(mips_wit_env.exit_code)
+                }
+                Ordering::Greater if i == MIPSColumn::StepBudget.ix() => {
+                    mips_trace.witness.get_mut(&instr).unwrap().cols[i]
+                        .push(Fp::from(mips_wit_env.step_budget))
+                }
                 Ordering::Greater => {
                     // TODO: error
                     mips_trace.witness.get_mut(&instr).unwrap().cols[i]
@@ -14,17 +14,24 @@ use strum::EnumCount;
 
 use super::{ITypeInstruction, JTypeInstruction, RTypeInstruction};
 
-pub(crate) const MIPS_HASH_COUNTER_OFFSET: usize = 80;
-pub(crate) const MIPS_IS_SYSCALL_OFFSET: usize = 81;
-pub(crate) const MIPS_READING_PREIMAGE_OFFSET: usize = 82;
-pub(crate) const MIPS_BYTES_READ_OFFSET: usize = 83;
-pub(crate) const MIPS_PREIMAGE_LEFT_OFFSET: usize = 84;
-pub(crate) const MIPS_PREIMAGE_BYTES_OFFSET: usize = 85;
-pub(crate) const MIPS_HAS_N_BYTES_OFFSET: usize = 89;
+pub(crate) const MIPS_HASH_COUNTER_OFFSET: usize = 100;
+pub(crate) const MIPS_IS_SYSCALL_OFFSET: usize = 101;
+pub(crate) const MIPS_READING_PREIMAGE_OFFSET: usize = 102;
+pub(crate) const MIPS_BYTES_READ_OFFSET: usize = 103;
+pub(crate) const MIPS_PREIMAGE_LEFT_OFFSET: usize = 104;
+pub(crate) const MIPS_PREIMAGE_BYTES_OFFSET: usize = 105;
+pub(crate) const MIPS_HAS_N_BYTES_OFFSET: usize = 109;
 pub(crate) const MIPS_CHUNK_BYTES_LENGTH: usize = 4;
+pub(crate) const MIPS_HINT_COUNTER_OFFSET: usize = 113;
+pub(crate) const MIPS_PREIMAGE_LEFT_CURR_OFFSET: usize = 114;
+pub(crate) const MIPS_ROW_BYTES_OFFSET: usize = 115;
+
+/// The number of scratch columns that can be allocated by an instruction, the
+/// following ones being reserved for the values stored at fixed offsets
+pub const MAX_SCRATCH: usize = MIPS_HASH_COUNTER_OFFSET;
 
 /// The number of columns used for relation witness in the MIPS circuit
-pub const MIPS_REL_COLS: usize = SCRATCH_SIZE + 2;
+pub const MIPS_REL_COLS: usize = SCRATCH_SIZE + 5;
 
 /// The number of witness columns used to store the instruction selectors.
 pub const MIPS_SEL_COLS: usize =
@@ -35,11 +42,18 @@ pub const MIPS_COLUMNS: usize = MIPS_REL_COLS + MIPS_SEL_COLS;
 
 /// Abstract columns (or variables of our multi-variate polynomials) that will be used to
 /// describe our constraints.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub enum ColumnAlias {
     // Can be seen as the abstract indexed variable X_{i}
     ScratchState(usize),
     InstructionCounter,
+    /// Whether the program has halted, set by the `exit_group` syscall
+    Halted,
+    /// The exit code reported by the `exit_group` syscall, bound on the row
+    /// where the program halts
+    ExitCode,
+    /// The number of steps the program may still execute
+    StepBudget,
 }
 
 /// The columns used by the MIPS circuit.
@@ -55,6 +69,9 @@ impl Indexer for ColumnAlias {
                 i
             }
             ColumnAlias::InstructionCounter => SCRATCH_SIZE,
+            ColumnAlias::Halted => SCRATCH_SIZE + 2,
+            ColumnAlias::ExitCode => SCRATCH_SIZE + 3,
+            ColumnAlias::StepBudget => SCRATCH_SIZE + 4,
         }
     }
 }
@@ -74,7 +91,9 @@ impl Indexer for Instruction {
 /// It does contain
 /// [MIPS_SEL_COLS] columns for the instruction selectors
 /// + [SCRATCH_SIZE] columns
-/// + 2 additional columns to keep track of the instruction index and one for the system error code.
+/// + 5 additional columns to keep track of the instruction index, one for the
+/// system error code, one for the halt flag, one for the exit code and one for
+/// the remaining step budget.
 /// The columns are, in order,
 /// - the 32 general purpose registers
 /// - the low and hi registers used by some arithmetic instructions
@@ -94,6 +113,9 @@ impl Indexer for Instruction {
 /// - how many bytes are left to be read for the current preimage
 /// - the (at most) 4 bytes of the preimage key that are currently being processed
 /// - 4 helpers to check if at least n bytes were read in the current row
+/// - the hint counter
+/// - how many bytes were left to be read for the current preimage before this row
+/// - the number of bytes of the preimage read in the current row
 pub type MIPSWitness<T> = Witness<MIPS_COLUMNS, T>;
 
 // IMPLEMENTATIONS FOR COLUMN ALIAS
@@ -121,6 +143,23 @@ impl ColumnIndexer for ColumnAlias {
     }
 }
 
+impl TryFrom<Column> for ColumnAlias {
+    type Error = Column;
+
+    /// Map a relation column back to its alias. Any other column, including
+    /// the error column that has no alias, is returned as the error.
+    fn try_from(column: Column) -> Result<Self, Self::Error> {
+        match column {
+            Column::Relation(i) if i < SCRATCH_SIZE => Ok(ColumnAlias::ScratchState(i)),
+            Column::Relation(i) if i == SCRATCH_SIZE => Ok(ColumnAlias::InstructionCounter),
+            Column::Relation(i) if i == SCRATCH_SIZE + 2 => Ok(ColumnAlias::Halted),
+            Column::Relation(i) if i == SCRATCH_SIZE + 3 => Ok(ColumnAlias::ExitCode),
+            Column::Relation(i) if i == SCRATCH_SIZE + 4 => Ok(ColumnAlias::StepBudget),
+            _ => Err(column),
+        }
+    }
+}
+
 // IMPLEMENTATIONS FOR SELECTOR
 
 impl<T: Clone> Index<Instruction> for MIPSWitness<T> {
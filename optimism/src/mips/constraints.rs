@@ -2,22 +2,29 @@ use crate::{
     lookups::{Lookup, LookupTableIDs},
     mips::{
         column::{
-            ColumnAlias as MIPSColumn, MIPS_BYTES_READ_OFFSET, MIPS_CHUNK_BYTES_LENGTH,
-            MIPS_HASH_COUNTER_OFFSET, MIPS_HAS_N_BYTES_OFFSET, MIPS_PREIMAGE_BYTES_OFFSET,
-            MIPS_PREIMAGE_LEFT_OFFSET, MIPS_READING_PREIMAGE_OFFSET,
+            ColumnAlias as MIPSColumn, MAX_SCRATCH, MIPS_BYTES_READ_OFFSET,
+            MIPS_CHUNK_BYTES_LENGTH, MIPS_HASH_COUNTER_OFFSET, MIPS_HAS_N_BYTES_OFFSET,
+            MIPS_HINT_COUNTER_OFFSET, MIPS_PREIMAGE_BYTES_OFFSET, MIPS_PREIMAGE_LEFT_CURR_OFFSET,
+            MIPS_PREIMAGE_LEFT_OFFSET, MIPS_READING_PREIMAGE_OFFSET, MIPS_ROW_BYTES_OFFSET,
         },
         interpreter::InterpreterEnv,
-        registers::{REGISTER_PREIMAGE_KEY_START, REGISTER_PREIMAGE_OFFSET},
+        registers::REGISTER_PREIMAGE_KEY_START,
     },
     E,
 };
 use ark_ff::Field;
 use kimchi::circuits::{
-    expr::{ConstantExpr, ConstantTerm::Literal, Expr, ExprInner, Operations, Variable},
+    expr::{
+        ConstantExpr, ConstantExprInner, ConstantTerm::Literal, Expr, ExprInner, Operations,
+        Variable,
+    },
     gate::CurrOrNext,
 };
 use kimchi_msm::columns::{Column, ColumnIndexer as _};
-use std::array;
+use std::{
+    array,
+    collections::{BTreeMap, BTreeSet},
+};
 
 /// The environment keeping the constraints between the different polynomials
 pub struct Env<Fp> {
@@ -25,19 +32,459 @@ pub struct Env<Fp> {
     /// A list of constraints, which are multi-variate polynomials over a field,
     /// represented using the expression framework of `kimchi`.
     pub constraints: Vec<E<Fp>>,
+    /// The constraints added with a label, see [Env::add_labeled_constraint].
+    /// They are also part of [Env::constraints].
+    pub labeled_constraints: Vec<(String, E<Fp>)>,
     pub lookups: Vec<Lookup<E<Fp>>>,
 }
 
+/// Errors that can occur when allocating scratch columns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScratchError {
+    /// All the scratch columns available to an instruction are already used
+    Overflow { max: usize },
+}
+
+/// The constraints and lookups collected by an [Env], see [Env::finish]
+#[derive(Clone, Debug)]
+pub struct MipsRelation<Fp> {
+    /// The constraints, without duplicates
+    pub constraints: Vec<E<Fp>>,
+    /// The labels given to some of the constraints, see
+    /// [Env::add_labeled_constraint]
+    pub labeled_constraints: Vec<(String, E<Fp>)>,
+    pub lookups: Vec<Lookup<E<Fp>>>,
+    /// The number of scratch columns used by the constraints and lookups
+    pub scratch_used: usize,
+}
+
+impl<Fp: Field> MipsRelation<Fp> {
+    /// The labels of `constraint`, e.g. to report which gate a failing
+    /// constraint comes from
+    pub fn labels_of(&self, constraint: &E<Fp>) -> Vec<&str> {
+        self.labeled_constraints
+            .iter()
+            .filter(|(_, labeled)| labeled == constraint)
+            .map(|(label, _)| label.as_str())
+            .collect()
+    }
+}
+
 impl<Fp: Field> Default for Env<Fp> {
     fn default() -> Self {
         Self {
             scratch_state_idx: 0,
             constraints: Vec::new(),
+            labeled_constraints: Vec::new(),
             lookups: Vec::new(),
         }
     }
 }
 
+impl<Fp: Field> Env<Fp> {
+    /// The number of scratch columns allocated for the current instruction
+    pub fn scratch_used(&self) -> usize {
+        self.scratch_state_idx
+    }
+
+    /// Add the constraint `assert_equals_zero` like
+    /// [InterpreterEnv::add_constraint], and tag it with `label` so that it
+    /// can be retrieved with [Env::constraints_by_label].
+    pub fn add_labeled_constraint(&mut self, label: &str, assert_equals_zero: E<Fp>) {
+        self.labeled_constraints
+            .push((label.to_string(), assert_equals_zero.clone()));
+        self.add_constraint(assert_equals_zero)
+    }
+
+    /// The constraints tagged with `label`, in the order they were added
+    pub fn constraints_by_label(&self, label: &str) -> Vec<&E<Fp>> {
+        self.labeled_constraints
+            .iter()
+            .filter(|(tag, _)| tag == label)
+            .map(|(_, constraint)| constraint)
+            .collect()
+    }
+
+    /// The maximum total degree of the constraints, each cell being of degree
+    /// 1, e.g. to size the quotient polynomial. When `include_lookups` is set,
+    /// the expressions of the lookups, i.e. their magnitudes and values, are
+    /// taken into account as well. An environment without any constraint has
+    /// degree 0.
+    pub fn max_constraint_degree(&self, include_lookups: bool) -> usize {
+        let degree = |x: &E<Fp>| x.degree(1, 0) as usize;
+        let constraints = self.constraints.iter().map(degree);
+        let lookups = self
+            .lookups
+            .iter()
+            .filter(|_| include_lookups)
+            .flat_map(|lookup| std::iter::once(&lookup.magnitude).chain(&lookup.value))
+            .map(degree);
+        constraints.chain(lookups).max().unwrap_or(0)
+    }
+
+    /// Merge `other` into this environment, e.g. to compose a circuit from
+    /// gadgets built in separate environments. The scratch columns allocated
+    /// by `other` are moved after the ones allocated by `self`, so that the
+    /// constraints and lookups of both environments do not alias. The columns
+    /// at fixed offsets, from [MAX_SCRATCH] on, and the other columns are
+    /// shared.
+    pub fn merge(mut self, other: Env<Fp>) -> Env<Fp> {
+        let offset = self.scratch_state_idx;
+        assert!(
+            offset + other.scratch_state_idx <= MAX_SCRATCH,
+            "The merged environments use more than the {MAX_SCRATCH} scratch columns available"
+        );
+        let shift = |x: E<Fp>| shift_scratch(&x, offset);
+        self.constraints
+            .extend(other.constraints.into_iter().map(shift));
+        self.labeled_constraints.extend(
+            other
+                .labeled_constraints
+                .into_iter()
+                .map(|(label, constraint)| (label, shift(constraint))),
+        );
+        self.lookups.extend(
+            other
+                .lookups
+                .into_iter()
+                .map(|lookup| lookup.map_expr(shift)),
+        );
+        self.scratch_state_idx += other.scratch_state_idx;
+        self
+    }
+
+    /// Package the constraints and lookups collected so far in a
+    /// [MipsRelation]. Structurally identical constraints are only kept once,
+    /// in the order they were first added.
+    pub fn finish(self) -> MipsRelation<Fp> {
+        let mut constraints: Vec<E<Fp>> = Vec::with_capacity(self.constraints.len());
+        for constraint in self.constraints {
+            if !constraints.contains(&constraint) {
+                constraints.push(constraint);
+            }
+        }
+        MipsRelation {
+            constraints,
+            labeled_constraints: self.labeled_constraints,
+            lookups: self.lookups,
+            scratch_used: self.scratch_state_idx,
+        }
+    }
+
+    /// Constrain the instruction counter to be incremented by one between
+    /// the current and the next row, or to stay the same when `halted` is set,
+    /// i.e. `next_counter - curr_counter - (1 - halted) = 0`.
+    /// As it relates consecutive rows, it only holds on the trace of the whole
+    /// execution, see [Env::step_transitions].
+    pub fn constrain_instruction_counter(&mut self, halted: &E<Fp>) {
+        let [curr, next] = Self::transition(MIPSColumn::InstructionCounter);
+        self.add_constraint(next - curr - (Self::constant(1) - halted.clone()));
+    }
+
+    /// Bind the step budget, i.e. the number of steps the program may still
+    /// execute, to `budget` on the current row. The budget given to the
+    /// whole execution is a public input, bound on the first row of its trace
+    /// less the step of that row.
+    pub fn set_step_budget(&mut self, budget: E<Fp>) {
+        let remaining = self.variable(MIPSColumn::StepBudget);
+        self.assert_equal(remaining, budget);
+    }
+
+    /// Constrain the step budget left after the step of the current row: it
+    /// is range-checked to stay nonnegative, and the program must have halted
+    /// once it reaches zero.
+    pub fn constrain_step_budget(&mut self, halted: &E<Fp>) {
+        let budget = self.variable(MIPSColumn::StepBudget);
+        let running = Self::constant(1) - halted.clone();
+        self.range_check_32(&budget);
+        let exhausted = self.is_zero(&budget);
+        self.add_constraint(exhausted * running);
+    }
+
+    /// Constrain the step budget to be decremented by one between the current
+    /// and the next row, or to stay the same when `halted` is set.
+    /// As it relates consecutive rows, it only holds on the trace of the whole
+    /// execution, see [Env::step_transitions].
+    pub fn constrain_step_budget_transition(&mut self, halted: &E<Fp>) {
+        let [curr, next] = Self::transition(MIPSColumn::StepBudget);
+        self.add_constraint(next - curr + (Self::constant(1) - halted.clone()));
+    }
+
+    /// The constraints between the consecutive rows of the trace of the whole
+    /// execution, which holds one row per step in the order of execution.
+    /// They cannot be part of the circuit of an instruction, whose rows are
+    /// the possibly distant steps executing it, padded with copies of the
+    /// first one.
+    pub fn step_transitions() -> Vec<E<Fp>> {
+        let mut env = Self::default();
+        let halted = env.variable(MIPSColumn::Halted);
+        env.constrain_instruction_counter(&halted);
+        env.constrain_step_budget_transition(&halted);
+        env.constraints
+    }
+
+    /// The cells of `column` in the current and in the next row
+    fn transition(column: MIPSColumn) -> [E<Fp>; 2] {
+        let col = column.to_column();
+        [CurrOrNext::Curr, CurrOrNext::Next]
+            .map(|row| Expr::Atom(ExprInner::Cell(Variable { col, row })))
+    }
+
+    /// Return an expression equal to `x` of degree at most `max_degree`. The
+    /// products exceeding the bound are split by storing their factor of
+    /// highest degree in a fresh scratch column, constrained to be equal to
+    /// the factor, until they fit. The added constraints are themselves of
+    /// degree at most `max_degree`.
+    pub fn reduce_degree(&mut self, x: &E<Fp>, max_degree: u64) -> E<Fp> {
+        assert!(
+            max_degree >= 2,
+            "Cannot reduce the degree of a product below 2"
+        );
+        let degree = |x: &E<Fp>| x.degree(1, 0);
+        if degree(x) <= max_degree {
+            return x.clone();
+        }
+        match x {
+            Operations::Mul(a, b) => {
+                let mut a = self.reduce_degree(a, max_degree);
+                let mut b = self.reduce_degree(b, max_degree);
+                while degree(&a) + degree(&b) > max_degree {
+                    if degree(&a) >= degree(&b) {
+                        a = self.store(&a);
+                    } else {
+                        b = self.store(&b);
+                    }
+                }
+                a * b
+            }
+            Operations::Square(a) => {
+                let a = self.reduce_degree(a, max_degree);
+                self.reduce_degree(&(a.clone() * a), max_degree)
+            }
+            Operations::Pow(a, n) => {
+                let a = self.reduce_degree(a, max_degree);
+                (1..*n).fold(a.clone(), |acc, _| {
+                    self.reduce_degree(&(acc * a.clone()), max_degree)
+                })
+            }
+            Operations::Add(a, b) => Operations::Add(
+                Box::new(self.reduce_degree(a, max_degree)),
+                Box::new(self.reduce_degree(b, max_degree)),
+            ),
+            Operations::Sub(a, b) => Operations::Sub(
+                Box::new(self.reduce_degree(a, max_degree)),
+                Box::new(self.reduce_degree(b, max_degree)),
+            ),
+            Operations::Double(a) => {
+                Operations::Double(Box::new(self.reduce_degree(a, max_degree)))
+            }
+            Operations::Cache(id, a) => {
+                Operations::Cache(*id, Box::new(self.reduce_degree(a, max_degree)))
+            }
+            Operations::IfFeature(feature, a, b) => Operations::IfFeature(
+                *feature,
+                Box::new(self.reduce_degree(a, max_degree)),
+                Box::new(self.reduce_degree(b, max_degree)),
+            ),
+            // The atoms are at most of degree 1
+            Operations::Atom(_) => unreachable!(),
+        }
+    }
+
+    /// Store `x` in a fresh scratch column, and return the column
+    fn store(&mut self, x: &E<Fp>) -> E<Fp> {
+        let pos = self.alloc_scratch();
+        self.copy(x, pos)
+    }
+
+    /// Allocate a scratch column, or return an error if the instruction
+    /// already uses all the [MAX_SCRATCH] columns available.
+    pub fn try_alloc_scratch(&mut self) -> Result<MIPSColumn, ScratchError> {
+        if self.scratch_state_idx >= MAX_SCRATCH {
+            return Err(ScratchError::Overflow { max: MAX_SCRATCH });
+        }
+        Ok(self.alloc_scratch())
+    }
+
+    /// Constrain `x` to be a 32-bits word.
+    /// The word is split into a low and a high 16-bits limbs, the high limb
+    /// being stored in a fresh scratch column and the low one being deduced
+    /// from it, and both limbs are looked up in the 16-bits range check table.
+    pub fn range_check_32(&mut self, x: &E<Fp>) {
+        self.range_check_bits(x, 32)
+    }
+
+    /// Constrain `x` to fit in `bits` bits, with `bits` at most 32.
+    /// Values of at most 16 bits are looked up directly, with an additional
+    /// lookup of the value shifted to the top of the table range when it is
+    /// not of the exact size of the table.
+    /// Wider values are decomposed in 16-bits limbs, see [Env::range_check_32].
+    pub fn range_check_bits(&mut self, x: &E<Fp>, bits: u32) {
+        assert!(bits <= 32, "Cannot range check more than 32 bits");
+        if bits > 16 {
+            let high = {
+                let pos = self.alloc_scratch();
+                self.variable(pos)
+            };
+            let low = x.clone() - high.clone() * Self::constant(1 << 16);
+            self.range_check_bits(&high, bits - 16);
+            self.range_check_bits(&low, 16);
+        } else {
+            let (table_id, table_bits) = if bits <= 8 {
+                (LookupTableIDs::ByteLookup, 8)
+            } else {
+                (LookupTableIDs::RangeCheck16Lookup, 16)
+            };
+            self.add_lookup(Lookup::read_one(table_id, vec![x.clone()]));
+            if bits < table_bits {
+                let shifted = x.clone() * Self::constant(1 << (table_bits - bits));
+                self.add_lookup(Lookup::read_one(table_id, vec![shifted]));
+            }
+        }
+    }
+
+    /// Constrain `res` to be the number of leading zeros of the 32-bits word
+    /// `x`.
+    /// The word is decomposed in 32 boolean bits, and a one-hot mask selects
+    /// its highest set bit, which must be set while all the bits above it are
+    /// not. The mask has an additional entry for `x = 0`, deduced from the
+    /// others, and the number of leading zeros is read from the position
+    /// selected by the mask.
+    /// The bits and the mask are stored in 64 fresh scratch columns, in this
+    /// order.
+    pub fn constrain_leading_zeros(&mut self, x: &E<Fp>, res: &E<Fp>) {
+        let alloc_bits = |env: &mut Self| -> Vec<E<Fp>> {
+            (0..32)
+                .map(|_| {
+                    let pos = env.alloc_scratch();
+                    env.variable(pos)
+                })
+                .collect()
+        };
+        let bits = alloc_bits(self);
+        let mask = alloc_bits(self);
+
+        for bit in bits.iter() {
+            self.assert_boolean(bit.clone());
+        }
+        let decomposition = bits
+            .iter()
+            .enumerate()
+            .fold(Self::constant(0), |acc, (i, bit)| {
+                acc + bit.clone() * Self::constant(1 << i)
+            });
+        self.add_constraint(x.clone() - decomposition);
+
+        for selected in mask.iter() {
+            self.assert_boolean(selected.clone());
+        }
+        // At most one position is selected, none meaning that x is 0
+        let is_zero = mask
+            .iter()
+            .fold(Self::constant(1), |acc, selected| acc - selected.clone());
+        self.assert_boolean(is_zero.clone());
+        self.add_constraint(is_zero.clone() * x.clone());
+
+        // The selected bit is set, and the bits above it are not. Both terms
+        // are small non-negative integers, so their sum is 0 only if both are.
+        for (i, selected) in mask.iter().enumerate() {
+            let above = bits[i + 1..]
+                .iter()
+                .fold(Self::constant(0), |acc, bit| acc + bit.clone());
+            self.add_constraint(selected.clone() * (Self::constant(1) - bits[i].clone() + above));
+        }
+
+        let leading_zeros = mask
+            .iter()
+            .enumerate()
+            .fold(is_zero * Self::constant(32), |acc, (i, selected)| {
+                acc + selected.clone() * Self::constant(31 - i as u32)
+            });
+        self.add_constraint(res.clone() - leading_zeros);
+    }
+
+    /// Decompose the 32-bits word `x` in its sign bit, stored in a fresh
+    /// scratch column, and its 31 lower bits, which are range checked.
+    /// Returns the value of `x` as a signed integer, i.e. `x - 2^32` when the
+    /// sign bit is set, along with the sign bit.
+    fn decompose_signed(&mut self, x: &E<Fp>) -> (E<Fp>, E<Fp>) {
+        let sign = {
+            let pos = self.alloc_scratch();
+            self.variable(pos)
+        };
+        self.assert_boolean(sign.clone());
+        self.range_check_bits(&(x.clone() - sign.clone() * Self::constant(1 << 31)), 31);
+        (x.clone() - sign.clone() * Expr::from(1u64 << 32), sign)
+    }
+
+    /// Constrain `q` and `r` to be the quotient and the remainder of the
+    /// division of the 32-bits word `x` by `y`, as signed integers when
+    /// `signed` is set and as unsigned ones otherwise, i.e. `x = q y + r` with
+    /// `|r| < |y|`, `r` having the sign of `x` in the signed case.
+    /// The relation holds over the integers, as `q` and `r` are range checked
+    /// on 32 bits, the signed variant decomposing `x`, `y`, `q` and `r` in
+    /// their sign and lower bits. The bound on the remainder is checked with a
+    /// lookup of `|y| - |r| - 1`, stored in a fresh scratch column.
+    /// The quotient of `-2^31` by `-1` does not fit in 32 bits and wraps to
+    /// `-2^31`, so in the signed case this division is detected with a zero
+    /// check of the distance of the sign and lower bits of `x` and `y` to
+    /// those of `-2^31` and `-1`, and the relation is then shifted by `2^32`.
+    /// Returns the flag set when `y` is 0, in which case the results are left
+    /// unpredictable by MIPS and are not constrained.
+    pub fn constrain_divmod(
+        &mut self,
+        x: &E<Fp>,
+        y: &E<Fp>,
+        q: &E<Fp>,
+        r: &E<Fp>,
+        signed: bool,
+    ) -> E<Fp> {
+        let y_is_zero = self.is_zero(y);
+        let y_is_not_zero = Self::constant(1) - y_is_zero.clone();
+        let gap = {
+            let pos = self.alloc_scratch();
+            self.variable(pos)
+        };
+        let (x, y, q, r, abs_y, abs_r) = if signed {
+            let [(x_signed, x_sign), (y_signed, y_sign), (q_signed, _), (r_signed, r_sign)] =
+                [x, y, q, r].map(|v| self.decompose_signed(v));
+            // The remainder has the sign of the dividend, unless it is 0
+            self.add_constraint(
+                y_is_not_zero.clone() * (r_sign.clone() - x_sign.clone()) * r.clone(),
+            );
+            // A sum of non-negative terms, 0 only when x = -2^31 and y = -1
+            let x_low = x.clone() - x_sign.clone() * Self::constant(1 << 31);
+            let y_low = y.clone() - y_sign.clone() * Self::constant(1 << 31);
+            let overflow = self.is_zero(
+                &(x_low + Self::constant((1 << 31) - 1) - y_low + Self::constant(2)
+                    - x_sign
+                    - y_sign.clone()),
+            );
+            let x_signed = x_signed + overflow * Expr::from(1u64 << 32);
+            let abs =
+                |v: &E<Fp>, sign: E<Fp>| v.clone() * (Self::constant(1) - sign * Self::constant(2));
+            let abs_y = abs(&y_signed, y_sign);
+            let abs_r = abs(&r_signed, r_sign);
+            (x_signed, y_signed, q_signed, r_signed, abs_y, abs_r)
+        } else {
+            self.range_check_32(q);
+            self.range_check_32(r);
+            (
+                x.clone(),
+                y.clone(),
+                q.clone(),
+                r.clone(),
+                y.clone(),
+                r.clone(),
+            )
+        };
+        self.add_constraint(y_is_not_zero.clone() * (x - q * y - r));
+        self.add_constraint(y_is_not_zero * (abs_y - abs_r - Self::constant(1) - gap.clone()));
+        self.range_check_32(&gap);
+        y_is_zero
+    }
+}
+
 impl<Fp: Field> InterpreterEnv for Env<Fp> {
     /// In the concrete implementation for the constraints, the interpreter will
     /// work over columns. The position in this case can be seen as a new
@@ -53,6 +500,10 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         // See crate::SCRATCH_SIZE for the maximum number of columns the circuit
         // can use.
         let scratch_idx = self.scratch_state_idx;
+        debug_assert!(
+            scratch_idx < MAX_SCRATCH,
+            "Cannot allocate more than {MAX_SCRATCH} scratch columns"
+        );
         self.scratch_state_idx += 1;
         MIPSColumn::ScratchState(scratch_idx)
     }
@@ -83,6 +534,12 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
     }
 
     fn add_lookup(&mut self, lookup: Lookup<Self::Variable>) {
+        debug_assert_eq!(
+            lookup.value.len(),
+            lookup.table_id.arity(),
+            "Lookup into {:?} does not match the arity of the table",
+            lookup.table_id
+        );
         self.lookups.push(lookup);
     }
 
@@ -90,6 +547,15 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         self.variable(MIPSColumn::InstructionCounter)
     }
 
+    fn access_time(&self, last_accessed_location: &Self::Position) -> Self::Variable {
+        match last_accessed_location {
+            MIPSColumn::ScratchState(idx) => {
+                self.instruction_start_time() + Self::constant(*idx as u32 + 1)
+            }
+            _ => panic!("The access times are stored in the scratch columns"),
+        }
+    }
+
     unsafe fn fetch_register(
         &mut self,
         _idx: &Self::Variable,
@@ -155,11 +621,13 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
     unsafe fn bitmask(
         &mut self,
         _x: &Self::Variable,
-        _highest_bit: u32,
-        _lowest_bit: u32,
+        highest_bit: u32,
+        lowest_bit: u32,
         position: Self::Position,
     ) -> Self::Variable {
-        self.variable(position)
+        let res = self.variable(position);
+        self.range_check_bits(&res, highest_bit - lowest_bit);
+        res
     }
 
     unsafe fn shift_left(
@@ -168,7 +636,9 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         _by: &Self::Variable,
         position: Self::Position,
     ) -> Self::Variable {
-        self.variable(position)
+        let res = self.variable(position);
+        self.range_check_32(&res);
+        res
     }
 
     unsafe fn shift_right(
@@ -177,7 +647,9 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         _by: &Self::Variable,
         position: Self::Position,
     ) -> Self::Variable {
-        self.variable(position)
+        let res = self.variable(position);
+        self.range_check_32(&res);
+        res
     }
 
     unsafe fn shift_right_arithmetic(
@@ -261,28 +733,38 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
 
     unsafe fn add_witness(
         &mut self,
-        _y: &Self::Variable,
-        _x: &Self::Variable,
+        y: &Self::Variable,
+        x: &Self::Variable,
         out_position: Self::Position,
         overflow_position: Self::Position,
     ) -> (Self::Variable, Self::Variable) {
-        (
-            self.variable(out_position),
-            self.variable(overflow_position),
-        )
+        let res = self.variable(out_position);
+        let overflow = self.variable(overflow_position);
+        // y + x = res + overflow * 2^32
+        self.add_constraint(
+            y.clone() + x.clone() - res.clone() - overflow.clone() * Expr::from(1u64 << 32),
+        );
+        self.assert_boolean(overflow.clone());
+        self.range_check_32(&res);
+        (res, overflow)
     }
 
     unsafe fn sub_witness(
         &mut self,
-        _y: &Self::Variable,
-        _x: &Self::Variable,
+        y: &Self::Variable,
+        x: &Self::Variable,
         out_position: Self::Position,
         underflow_position: Self::Position,
     ) -> (Self::Variable, Self::Variable) {
-        (
-            self.variable(out_position),
-            self.variable(underflow_position),
-        )
+        let res = self.variable(out_position);
+        let underflow = self.variable(underflow_position);
+        // y - x = res - underflow * 2^32
+        self.add_constraint(
+            y.clone() - x.clone() - res.clone() + underflow.clone() * Expr::from(1u64 << 32),
+        );
+        self.assert_boolean(underflow.clone());
+        self.range_check_32(&res);
+        (res, underflow)
     }
 
     unsafe fn mul_signed_witness(
@@ -316,44 +798,49 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
 
     unsafe fn divmod_signed(
         &mut self,
-        _x: &Self::Variable,
-        _y: &Self::Variable,
+        x: &Self::Variable,
+        y: &Self::Variable,
         position_quotient: Self::Position,
         position_remainder: Self::Position,
     ) -> (Self::Variable, Self::Variable) {
-        (
-            self.variable(position_quotient),
-            self.variable(position_remainder),
-        )
+        let quotient = self.variable(position_quotient);
+        let remainder = self.variable(position_remainder);
+        self.constrain_divmod(x, y, &quotient, &remainder, true);
+        (quotient, remainder)
     }
 
     unsafe fn divmod(
         &mut self,
-        _x: &Self::Variable,
-        _y: &Self::Variable,
+        x: &Self::Variable,
+        y: &Self::Variable,
         position_quotient: Self::Position,
         position_remainder: Self::Position,
     ) -> (Self::Variable, Self::Variable) {
-        (
-            self.variable(position_quotient),
-            self.variable(position_remainder),
-        )
+        let quotient = self.variable(position_quotient);
+        let remainder = self.variable(position_remainder);
+        self.constrain_divmod(x, y, &quotient, &remainder, false);
+        (quotient, remainder)
     }
 
     unsafe fn count_leading_zeros(
         &mut self,
-        _x: &Self::Variable,
+        x: &Self::Variable,
         position: Self::Position,
     ) -> Self::Variable {
-        self.variable(position)
+        let res = self.variable(position);
+        self.constrain_leading_zeros(x, &res);
+        res
     }
 
     unsafe fn count_leading_ones(
         &mut self,
-        _x: &Self::Variable,
+        x: &Self::Variable,
         position: Self::Position,
     ) -> Self::Variable {
-        self.variable(position)
+        let res = self.variable(position);
+        // The leading ones of x are the leading zeros of its complement
+        self.constrain_leading_zeros(&(Self::constant(0xffff_ffff) - x.clone()), &res);
+        res
     }
 
     fn copy(&mut self, x: &Self::Variable, position: Self::Position) -> Self::Variable {
@@ -362,16 +849,32 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         res
     }
 
-    fn set_halted(&mut self, _flag: Self::Variable) {
-        // TODO
+    fn set_halted(&mut self, flag: Self::Variable) {
+        let halted = self.variable(MIPSColumn::Halted);
+        self.assert_boolean(halted.clone());
+        self.assert_equal(halted, flag);
     }
 
-    fn report_exit(&mut self, _exit_code: &Self::Variable) {}
+    fn consume_step(&mut self) {
+        let halted = self.variable(MIPSColumn::Halted);
+        self.constrain_step_budget(&halted);
+    }
+
+    fn report_exit(&mut self, exit_code: &Self::Variable) {
+        // The exit code is only bound on the row where the machine halts
+        let halted = self.variable(MIPSColumn::Halted);
+        let exit_code = self.copy(&(halted * exit_code.clone()), MIPSColumn::ExitCode);
+        // The exit code is a byte
+        self.add_lookup(Lookup::read_one(
+            LookupTableIDs::ByteLookup,
+            vec![exit_code],
+        ));
+    }
 
     fn request_preimage_write(
         &mut self,
         _addr: &Self::Variable,
-        _len: &Self::Variable,
+        len: &Self::Variable,
         pos: Self::Position,
     ) -> Self::Variable {
         // The (at most) 4-byte chunk that has been read from the preimage
@@ -388,12 +891,15 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
             self.variable(Self::Position::ScratchState(MIPS_READING_PREIMAGE_OFFSET));
         // How many hashes have been performed so far in the circuit
         let hash_counter = self.variable(Self::Position::ScratchState(MIPS_HASH_COUNTER_OFFSET));
-        // How many bytes remain to be read from the preimage
-        let preimage_left = self.variable(Self::Position::ScratchState(MIPS_PREIMAGE_LEFT_OFFSET));
+        // How many bytes remain to be read from the preimage, before and after this row
+        let preimage_left_curr =
+            self.variable(Self::Position::ScratchState(MIPS_PREIMAGE_LEFT_CURR_OFFSET));
+        let preimage_left_next =
+            self.variable(Self::Position::ScratchState(MIPS_PREIMAGE_LEFT_OFFSET));
         // How many bytes have been read from the preimage so far
         let byte_counter = self.variable(Self::Position::ScratchState(MIPS_BYTES_READ_OFFSET));
         // How many bytes have been read from the preimage in this row
-        let row_bytes = self.variable(Self::Position::ScratchState(REGISTER_PREIMAGE_OFFSET));
+        let row_bytes = self.variable(Self::Position::ScratchState(MIPS_ROW_BYTES_OFFSET));
         // The chunk of at most 4 bytes that has been read from the preimage
         let this_chunk = self.variable(pos);
 
@@ -461,6 +967,14 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
                         reading_preimage.clone() * flag.clone() * (flag.clone() - Expr::from(1)),
                     );
                 }
+                // Having at least n + 1 bytes implies having at least n bytes
+                for (flag, next_flag) in has_n_bytes.iter().zip(&has_n_bytes[1..]) {
+                    self.constraints.push(
+                        reading_preimage.clone()
+                            * next_flag.clone()
+                            * (Expr::from(1) - flag.clone()),
+                    );
+                }
             }
 
             // Constrain the bytes flags depending on the number of bytes read in this row
@@ -499,6 +1013,25 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
             }
         }
 
+        // Bound the number of preimage bytes read in this row, and decrement
+        // the number of bytes left accordingly
+        let bytes_read = has_n_bytes
+            .iter()
+            .fold(Expr::from(0), |acc, flag| acc + flag.clone());
+        {
+            // The flags count the bytes read in this row
+            self.constraints
+                .push(reading_preimage.clone() * (row_bytes - bytes_read.clone()));
+            self.constraints.push(
+                reading_preimage.clone()
+                    * (preimage_left_next.clone() - preimage_left_curr + bytes_read.clone()),
+            );
+            // At most a word is read
+            self.range_check_bits(&(Expr::from(4) - bytes_read.clone()), 3);
+            // At most the requested length is read
+            self.range_check_32(&(len.clone() - bytes_read));
+        }
+
         // COMMUNICATION CHANNEL: Write preimage chunk (1, 2, 3, or 4 bytes)
         for i in 0..MIPS_CHUNK_BYTES_LENGTH {
             self.add_lookup(Lookup::write_if(
@@ -519,23 +1052,150 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
                 ))
         });
 
-        // If no more bytes left to be read, and syscall row, then the end of the preimage is true
-        // Otherwise, there was no a syscall in this row or there is still more to read
-        // FIXME: can the condition be a degree-3 variable?
-        let is_syscall = self.variable(Self::Position::ScratchState(MIPS_BYTES_READ_OFFSET));
-
-        let end_of_preimage = is_syscall * reading_preimage * preimage_left;
+        // If no more bytes are left to be read after reading the preimage in
+        // this row, then the end of the preimage is reached
+        let end_of_preimage = reading_preimage * self.is_zero(&preimage_left_next);
         self.add_lookup(Lookup::read_if(
             end_of_preimage,
             LookupTableIDs::SyscallLookup,
-            vec![hash_counter, preimage_key],
+            vec![hash_counter, preimage_key, Expr::from(0)],
         ));
 
         // Return chunk of preimage as variable
         this_chunk
     }
 
-    fn request_hint_write(&mut self, _addr: &Self::Variable, _len: &Self::Variable) {
-        // No-op, witness only
+    fn request_hint_write(&mut self, addr: &Self::Variable, len: &Self::Variable) {
+        // How many hints have been written so far in the circuit
+        let hint_counter = self.variable(Self::Position::ScratchState(MIPS_HINT_COUNTER_OFFSET));
+        // COMMUNICATION CHANNEL: Write hint
+        self.add_lookup(Lookup::write_one(
+            LookupTableIDs::HintLookup,
+            vec![hint_counter, addr.clone(), len.clone()],
+        ));
+    }
+}
+
+/// Move the scratch columns of `x` that are allocated by the instructions,
+/// i.e. the ones below [MAX_SCRATCH], by `offset`, see [Env::merge]
+fn shift_scratch<Fp: Field>(x: &E<Fp>, offset: usize) -> E<Fp> {
+    let shift = |x: &E<Fp>| Box::new(shift_scratch(x, offset));
+    match x {
+        Operations::Atom(ExprInner::Cell(Variable { col, row })) => {
+            let col = match MIPSColumn::try_from(*col) {
+                Ok(MIPSColumn::ScratchState(i)) if i < MAX_SCRATCH => {
+                    MIPSColumn::ScratchState(i + offset).to_column()
+                }
+                _ => *col,
+            };
+            Operations::Atom(ExprInner::Cell(Variable { col, row: *row }))
+        }
+        Operations::Atom(_) => x.clone(),
+        Operations::Pow(x, n) => Operations::Pow(shift(x), *n),
+        Operations::Double(x) => Operations::Double(shift(x)),
+        Operations::Square(x) => Operations::Square(shift(x)),
+        Operations::Cache(id, x) => Operations::Cache(*id, shift(x)),
+        Operations::Add(x, y) => Operations::Add(shift(x), shift(y)),
+        Operations::Sub(x, y) => Operations::Sub(shift(x), shift(y)),
+        Operations::Mul(x, y) => Operations::Mul(shift(x), shift(y)),
+        Operations::IfFeature(feature, x, y) => Operations::IfFeature(*feature, shift(x), shift(y)),
+    }
+}
+
+/// Collect the columns referenced by the constraints and the lookups of
+/// `env`, e.g. to know which ones must be laid out in the witness. Columns
+/// without an alias, like the instruction selectors, are not reported.
+pub fn referenced_columns<Fp: Field>(env: &Env<Fp>) -> BTreeSet<MIPSColumn> {
+    fn collect<Fp: Field>(x: &E<Fp>, columns: &mut BTreeSet<MIPSColumn>) {
+        match x {
+            Operations::Atom(ExprInner::Cell(Variable { col, .. })) => {
+                if let Ok(column) = MIPSColumn::try_from(*col) {
+                    columns.insert(column);
+                }
+            }
+            Operations::Atom(_) => (),
+            Operations::Pow(x, _)
+            | Operations::Double(x)
+            | Operations::Square(x)
+            | Operations::Cache(_, x) => collect(x, columns),
+            Operations::Add(x, y)
+            | Operations::Sub(x, y)
+            | Operations::Mul(x, y)
+            | Operations::IfFeature(_, x, y) => {
+                collect(x, columns);
+                collect(y, columns);
+            }
+        }
+    }
+
+    let mut columns = BTreeSet::new();
+    for constraint in &env.constraints {
+        collect(constraint, &mut columns);
+    }
+    for lookup in &env.lookups {
+        collect(&lookup.magnitude, &mut columns);
+        for value in &lookup.value {
+            collect(value, &mut columns);
+        }
+    }
+    columns
+}
+
+/// Evaluate `x`, the value of the cells being given by `cell`. The MIPS
+/// expressions only use literal constants, see [InterpreterEnv::constant].
+pub fn evaluate<Fp: Field>(x: &E<Fp>, cell: &impl Fn(Variable<Column>) -> Fp) -> Fp {
+    fn constant<Fp: Field>(x: &ConstantExpr<Fp>) -> Fp {
+        match x {
+            Operations::Atom(ConstantExprInner::Constant(Literal(x))) => *x,
+            Operations::Atom(_) => panic!("Only literal constants can be evaluated"),
+            Operations::Pow(x, n) => constant(x).pow([*n]),
+            Operations::Double(x) => constant(x).double(),
+            Operations::Square(x) => constant(x).square(),
+            Operations::Cache(_, x) => constant(x),
+            Operations::Add(x, y) => constant(x) + constant(y),
+            Operations::Sub(x, y) => constant(x) - constant(y),
+            Operations::Mul(x, y) => constant(x) * constant(y),
+            Operations::IfFeature(..) => panic!("Feature flags are not used by the MIPS circuit"),
+        }
+    }
+
+    match x {
+        Operations::Atom(ExprInner::Cell(variable)) => cell(*variable),
+        Operations::Atom(ExprInner::Constant(x)) => constant(x),
+        Operations::Atom(_) => panic!("Only cells and constants can be evaluated"),
+        Operations::Pow(x, n) => evaluate(x, cell).pow([*n]),
+        Operations::Double(x) => evaluate(x, cell).double(),
+        Operations::Square(x) => evaluate(x, cell).square(),
+        Operations::Cache(_, x) => evaluate(x, cell),
+        Operations::Add(x, y) => evaluate(x, cell) + evaluate(y, cell),
+        Operations::Sub(x, y) => evaluate(x, cell) - evaluate(y, cell),
+        Operations::Mul(x, y) => evaluate(x, cell) * evaluate(y, cell),
+        Operations::IfFeature(..) => panic!("Feature flags are not used by the MIPS circuit"),
+    }
+}
+
+/// Materialize `lookups` over the first `rows` rows of the witness, e.g. to
+/// populate the lookup tables, the value of a cell on a row being given by
+/// `cell`. On each row, each lookup gives an entry with its concrete value
+/// and magnitude, and keeps its mode. The conditional lookups, built with
+/// `read_if` or `write_if`, only give an entry on the rows where their
+/// condition holds, i.e. where their magnitude is not zero.
+/// The entries are grouped by table, in the order of the rows and then of
+/// the lookups.
+pub fn to_lookup_table_entries<Fp: Field>(
+    lookups: &[Lookup<E<Fp>>],
+    rows: usize,
+    cell: impl Fn(usize, Variable<Column>) -> Fp,
+) -> BTreeMap<LookupTableIDs, Vec<Lookup<Fp>>> {
+    let mut entries: BTreeMap<LookupTableIDs, Vec<Lookup<Fp>>> = BTreeMap::new();
+    for row in 0..rows {
+        let cell = |variable| cell(row, variable);
+        for lookup in lookups {
+            let entry = lookup.clone().map_expr(|x| evaluate(&x, &cell));
+            if !entry.magnitude.is_zero() {
+                entries.entry(entry.table_id).or_default().push(entry);
+            }
+        }
     }
+    entries
 }
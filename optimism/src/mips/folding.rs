@@ -8,7 +8,10 @@ use crate::{
     Curve, Fp,
 };
 use ark_poly::{Evaluations, Radix2EvaluationDomain};
-use folding::{expressions::FoldingColumnTrait, FoldingConfig};
+use folding::{
+    expressions::{ColumnKind, FoldingColumnTrait},
+    Alphas, FoldingConfig,
+};
 use kimchi_msm::columns::Column;
 use std::ops::Index;
 
@@ -56,9 +59,9 @@ impl Index<Column> for MIPSFoldingWitness {
 pub struct MIPSFoldingConfig;
 
 impl FoldingColumnTrait for MIPSColumn {
-    fn is_witness(&self) -> bool {
+    fn kind(&self) -> ColumnKind {
         // All MIPS columns are witness columns
-        true
+        ColumnKind::Witness
     }
 }
 
@@ -72,4 +75,16 @@ impl FoldingConfig for MIPSFoldingConfig {
     type Witness = MIPSFoldingWitness;
     type Structure = Trace<MIPS_COLUMNS, MIPS_REL_COLS, MIPS_SEL_COLS, MIPSFoldingConfig>;
     type Env = MIPSFoldingEnvironment;
+
+    fn identity_instance(
+        _structure: &Self::Structure,
+        _srs: &Self::Srs,
+        domain: Radix2EvaluationDomain<Fp>,
+        alphas: Alphas<Fp>,
+    ) -> (Self::Instance, Self::Witness) {
+        (
+            MIPSFoldingInstance::zero(alphas),
+            MIPSFoldingWitness::zero(domain),
+        )
+    }
 }
@@ -1,15 +1,22 @@
 use crate::{
     cannon::PAGE_ADDRESS_SIZE,
     lookups::{Lookup, LookupTableIDs},
-    mips::registers::{
-        REGISTER_CURRENT_IP, REGISTER_HEAP_POINTER, REGISTER_HI, REGISTER_LO, REGISTER_NEXT_IP,
-        REGISTER_PREIMAGE_KEY_END, REGISTER_PREIMAGE_OFFSET,
+    mips::{
+        column::MAX_SCRATCH,
+        registers::{
+            Registers, REGISTER_CURRENT_IP, REGISTER_HEAP_POINTER, REGISTER_HI, REGISTER_LO,
+            REGISTER_NEXT_IP, REGISTER_PREIMAGE_KEY_END, REGISTER_PREIMAGE_OFFSET,
+        },
     },
 };
 use ark_ff::{One, Zero};
 use strum::{EnumCount, IntoEnumIterator};
 use strum_macros::{EnumCount, EnumIter};
 
+/// The number of access times spanned by an instruction, see
+/// [InterpreterEnv::access_time]
+pub const ACCESS_TIMES_PER_INSTRUCTION: u32 = MAX_SCRATCH as u32 + 1;
+
 pub const FD_STDIN: u32 = 0;
 pub const FD_STDOUT: u32 = 1;
 pub const FD_STDERR: u32 = 2;
@@ -25,6 +32,13 @@ pub const SYSCALL_EXIT_GROUP: u32 = 4246;
 pub const SYSCALL_READ: u32 = 4003;
 pub const SYSCALL_WRITE: u32 = 4004;
 pub const SYSCALL_FCNTL: u32 = 4055;
+pub const SYSCALL_SCHED_YIELD: u32 = 4162;
+pub const SYSCALL_FUTEX: u32 = 4238;
+pub const SYSCALL_SET_THREAD_AREA: u32 = 4283;
+
+/// Address at which Cannon initializes the stack pointer. The program break
+/// must stay below it.
+pub const STACK_START_ADDRESS: u32 = 0x7fff_d000;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, EnumCount, EnumIter, Hash, Ord, PartialOrd)]
 pub enum Instruction {
@@ -80,6 +94,10 @@ pub enum RTypeInstruction {
     MultiplyToRegister,           // mul
     CountLeadingOnes,             // clo
     CountLeadingZeros,            // clz
+    SignExtendByte,               // seb
+    SignExtendHalfword,           // seh
+    SwapBytesInHalfwords,         // wsbh
+    RotateRight,                  // rotr
 }
 
 #[derive(
@@ -217,13 +235,35 @@ pub trait InterpreterEnv {
 
     fn instruction_counter(&self) -> Self::Variable;
 
+    /// The time of an access to a register or to the memory, whose previous
+    /// access time is stored in the scratch column `last_accessed_location`.
+    /// The accesses of an instruction are ordered by these columns, after
+    /// [InterpreterEnv::instruction_start_time], so that a register or an
+    /// address can be accessed several times by the same instruction.
+    fn access_time(&self, last_accessed_location: &Self::Position) -> Self::Variable;
+
+    /// The time at which the current instruction starts, i.e. at which it
+    /// reads the instruction pointers. The next instruction starts
+    /// [ACCESS_TIMES_PER_INSTRUCTION] later.
+    fn instruction_start_time(&self) -> Self::Variable {
+        self.instruction_counter() * Self::constant(ACCESS_TIMES_PER_INSTRUCTION)
+    }
+
+    /// The time at which the next instruction starts, see
+    /// [InterpreterEnv::instruction_start_time]
+    fn next_instruction_start_time(&self) -> Self::Variable {
+        self.instruction_start_time() + Self::constant(ACCESS_TIMES_PER_INSTRUCTION)
+    }
+
     /// Fetch the value of the general purpose register with index `idx` and store it in local
     /// position `output`.
     ///
     /// # Safety
     ///
     /// No lookups or other constraints are added as part of this operation. The caller must
-    /// manually add the lookups for this operation.
+    /// manually add the lookups for this operation, see [InterpreterEnv::access_register_if]
+    /// which reads the old value from [LookupTableIDs::RegisterLookup] at the time of its last
+    /// access. Adding them here as well would count the access twice in the lookup argument.
     unsafe fn fetch_register(
         &mut self,
         idx: &Self::Variable,
@@ -235,7 +275,8 @@ pub trait InterpreterEnv {
     /// # Safety
     ///
     /// No lookups or other constraints are added as part of this operation. The caller must
-    /// manually add the lookups for this operation.
+    /// manually add the lookups for this operation, see [InterpreterEnv::access_register_if]
+    /// which writes the new value to [LookupTableIDs::RegisterLookup] when `if_is_true` is set.
     unsafe fn push_register_if(
         &mut self,
         idx: &Self::Variable,
@@ -305,21 +346,16 @@ pub trait InterpreterEnv {
         new_value: &Self::Variable,
         if_is_true: &Self::Variable,
     ) {
-        let last_accessed = {
+        let (last_accessed, new_accessed) = {
             let last_accessed_location = self.alloc_scratch();
-            unsafe { self.fetch_register_access(idx, last_accessed_location) }
-        };
-        let instruction_counter = self.instruction_counter();
-        let elapsed_time = instruction_counter.clone() - last_accessed.clone();
-        let new_accessed = {
-            // Here, we write as if the register had been written *at the start of the next
-            // instruction*. This ensures that we can't 'time travel' within this
-            // instruction, and claim to read the value that we're about to write!
-
-            // FIXME: A register should allow multiple accesses within the same instruction.
-
-            instruction_counter + Self::constant(1)
+            // Each access has its own time, after the previous accesses of
+            // this instruction. This ensures that we can't 'time travel', and
+            // claim to read a value that is only written later on.
+            let new_accessed = self.access_time(&last_accessed_location);
+            let last_accessed = unsafe { self.fetch_register_access(idx, last_accessed_location) };
+            (last_accessed, new_accessed)
         };
+        let elapsed_time = new_accessed.clone() - last_accessed.clone() - Self::constant(1);
         unsafe { self.push_register_access_if(idx, new_accessed.clone(), if_is_true) };
         self.add_lookup(Lookup::write_if(
             if_is_true.clone(),
@@ -331,7 +367,7 @@ pub trait InterpreterEnv {
             LookupTableIDs::RegisterLookup,
             vec![idx.clone(), new_accessed, new_value.clone()],
         ));
-        self.range_check64(&elapsed_time);
+        self.range_check_elapsed_time(&elapsed_time);
     }
 
     fn read_register(&mut self, idx: &Self::Variable) -> Self::Variable {
@@ -443,18 +479,15 @@ pub trait InterpreterEnv {
         old_value: &Self::Variable,
         new_value: &Self::Variable,
     ) {
-        let last_accessed = {
+        let (last_accessed, new_accessed) = {
             let last_accessed_location = self.alloc_scratch();
-            unsafe { self.fetch_memory_access(addr, last_accessed_location) }
-        };
-        let instruction_counter = self.instruction_counter();
-        let elapsed_time = instruction_counter.clone() - last_accessed.clone();
-        let new_accessed = {
-            // Here, we write as if the memory had been written *at the start of the next
-            // instruction*. This ensures that we can't 'time travel' within this
-            // instruction, and claim to read the value that we're about to write!
-            instruction_counter + Self::constant(1)
+            // Like for the registers, each access has its own time, see
+            // [InterpreterEnv::access_register_if]
+            let new_accessed = self.access_time(&last_accessed_location);
+            let last_accessed = unsafe { self.fetch_memory_access(addr, last_accessed_location) };
+            (last_accessed, new_accessed)
         };
+        let elapsed_time = new_accessed.clone() - last_accessed.clone() - Self::constant(1);
         unsafe { self.push_memory_access(addr, new_accessed.clone()) };
         self.add_lookup(Lookup::write_one(
             LookupTableIDs::MemoryLookup,
@@ -464,7 +497,28 @@ pub trait InterpreterEnv {
             LookupTableIDs::MemoryLookup,
             vec![addr.clone(), new_accessed, new_value.clone()],
         ));
-        self.range_check64(&elapsed_time);
+        self.range_check_elapsed_time(&elapsed_time);
+    }
+
+    /// Range-checks `elapsed_time`, the time elapsed since the last access to
+    /// a memory address or a register, less one, to 32 bits. It ensures that
+    /// the access times of each address are strictly increasing, so that a
+    /// read cannot be matched with a write made later.
+    /// The value is split into two 16-bit limbs looked up in the range check
+    /// table, only the high one being stored in a scratch column.
+    fn range_check_elapsed_time(&mut self, elapsed_time: &Self::Variable) {
+        // FIXME: Support more than 2^32 access times, i.e. about 2^32 /
+        // ACCESS_TIMES_PER_INSTRUCTION instructions, between two accesses
+        let high = {
+            let pos = self.alloc_scratch();
+            unsafe { self.bitmask(elapsed_time, 32, 16, pos) }
+        };
+        // The high limb is range checked when extracted
+        let low = elapsed_time.clone() - high * Self::constant(1 << 16);
+        self.add_lookup(Lookup::read_one(
+            LookupTableIDs::RangeCheck16Lookup,
+            vec![low],
+        ));
     }
 
     fn read_memory(&mut self, addr: &Self::Variable) -> Self::Variable {
@@ -491,13 +545,9 @@ pub trait InterpreterEnv {
         };
     }
 
-    fn range_check64(&mut self, _value: &Self::Variable) {
-        // TODO
-    }
-
     fn set_instruction_pointer(&mut self, ip: Self::Variable) {
         let idx = Self::constant(REGISTER_CURRENT_IP as u32);
-        let new_accessed = self.instruction_counter() + Self::constant(1);
+        let new_accessed = self.next_instruction_start_time();
         unsafe {
             self.push_register_access(&idx, new_accessed.clone());
         }
@@ -518,14 +568,14 @@ pub trait InterpreterEnv {
         };
         self.add_lookup(Lookup::write_one(
             LookupTableIDs::RegisterLookup,
-            vec![idx, self.instruction_counter(), ip.clone()],
+            vec![idx, self.instruction_start_time(), ip.clone()],
         ));
         ip
     }
 
     fn set_next_instruction_pointer(&mut self, ip: Self::Variable) {
         let idx = Self::constant(REGISTER_NEXT_IP as u32);
-        let new_accessed = self.instruction_counter() + Self::constant(1);
+        let new_accessed = self.next_instruction_start_time();
         unsafe {
             self.push_register_access(&idx, new_accessed.clone());
         }
@@ -546,7 +596,7 @@ pub trait InterpreterEnv {
         };
         self.add_lookup(Lookup::write_one(
             LookupTableIDs::RegisterLookup,
-            vec![idx, self.instruction_counter(), ip.clone()],
+            vec![idx, self.instruction_start_time(), ip.clone()],
         ));
         ip
     }
@@ -679,6 +729,51 @@ pub trait InterpreterEnv {
         position: Self::Position,
     ) -> Self::Variable;
 
+    /// Returns 1 if `x < y` as unsigned 32-bits integers, or 0 otherwise.
+    /// The result is the complement of the carry bit of `x + 2^32 - y`, which
+    /// is split into this bit and its low 32 bits, both being range checked.
+    fn less_than(&mut self, x: &Self::Variable, y: &Self::Variable) -> Self::Variable {
+        let two_to_32 = Self::constant(1 << 16) * Self::constant(1 << 16);
+        let shifted_diff = x.clone() + two_to_32.clone() - y.clone();
+        let carry = {
+            let pos = self.alloc_scratch();
+            unsafe { self.bitmask(&shifted_diff, 33, 32, pos) }
+        };
+        let low = {
+            let pos = self.alloc_scratch();
+            unsafe { self.bitmask(&shifted_diff, 32, 0, pos) }
+        };
+        self.add_constraint(shifted_diff - carry.clone() * two_to_32 - low);
+        Self::constant(1) - carry
+    }
+
+    /// Returns 1 if `x < y` as signed 32-bits integers, or 0 otherwise.
+    /// Flipping the sign bit maps the signed integers onto the unsigned ones
+    /// in order, so the flipped values are compared with [Self::less_than].
+    fn less_than_signed(&mut self, x: &Self::Variable, y: &Self::Variable) -> Self::Variable {
+        let x = self.flip_sign_bit(x);
+        let y = self.flip_sign_bit(y);
+        self.less_than(&x, &y)
+    }
+
+    /// Returns `x xor 2^31` for the 32-bits value `x`. `x` is decomposed in
+    /// its sign bit and its 31 low bits, both being range checked.
+    fn flip_sign_bit(&mut self, x: &Self::Variable) -> Self::Variable {
+        let sign_bit = {
+            let pos = self.alloc_scratch();
+            unsafe { self.bitmask(x, 32, 31, pos) }
+        };
+        let low_bits = {
+            let pos = self.alloc_scratch();
+            unsafe { self.bitmask(x, 31, 0, pos) }
+        };
+        self.assert_equal(
+            x.clone(),
+            sign_bit.clone() * Self::constant(1 << 31) + low_bits.clone(),
+        );
+        (Self::constant(1) - sign_bit) * Self::constant(1 << 31) + low_bits
+    }
+
     /// Returns `x or y`, storing the result in `position`.
     ///
     /// # Safety
@@ -736,8 +831,9 @@ pub trait InterpreterEnv {
     ///
     /// # Safety
     ///
-    /// There are no constraints on the returned values; callers must manually add constraints to
-    /// ensure that they are correctly constructed.
+    /// The overflow bit is constrained to be boolean and to satisfy
+    /// `x + y = out + overflow * 2^32`, but the output is not range checked; callers must
+    /// manually add constraints to ensure that it is a 32-bits value.
     unsafe fn add_witness(
         &mut self,
         y: &Self::Variable,
@@ -746,13 +842,14 @@ pub trait InterpreterEnv {
         overflow_position: Self::Position,
     ) -> (Self::Variable, Self::Variable);
 
-    /// Returns `x + y` and the underflow bit, storing the results in `position_out` and
+    /// Returns `x - y` and the underflow bit, storing the results in `position_out` and
     /// `position_underflow` respectively.
     ///
     /// # Safety
     ///
-    /// There are no constraints on the returned values; callers must manually add constraints to
-    /// ensure that they are correctly constructed.
+    /// The underflow bit is constrained to be boolean and to satisfy
+    /// `x - y = out - underflow * 2^32`, but the output is not range checked; callers must
+    /// manually add constraints to ensure that it is a 32-bits value.
     unsafe fn sub_witness(
         &mut self,
         y: &Self::Variable,
@@ -806,14 +903,16 @@ pub trait InterpreterEnv {
         position_lo: Self::Position,
     ) -> (Self::Variable, Self::Variable);
 
-    /// Returns `(x / y, x % y)`, storing the results in `position_quotient` and
-    /// `position_remainder` respectively.
+    /// Returns `(x / y, x % y)` as signed integers, storing the results in `position_quotient`
+    /// and `position_remainder` respectively.
+    /// The constraints environment checks that `x = q y + r` and `|r| < |y|`, `r` having the
+    /// sign of `x`, using additional scratch columns. When `y` is 0, the results are
+    /// unpredictable and are not constrained.
     ///
     /// # Safety
     ///
-    /// There are no constraints on the returned values; callers must manually add constraints to
-    /// ensure that the pair of returned values correspond to the given values `x` and `y`, and
-    /// that they fall within the desired range.
+    /// The returned values are only constrained by the environment when `y` is not 0, callers
+    /// must not assume anything about them otherwise.
     unsafe fn divmod_signed(
         &mut self,
         x: &Self::Variable,
@@ -824,12 +923,13 @@ pub trait InterpreterEnv {
 
     /// Returns `(x / y, x % y)`, storing the results in `position_quotient` and
     /// `position_remainder` respectively.
+    /// The constraints environment checks that `x = q y + r` and `r < y`, using additional
+    /// scratch columns. When `y` is 0, the results are unpredictable and are not constrained.
     ///
     /// # Safety
     ///
-    /// There are no constraints on the returned values; callers must manually add constraints to
-    /// ensure that the pair of returned values correspond to the given values `x` and `y`, and
-    /// that they fall within the desired range.
+    /// The returned values are only constrained by the environment when `y` is not 0, callers
+    /// must not assume anything about them otherwise.
     unsafe fn divmod(
         &mut self,
         x: &Self::Variable,
@@ -839,11 +939,13 @@ pub trait InterpreterEnv {
     ) -> (Self::Variable, Self::Variable);
 
     /// Returns the number of leading 0s in `x`, storing the result in `position`.
+    /// The constraints environment checks the returned value against the bits of `x`, using
+    /// additional scratch columns.
     ///
     /// # Safety
     ///
-    /// There are no constraints on the returned value; callers must manually add constraints to
-    /// ensure that it is correctly constructed.
+    /// The returned value is only constrained by the environment, callers must not assume any
+    /// other constraint on it.
     unsafe fn count_leading_zeros(
         &mut self,
         x: &Self::Variable,
@@ -851,11 +953,13 @@ pub trait InterpreterEnv {
     ) -> Self::Variable;
 
     /// Returns the number of leading 1s in `x`, storing the result in `position`.
+    /// The constraints environment checks the returned value against the bits of `x`, using
+    /// additional scratch columns.
     ///
     /// # Safety
     ///
-    /// There are no constraints on the returned value; callers must manually add constraints to
-    /// ensure that it is correctly constructed.
+    /// The returned value is only constrained by the environment, callers must not assume any
+    /// other constraint on it.
     unsafe fn count_leading_ones(
         &mut self,
         x: &Self::Variable,
@@ -886,8 +990,125 @@ pub trait InterpreterEnv {
         old_ptr
     }
 
+    /// Move the program break, i.e. the heap pointer, to `new_brk`, and
+    /// return the resulting break.
+    /// Like Linux, the break is left unchanged and its current value is
+    /// returned when the request is invalid, i.e. when it would shrink the
+    /// heap or move the break into the stack region starting at
+    /// [STACK_START_ADDRESS].
+    fn syscall_brk(&mut self, new_brk: &Self::Variable) -> Self::Variable {
+        let idx = Self::constant(REGISTER_HEAP_POINTER as u32);
+        let old_brk = self.read_register(&idx);
+        let is_shrink = self.less_than(new_brk, &old_brk);
+        let below_stack = self.less_than(new_brk, &Self::constant(STACK_START_ADDRESS));
+        let is_valid = {
+            let pos = self.alloc_scratch();
+            self.copy(&((Self::constant(1) - is_shrink) * below_stack), pos)
+        };
+        self.write_register_if(&idx, new_brk.clone(), &is_valid);
+        let pos = self.alloc_scratch();
+        self.copy(
+            &(is_valid.clone() * new_brk.clone() + (Self::constant(1) - is_valid) * old_brk),
+            pos,
+        )
+    }
+
     fn set_halted(&mut self, flag: Self::Variable);
 
+    /// Account for the execution of one step of the program: the step budget,
+    /// i.e. the number of steps the program may still execute, is decremented
+    /// by one, including for the step halting the program. The program must
+    /// have halted once the budget is exhausted.
+    fn consume_step(&mut self);
+
+    /// Write 1 into the register `rd` if the value of the register `rs` is
+    /// less than the value of the register `rt`, and 0 otherwise, as done by
+    /// `slt` when `signed` is set and by `sltu` otherwise.
+    fn set_less_than(
+        &mut self,
+        rd: &Self::Variable,
+        rs: &Self::Variable,
+        rt: &Self::Variable,
+        signed: bool,
+    ) {
+        let rs = self.read_register(rs);
+        let rt = self.read_register(rt);
+        let res = if signed {
+            self.less_than_signed(&rs, &rt)
+        } else {
+            self.less_than(&rs, &rt)
+        };
+        self.write_register(rd, res);
+    }
+
+    /// Write 1 into the register `rt` if the value of the register `rs` is
+    /// less than the sign-extended 16 bits `immediate`, and 0 otherwise, as
+    /// done by `slti` when `signed` is set and by `sltiu` otherwise.
+    /// Note that `sltiu` also sign-extends the immediate before comparing it
+    /// as an unsigned integer.
+    fn set_less_than_immediate(
+        &mut self,
+        rt: &Self::Variable,
+        rs: &Self::Variable,
+        immediate: &Self::Variable,
+        signed: bool,
+    ) {
+        let rs = self.read_register(rs);
+        let immediate = self.sign_extend(immediate, 16);
+        let res = if signed {
+            self.less_than_signed(&rs, &immediate)
+        } else {
+            self.less_than(&rs, &immediate)
+        };
+        self.write_register(rt, res);
+    }
+
+    /// Multiply the values of the registers `rs` and `rt`, writing the high
+    /// 32 bits of the product into HI and the low 32 bits into LO, as done by
+    /// `mult` when `signed` is set and by `multu` otherwise.
+    fn multiply_to_hi_lo(&mut self, rs: &Self::Variable, rt: &Self::Variable, signed: bool) {
+        let rs = self.read_register(rs);
+        let rt = self.read_register(rt);
+        let (hi, lo) = {
+            // Fixme: constrain
+            let hi_pos = self.alloc_scratch();
+            let lo_pos = self.alloc_scratch();
+            if signed {
+                unsafe { self.mul_hi_lo_signed(&rs, &rt, hi_pos, lo_pos) }
+            } else {
+                unsafe { self.mul_hi_lo(&rs, &rt, hi_pos, lo_pos) }
+            }
+        };
+        self.write_register(&Self::constant(REGISTER_HI as u32), hi);
+        self.write_register(&Self::constant(REGISTER_LO as u32), lo);
+    }
+
+    /// Divide the value of the register `rs` by the value of the register
+    /// `rt`, writing the quotient into LO and the remainder into HI, as done
+    /// by `div` when `signed` is set and by `divu` otherwise.
+    fn divide_to_hi_lo(&mut self, rs: &Self::Variable, rt: &Self::Variable, signed: bool) {
+        let rs = self.read_register(rs);
+        let rt = self.read_register(rt);
+        let (quotient, remainder) = {
+            let quotient_pos = self.alloc_scratch();
+            let remainder_pos = self.alloc_scratch();
+            if signed {
+                unsafe { self.divmod_signed(&rs, &rt, quotient_pos, remainder_pos) }
+            } else {
+                unsafe { self.divmod(&rs, &rt, quotient_pos, remainder_pos) }
+            }
+        };
+        self.write_register(&Self::constant(REGISTER_LO as u32), quotient);
+        self.write_register(&Self::constant(REGISTER_HI as u32), remainder);
+    }
+
+    /// Return from a syscall, writing its result into `$v0` and its error
+    /// code into `$a3`, 0 meaning success.
+    fn finish_syscall(&mut self, result: Self::Variable, errno: Self::Variable) {
+        self.write_register(&Self::constant(2), result);
+        self.write_register(&Self::constant(7), errno);
+    }
+
     fn sign_extend(&mut self, x: &Self::Variable, bitlength: u32) -> Self::Variable {
         // FIXME: Constrain `high_bit`
         let high_bit = {
@@ -897,6 +1118,143 @@ pub trait InterpreterEnv {
         high_bit * Self::constant(((1 << (32 - bitlength)) - 1) << bitlength) + x.clone()
     }
 
+    /// Sign-extends the `bitlength` lowest bits of the 32-bit value `x`, as done
+    /// by `seb` for 8 bits and `seh` for 16 bits.
+    /// `x` is decomposed in its bits above `bitlength`, its sign bit
+    /// `bitlength - 1` and the bits below it, each of them being range-checked
+    /// when extracted. The result is constrained to be the bits below the sign
+    /// bit, with the sign bit copied into all the upper bits.
+    fn sign_extend_low_bits(&mut self, x: &Self::Variable, bitlength: u32) -> Self::Variable {
+        let low_bits = {
+            let pos = self.alloc_scratch();
+            unsafe { self.bitmask(x, bitlength - 1, 0, pos) }
+        };
+        let sign_bit = {
+            let pos = self.alloc_scratch();
+            unsafe { self.bitmask(x, bitlength, bitlength - 1, pos) }
+        };
+        let high_bits = {
+            let pos = self.alloc_scratch();
+            unsafe { self.bitmask(x, 32, bitlength, pos) }
+        };
+        self.assert_boolean(sign_bit.clone());
+        self.assert_equal(
+            x.clone(),
+            high_bits * Self::constant(1 << bitlength)
+                + sign_bit.clone() * Self::constant(1 << (bitlength - 1))
+                + low_bits.clone(),
+        );
+        let pos = self.alloc_scratch();
+        self.copy(
+            &(low_bits + sign_bit * Self::constant(u32::MAX << (bitlength - 1))),
+            pos,
+        )
+    }
+
+    /// Swaps the two bytes of each halfword of the 32-bit value `x`, as done by
+    /// `wsbh`.
+    /// The bytes of `x` are range-checked with the byte lookup table, `x` is
+    /// constrained to be their composition and the result their permutation.
+    fn swap_bytes_in_halfwords(&mut self, x: &Self::Variable) -> Self::Variable {
+        let [b0, b1, b2, b3] = std::array::from_fn(|i| {
+            let i = i as u32;
+            let pos = self.alloc_scratch();
+            let byte = unsafe { self.bitmask(x, 8 * (i + 1), 8 * i, pos) };
+            self.add_lookup(Lookup::read_one(
+                LookupTableIDs::ByteLookup,
+                vec![byte.clone()],
+            ));
+            byte
+        });
+        self.assert_equal(
+            x.clone(),
+            b0.clone()
+                + b1.clone() * Self::constant(1 << 8)
+                + b2.clone() * Self::constant(1 << 16)
+                + b3.clone() * Self::constant(1 << 24),
+        );
+        let pos = self.alloc_scratch();
+        self.copy(
+            &(b1 + b0 * Self::constant(1 << 8)
+                + b3 * Self::constant(1 << 16)
+                + b2 * Self::constant(1 << 24)),
+            pos,
+        )
+    }
+
+    /// Rotates the 32-bit value `x` right by `by` bits, as done by `rotr`.
+    /// `x` is split at `by` into its high bits, i.e. `x >> by`, and its `by`
+    /// low bits, range-checked below `2^by`. The rotation moves the low bits
+    /// to the top, i.e. multiplies them by `2^(32 - by)`, and adds the high
+    /// bits. Both powers of two are built from the 5 bits of `by`, `31 - by`
+    /// having the complemented bits of `by`.
+    fn rotate_right(&mut self, x: &Self::Variable, by: &Self::Variable) -> Self::Variable {
+        let by_bits: [_; 5] = std::array::from_fn(|i| {
+            let i = i as u32;
+            let pos = self.alloc_scratch();
+            unsafe { self.bitmask(by, i + 1, i, pos) }
+        });
+        self.assert_equal(
+            by.clone(),
+            by_bits
+                .iter()
+                .enumerate()
+                .fold(Self::constant(0), |acc, (i, bit)| {
+                    acc + bit.clone() * Self::constant(1 << i)
+                }),
+        );
+        // 2^by and 2^(32 - by) = 2 * 2^(31 - by)
+        let (mut pow, mut pow_complement) = (Self::constant(1), Self::constant(2));
+        for (i, bit) in by_bits.iter().enumerate() {
+            // 2^(2^i) - 1
+            let factor = Self::constant((1 << (1 << i)) - 1);
+            pow = {
+                let pos = self.alloc_scratch();
+                self.copy(
+                    &(pow * (Self::constant(1) + bit.clone() * factor.clone())),
+                    pos,
+                )
+            };
+            pow_complement = {
+                let pos = self.alloc_scratch();
+                self.copy(
+                    &(pow_complement
+                        * (Self::constant(1) + (Self::constant(1) - bit.clone()) * factor)),
+                    pos,
+                )
+            };
+        }
+        // The high bits are range-checked to 32 bits, so that the split does
+        // not wrap around the field
+        let high = unsafe {
+            let pos = self.alloc_scratch();
+            self.shift_right(x, by, pos)
+        };
+        let low = x.clone() - high.clone() * pow.clone();
+        // low is in [0, 2^by)
+        for value in [low.clone(), pow - Self::constant(1) - low.clone()] {
+            let checked = {
+                let pos = self.alloc_scratch();
+                unsafe { self.bitmask(&value, 32, 0, pos) }
+            };
+            self.assert_equal(value, checked);
+        }
+        let pos = self.alloc_scratch();
+        self.copy(&(low * pow_complement + high), pos)
+    }
+
+    /// Returns the value loaded by `lui`, i.e. `immediate << 16`.
+    /// The immediate is range-checked to 16 bits and the result is constrained
+    /// to be `immediate * 2^16`, which leaves its lower half zero.
+    fn load_upper_immediate(&mut self, immediate: &Self::Variable) -> Self::Variable {
+        self.add_lookup(Lookup::read_one(
+            LookupTableIDs::RangeCheck16Lookup,
+            vec![immediate.clone()],
+        ));
+        let pos = self.alloc_scratch();
+        self.copy(&(immediate.clone() * Self::constant(1 << 16)), pos)
+    }
+
     fn report_exit(&mut self, exit_code: &Self::Variable);
 
     /// Request the preimage oracle for `len` bytes and store the bytes starting
@@ -904,6 +1262,9 @@ pub trait InterpreterEnv {
     /// The number of bytes actually read will be set into `pos`.
     /// The first 8 bytes will be the length of the preimage, encoded as an
     /// unsigned 64bits, and the rest will be the preimage.
+    /// At most 4 bytes, and at most `len` bytes, of the preimage are read, and
+    /// the number of bytes left to read is decremented by the number of bytes
+    /// read, the end of the preimage being reached when it gets to zero.
     fn request_preimage_write(
         &mut self,
         addr: &Self::Variable,
@@ -912,14 +1273,211 @@ pub trait InterpreterEnv {
     ) -> Self::Variable;
 
     fn request_hint_write(&mut self, addr: &Self::Variable, len: &Self::Variable);
+
+    /// Read a chunk of at most `len` bytes of the preimage into `addr`, and
+    /// return the number of bytes actually read.
+    /// The preimage offset register is read before the request and written
+    /// back after it, so that the register lookups enforce
+    /// `preimage_offset_next = preimage_offset + read_len` across multi-chunk
+    /// reads.
+    fn read_preimage_chunk(
+        &mut self,
+        addr: &Self::Variable,
+        len: &Self::Variable,
+    ) -> Self::Variable {
+        let idx = Self::constant(REGISTER_PREIMAGE_OFFSET as u32);
+        let preimage_offset = self.read_register(&idx);
+        let read_len = {
+            let pos = self.alloc_scratch();
+            self.request_preimage_write(addr, len, pos)
+        };
+        self.write_register(&idx, preimage_offset + read_len.clone());
+        read_len
+    }
+
+    /// Reset the preimage offset, as done when a new preimage key is written
+    fn reset_preimage_offset(&mut self) {
+        self.write_register(
+            &Self::constant(REGISTER_PREIMAGE_OFFSET as u32),
+            Self::constant(0u32),
+        );
+    }
+}
+
+/// Decodes the instruction words of a program into [Instruction]s,
+/// independently of the environment running them.
+///
+/// Only the witness environment decodes the words it reads from the memory,
+/// through its registers. The constraint environment is built per decoded
+/// [Instruction], see [interpret_decoded_instruction].
+pub trait InstructionDecoder {
+    /// Returns the value of the general purpose register `idx`. The system
+    /// calls share a single encoding, and are told apart by the syscall number
+    /// in the register 2 and by the file descriptor in the register 4.
+    fn general_purpose_register(&self, idx: usize) -> u32;
+
+    /// Decodes the instruction word `instruction`, as read from the memory in
+    /// big-endian order.
+    fn decode_instruction(&self, instruction: u32) -> Instruction {
+        match instruction >> 26 {
+            0x00 => match instruction & 0x3F {
+                0x00 => Instruction::RType(RTypeInstruction::ShiftLeftLogical),
+                // srl, or rotr when the bit 21 is set
+                0x02 => match (instruction >> 21) & 0x1F {
+                    0x00 => Instruction::RType(RTypeInstruction::ShiftRightLogical),
+                    0x01 => Instruction::RType(RTypeInstruction::RotateRight),
+                    _ => panic!("Unhandled instruction {:#X}", instruction),
+                },
+                0x03 => Instruction::RType(RTypeInstruction::ShiftRightArithmetic),
+                0x04 => Instruction::RType(RTypeInstruction::ShiftLeftLogicalVariable),
+                0x06 => Instruction::RType(RTypeInstruction::ShiftRightLogicalVariable),
+                0x07 => Instruction::RType(RTypeInstruction::ShiftRightArithmeticVariable),
+                0x08 => Instruction::RType(RTypeInstruction::JumpRegister),
+                0x09 => Instruction::RType(RTypeInstruction::JumpAndLinkRegister),
+                0x0a => Instruction::RType(RTypeInstruction::MoveZero),
+                0x0b => Instruction::RType(RTypeInstruction::MoveNonZero),
+                0x0c => match self.general_purpose_register(2) {
+                    SYSCALL_MMAP => Instruction::RType(RTypeInstruction::SyscallMmap),
+                    SYSCALL_BRK | SYSCALL_CLONE => {
+                        Instruction::RType(RTypeInstruction::SyscallOther)
+                    }
+                    SYSCALL_EXIT_GROUP => Instruction::RType(RTypeInstruction::SyscallExitGroup),
+                    SYSCALL_READ => match self.general_purpose_register(4) {
+                        FD_HINT_READ => Instruction::RType(RTypeInstruction::SyscallReadHint),
+                        FD_PREIMAGE_READ => {
+                            Instruction::RType(RTypeInstruction::SyscallReadPreimage)
+                        }
+                        _ => Instruction::RType(RTypeInstruction::SyscallReadOther),
+                    },
+                    SYSCALL_WRITE => match self.general_purpose_register(4) {
+                        FD_PREIMAGE_WRITE => {
+                            Instruction::RType(RTypeInstruction::SyscallWritePreimage)
+                        }
+                        FD_HINT_WRITE => Instruction::RType(RTypeInstruction::SyscallWriteHint),
+                        _ => Instruction::RType(RTypeInstruction::SyscallWriteOther),
+                    },
+                    SYSCALL_FCNTL => Instruction::RType(RTypeInstruction::SyscallFcntl),
+                    SYSCALL_FUTEX | SYSCALL_SCHED_YIELD | SYSCALL_SET_THREAD_AREA => {
+                        // Thread-related, no-ops for single-threaded programs
+                        Instruction::RType(RTypeInstruction::SyscallOther)
+                    }
+                    _ => {
+                        // NB: This has well-defined behavior. Don't panic!
+                        Instruction::RType(RTypeInstruction::SyscallOther)
+                    }
+                },
+                0x0f => Instruction::RType(RTypeInstruction::Sync),
+                0x10 => Instruction::RType(RTypeInstruction::MoveFromHi),
+                0x11 => Instruction::RType(RTypeInstruction::MoveToHi),
+                0x12 => Instruction::RType(RTypeInstruction::MoveFromLo),
+                0x13 => Instruction::RType(RTypeInstruction::MoveToLo),
+                0x18 => Instruction::RType(RTypeInstruction::Multiply),
+                0x19 => Instruction::RType(RTypeInstruction::MultiplyUnsigned),
+                0x1a => Instruction::RType(RTypeInstruction::Div),
+                0x1b => Instruction::RType(RTypeInstruction::DivUnsigned),
+                0x20 => Instruction::RType(RTypeInstruction::Add),
+                0x21 => Instruction::RType(RTypeInstruction::AddUnsigned),
+                0x22 => Instruction::RType(RTypeInstruction::Sub),
+                0x23 => Instruction::RType(RTypeInstruction::SubUnsigned),
+                0x24 => Instruction::RType(RTypeInstruction::And),
+                0x25 => Instruction::RType(RTypeInstruction::Or),
+                0x26 => Instruction::RType(RTypeInstruction::Xor),
+                0x27 => Instruction::RType(RTypeInstruction::Nor),
+                0x2a => Instruction::RType(RTypeInstruction::SetLessThan),
+                0x2b => Instruction::RType(RTypeInstruction::SetLessThanUnsigned),
+                _ => {
+                    panic!("Unhandled instruction {:#X}", instruction)
+                }
+            },
+            0x01 => {
+                // RegImm instructions
+                match (instruction >> 16) & 0x1F {
+                    0x0 => Instruction::IType(ITypeInstruction::BranchLtZero),
+                    0x1 => Instruction::IType(ITypeInstruction::BranchGeqZero),
+                    _ => panic!("Unhandled instruction {:#X}", instruction),
+                }
+            }
+            0x02 => Instruction::JType(JTypeInstruction::Jump),
+            0x03 => Instruction::JType(JTypeInstruction::JumpAndLink),
+            0x04 => Instruction::IType(ITypeInstruction::BranchEq),
+            0x05 => Instruction::IType(ITypeInstruction::BranchNeq),
+            0x06 => Instruction::IType(ITypeInstruction::BranchLeqZero),
+            0x07 => Instruction::IType(ITypeInstruction::BranchGtZero),
+            0x08 => Instruction::IType(ITypeInstruction::AddImmediate),
+            0x09 => Instruction::IType(ITypeInstruction::AddImmediateUnsigned),
+            0x0A => Instruction::IType(ITypeInstruction::SetLessThanImmediate),
+            0x0B => Instruction::IType(ITypeInstruction::SetLessThanImmediateUnsigned),
+            0x0C => Instruction::IType(ITypeInstruction::AndImmediate),
+            0x0D => Instruction::IType(ITypeInstruction::OrImmediate),
+            0x0E => Instruction::IType(ITypeInstruction::XorImmediate),
+            0x0F => Instruction::IType(ITypeInstruction::LoadUpperImmediate),
+            0x1C => match instruction & 0x3F {
+                0x02 => Instruction::RType(RTypeInstruction::MultiplyToRegister),
+                0x20 => Instruction::RType(RTypeInstruction::CountLeadingZeros),
+                0x21 => Instruction::RType(RTypeInstruction::CountLeadingOnes),
+                _ => panic!("Unhandled instruction {:#X}", instruction),
+            },
+            0x1F => match instruction & 0x3F {
+                // bshfl, the operation being given by the sa field
+                0x20 => match (instruction >> 6) & 0x1F {
+                    0x02 => Instruction::RType(RTypeInstruction::SwapBytesInHalfwords),
+                    0x10 => Instruction::RType(RTypeInstruction::SignExtendByte),
+                    0x18 => Instruction::RType(RTypeInstruction::SignExtendHalfword),
+                    _ => panic!("Unhandled instruction {:#X}", instruction),
+                },
+                _ => panic!("Unhandled instruction {:#X}", instruction),
+            },
+            0x20 => Instruction::IType(ITypeInstruction::Load8),
+            0x21 => Instruction::IType(ITypeInstruction::Load16),
+            0x22 => Instruction::IType(ITypeInstruction::LoadWordLeft),
+            0x23 => Instruction::IType(ITypeInstruction::Load32),
+            0x24 => Instruction::IType(ITypeInstruction::Load8Unsigned),
+            0x25 => Instruction::IType(ITypeInstruction::Load16Unsigned),
+            0x26 => Instruction::IType(ITypeInstruction::LoadWordRight),
+            0x28 => Instruction::IType(ITypeInstruction::Store8),
+            0x29 => Instruction::IType(ITypeInstruction::Store16),
+            0x2a => Instruction::IType(ITypeInstruction::StoreWordLeft),
+            0x2b => Instruction::IType(ITypeInstruction::Store32),
+            0x2e => Instruction::IType(ITypeInstruction::StoreWordRight),
+            0x30 => {
+                // Note: This is ll (LoadLinked), but we're only simulating a single processor.
+                Instruction::IType(ITypeInstruction::Load32)
+            }
+            0x38 => {
+                // Note: This is sc (StoreConditional), but we're only simulating a single processor.
+                Instruction::IType(ITypeInstruction::Store32Conditional)
+            }
+            _ => {
+                panic!("Unhandled instruction {:#X}", instruction)
+            }
+        }
+    }
+}
+
+impl InstructionDecoder for Registers<u32> {
+    fn general_purpose_register(&self, idx: usize) -> u32 {
+        self.general_purpose[idx]
+    }
 }
 
-pub fn interpret_instruction<Env: InterpreterEnv>(env: &mut Env, instr: Instruction) {
+/// Decodes the instruction word `instr_word` with `decoder` and runs the
+/// decoded instruction, see [interpret_decoded_instruction].
+pub fn interpret_instruction<Env: InterpreterEnv>(
+    env: &mut Env,
+    decoder: &impl InstructionDecoder,
+    instr_word: u32,
+) {
+    let instr = decoder.decode_instruction(instr_word);
+    interpret_decoded_instruction(env, instr)
+}
+
+pub fn interpret_decoded_instruction<Env: InterpreterEnv>(env: &mut Env, instr: Instruction) {
     match instr {
         Instruction::RType(instr) => interpret_rtype(env, instr),
         Instruction::JType(instr) => interpret_jtype(env, instr),
         Instruction::IType(instr) => interpret_itype(env, instr),
     }
+    env.consume_step();
 }
 
 pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstruction) {
@@ -1071,8 +1629,7 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
                     pos,
                 )
             };
-            env.write_register(&Env::constant(2), return_position);
-            env.write_register(&Env::constant(7), Env::constant(0));
+            env.finish_syscall(return_position, Env::constant(0));
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
@@ -1092,18 +1649,8 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
         RTypeInstruction::SyscallReadPreimage => {
             let addr = env.read_register(&Env::constant(5));
             let length = env.read_register(&Env::constant(6));
-            let preimage_offset =
-                env.read_register(&Env::constant(REGISTER_PREIMAGE_OFFSET as u32));
-            let read_length = {
-                let pos = env.alloc_scratch();
-                env.request_preimage_write(&addr, &length, pos)
-            };
-            env.write_register(
-                &Env::constant(REGISTER_PREIMAGE_OFFSET as u32),
-                preimage_offset + read_length.clone(),
-            );
-            env.write_register(&Env::constant(2), read_length);
-            env.write_register(&Env::constant(7), Env::constant(0));
+            let read_length = env.read_preimage_chunk(&addr, &length);
+            env.finish_syscall(read_length, Env::constant(0));
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
@@ -1126,8 +1673,7 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
             let v0 = other_fd.clone() * Env::constant(0xFFFFFFFF);
             let v1 = other_fd * Env::constant(0x9); // EBADF
 
-            env.write_register(&Env::constant(2), v0);
-            env.write_register(&Env::constant(7), v1);
+            env.finish_syscall(v0, v1);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
@@ -1136,8 +1682,7 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
             let length = env.read_register(&Env::constant(6));
             // TODO: Message preimage oracle
             env.request_hint_write(&addr, &length);
-            env.write_register(&Env::constant(2), length);
-            env.write_register(&Env::constant(7), Env::constant(0));
+            env.finish_syscall(length, Env::constant(0));
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
@@ -1275,17 +1820,12 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
             // Update the preimage key.
             env.write_register(&register_idx, value);
             // Reset the preimage offset.
-            env.write_register(
-                &Env::constant(REGISTER_PREIMAGE_OFFSET as u32),
-                Env::constant(0u32),
-            );
-            // Return the number of bytes read.
-            env.write_register(
-                &Env::constant(2),
+            env.reset_preimage_offset();
+            // Return the number of bytes read, without error.
+            env.finish_syscall(
                 overwrite_0 + overwrite_1 + overwrite_2 + overwrite_3,
+                Env::constant(0u32),
             );
-            // Set the error register to 0.
-            env.write_register(&Env::constant(7), Env::constant(0u32));
 
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
@@ -1314,8 +1854,7 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
             let v0 = known_fd * write_length + other_fd.clone() * Env::constant(0xFFFFFFFF);
             let v1 = other_fd * Env::constant(0x9); // EBADF
 
-            env.write_register(&Env::constant(2), v0);
-            env.write_register(&Env::constant(7), v1);
+            env.finish_syscall(v0, v1);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
@@ -1344,19 +1883,30 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
                     * Env::constant(0x9) /* EBADF */
                 + (Env::constant(1) - is_getfl.clone()) * Env::constant(0x16) /* EINVAL */;
 
-            env.write_register(&Env::constant(2), v0);
-            env.write_register(&Env::constant(7), v1);
+            env.finish_syscall(v0, v1);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
         RTypeInstruction::SyscallOther => {
+            // The programs run by Cannon are single-threaded. As in the
+            // reference implementation, `clone` returns 1 as the id of a thread
+            // that is never created, and the other thread-related syscalls,
+            // e.g. `futex`, like any other unsupported syscall, are no-ops
+            // returning 0 without error. `brk` moves the break to the address
+            // in $a0, see [InterpreterEnv::syscall_brk]. The request is zeroed
+            // for the other syscalls, which leaves the break unchanged.
             let syscall_num = env.read_register(&Env::constant(2));
             let is_sysbrk = env.equal(&syscall_num, &Env::constant(SYSCALL_BRK));
             let is_sysclone = env.equal(&syscall_num, &Env::constant(SYSCALL_CLONE));
-            let v0 = { is_sysbrk * Env::constant(0x40000000) + is_sysclone };
+            let new_brk = {
+                let a0 = env.read_register(&Env::constant(4));
+                let pos = env.alloc_scratch();
+                env.copy(&(is_sysbrk.clone() * a0), pos)
+            };
+            let brk = env.syscall_brk(&new_brk);
+            let v0 = { is_sysbrk * brk + is_sysclone };
             let v1 = Env::constant(0);
-            env.write_register(&Env::constant(2), v0);
-            env.write_register(&Env::constant(7), v1);
+            env.finish_syscall(v0, v1);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
@@ -1405,58 +1955,22 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
         RTypeInstruction::Multiply => {
-            let rs = env.read_register(&rs);
-            let rt = env.read_register(&rt);
-            let (hi, lo) = {
-                // Fixme: constrain
-                let hi_pos = env.alloc_scratch();
-                let lo_pos = env.alloc_scratch();
-                unsafe { env.mul_hi_lo_signed(&rs, &rt, hi_pos, lo_pos) }
-            };
-            env.write_register(&Env::constant(REGISTER_HI as u32), hi);
-            env.write_register(&Env::constant(REGISTER_LO as u32), lo);
+            env.multiply_to_hi_lo(&rs, &rt, true);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
         RTypeInstruction::MultiplyUnsigned => {
-            let rs = env.read_register(&rs);
-            let rt = env.read_register(&rt);
-            let (hi, lo) = {
-                // Fixme: constrain
-                let hi_pos = env.alloc_scratch();
-                let lo_pos = env.alloc_scratch();
-                unsafe { env.mul_hi_lo(&rs, &rt, hi_pos, lo_pos) }
-            };
-            env.write_register(&Env::constant(REGISTER_HI as u32), hi);
-            env.write_register(&Env::constant(REGISTER_LO as u32), lo);
+            env.multiply_to_hi_lo(&rs, &rt, false);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
         RTypeInstruction::Div => {
-            let rs = env.read_register(&rs);
-            let rt = env.read_register(&rt);
-            let (quotient, remainder) = {
-                // Fixme: constrain
-                let quotient_pos = env.alloc_scratch();
-                let remainder_pos = env.alloc_scratch();
-                unsafe { env.divmod_signed(&rs, &rt, quotient_pos, remainder_pos) }
-            };
-            env.write_register(&Env::constant(REGISTER_LO as u32), quotient);
-            env.write_register(&Env::constant(REGISTER_HI as u32), remainder);
+            env.divide_to_hi_lo(&rs, &rt, true);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
         RTypeInstruction::DivUnsigned => {
-            let rs = env.read_register(&rs);
-            let rt = env.read_register(&rt);
-            let (quotient, remainder) = {
-                // Fixme: constrain
-                let quotient_pos = env.alloc_scratch();
-                let remainder_pos = env.alloc_scratch();
-                unsafe { env.divmod(&rs, &rt, quotient_pos, remainder_pos) }
-            };
-            env.write_register(&Env::constant(REGISTER_LO as u32), quotient);
-            env.write_register(&Env::constant(REGISTER_HI as u32), remainder);
+            env.divide_to_hi_lo(&rs, &rt, false);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
@@ -1569,26 +2083,12 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
         RTypeInstruction::SetLessThan => {
-            let rs = env.read_register(&rs);
-            let rt = env.read_register(&rt);
-            let res = {
-                // FIXME: Constrain
-                let pos = env.alloc_scratch();
-                unsafe { env.test_less_than_signed(&rs, &rt, pos) }
-            };
-            env.write_register(&rd, res);
+            env.set_less_than(&rd, &rs, &rt, true);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
         RTypeInstruction::SetLessThanUnsigned => {
-            let rs = env.read_register(&rs);
-            let rt = env.read_register(&rt);
-            let res = {
-                // FIXME: Constrain
-                let pos = env.alloc_scratch();
-                unsafe { env.test_less_than(&rs, &rt, pos) }
-            };
-            env.write_register(&rd, res);
+            env.set_less_than(&rd, &rs, &rt, false);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
@@ -1607,7 +2107,6 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
         RTypeInstruction::CountLeadingOnes => {
             let rs = env.read_register(&rs);
             let leading_ones = {
-                // FIXME: Constrain
                 let pos = env.alloc_scratch();
                 unsafe { env.count_leading_ones(&rs, pos) }
             };
@@ -1618,7 +2117,6 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
         RTypeInstruction::CountLeadingZeros => {
             let rs = env.read_register(&rs);
             let leading_zeros = {
-                // FIXME: Constrain
                 let pos = env.alloc_scratch();
                 unsafe { env.count_leading_zeros(&rs, pos) }
             };
@@ -1626,6 +2124,34 @@ pub fn interpret_rtype<Env: InterpreterEnv>(env: &mut Env, instr: RTypeInstructi
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
+        RTypeInstruction::SignExtendByte => {
+            let rt = env.read_register(&rt);
+            let res = env.sign_extend_low_bits(&rt, 8);
+            env.write_register(&rd, res);
+            env.set_instruction_pointer(next_instruction_pointer.clone());
+            env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
+        }
+        RTypeInstruction::SignExtendHalfword => {
+            let rt = env.read_register(&rt);
+            let res = env.sign_extend_low_bits(&rt, 16);
+            env.write_register(&rd, res);
+            env.set_instruction_pointer(next_instruction_pointer.clone());
+            env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
+        }
+        RTypeInstruction::SwapBytesInHalfwords => {
+            let rt = env.read_register(&rt);
+            let res = env.swap_bytes_in_halfwords(&rt);
+            env.write_register(&rd, res);
+            env.set_instruction_pointer(next_instruction_pointer.clone());
+            env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
+        }
+        RTypeInstruction::RotateRight => {
+            let rt = env.read_register(&rt);
+            let res = env.rotate_right(&rt, &shamt);
+            env.write_register(&rd, res);
+            env.set_instruction_pointer(next_instruction_pointer.clone());
+            env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
+        }
     };
 }
 
@@ -1893,26 +2419,12 @@ pub fn interpret_itype<Env: InterpreterEnv>(env: &mut Env, instr: ITypeInstructi
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
         ITypeInstruction::SetLessThanImmediate => {
-            let rs = env.read_register(&rs);
-            let immediate = env.sign_extend(&immediate, 16);
-            let res = {
-                // FIXME: Constrain
-                let pos = env.alloc_scratch();
-                unsafe { env.test_less_than_signed(&rs, &immediate, pos) }
-            };
-            env.write_register(&rt, res);
+            env.set_less_than_immediate(&rt, &rs, &immediate, true);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
         ITypeInstruction::SetLessThanImmediateUnsigned => {
-            let rs = env.read_register(&rs);
-            let immediate = env.sign_extend(&immediate, 16);
-            let res = {
-                // FIXME: Constrain
-                let pos = env.alloc_scratch();
-                unsafe { env.test_less_than(&rs, &immediate, pos) }
-            };
-            env.write_register(&rt, res);
+            env.set_less_than_immediate(&rt, &rs, &immediate, false);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
         }
@@ -1951,7 +2463,7 @@ pub fn interpret_itype<Env: InterpreterEnv>(env: &mut Env, instr: ITypeInstructi
         }
         ITypeInstruction::LoadUpperImmediate => {
             // lui $reg, [most significant 16 bits of immediate]
-            let immediate_value = immediate * Env::constant(1 << 16);
+            let immediate_value = env.load_upper_immediate(&immediate);
             env.write_register(&rt, immediate_value);
             env.set_instruction_pointer(next_instruction_pointer.clone());
             env.set_next_instruction_pointer(next_instruction_pointer + Env::constant(4u32));
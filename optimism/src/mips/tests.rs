@@ -1,20 +1,1025 @@
 use crate::{
+    lookups::{Lookup, LookupTableIDs},
     mips::{
-        constraints::Env,
+        column::{
+            ColumnAlias, MAX_SCRATCH, MIPS_BYTES_READ_OFFSET, MIPS_CHUNK_BYTES_LENGTH,
+            MIPS_HASH_COUNTER_OFFSET, MIPS_HAS_N_BYTES_OFFSET, MIPS_HINT_COUNTER_OFFSET,
+            MIPS_PREIMAGE_BYTES_OFFSET, MIPS_PREIMAGE_LEFT_CURR_OFFSET, MIPS_PREIMAGE_LEFT_OFFSET,
+            MIPS_READING_PREIMAGE_OFFSET, MIPS_ROW_BYTES_OFFSET,
+        },
+        constraints::{referenced_columns, to_lookup_table_entries, Env, ScratchError},
         interpreter::{
+            debugging::InstructionParts,
+            interpret_decoded_instruction, interpret_instruction,
             ITypeInstruction::{self, *},
             Instruction::{self, *},
+            InstructionDecoder, InterpreterEnv,
             JTypeInstruction::{self, *},
             RTypeInstruction::{self, *},
+            FD_HINT_READ, FD_HINT_WRITE, FD_PREIMAGE_READ, FD_PREIMAGE_WRITE, FD_STDIN, FD_STDOUT,
+            SYSCALL_BRK, SYSCALL_EXIT_GROUP, SYSCALL_FCNTL, SYSCALL_MMAP, SYSCALL_READ,
+            SYSCALL_WRITE,
         },
+        registers::{Registers, REGISTER_PREIMAGE_KEY_START},
         trace::MIPSTrace,
+        witness::SCRATCH_SIZE,
     },
     trace::Tracer,
+    RAMLookupMode, E,
+};
+use ark_ff::{Field, Zero};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use kimchi::{
+    circuits::{
+        expr::{Challenges, ColumnEvaluations, Constants, ExprError, ExprInner, Variable},
+        gate::CurrOrNext,
+    },
+    curve::KimchiCurve,
+    proof::PointEvaluations,
 };
+use kimchi_msm::columns::{Column, ColumnIndexer as _};
 use strum::{EnumCount, IntoEnumIterator};
 
 type Fp = ark_bn254::Fr;
 
+#[test]
+fn test_set_halted_constrains_the_halt_flag() {
+    let mut env = Env::<Fp>::default();
+    let flag = Env::<Fp>::constant(1);
+    env.set_halted(flag.clone());
+
+    let halted = env.variable(ColumnAlias::Halted);
+    assert_eq!(
+        env.constraints,
+        vec![
+            halted.clone() * halted.clone() - halted.clone(),
+            halted - flag
+        ]
+    );
+    // the flag is stored in a dedicated column, not in the scratch state
+    assert_eq!(env.scratch_state_idx, 0);
+}
+
+#[test]
+fn test_report_exit_constrains_the_exit_code() {
+    let mut env = Env::<Fp>::default();
+    let exit_code = Env::<Fp>::constant(3);
+    env.report_exit(&exit_code);
+
+    let halted = env.variable(ColumnAlias::Halted);
+    let exit_code_column = env.variable(ColumnAlias::ExitCode);
+    assert_eq!(
+        env.constraints,
+        vec![halted * exit_code - exit_code_column.clone()]
+    );
+    assert_eq!(env.lookups.len(), 1);
+    let lookup = &env.lookups[0];
+    assert_eq!(lookup.table_id, LookupTableIDs::ByteLookup);
+    assert!(matches!(lookup.mode, RAMLookupMode::Read));
+    assert_eq!(lookup.value, vec![exit_code_column]);
+    // the exit code is stored in a dedicated column, not in the scratch state
+    assert_eq!(env.scratch_state_idx, 0);
+}
+
+#[test]
+fn test_constrain_instruction_counter() {
+    let mut env = Env::<Fp>::default();
+    let halted = env.variable(ColumnAlias::Halted);
+    env.constrain_instruction_counter(&halted);
+
+    let counter = ColumnAlias::InstructionCounter.to_column();
+    // the transition relates the counter of the current row to the one of
+    // the next row
+    let [curr, next] = [CurrOrNext::Curr, CurrOrNext::Next]
+        .map(|row| E::<Fp>::Atom(ExprInner::Cell(Variable { col: counter, row })));
+    let transition = next - curr - (Env::<Fp>::constant(1) - halted);
+    assert_eq!(env.constraints, vec![transition.clone()]);
+
+    // the transition is checked between the steps of the execution, not by
+    // the circuit of each instruction
+    assert_eq!(Env::<Fp>::step_transitions()[0], transition);
+    let mut env = Env::<Fp>::default();
+    env.consume_step();
+    assert!(!env.constraints.contains(&transition));
+}
+
+#[test]
+fn test_step_budget_constraints() {
+    let mut env = Env::<Fp>::default();
+    let halted = env.variable(ColumnAlias::Halted);
+    env.constrain_step_budget(&halted);
+    let budget = env.variable(ColumnAlias::StepBudget);
+    let running = Env::<Fp>::constant(1) - halted.clone();
+    // the high limb of the range check, then the zero test
+    let [exhausted, inverse] = [1, 2].map(|i| env.variable(ColumnAlias::ScratchState(i)));
+    assert_eq!(
+        env.constraints,
+        vec![
+            budget.clone() * inverse + exhausted.clone() - Env::<Fp>::constant(1),
+            budget.clone() * exhausted.clone(),
+            exhausted * running.clone(),
+        ]
+    );
+    // the budget stays nonnegative
+    let high = env.variable(ColumnAlias::ScratchState(0));
+    assert!(env.lookups.iter().any(
+        |lookup| lookup.table_id == LookupTableIDs::RangeCheck16Lookup
+            && lookup.value == vec![high.clone()]
+    ));
+    assert!(env
+        .lookups
+        .iter()
+        .any(|lookup| lookup.value
+            == vec![budget.clone() - high.clone() * Env::<Fp>::constant(1 << 16)]));
+
+    // the decrement relates the current row to the next one, and is only part
+    // of the transitions between the steps
+    let column = ColumnAlias::StepBudget.to_column();
+    let [curr, next] = [CurrOrNext::Curr, CurrOrNext::Next]
+        .map(|row| E::<Fp>::Atom(ExprInner::Cell(Variable { col: column, row })));
+    let decrement = next - curr + running;
+    assert!(!env.constraints.contains(&decrement));
+    assert!(Env::<Fp>::step_transitions().contains(&decrement));
+
+    let mut env = Env::<Fp>::default();
+    env.set_step_budget(Env::<Fp>::constant(1000));
+    assert_eq!(env.constraints, vec![budget - Env::<Fp>::constant(1000)]);
+}
+
+#[test]
+fn test_reduce_degree() {
+    let mut env = Env::<Fp>::default();
+    let [a, b, c, d] = [(); 4].map(|_| {
+        let pos = env.alloc_scratch();
+        env.variable(pos)
+    });
+    let product = a.clone() * b.clone() * c.clone() * d.clone();
+    assert_eq!(product.degree(1, 0), 4);
+
+    let reduced = env.reduce_degree(&product, 2);
+    assert!(reduced.degree(1, 0) <= 2);
+    assert!(env
+        .constraints
+        .iter()
+        .all(|constraint| constraint.degree(1, 0) <= 2));
+    let [ab, abc] = [4, 5].map(|i| env.variable(ColumnAlias::ScratchState(i)));
+    assert_eq!(
+        env.constraints,
+        vec![a * b - ab.clone(), ab * c - abc.clone()]
+    );
+    assert_eq!(reduced, abc * d);
+
+    // expressions within the bound are left untouched
+    env.constraints.clear();
+    assert_eq!(env.reduce_degree(&reduced, 2), reduced);
+    assert!(env.constraints.is_empty());
+}
+
+#[test]
+fn test_referenced_columns() {
+    let mut env = Env::<Fp>::default();
+    interpret_decoded_instruction(&mut env, RType(SyscallReadPreimage));
+    let columns = referenced_columns(&env);
+
+    // The hash counter is only used by the lookups
+    let hash_counter = ColumnAlias::ScratchState(MIPS_HASH_COUNTER_OFFSET);
+    assert!(columns.contains(&hash_counter));
+    assert!(env
+        .lookups
+        .iter()
+        .any(|lookup| lookup.value.contains(&env.variable(hash_counter))));
+    // The bytes read in the row are stored out of the allocatable columns
+    let row_bytes = ColumnAlias::ScratchState(MIPS_ROW_BYTES_OFFSET);
+    assert!(columns.contains(&row_bytes));
+    assert!((MAX_SCRATCH..SCRATCH_SIZE).contains(&MIPS_ROW_BYTES_OFFSET));
+}
+
+#[test]
+fn test_register_accesses_register_lookups() {
+    let mut env = Env::<Fp>::default();
+    let idx = Env::<Fp>::constant(5);
+    let value = env.read_register(&idx);
+    let flag = {
+        let pos = env.alloc_scratch();
+        env.variable(pos)
+    };
+    let new_value = value.clone() + Env::<Fp>::constant(1);
+    env.write_register_if(&idx, new_value.clone(), &flag);
+
+    let [read_value, read_last_access, read_high, _, old_value, idx_is_zero, idx_inverse, written, write_last_access, write_high] =
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9].map(|i| env.variable(ColumnAlias::ScratchState(i)));
+    assert_eq!(value, read_value);
+    // each access is timed by the column of its last access, so that the
+    // write comes after the read
+    let [read_access, write_access] =
+        [1, 8].map(|i| env.access_time(&ColumnAlias::ScratchState(i)));
+    let start = env.instruction_start_time();
+    assert_eq!(read_access, start.clone() + Env::<Fp>::constant(2));
+    assert_eq!(write_access, start + Env::<Fp>::constant(9));
+    let one = Env::<Fp>::constant(1);
+    let lookups: Vec<_> = env
+        .lookups
+        .iter()
+        .filter(|lookup| lookup.table_id == LookupTableIDs::RegisterLookup)
+        .map(|lookup| (lookup.mode, lookup.magnitude.clone(), lookup.value.clone()))
+        .collect();
+    assert_eq!(lookups.len(), 4);
+    // the time elapsed since the last access is range-checked for both
+    // accesses, as for the memory
+    let range_checks: Vec<_> = env
+        .lookups
+        .iter()
+        .filter(|lookup| lookup.table_id == LookupTableIDs::RangeCheck16Lookup)
+        .map(|lookup| lookup.value.clone())
+        .collect();
+    let limbs = |access: E<Fp>, last_access: E<Fp>, high: E<Fp>| {
+        let elapsed_time = access - last_access - one.clone();
+        let low = elapsed_time - high.clone() * Env::<Fp>::constant(1 << 16);
+        [vec![high], vec![low]]
+    };
+    assert_eq!(
+        range_checks,
+        [
+            limbs(read_access.clone(), read_last_access.clone(), read_high),
+            limbs(write_access.clone(), write_last_access.clone(), write_high)
+        ]
+        .concat()
+    );
+    // the read consumes the value at its last access, and puts it back at the
+    // time of the read
+    assert!(matches!(lookups[0].0, RAMLookupMode::Write));
+    assert_eq!(lookups[0].1, one);
+    assert_eq!(
+        lookups[0].2,
+        vec![idx.clone(), read_last_access, read_value.clone()]
+    );
+    assert!(matches!(lookups[1].0, RAMLookupMode::Read));
+    assert_eq!(lookups[1].1, one);
+    assert_eq!(lookups[1].2, vec![idx.clone(), read_access, read_value]);
+    // the write does the same with the new value, gated by the flag
+    assert!(matches!(lookups[2].0, RAMLookupMode::Write));
+    assert_eq!(lookups[2].1, flag);
+    assert_eq!(
+        lookups[2].2,
+        vec![idx.clone(), write_last_access, old_value]
+    );
+    assert!(matches!(lookups[3].0, RAMLookupMode::Read));
+    assert_eq!(lookups[3].1, flag);
+    assert_eq!(
+        lookups[3].2,
+        vec![idx.clone(), write_access, written.clone()]
+    );
+
+    // the register 0 is only ever written 0, so that it always reads as zero
+    assert_eq!(
+        env.constraints,
+        vec![
+            idx.clone() * idx_inverse + idx_is_zero.clone() - one.clone(),
+            idx * idx_is_zero.clone(),
+            (one - idx_is_zero) * new_value - written,
+        ]
+    );
+}
+
+#[test]
+fn test_memory_accesses_register_lookups() {
+    let mut env = Env::<Fp>::default();
+    let addr = Env::<Fp>::constant(0x1000);
+    let value = Env::<Fp>::constant(42);
+    env.write_memory(&addr, value.clone());
+
+    let lookups: Vec<_> = env
+        .lookups
+        .iter()
+        .map(|lookup| (lookup.table_id, lookup.mode, lookup.value.clone()))
+        .collect();
+    assert_eq!(lookups.len(), 4);
+    let [old_value, last_accessed, high] = [0, 1, 2].map(ColumnAlias::ScratchState);
+    let access = env.access_time(&last_accessed);
+    // the old value is consumed at the time of the last access, and the new
+    // one is written at the time of this access
+    let (table_id, mode, entry) = &lookups[0];
+    assert_eq!(*table_id, LookupTableIDs::MemoryLookup);
+    assert!(matches!(mode, RAMLookupMode::Write));
+    assert_eq!(
+        entry,
+        &vec![
+            addr.clone(),
+            env.variable(last_accessed),
+            env.variable(old_value)
+        ]
+    );
+    let (table_id, mode, entry) = &lookups[1];
+    assert_eq!(*table_id, LookupTableIDs::MemoryLookup);
+    assert!(matches!(mode, RAMLookupMode::Read));
+    assert_eq!(entry, &vec![addr, access.clone(), value]);
+    // the time elapsed since the last access is range-checked, the high limb
+    // when it is extracted
+    let elapsed_time = access - env.variable(last_accessed) - Env::<Fp>::constant(1);
+    let high = env.variable(high);
+    let low = elapsed_time - high.clone() * Env::<Fp>::constant(1 << 16);
+    for ((table_id, mode, entry), limb) in lookups[2..].iter().zip([high, low]) {
+        assert_eq!(*table_id, LookupTableIDs::RangeCheck16Lookup);
+        assert!(matches!(mode, RAMLookupMode::Read));
+        assert_eq!(entry, &vec![limb]);
+    }
+}
+
+#[test]
+fn test_scratch_allocation_is_bounded() {
+    let mut env = Env::<Fp>::default();
+    for i in 0..MAX_SCRATCH {
+        assert_eq!(env.try_alloc_scratch(), Ok(ColumnAlias::ScratchState(i)));
+    }
+    assert_eq!(env.scratch_used(), MAX_SCRATCH);
+    assert_eq!(
+        env.try_alloc_scratch(),
+        Err(ScratchError::Overflow { max: MAX_SCRATCH })
+    );
+    assert_eq!(env.scratch_used(), MAX_SCRATCH);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "scratch columns")]
+fn test_alloc_scratch_past_the_limit_panics() {
+    let mut env = Env::<Fp>::default();
+    for _ in 0..=MAX_SCRATCH {
+        env.alloc_scratch();
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "does not match the arity")]
+fn test_add_lookup_with_wrong_arity_panics() {
+    let mut env = Env::<Fp>::default();
+    env.add_lookup(Lookup::write_one(
+        LookupTableIDs::SyscallLookup,
+        vec![Env::<Fp>::constant(1), Env::<Fp>::constant(2)],
+    ));
+}
+
+#[test]
+fn test_decode_instruction_table() {
+    let word = |op_code, rs, rt, shamt, funct| {
+        InstructionParts {
+            op_code,
+            rs,
+            rt,
+            rd: 0,
+            shamt,
+            funct,
+        }
+        .encode()
+    };
+    let registers = Registers::<u32>::default();
+    for (word, instr) in [
+        // SPECIAL, told apart by the funct field
+        (word(0x00, 0, 0, 0, 0x00), RType(ShiftLeftLogical)),
+        (word(0x00, 0, 0, 0, 0x02), RType(ShiftRightLogical)),
+        // rotr shares the funct of srl, with the bit 21 set
+        (word(0x00, 1, 0, 0, 0x02), RType(RotateRight)),
+        (word(0x00, 0, 0, 0, 0x08), RType(JumpRegister)),
+        (word(0x00, 0, 0, 0, 0x1a), RType(Div)),
+        (word(0x00, 0, 0, 0, 0x21), RType(AddUnsigned)),
+        (word(0x00, 0, 0, 0, 0x2b), RType(SetLessThanUnsigned)),
+        // SPECIAL2, and SPECIAL3 whose bshfl operation is in the sa field
+        (word(0x1c, 0, 0, 0, 0x02), RType(MultiplyToRegister)),
+        (word(0x1c, 0, 0, 0, 0x20), RType(CountLeadingZeros)),
+        (word(0x1f, 0, 0, 0x02, 0x20), RType(SwapBytesInHalfwords)),
+        (word(0x1f, 0, 0, 0x10, 0x20), RType(SignExtendByte)),
+        // REGIMM, told apart by the rt field
+        (word(0x01, 0, 0x00, 0, 0), IType(BranchLtZero)),
+        (word(0x01, 0, 0x01, 0, 0), IType(BranchGeqZero)),
+        (word(0x02, 0, 0, 0, 0), JType(Jump)),
+        (word(0x03, 0, 0, 0, 0), JType(JumpAndLink)),
+        (word(0x04, 0, 0, 0, 0), IType(BranchEq)),
+        (word(0x09, 0, 0, 0, 0), IType(AddImmediateUnsigned)),
+        (word(0x0f, 0, 0, 0, 0), IType(LoadUpperImmediate)),
+        (word(0x22, 0, 0, 0, 0), IType(LoadWordLeft)),
+        (word(0x23, 0, 0, 0, 0), IType(Load32)),
+        (word(0x2b, 0, 0, 0, 0), IType(Store32)),
+        // ll and sc, for a single processor
+        (word(0x30, 0, 0, 0, 0), IType(Load32)),
+        (word(0x38, 0, 0, 0, 0), IType(Store32Conditional)),
+    ] {
+        assert_eq!(registers.decode_instruction(word), instr, "{word:#010x}");
+    }
+
+    // The system calls are told apart by the registers 2 and 4
+    let syscall = word(0x00, 0, 0, 0, 0x0c);
+    let mut registers = Registers::<u32>::default();
+    for (number, fd, instr) in [
+        (SYSCALL_MMAP, 0, SyscallMmap),
+        (SYSCALL_EXIT_GROUP, 0, SyscallExitGroup),
+        (SYSCALL_READ, FD_HINT_READ, SyscallReadHint),
+        (SYSCALL_READ, FD_PREIMAGE_READ, SyscallReadPreimage),
+        (SYSCALL_READ, FD_STDIN, SyscallReadOther),
+        (SYSCALL_WRITE, FD_HINT_WRITE, SyscallWriteHint),
+        (SYSCALL_WRITE, FD_PREIMAGE_WRITE, SyscallWritePreimage),
+        (SYSCALL_WRITE, FD_STDOUT, SyscallWriteOther),
+        (SYSCALL_FCNTL, 0, SyscallFcntl),
+        (SYSCALL_BRK, 0, SyscallOther),
+    ] {
+        registers.general_purpose[2] = number;
+        registers.general_purpose[4] = fd;
+        assert_eq!(registers.decode_instruction(syscall), RType(instr));
+    }
+}
+
+#[test]
+fn test_interpret_addu_word() {
+    // addu $3, $1, $2
+    let word = InstructionParts {
+        op_code: 0x00,
+        rs: 1,
+        rt: 2,
+        rd: 3,
+        shamt: 0,
+        funct: 0x21,
+    }
+    .encode();
+    let mut env = Env::<Fp>::default();
+    interpret_instruction(&mut env, &Registers::<u32>::default(), word);
+
+    let mut expected = Env::<Fp>::default();
+    interpret_decoded_instruction(&mut expected, RType(AddUnsigned));
+    assert_eq!(env.constraints, expected.constraints);
+    // The sum and its carry, as counted in test_mips_number_constraints
+    assert_eq!(env.constraints.len(), 8);
+    // The sum of the registers rs and rt, and its carry
+    let [rs, rt, res, overflow] =
+        [20, 23, 26, 27].map(|idx| env.variable(ColumnAlias::ScratchState(idx)));
+    let two_to_32 = E::<Fp>::from(1u64 << 32);
+    assert_eq!(
+        env.constraints[..2],
+        [
+            rs + rt - res - overflow.clone() * two_to_32,
+            overflow.clone() * overflow.clone() - overflow,
+        ]
+    );
+}
+
+#[test]
+fn test_add_witness_constrains_the_carry() {
+    let mut env = Env::<Fp>::default();
+    let x = Env::<Fp>::constant(0xffff_ffff);
+    let y = Env::<Fp>::constant(2);
+    let (res_pos, overflow_pos) = (env.alloc_scratch(), env.alloc_scratch());
+    let (res, overflow) = unsafe { env.add_witness(&x, &y, res_pos, overflow_pos) };
+    let two_to_32 = E::<Fp>::from(1u64 << 32);
+    assert_eq!(
+        env.constraints,
+        vec![
+            x.clone() + y.clone() - res.clone() - overflow.clone() * two_to_32.clone(),
+            overflow.clone() * overflow.clone() - overflow,
+        ]
+    );
+    // the result is range-checked, its high limb being allocated after it
+    let assert_range_checked = |env: &Env<Fp>, res: E<Fp>, high: E<Fp>| {
+        let low = res - high.clone() * Env::<Fp>::constant(1 << 16);
+        for limb in [high, low] {
+            assert!(env.lookups.iter().any(|lookup| lookup.table_id
+                == LookupTableIDs::RangeCheck16Lookup
+                && lookup.value == vec![limb.clone()]));
+        }
+    };
+    assert_range_checked(&env, res, env.variable(ColumnAlias::ScratchState(2)));
+
+    env.constraints.clear();
+    let (res_pos, underflow_pos) = (env.alloc_scratch(), env.alloc_scratch());
+    let (res, underflow) = unsafe { env.sub_witness(&y, &x, res_pos, underflow_pos) };
+    assert_eq!(
+        env.constraints,
+        vec![
+            y - x - res.clone() + underflow.clone() * two_to_32,
+            underflow.clone() * underflow.clone() - underflow,
+        ]
+    );
+    assert_range_checked(&env, res, env.variable(ColumnAlias::ScratchState(5)));
+}
+
+#[test]
+fn test_finish_collects_the_relation() {
+    let mut env = Env::<Fp>::default();
+    let x = Env::<Fp>::constant(42);
+    let copy_pos = env.alloc_scratch();
+    // copying the same value twice gives the same constraint
+    env.copy(&x, copy_pos);
+    env.copy(&x, copy_pos);
+    let chunk_pos = env.alloc_scratch();
+    let addr = Env::<Fp>::constant(0x1000);
+    let len = Env::<Fp>::constant(4);
+    env.request_preimage_write(&addr, &len, chunk_pos);
+    assert_eq!(env.constraints.len(), 21);
+
+    let relation = env.finish();
+    // one copy constraint, the 15 constraints of the preimage chunk, the
+    // count of the bytes read, the decrement of the bytes left and the 2
+    // constraints of its zero test
+    assert_eq!(relation.constraints.len(), 20);
+    // the 4 range checks bounding the bytes read, the 4 bytes written and the
+    // hash read of the preimage
+    assert_eq!(relation.lookups.len(), 9);
+    // the high limb of the length bound and the zero test of the bytes left
+    assert_eq!(relation.scratch_used, 5);
+}
+
+#[test]
+fn test_labeled_constraints() {
+    let mut env = Env::<Fp>::default();
+    let [x, y] = [env.alloc_scratch(), env.alloc_scratch()].map(|pos| env.variable(pos));
+    env.add_labeled_constraint("x is boolean", x.clone() * x.clone() - x.clone());
+    env.add_constraint(x.clone() - y.clone());
+    env.add_labeled_constraint("y is one", y.clone() - Env::<Fp>::constant(1));
+    // the labeled constraints are enforced like the other ones
+    assert_eq!(env.constraints.len(), 3);
+
+    assert_eq!(
+        env.constraints_by_label("x is boolean"),
+        vec![&(x.clone() * x.clone() - x.clone())]
+    );
+    assert_eq!(
+        env.constraints_by_label("y is one"),
+        vec![&(y.clone() - Env::<Fp>::constant(1))]
+    );
+    assert!(env.constraints_by_label("unknown").is_empty());
+
+    // the labels are kept in the relation, to be reported with the failing
+    // constraints
+    let relation = env.finish();
+    assert_eq!(
+        relation.labels_of(&relation.constraints[0]),
+        vec!["x is boolean"]
+    );
+    assert!(relation.labels_of(&relation.constraints[1]).is_empty());
+    assert_eq!(
+        relation.labels_of(&relation.constraints[2]),
+        vec!["y is one"]
+    );
+}
+
+#[test]
+fn test_max_constraint_degree() {
+    let mut env = Env::<Fp>::default();
+    assert_eq!(env.max_constraint_degree(true), 0);
+
+    let [x, y, z] = [(); 3].map(|_| {
+        let pos = env.alloc_scratch();
+        env.variable(pos)
+    });
+    env.add_constraint(x.clone() - y.clone());
+    env.add_constraint(x.clone() * y.clone() * z.clone() - Env::<Fp>::constant(1));
+    env.add_constraint(x.clone() * (y.clone() + z.clone()));
+    assert_eq!(env.max_constraint_degree(false), 3);
+
+    // a lookup of degree 4, only counted when the lookups are included
+    env.add_lookup(Lookup::read_if(
+        x.clone(),
+        LookupTableIDs::ByteLookup,
+        vec![x.clone() * y.clone() * z.clone() * z],
+    ));
+    assert_eq!(env.max_constraint_degree(false), 3);
+    assert_eq!(env.max_constraint_degree(true), 4);
+}
+
+#[test]
+fn test_merge_envs_does_not_alias_scratch_columns() {
+    let gadget = |value: u32| {
+        let mut env = Env::<Fp>::default();
+        let pos = env.alloc_scratch();
+        let x = env.variable(pos);
+        env.add_labeled_constraint("x", x.clone() - Env::<Fp>::constant(value));
+        env.add_lookup(Lookup::read_one(LookupTableIDs::ByteLookup, vec![x]));
+        // a column at a fixed offset, shared by the gadgets
+        let hash_counter = env.variable(ColumnAlias::ScratchState(MIPS_HASH_COUNTER_OFFSET));
+        env.add_constraint(hash_counter - Env::<Fp>::constant(value));
+        env
+    };
+    let (left, right) = (gadget(1), gadget(2));
+    assert_eq!(left.scratch_state_idx, 1);
+    assert_eq!(right.scratch_state_idx, 1);
+
+    let merged = left.merge(right);
+    assert_eq!(merged.scratch_state_idx, 2);
+    let [x, y] = [0, 1].map(|i| merged.variable(ColumnAlias::ScratchState(i)));
+    let hash_counter = merged.variable(ColumnAlias::ScratchState(MIPS_HASH_COUNTER_OFFSET));
+    let constant = Env::<Fp>::constant;
+    assert_eq!(
+        merged.constraints,
+        vec![
+            x.clone() - constant(1),
+            hash_counter.clone() - constant(1),
+            y.clone() - constant(2),
+            hash_counter - constant(2),
+        ]
+    );
+    assert_eq!(
+        merged.constraints_by_label("x"),
+        vec![&(x.clone() - constant(1)), &(y.clone() - constant(2))]
+    );
+    let values: Vec<_> = merged
+        .lookups
+        .iter()
+        .map(|lookup| lookup.value.clone())
+        .collect();
+    assert_eq!(values, vec![vec![x], vec![y]]);
+}
+
+#[test]
+fn test_request_preimage_write_bounds_the_bytes_read() {
+    let mut env = Env::<Fp>::default();
+    let chunk_pos = env.alloc_scratch();
+    let addr = Env::<Fp>::constant(0x1000);
+    let len = Env::<Fp>::constant(4);
+    env.request_preimage_write(&addr, &len, chunk_pos);
+
+    let has_n_bytes: Vec<_> = (0..MIPS_CHUNK_BYTES_LENGTH)
+        .map(|i| env.variable(ColumnAlias::ScratchState(MIPS_HAS_N_BYTES_OFFSET + i)))
+        .collect();
+    let bytes_read = has_n_bytes
+        .iter()
+        .fold(Env::<Fp>::constant(0), |acc, flag| acc + flag.clone());
+    // The flags are monotone and count the bytes read in this row
+    let reading_preimage = env.variable(ColumnAlias::ScratchState(MIPS_READING_PREIMAGE_OFFSET));
+    let row_bytes = env.variable(ColumnAlias::ScratchState(MIPS_ROW_BYTES_OFFSET));
+    for k in 0..MIPS_CHUNK_BYTES_LENGTH - 1 {
+        assert!(env.constraints.contains(
+            &(reading_preimage.clone()
+                * has_n_bytes[k + 1].clone()
+                * (Env::<Fp>::constant(1) - has_n_bytes[k].clone()))
+        ));
+    }
+    assert!(env
+        .constraints
+        .contains(&(reading_preimage.clone() * (row_bytes - bytes_read.clone()))));
+    let preimage_left_curr =
+        env.variable(ColumnAlias::ScratchState(MIPS_PREIMAGE_LEFT_CURR_OFFSET));
+    let preimage_left_next = env.variable(ColumnAlias::ScratchState(MIPS_PREIMAGE_LEFT_OFFSET));
+    assert!(env.constraints.contains(
+        &(reading_preimage.clone()
+            * (preimage_left_next - preimage_left_curr + bytes_read.clone()))
+    ));
+
+    // The bounds are range checked before the preimage bytes are written
+    let at_most_a_word = Env::<Fp>::constant(4) - bytes_read.clone();
+    let high = env.variable(ColumnAlias::ScratchState(1));
+    let at_most_len = len - bytes_read;
+    let expected = [
+        (LookupTableIDs::ByteLookup, at_most_a_word.clone()),
+        (
+            LookupTableIDs::ByteLookup,
+            at_most_a_word * Env::<Fp>::constant(1 << 5),
+        ),
+        (LookupTableIDs::RangeCheck16Lookup, high.clone()),
+        (
+            LookupTableIDs::RangeCheck16Lookup,
+            at_most_len - high * Env::<Fp>::constant(1 << 16),
+        ),
+    ];
+    for (lookup, (table_id, value)) in env.lookups.iter().zip(expected) {
+        assert_eq!(lookup.table_id, table_id);
+        assert!(matches!(lookup.mode, RAMLookupMode::Read));
+        assert_eq!(lookup.value, vec![value]);
+    }
+    assert!(env.lookups[4..]
+        .iter()
+        .all(|lookup| lookup.table_id == LookupTableIDs::SyscallLookup));
+}
+
+#[test]
+fn test_preimage_write_lookup_table_entries() {
+    let mut env = Env::<Fp>::default();
+    let chunk_pos = env.alloc_scratch();
+    let addr = Env::<Fp>::constant(0x1000);
+    let len = Env::<Fp>::constant(4);
+    env.request_preimage_write(&addr, &len, chunk_pos);
+
+    // The preimage [1, 2, 3, 4, 5, 6], with the key 0x1234, is read by the
+    // third hash in two rows, of 4 and 2 bytes
+    let hash_counter = 2;
+    let key = 0x1234;
+    let rows: Vec<Vec<Fp>> = [(0, [1, 2, 3, 4], 4, 6), (4, [5, 6, 0, 0], 2, 2)]
+        .into_iter()
+        .map(|(bytes_read, bytes, row_bytes, left)| {
+            let mut row = vec![Fp::zero(); SCRATCH_SIZE];
+            let mut write = |column: usize, value: u64| row[column] = Fp::from(value);
+            write(MIPS_READING_PREIMAGE_OFFSET, 1);
+            write(MIPS_HASH_COUNTER_OFFSET, hash_counter);
+            write(MIPS_BYTES_READ_OFFSET, bytes_read);
+            write(MIPS_ROW_BYTES_OFFSET, row_bytes);
+            write(REGISTER_PREIMAGE_KEY_START + 7, key);
+            write(MIPS_PREIMAGE_LEFT_CURR_OFFSET, left);
+            write(MIPS_PREIMAGE_LEFT_OFFSET, left - row_bytes);
+            for (i, byte) in bytes.into_iter().enumerate() {
+                write(MIPS_PREIMAGE_BYTES_OFFSET + i, byte);
+                write(MIPS_HAS_N_BYTES_OFFSET + i, (i < row_bytes as usize) as u64);
+            }
+            // the zero test of the bytes left, after the high limb of the
+            // length bound
+            write(2, (left == row_bytes) as u64);
+            row
+        })
+        .collect();
+
+    let entries = to_lookup_table_entries(&env.lookups, rows.len(), |row, variable| {
+        assert_eq!(variable.row, CurrOrNext::Curr);
+        match variable.col {
+            Column::Relation(i) => rows[row][i],
+            col => panic!("unexpected column {col:?}"),
+        }
+    });
+    let syscall_entries: Vec<_> = entries[&LookupTableIDs::SyscallLookup]
+        .iter()
+        .map(|entry| (entry.mode, entry.magnitude, entry.value.clone()))
+        .collect();
+    let expected: Vec<_> = (0..6)
+        .map(|i| (RAMLookupMode::Write, vec![hash_counter, i, i + 1]))
+        // the hash is read once the preimage has been read entirely
+        .chain([(RAMLookupMode::Read, vec![hash_counter, key, 0])])
+        .map(|(mode, value)| {
+            (
+                mode,
+                Fp::from(1u64),
+                value.into_iter().map(Fp::from).collect(),
+            )
+        })
+        .collect();
+    assert_eq!(syscall_entries, expected);
+    // the range checks bounding the bytes read are entered on every row
+    assert_eq!(entries[&LookupTableIDs::ByteLookup].len(), 2 * 2);
+}
+
+#[test]
+fn test_request_hint_write_registers_lookup() {
+    let mut env = Env::<Fp>::default();
+    let addr = Env::<Fp>::constant(0x1000);
+    let len = Env::<Fp>::constant(8);
+    env.request_hint_write(&addr, &len);
+
+    assert_eq!(env.lookups.len(), 1);
+    let lookup = &env.lookups[0];
+    assert_eq!(lookup.table_id, LookupTableIDs::HintLookup);
+    assert!(matches!(lookup.mode, RAMLookupMode::Write));
+    let hint_counter = env.variable(ColumnAlias::ScratchState(MIPS_HINT_COUNTER_OFFSET));
+    assert_eq!(lookup.value, vec![hint_counter, addr, len]);
+}
+
+#[test]
+fn test_bitmask_registers_range_lookups() {
+    let mut env = Env::<Fp>::default();
+    let x = Env::<Fp>::constant(0x12345678);
+
+    // A byte is looked up in the byte table
+    let pos = env.alloc_scratch();
+    let byte = unsafe { env.bitmask(&x, 32, 24, pos) };
+    assert_eq!(env.lookups.len(), 1);
+    assert_eq!(env.lookups[0].table_id, LookupTableIDs::ByteLookup);
+    assert!(matches!(env.lookups[0].mode, RAMLookupMode::Read));
+    assert_eq!(env.lookups[0].value, vec![byte]);
+
+    // A value larger than 16 bits is split into 16-bits limbs, the high one
+    // being stored in the next scratch column
+    env.lookups.clear();
+    let pos = env.alloc_scratch();
+    let target = unsafe { env.bitmask(&x, 26, 0, pos) };
+    assert_eq!(env.scratch_state_idx, 3);
+    let high = env.variable(ColumnAlias::ScratchState(2));
+    let low = target - high.clone() * Env::<Fp>::constant(1 << 16);
+    let lookups: Vec<_> = env
+        .lookups
+        .iter()
+        .map(|lookup| (lookup.table_id, lookup.value.clone()))
+        .collect();
+    assert_eq!(
+        lookups,
+        vec![
+            // the 10 bits of the high limb
+            (LookupTableIDs::RangeCheck16Lookup, vec![high.clone()]),
+            (
+                LookupTableIDs::RangeCheck16Lookup,
+                vec![high * Env::<Fp>::constant(1 << 6)]
+            ),
+            (LookupTableIDs::RangeCheck16Lookup, vec![low]),
+        ]
+    );
+}
+
+/// Evaluates `expr` with the scratch columns set to `scratch`
+fn eval_with_scratch(expr: &E<Fp>, scratch: &[Fp]) -> Fp {
+    struct Scratch<'a>(&'a [Fp]);
+
+    impl ColumnEvaluations<Fp> for Scratch<'_> {
+        type Column = Column;
+
+        fn evaluate(&self, col: Column) -> Result<PointEvaluations<Fp>, ExprError<Column>> {
+            match col {
+                Column::Relation(i) if i < self.0.len() => Ok(PointEvaluations {
+                    zeta: self.0[i],
+                    zeta_omega: self.0[i],
+                }),
+                _ => Err(ExprError::MissingEvaluation(col, CurrOrNext::Curr)),
+            }
+        }
+    }
+
+    let constants = Constants {
+        endo_coefficient: Fp::zero(),
+        mds: &ark_bn254::G1Affine::sponge_params().mds,
+        zk_rows: 0,
+    };
+    let challenges = Challenges {
+        alpha: Fp::zero(),
+        beta: Fp::zero(),
+        gamma: Fp::zero(),
+        joint_combiner: None,
+    };
+    expr.evaluate_(
+        Radix2EvaluationDomain::new(1).unwrap(),
+        Fp::zero(),
+        &Scratch(scratch),
+        &constants,
+        &challenges,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_count_leading_zeros_is_constrained() {
+    let mut env = Env::<Fp>::default();
+    let x_pos = env.alloc_scratch();
+    let x = env.variable(x_pos);
+    let res_pos = env.alloc_scratch();
+    unsafe { env.count_leading_zeros(&x, res_pos) };
+    // the 32 bits of x and its 32 entries of the one-hot mask
+    assert_eq!(env.scratch_state_idx, 2 + 64);
+    assert_eq!(env.constraints.len(), 100);
+
+    // The scratch columns for the count `count` of the leading zeros of `x`,
+    // the mask selecting the bit at the corresponding position
+    let scratch = |x: u32, count: u32| -> Vec<Fp> {
+        let bits = (0..32).map(|i| Fp::from((x >> i) & 1));
+        let mask = (0..32).map(|i| Fp::from(count < 32 && i == 31 - count));
+        [Fp::from(x), Fp::from(count)]
+            .into_iter()
+            .chain(bits)
+            .chain(mask)
+            .collect()
+    };
+    let is_satisfied = |scratch: &[Fp]| -> bool {
+        env.constraints
+            .iter()
+            .all(|c| eval_with_scratch(c, scratch).is_zero())
+    };
+
+    // 32, 0 and 15 leading zeros
+    for x in [0, 0x8000_0000, 0x0001_2345] {
+        for count in 0..=32 {
+            assert_eq!(
+                is_satisfied(&scratch(x, count)),
+                count == x.leading_zeros(),
+                "x = {x:#x}, count = {count}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_sign_extend_low_bits_is_constrained() {
+    let mut env = Env::<Fp>::default();
+    let x_pos = env.alloc_scratch();
+    let x = env.variable(x_pos);
+    env.sign_extend_low_bits(&x, 8);
+    // the low bits, the sign bit, the high bits and the high limb of their
+    // range check, and the result
+    assert_eq!(env.scratch_state_idx, 1 + 5);
+    assert_eq!(env.constraints.len(), 3);
+
+    let is_satisfied = |[x, low_bits, sign_bit, high_bits, res]: [u32; 5]| -> bool {
+        let scratch = [x, low_bits, sign_bit, high_bits, 0, res].map(Fp::from);
+        env.constraints
+            .iter()
+            .all(|c| eval_with_scratch(c, &scratch).is_zero())
+    };
+    assert!(is_satisfied([0x1234_5680, 0, 1, 0x12_3456, 0xFFFF_FF80]));
+    assert!(is_satisfied([0x1234_567F, 0x7F, 0, 0x12_3456, 0x7F]));
+    // the bits must be those of x
+    assert!(!is_satisfied([0x1234_5680, 0x7F, 0, 0x12_3456, 0x7F]));
+    assert!(!is_satisfied([0x1234_5680, 0, 1, 0, 0xFFFF_FF80]));
+}
+
+/// The scratch columns of the division of `x` by `y` into the claimed quotient
+/// `q` and remainder `r`, in the order of [Env::constrain_divmod]. The range
+/// check limbs are only used by the lookups and are left to 0.
+fn divmod_scratch(x: u32, y: u32, q: u32, r: u32, signed: bool) -> Vec<Fp> {
+    let abs = |v: u32| if signed { (v as i32).unsigned_abs() } else { v };
+    let gap = if y == 0 {
+        Fp::zero()
+    } else {
+        Fp::from(abs(y)) - Fp::from(abs(r)) - Fp::from(1u32)
+    };
+    let mut scratch: Vec<Fp> = [x, y, q, r].into_iter().map(Fp::from).collect();
+    scratch.extend([
+        Fp::from(y == 0),
+        Fp::from(y).inverse().unwrap_or_else(Fp::zero),
+        gap,
+    ]);
+    if signed {
+        for v in [x, y, q, r] {
+            scratch.extend([Fp::from(v >> 31), Fp::zero()]);
+        }
+        let distance = Fp::from(x & 0x7FFF_FFFF) + Fp::from(0x7FFF_FFFF - (y & 0x7FFF_FFFF))
+            - Fp::from(x >> 31)
+            - Fp::from(y >> 31)
+            + Fp::from(2u32);
+        scratch.extend([
+            Fp::from(distance.is_zero()),
+            distance.inverse().unwrap_or_else(Fp::zero),
+        ]);
+    } else {
+        scratch.extend([Fp::zero(); 2]);
+    }
+    scratch.push(Fp::zero());
+    scratch
+}
+
+#[test]
+fn test_divmod_is_constrained() {
+    let mut env = Env::<Fp>::default();
+    let [x, y] = [(); 2].map(|_| {
+        let pos = env.alloc_scratch();
+        env.variable(pos)
+    });
+    let (q_pos, r_pos) = (env.alloc_scratch(), env.alloc_scratch());
+    unsafe { env.divmod(&x, &y, q_pos, r_pos) };
+    assert_eq!(env.scratch_state_idx, 4 + 6);
+    // the 2 constraints of the zero check of y, the relation and the bound
+    assert_eq!(env.constraints.len(), 4);
+
+    let is_satisfied = |x: u32, y: u32, q: u32, r: u32| -> bool {
+        let scratch = divmod_scratch(x, y, q, r, false);
+        env.constraints
+            .iter()
+            .all(|c| eval_with_scratch(c, &scratch).is_zero())
+    };
+
+    // exact division
+    assert!(is_satisfied(42, 7, 6, 0));
+    assert!(!is_satisfied(42, 7, 5, 0));
+    // division with a remainder
+    assert!(is_satisfied(45, 7, 6, 3));
+    assert!(!is_satisfied(45, 7, 6, 2));
+    // a remainder of at least y is only rejected by the range check lookup
+    assert!(is_satisfied(45, 7, 5, 10));
+    assert!(is_satisfied(u32::MAX, 0x1_0000, 0xFFFF, 0xFFFF));
+    // the results are not constrained when dividing by 0
+    assert!(is_satisfied(45, 0, 0, 0));
+    assert!(is_satisfied(45, 0, 1, 2));
+}
+
+#[test]
+fn test_signed_divmod_is_constrained() {
+    let mut env = Env::<Fp>::default();
+    let [x, y] = [(); 2].map(|_| {
+        let pos = env.alloc_scratch();
+        env.variable(pos)
+    });
+    let (q_pos, r_pos) = (env.alloc_scratch(), env.alloc_scratch());
+    unsafe { env.divmod_signed(&x, &y, q_pos, r_pos) };
+    assert_eq!(env.scratch_state_idx, 4 + 14);
+    // the 2 constraints of the zero check of y, the 4 sign bits, the sign of
+    // the remainder, the 2 constraints of the overflow check, the relation
+    // and the bound
+    assert_eq!(env.constraints.len(), 11);
+
+    let is_satisfied = |x: i32, y: i32, q: i32, r: i32| -> bool {
+        let scratch = divmod_scratch(x as u32, y as u32, q as u32, r as u32, true);
+        env.constraints
+            .iter()
+            .all(|c| eval_with_scratch(c, &scratch).is_zero())
+    };
+
+    // exact division
+    assert!(is_satisfied(42, -7, -6, 0));
+    assert!(!is_satisfied(42, -7, 6, 0));
+    // division with a remainder
+    assert!(is_satisfied(45, 7, 6, 3));
+    assert!(!is_satisfied(45, 7, 7, -4));
+    // negative dividend, the quotient being rounded towards 0 and the
+    // remainder having the sign of the dividend
+    assert!(is_satisfied(-7, 2, -3, -1));
+    assert!(is_satisfied(-7, -2, 3, -1));
+    assert!(!is_satisfied(-7, 2, -4, 1));
+    assert!(!is_satisfied(-7, 2, -3, 1));
+    // the quotient of -2^31 by -1 wraps to -2^31
+    assert!(is_satisfied(i32::MIN, -1, i32::MIN, 0));
+    assert!(!is_satisfied(i32::MIN, -1, i32::MAX, 0));
+    assert!(is_satisfied(i32::MIN, 1, i32::MIN, 0));
+    // the results are not constrained when dividing by 0
+    assert!(is_satisfied(-7, 0, 1, 2));
+}
+
 // Manually change the number of constraints if they are modififed in the interpreter
 #[test]
 fn test_mips_number_constraints() {
@@ -24,6 +1029,7 @@ fn test_mips_number_constraints() {
     let mut constraints_env = Env::<Fp> {
         scratch_state_idx: 0,
         constraints: Vec::new(),
+        labeled_constraints: Vec::new(),
         lookups: Vec::new(),
     };
 
@@ -34,11 +1040,15 @@ fn test_mips_number_constraints() {
         assert_eq!(mips_circuit.constraints.get(instr).unwrap().len(), num)
     };
 
+    // The counts include the 3 constraints of the step budget, checked after
+    // every instruction
+
     let mut i = 0;
     for instr in Instruction::iter().flat_map(|x| x.into_iter()) {
         match instr {
             RType(rtype) => match rtype {
-                JumpRegister | SyscallExitGroup | Sync => assert_num_constraints(&instr, 0),
+                JumpRegister | Sync => assert_num_constraints(&instr, 3),
+                SyscallExitGroup => assert_num_constraints(&instr, 6),
                 ShiftLeftLogical
                 | ShiftRightLogical
                 | ShiftRightArithmetic
@@ -51,53 +1061,46 @@ fn test_mips_number_constraints() {
                 | MoveFromLo
                 | MoveToLo
                 | MoveToHi
-                | Add
-                | AddUnsigned
-                | Sub
-                | SubUnsigned
                 | And
                 | Or
                 | Xor
                 | Nor
-                | SetLessThan
-                | SetLessThanUnsigned
-                | MultiplyToRegister
-                | CountLeadingOnes
-                | CountLeadingZeros => assert_num_constraints(&instr, 3),
-                MoveZero | MoveNonZero => assert_num_constraints(&instr, 5),
+                | MultiplyToRegister => assert_num_constraints(&instr, 6),
+                SetLessThanUnsigned => assert_num_constraints(&instr, 7),
+                SetLessThan => assert_num_constraints(&instr, 9),
+                CountLeadingOnes | CountLeadingZeros => assert_num_constraints(&instr, 106),
+                RotateRight => assert_num_constraints(&instr, 20),
+                MoveZero | MoveNonZero | SwapBytesInHalfwords | Add | AddUnsigned | Sub
+                | SubUnsigned => assert_num_constraints(&instr, 8),
+                SignExtendByte | SignExtendHalfword => assert_num_constraints(&instr, 9),
                 SyscallReadOther | SyscallWriteHint | SyscallWriteOther | Multiply
-                | MultiplyUnsigned | Div | DivUnsigned => assert_num_constraints(&instr, 6),
-                SyscallOther => assert_num_constraints(&instr, 10),
-                SyscallMmap => assert_num_constraints(&instr, 11),
-                SyscallReadPreimage => assert_num_constraints(&instr, 21),
-                SyscallFcntl => assert_num_constraints(&instr, 22),
-                SyscallWritePreimage => assert_num_constraints(&instr, 30),
+                | MultiplyUnsigned => assert_num_constraints(&instr, 9),
+                DivUnsigned => assert_num_constraints(&instr, 13),
+                Div => assert_num_constraints(&instr, 20),
+                SyscallOther => assert_num_constraints(&instr, 21),
+                SyscallMmap => assert_num_constraints(&instr, 14),
+                SyscallReadPreimage => assert_num_constraints(&instr, 31),
+                SyscallFcntl => assert_num_constraints(&instr, 25),
+                SyscallWritePreimage => assert_num_constraints(&instr, 33),
             },
             JType(jtype) => match jtype {
-                Jump => assert_num_constraints(&instr, 0),
-                JumpAndLink => assert_num_constraints(&instr, 3),
+                Jump => assert_num_constraints(&instr, 3),
+                JumpAndLink => assert_num_constraints(&instr, 6),
             },
             IType(itype) => match itype {
                 BranchLeqZero | BranchGtZero | BranchLtZero | BranchGeqZero | Store8 | Store16 => {
-                    assert_num_constraints(&instr, 0)
+                    assert_num_constraints(&instr, 5)
+                }
+                AndImmediate | OrImmediate | XorImmediate => assert_num_constraints(&instr, 6),
+                SetLessThanImmediateUnsigned => assert_num_constraints(&instr, 7),
+                SetLessThanImmediate => assert_num_constraints(&instr, 9),
+                BranchEq | BranchNeq | Store32 | LoadUpperImmediate => {
+                    assert_num_constraints(&instr, 7)
                 }
-                BranchEq | BranchNeq | Store32 => assert_num_constraints(&instr, 2),
-                AddImmediate
-                | AddImmediateUnsigned
-                | SetLessThanImmediate
-                | SetLessThanImmediateUnsigned
-                | AndImmediate
-                | OrImmediate
-                | XorImmediate
-                | LoadUpperImmediate
-                | Load8
-                | Load16
-                | Load32
-                | Load8Unsigned
-                | Load16Unsigned
-                | Store32Conditional => assert_num_constraints(&instr, 3),
+                AddImmediate | AddImmediateUnsigned | Load8 | Load16 | Load32 | Load8Unsigned
+                | Load16Unsigned | Store32Conditional => assert_num_constraints(&instr, 8),
                 LoadWordLeft | LoadWordRight | StoreWordLeft | StoreWordRight => {
-                    assert_num_constraints(&instr, 12)
+                    assert_num_constraints(&instr, 17)
                 }
             },
         }
@@ -111,18 +1114,35 @@ fn test_mips_number_constraints() {
 
 mod folding {
     use crate::{
-        cannon::{HostProgram, PAGE_ADDRESS_MASK, PAGE_ADDRESS_SIZE, PAGE_SIZE},
+        cannon::{
+            HostProgram, Meta, Start, StepFrequency, VmConfiguration, PAGE_ADDRESS_MASK,
+            PAGE_ADDRESS_SIZE, PAGE_SIZE,
+        },
         folding::ScalarField,
+        lookups::LookupTableIDs,
         mips::{
+            column::{ColumnAlias, MIPS_REL_COLS},
+            constraints::{evaluate, to_lookup_table_entries, Env as CEnv},
             folding::MIPSFoldingConfig,
-            interpreter::{debugging::InstructionParts, interpret_itype, InterpreterEnv},
+            interpreter::{
+                debugging::InstructionParts, interpret_itype, interpret_rtype, Instruction,
+                InstructionDecoder, InterpreterEnv, STACK_START_ADDRESS, SYSCALL_BRK,
+                SYSCALL_CLONE, SYSCALL_FUTEX,
+            },
             registers::Registers,
-            witness::{Env as WEnv, SyscallEnv, SCRATCH_SIZE},
-            ITypeInstruction,
+            trace::MIPSTrace,
+            witness::{Env as WEnv, SyscallEnv, WitnessError, MAX_STEP_BUDGET, SCRATCH_SIZE},
+            ITypeInstruction, RTypeInstruction,
         },
         preimage_oracle::PreImageOracle,
+        trace::{Indexer, Tracer},
     };
-    use kimchi::o1_utils;
+    use ark_ff::{One, Zero};
+    use kimchi::{
+        circuits::{expr::Variable, gate::CurrOrNext},
+        o1_utils,
+    };
+    use kimchi_msm::columns::Column;
     use rand::{CryptoRng, RngCore};
 
     type Fp = ScalarField<MIPSFoldingConfig>;
@@ -184,6 +1204,8 @@ mod folding {
             scratch_state_idx: 0,
             scratch_state: [Fp::from(0); SCRATCH_SIZE],
             halt: false,
+            exit_code: Fp::from(0),
+            step_budget: MAX_STEP_BUDGET,
             // Keccak related
             syscall_env: SyscallEnv::default(),
             preimage: None,
@@ -192,6 +1214,7 @@ mod folding {
             preimage_key: None,
             keccak_env: None,
             hash_counter: 0,
+            hint_counter: 0,
         };
         env.registers.current_instruction_pointer = PAGE_INDEX_EXECUTABLE_MEMORY * PAGE_SIZE;
         env.registers.next_instruction_pointer = env.registers.current_instruction_pointer + 4;
@@ -210,13 +1233,263 @@ mod folding {
         env.memory[page as usize].1[page_address + 3] = (instr & 0xFF) as u8;
     }
 
+    /// A configuration executing the program without any of the side effects
+    /// of the command line interface
+    fn dummy_configuration(step_budget: u64) -> VmConfiguration {
+        VmConfiguration {
+            input_state_file: String::from("state.json"),
+            output_state_file: String::from("out.json"),
+            metadata_file: String::from("meta.json"),
+            proof_at: StepFrequency::Never,
+            stop_at: StepFrequency::Never,
+            snapshot_state_at: StepFrequency::Never,
+            info_at: StepFrequency::Never,
+            proof_fmt: String::from("proof-%d.json"),
+            snapshot_fmt: String::from("state-%d.json"),
+            pprof_cpu: false,
+            step_budget,
+            host: None,
+        }
+    }
+
+    /// The row of the MIPS circuit holding the witness of the last step, as
+    /// pushed by the main loop
+    fn witness_row(env: &WEnv<Fp>) -> Vec<Fp> {
+        let mut row = env.scratch_state.to_vec();
+        row.extend([
+            Fp::from(env.instruction_counter),
+            Fp::zero(),
+            Fp::from(env.halt),
+            env.exit_code,
+            Fp::from(env.step_budget),
+        ]);
+        assert_eq!(row.len(), MIPS_REL_COLS);
+        row
+    }
+
+    /// Write `program` from the instruction pointer and run it with a budget
+    /// of `step_budget` steps until it halts. Returns the instruction and the
+    /// witness row of each step, in the order of execution.
+    fn run_program(
+        env: &mut WEnv<Fp>,
+        program: &[u32],
+        step_budget: u64,
+    ) -> Vec<(Instruction, Vec<Fp>)> {
+        let start_pointer = env.registers.current_instruction_pointer;
+        for (i, instr) in program.iter().enumerate() {
+            env.registers.current_instruction_pointer = start_pointer + 4 * i as u32;
+            write_instruction(env, InstructionParts::decode(*instr));
+        }
+        env.registers.current_instruction_pointer = start_pointer;
+
+        let config = dummy_configuration(step_budget);
+        let meta = Meta { symbols: vec![] };
+        let start = Start::create(0);
+        env.set_step_budget(config.step_budget);
+        let mut rows = vec![];
+        while !env.halt {
+            let instr = env.step(&config, &meta, &start);
+            rows.push((instr, witness_row(env)));
+        }
+        rows
+    }
+
+    /// Check that the transitions between the steps hold on `rows`, the trace
+    /// of an execution padded with copies of its last row.
+    fn assert_step_transitions(rows: &[Vec<Fp>]) {
+        let last = rows.last().unwrap();
+        let next_rows = rows.iter().skip(1).chain([last]);
+        for (i, (curr, next)) in rows.iter().zip(next_rows).enumerate() {
+            for constraint in CEnv::<Fp>::step_transitions() {
+                let value = evaluate(&constraint, &|Variable { col, row }| match (col, row) {
+                    (Column::Relation(j), CurrOrNext::Curr) => curr[j],
+                    (Column::Relation(j), CurrOrNext::Next) => next[j],
+                    _ => panic!("Unexpected column {col:?}"),
+                });
+                assert!(value.is_zero(), "step {i}: {constraint:?}");
+            }
+        }
+    }
+
+    // addiu $a0, $zero, 3; addiu $v0, $zero, 4246; syscall, i.e. exit(3)
+    const EXIT_PROGRAM: [u32; 3] = [0x2404_0003, 0x2402_1096, 0x0000_000c];
+
+    #[test]
+    fn test_consume_step_decrements_the_budget_until_halted() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        dummy_env.set_step_budget(2);
+        dummy_env.consume_step();
+        assert_eq!(dummy_env.step_budget, 1);
+        // the high limb of the range check, then the zero test of the budget
+        assert_eq!(
+            dummy_env.scratch_state[..3],
+            [Fp::zero(), Fp::zero(), Fp::one()]
+        );
+        assert!(!dummy_env.halt);
+
+        // the program halts once its budget is exhausted
+        dummy_env.reset_scratch_state();
+        dummy_env.consume_step();
+        assert_eq!(dummy_env.step_budget, 0);
+        assert_eq!(dummy_env.scratch_state[1], Fp::one());
+        assert!(dummy_env.halt);
+    }
+
+    #[test]
+    fn test_step_budget_exhausted_by_the_halting_step() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        let rows = run_program(&mut dummy_env, &EXIT_PROGRAM, 3);
+        assert_eq!(
+            rows.iter().map(|(instr, _)| *instr).collect::<Vec<_>>(),
+            vec![
+                Instruction::IType(ITypeInstruction::AddImmediateUnsigned),
+                Instruction::IType(ITypeInstruction::AddImmediateUnsigned),
+                Instruction::RType(RTypeInstruction::SyscallExitGroup),
+            ]
+        );
+        let column = |i: usize, column: ColumnAlias| rows[i].1[column.ix()];
+        // the halting step consumes the last step of the budget
+        for (i, budget) in [2u64, 1, 0].into_iter().enumerate() {
+            assert_eq!(column(i, ColumnAlias::StepBudget), Fp::from(budget));
+            assert_eq!(column(i, ColumnAlias::Halted), Fp::from(i == 2));
+        }
+        assert_eq!(column(2, ColumnAlias::ExitCode), Fp::from(3u64));
+
+        let rows: Vec<_> = rows.into_iter().map(|(_, row)| row).collect();
+        assert_step_transitions(&rows);
+        // the budget given to the execution, a public input, is bound on the
+        // first row, which holds the budget left after the first step
+        let mut env = CEnv::<Fp>::default();
+        env.set_step_budget(CEnv::<Fp>::constant(3) - CEnv::<Fp>::constant(1));
+        let value = evaluate(&env.constraints[0], &|Variable { col, .. }| match col {
+            Column::Relation(j) => rows[0][j],
+            _ => panic!("Unexpected column {col:?}"),
+        });
+        assert!(value.is_zero());
+    }
+
+    #[test]
+    fn test_step_budget_exhausted_before_halting() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        let rows = run_program(&mut dummy_env, &EXIT_PROGRAM, 2);
+        // the program is stopped before reaching its exit
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].1[ColumnAlias::Halted.ix()], Fp::one());
+        assert_eq!(rows[1].1[ColumnAlias::StepBudget.ix()], Fp::zero());
+        assert_eq!(dummy_env.registers.general_purpose[4], 3);
+        let rows: Vec<_> = rows.into_iter().map(|(_, row)| row).collect();
+        assert_step_transitions(&rows);
+    }
+
+    #[test]
+    fn test_instruction_counter_follows_the_execution_trace() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        let rows = run_program(&mut dummy_env, &EXIT_PROGRAM, MAX_STEP_BUDGET);
+        assert_eq!(rows.len(), 3);
+        let column = |i: usize, column: ColumnAlias| rows[i].1[column.ix()];
+        for i in 0..3 {
+            assert_eq!(
+                column(i, ColumnAlias::InstructionCounter),
+                Fp::from(i as u64 + 1)
+            );
+            assert_eq!(column(i, ColumnAlias::Halted), Fp::from(i == 2));
+        }
+        let rows: Vec<_> = rows.into_iter().map(|(_, row)| row).collect();
+        assert_step_transitions(&rows);
+    }
+
+    /// Check that the circuit of each instruction holds on its rows in `rows`,
+    /// padded like in the main loop with copies of the first one, the last
+    /// row being followed by the first one. The range checks are checked
+    /// along with the constraints.
+    fn assert_instruction_circuits_hold(rows: &[(Instruction, Vec<Fp>)]) {
+        let domain_size = 4;
+        let circuit = MIPSTrace::new(domain_size, &mut CEnv::<Fp>::default());
+        for (instr, _) in rows.iter() {
+            let mut instr_rows: Vec<_> = rows
+                .iter()
+                .filter(|(other, _)| other == instr)
+                .map(|(_, row)| row.clone())
+                .collect();
+            instr_rows.resize(domain_size, instr_rows[0].clone());
+            for i in 0..domain_size {
+                let [curr, next] = [i, (i + 1) % domain_size].map(|j| &instr_rows[j]);
+                for constraint in circuit.constraints[instr].iter() {
+                    let value = evaluate(constraint, &|Variable { col, row }| match (col, row) {
+                        (Column::Relation(j), CurrOrNext::Curr) => curr[j],
+                        (Column::Relation(j), CurrOrNext::Next) => next[j],
+                        _ => panic!("Unexpected column {col:?}"),
+                    });
+                    assert!(value.is_zero(), "{instr:?}, row {i}: {constraint:?}");
+                }
+            }
+
+            // The main loop stores the instruction counter after the step,
+            // while the lookups refer to the counter of the step
+            let counter = ColumnAlias::InstructionCounter.ix();
+            let entries =
+                to_lookup_table_entries(&circuit.lookups[instr], domain_size, |i, variable| {
+                    match variable.col {
+                        Column::Relation(j) if j == counter => instr_rows[i][j] - Fp::one(),
+                        Column::Relation(j) => instr_rows[i][j],
+                        col => panic!("Unexpected column {col:?}"),
+                    }
+                });
+            for (table_id, bits) in [
+                (LookupTableIDs::ByteLookup, 8),
+                (LookupTableIDs::RangeCheck16Lookup, 16),
+            ] {
+                for entry in entries.get(&table_id).into_iter().flatten() {
+                    assert!(
+                        entry.value[0] < Fp::from(1u64 << bits),
+                        "{instr:?}: {table_id:?} {entry:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_execution_trace_satisfies_the_constraints() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        let rows = run_program(&mut dummy_env, &EXIT_PROGRAM, MAX_STEP_BUDGET);
+        assert_eq!(rows.len(), 3);
+
+        // the steps follow each other on the trace of the whole execution,
+        // and the circuit of each instruction holds on its own steps
+        let trace: Vec<_> = rows.iter().map(|(_, row)| row.clone()).collect();
+        assert_step_transitions(&trace);
+        assert_instruction_circuits_hold(&rows);
+    }
+
+    #[test]
+    fn test_unit_addiu_reading_and_writing_the_same_register() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        dummy_env.registers.general_purpose[29] = 0x1000;
+        // addiu $sp, $sp, -8, followed by the exit
+        let program = [
+            0x27bd_fff8,
+            EXIT_PROGRAM[0],
+            EXIT_PROGRAM[1],
+            EXIT_PROGRAM[2],
+        ];
+        let rows = run_program(&mut dummy_env, &program, MAX_STEP_BUDGET);
+        assert_eq!(dummy_env.registers.general_purpose[29], 0xff8);
+        // the write of $sp is timed after its read in the same instruction
+        assert_instruction_circuits_hold(&rows);
+    }
+
     #[test]
     fn test_unit_addiu_instruction() {
         let mut rng = o1_utils::tests::make_test_rng();
         // We only care about instruction parts and instruction pointer
         let mut dummy_env = dummy_env(&mut rng);
-        // FIXME: at the moment, we do not support writing and reading into the
-        // same register
         let reg_src = 1;
         let reg_dest = 2;
         // Instruction: 0b00100100001000010110110011101000
@@ -240,4 +1513,612 @@ mod folding {
             exp_res
         );
     }
+
+    #[test]
+    fn test_unit_lui_instruction() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t0
+        let reg_dest = 8;
+        // Instruction: 0b00111100000010000001001000110100
+        // lui $t0, 0x1234
+        write_instruction(
+            &mut dummy_env,
+            InstructionParts {
+                op_code: 0b001111,
+                rs: 0,
+                rt: reg_dest,
+                // The rest is the immediate value
+                rd: 0b00010,
+                shamt: 0b01000,
+                funct: 0b110100,
+            },
+        );
+        interpret_itype(&mut dummy_env, ITypeInstruction::LoadUpperImmediate);
+        assert_eq!(
+            dummy_env.registers.general_purpose[reg_dest as usize],
+            0x12340000
+        );
+    }
+
+    #[test]
+    fn test_unit_slt_instruction() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t0, $t1, $t2
+        let (reg_dest, reg_src1, reg_src2) = (8, 9, 10);
+        dummy_env.registers.general_purpose[reg_src1 as usize] = (-1i32) as u32;
+        dummy_env.registers.general_purpose[reg_src2 as usize] = 1;
+        // Instruction: 0b00000001001010100100000000101010
+        // slt $t0, $t1, $t2
+        write_instruction(
+            &mut dummy_env,
+            InstructionParts {
+                op_code: 0b000000,
+                rs: reg_src1,
+                rt: reg_src2,
+                rd: reg_dest,
+                shamt: 0b00000,
+                funct: 0b101010,
+            },
+        );
+        interpret_rtype(&mut dummy_env, RTypeInstruction::SetLessThan);
+        // -1 < 1
+        assert_eq!(dummy_env.registers.general_purpose[reg_dest as usize], 1);
+    }
+
+    #[test]
+    fn test_unit_add_sub_sequence_witness() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t0, $t1, $t2, $t3
+        let (reg_t0, reg_t1, reg_t2, reg_t3) = (8, 9, 10, 11);
+        dummy_env.registers.general_purpose[reg_t1 as usize] = 7;
+        dummy_env.registers.general_purpose[reg_t2 as usize] = 5;
+
+        // add $t0, $t1, $t2
+        write_instruction(
+            &mut dummy_env,
+            InstructionParts {
+                op_code: 0b000000,
+                rs: reg_t1,
+                rt: reg_t2,
+                rd: reg_t0,
+                shamt: 0b00000,
+                funct: 0b100000,
+            },
+        );
+        interpret_rtype(&mut dummy_env, RTypeInstruction::Add);
+        assert_eq!(dummy_env.registers.general_purpose[reg_t0 as usize], 12);
+        // the result is part of the witness of the instruction
+        assert!(dummy_env.scratch_state.contains(&Fp::from(12u32)));
+        // the next instruction is executed at the next step
+        dummy_env.reset_scratch_state();
+        dummy_env.instruction_counter += 1;
+
+        // sub $t3, $t0, $t2
+        write_instruction(
+            &mut dummy_env,
+            InstructionParts {
+                op_code: 0b000000,
+                rs: reg_t0,
+                rt: reg_t2,
+                rd: reg_t3,
+                shamt: 0b00000,
+                funct: 0b100010,
+            },
+        );
+        interpret_rtype(&mut dummy_env, RTypeInstruction::Sub);
+        assert_eq!(dummy_env.registers.general_purpose[reg_t3 as usize], 7);
+        assert!(dummy_env.scratch_state.contains(&Fp::from(7u32)));
+    }
+
+    #[test]
+    fn test_unit_sltu_instruction() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t0, $t1, $t2
+        let (reg_dest, reg_src1, reg_src2) = (8, 9, 10);
+        dummy_env.registers.general_purpose[reg_dest as usize] = 1;
+        dummy_env.registers.general_purpose[reg_src1 as usize] = (-1i32) as u32;
+        dummy_env.registers.general_purpose[reg_src2 as usize] = 1;
+        // Instruction: 0b00000001001010100100000000101011
+        // sltu $t0, $t1, $t2
+        write_instruction(
+            &mut dummy_env,
+            InstructionParts {
+                op_code: 0b000000,
+                rs: reg_src1,
+                rt: reg_src2,
+                rd: reg_dest,
+                shamt: 0b00000,
+                funct: 0b101011,
+            },
+        );
+        interpret_rtype(&mut dummy_env, RTypeInstruction::SetLessThanUnsigned);
+        // 0xFFFFFFFF >= 1 as unsigned integers
+        assert_eq!(dummy_env.registers.general_purpose[reg_dest as usize], 0);
+    }
+
+    #[test]
+    fn test_unit_slti_instruction_negative_immediate() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t0, $t1
+        let (reg_dest, reg_src) = (8, 9);
+        dummy_env.registers.general_purpose[reg_src as usize] = (-10i32) as u32;
+        // Instruction: 0b00101001001010001111111111111011
+        // slti $t0, $t1, -5
+        write_instruction(
+            &mut dummy_env,
+            InstructionParts {
+                op_code: 0b001010,
+                rs: reg_src,
+                rt: reg_dest,
+                // The rest is the immediate value, 0xFFFB
+                rd: 0b11111,
+                shamt: 0b11111,
+                funct: 0b111011,
+            },
+        );
+        interpret_itype(&mut dummy_env, ITypeInstruction::SetLessThanImmediate);
+        // -10 < -5
+        assert_eq!(dummy_env.registers.general_purpose[reg_dest as usize], 1);
+    }
+
+    #[test]
+    fn test_set_less_than_circuits_hold_across_signs() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t1, $t2
+        dummy_env.registers.general_purpose[9] = (-1i32) as u32;
+        dummy_env.registers.general_purpose[10] = 1;
+        // slt $t0, $t1, $t2; sltu $t3, $t1, $t2; slti $t4, $t2, -5;
+        // sltiu $t5, $t2, -5, followed by the exit
+        let program = [
+            0x012a_402a,
+            0x012a_582b,
+            0x294c_fffb,
+            0x2d4d_fffb,
+            EXIT_PROGRAM[0],
+            EXIT_PROGRAM[1],
+            EXIT_PROGRAM[2],
+        ];
+        let rows = run_program(&mut dummy_env, &program, MAX_STEP_BUDGET);
+        // -1 < 1, but 0xFFFFFFFF >= 1; 1 >= -5, but 1 < 0xFFFFFFFB
+        for (reg, res) in [(8, 1), (11, 0), (12, 0), (13, 1)] {
+            assert_eq!(
+                dummy_env.registers.general_purpose[reg], res,
+                "register {reg}"
+            );
+        }
+        assert_instruction_circuits_hold(&rows);
+    }
+
+    #[test]
+    fn test_unit_multu_instruction() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t1, $t2
+        let (reg_src1, reg_src2) = (9, 10);
+        dummy_env.registers.general_purpose[reg_src1 as usize] = 0xFFFF_FFFF;
+        dummy_env.registers.general_purpose[reg_src2 as usize] = 2;
+        // Instruction: 0b00000001001010100000000000011001
+        // multu $t1, $t2
+        write_instruction(
+            &mut dummy_env,
+            InstructionParts {
+                op_code: 0b000000,
+                rs: reg_src1,
+                rt: reg_src2,
+                rd: 0b00000,
+                shamt: 0b00000,
+                funct: 0b011001,
+            },
+        );
+        interpret_rtype(&mut dummy_env, RTypeInstruction::MultiplyUnsigned);
+        // 0xFFFFFFFF * 2 = 0x1_FFFFFFFE
+        assert_eq!(dummy_env.registers.hi, 1);
+        assert_eq!(dummy_env.registers.lo, 0xFFFF_FFFE);
+    }
+
+    #[test]
+    fn test_unit_div_instruction() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t1, $t2
+        let (reg_src1, reg_src2) = (9, 10);
+        dummy_env.registers.general_purpose[reg_src1 as usize] = (-7i32) as u32;
+        dummy_env.registers.general_purpose[reg_src2 as usize] = 2;
+        // Instruction: 0b00000001001010100000000000011010
+        // div $t1, $t2
+        write_instruction(
+            &mut dummy_env,
+            InstructionParts {
+                op_code: 0b000000,
+                rs: reg_src1,
+                rt: reg_src2,
+                rd: 0b00000,
+                shamt: 0b00000,
+                funct: 0b011010,
+            },
+        );
+        interpret_rtype(&mut dummy_env, RTypeInstruction::Div);
+        // The quotient is rounded towards zero, in LO, and the remainder has
+        // the sign of the dividend, in HI
+        assert_eq!(dummy_env.registers.lo, (-3i32) as u32);
+        assert_eq!(dummy_env.registers.hi, (-1i32) as u32);
+    }
+
+    #[test]
+    fn test_unit_seb_instruction() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t0, $t1
+        let (reg_dst, reg_src) = (8, 9);
+        // The bits above the lowest byte are ignored
+        dummy_env.registers.general_purpose[reg_src as usize] = 0x1234_5680;
+        // Instruction: 0b01111100000010010100010000100000
+        // seb $t0, $t1
+        write_instruction(
+            &mut dummy_env,
+            InstructionParts {
+                op_code: 0b011111,
+                rs: 0b00000,
+                rt: reg_src,
+                rd: reg_dst,
+                shamt: 0b10000,
+                funct: 0b100000,
+            },
+        );
+        let (instruction, _) = dummy_env.decode_instruction();
+        assert_eq!(
+            instruction,
+            Instruction::RType(RTypeInstruction::SignExtendByte)
+        );
+        interpret_rtype(&mut dummy_env, RTypeInstruction::SignExtendByte);
+        assert_eq!(
+            dummy_env.registers.general_purpose[reg_dst as usize],
+            0xFFFF_FF80
+        );
+    }
+
+    #[test]
+    fn test_unit_seh_instruction() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t0, $t1
+        let (reg_dst, reg_src) = (8, 9);
+        dummy_env.registers.general_purpose[reg_src as usize] = 0x8000;
+        // Instruction: 0b01111100000010010100011000100000
+        // seh $t0, $t1
+        let write_seh = |env: &mut WEnv<Fp>| {
+            write_instruction(
+                env,
+                InstructionParts {
+                    op_code: 0b011111,
+                    rs: 0b00000,
+                    rt: reg_src,
+                    rd: reg_dst,
+                    shamt: 0b11000,
+                    funct: 0b100000,
+                },
+            )
+        };
+        write_seh(&mut dummy_env);
+        let (instruction, _) = dummy_env.decode_instruction();
+        assert_eq!(
+            instruction,
+            Instruction::RType(RTypeInstruction::SignExtendHalfword)
+        );
+        interpret_rtype(&mut dummy_env, RTypeInstruction::SignExtendHalfword);
+        assert_eq!(
+            dummy_env.registers.general_purpose[reg_dst as usize],
+            0xFFFF_8000
+        );
+
+        // A positive halfword is left unchanged
+        dummy_env.registers.general_purpose[reg_src as usize] = 0x1234_7FFF;
+        write_seh(&mut dummy_env);
+        interpret_rtype(&mut dummy_env, RTypeInstruction::SignExtendHalfword);
+        assert_eq!(
+            dummy_env.registers.general_purpose[reg_dst as usize],
+            0x7FFF
+        );
+    }
+
+    #[test]
+    fn test_unit_wsbh_instruction() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t0, $t1
+        let (reg_dst, reg_src) = (8, 9);
+        dummy_env.registers.general_purpose[reg_src as usize] = 0x1122_3344;
+        // Instruction: 0b01111100000010010100000010100000
+        // wsbh $t0, $t1
+        write_instruction(
+            &mut dummy_env,
+            InstructionParts {
+                op_code: 0b011111,
+                rs: 0b00000,
+                rt: reg_src,
+                rd: reg_dst,
+                shamt: 0b00010,
+                funct: 0b100000,
+            },
+        );
+        let (instruction, _) = dummy_env.decode_instruction();
+        assert_eq!(
+            instruction,
+            Instruction::RType(RTypeInstruction::SwapBytesInHalfwords)
+        );
+        interpret_rtype(&mut dummy_env, RTypeInstruction::SwapBytesInHalfwords);
+        assert_eq!(
+            dummy_env.registers.general_purpose[reg_dst as usize],
+            0x2211_4433
+        );
+    }
+
+    // rotr $t0, $t1, shamt
+    fn rotr_word(shamt: u32) -> u32 {
+        InstructionParts {
+            op_code: 0b000000,
+            rs: 0b00001,
+            rt: 9,
+            rd: 8,
+            shamt,
+            funct: 0b000010,
+        }
+        .encode()
+    }
+
+    #[test]
+    fn test_unit_rotr_instruction() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // $t0, $t1
+        let (reg_dst, reg_src) = (8, 9);
+        dummy_env.registers.general_purpose[reg_src] = 0x1122_3344;
+        // Instruction: 0b00000000001010010100001000000010
+        // rotr $t0, $t1, 8
+        assert_eq!(
+            dummy_env.registers.decode_instruction(rotr_word(8)),
+            Instruction::RType(RTypeInstruction::RotateRight)
+        );
+        let program = [
+            rotr_word(8),
+            EXIT_PROGRAM[0],
+            EXIT_PROGRAM[1],
+            EXIT_PROGRAM[2],
+        ];
+        let rows = run_program(&mut dummy_env, &program, MAX_STEP_BUDGET);
+        assert_eq!(dummy_env.registers.general_purpose[reg_dst], 0x4411_2233);
+        assert_instruction_circuits_hold(&rows);
+    }
+
+    #[test]
+    fn test_unit_rotr_by_zero_instruction() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        let (reg_dst, reg_src) = (8, 9);
+        dummy_env.registers.general_purpose[reg_src] = 0x1122_3344;
+        // Rotating by 0 leaves the value unchanged, the high bits being the
+        // whole value and no low bit being moved to the top
+        let program = [
+            rotr_word(0),
+            EXIT_PROGRAM[0],
+            EXIT_PROGRAM[1],
+            EXIT_PROGRAM[2],
+        ];
+        let rows = run_program(&mut dummy_env, &program, MAX_STEP_BUDGET);
+        assert_eq!(dummy_env.registers.general_purpose[reg_dst], 0x1122_3344);
+        assert_instruction_circuits_hold(&rows);
+    }
+
+    fn write_syscall(env: &mut WEnv<Fp>) {
+        // Instruction: 0b00000000000000000000000000001100
+        // syscall
+        write_instruction(
+            env,
+            InstructionParts {
+                op_code: 0b000000,
+                rs: 0b00000,
+                rt: 0b00000,
+                rd: 0b00000,
+                shamt: 0b00000,
+                funct: 0b001100,
+            },
+        );
+    }
+
+    #[test]
+    fn test_signed_divmod_witness_satisfies_constraints() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        // exact division, negative dividend, overflow and division by 0
+        for (x, y) in [
+            (42, (-7i32) as u32),
+            ((-7i32) as u32, 2),
+            (0x8000_0000, 0xFFFF_FFFF),
+            (45, 0),
+        ] {
+            let mut dummy_env = dummy_env(&mut rng);
+            let mut constraints_env = CEnv::<Fp>::default();
+            let [x_pos, y_pos, q_pos, r_pos] = [(); 4].map(|_| {
+                let pos = dummy_env.alloc_scratch();
+                assert_eq!(constraints_env.alloc_scratch(), pos);
+                pos
+            });
+
+            dummy_env.write_column(x_pos, x as u64);
+            dummy_env.write_column(y_pos, y as u64);
+            let (q, r) = unsafe { dummy_env.divmod_signed(&(x as u64), &(y as u64), q_pos, r_pos) };
+            if y != 0 {
+                let (x, y) = (x as i32, y as i32);
+                assert_eq!(
+                    (q as u32, r as u32),
+                    (x.wrapping_div(y) as u32, x.wrapping_rem(y) as u32)
+                );
+            }
+
+            let x_var = constraints_env.variable(x_pos);
+            let y_var = constraints_env.variable(y_pos);
+            unsafe { constraints_env.divmod_signed(&x_var, &y_var, q_pos, r_pos) };
+            assert_eq!(
+                constraints_env.scratch_state_idx,
+                dummy_env.scratch_state_idx
+            );
+            for constraint in constraints_env.constraints.iter() {
+                assert!(
+                    super::eval_with_scratch(constraint, &dummy_env.scratch_state).is_zero(),
+                    "x = {x:#x}, y = {y:#x}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_boolean_column_names_the_column() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        let [zero, one, two] = [0, 1, 2].map(|value| {
+            let pos = dummy_env.alloc_scratch();
+            dummy_env.write_column(pos, value);
+            pos
+        });
+        assert_eq!(dummy_env.check_boolean_column(zero), Ok(()));
+        assert_eq!(dummy_env.check_boolean_column(one), Ok(()));
+
+        let err = dummy_env.check_boolean_column(two).unwrap_err();
+        assert_eq!(
+            err,
+            WitnessError::UnsatisfiedBooleanConstraint {
+                column: ColumnAlias::ScratchState(2),
+                value: Fp::from(2u64).to_string(),
+            }
+        );
+        assert!(err.to_string().contains("ScratchState(2)"));
+    }
+
+    #[test]
+    fn test_syscall_futex_is_a_noop() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        dummy_env.registers.general_purpose[2] = SYSCALL_FUTEX;
+        dummy_env.registers.general_purpose[7] = 1;
+        write_syscall(&mut dummy_env);
+        let (instruction, _) = dummy_env.decode_instruction();
+        assert_eq!(
+            instruction,
+            Instruction::RType(RTypeInstruction::SyscallOther)
+        );
+        interpret_rtype(&mut dummy_env, RTypeInstruction::SyscallOther);
+        // $v0 = 0 and $a3 = 0, i.e. no error, as in the reference implementation
+        assert_eq!(dummy_env.registers.general_purpose[2], 0);
+        assert_eq!(dummy_env.registers.general_purpose[7], 0);
+    }
+
+    #[test]
+    fn test_syscall_clone_returns_thread_id() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        dummy_env.registers.general_purpose[2] = SYSCALL_CLONE;
+        write_syscall(&mut dummy_env);
+        interpret_rtype(&mut dummy_env, RTypeInstruction::SyscallOther);
+        assert_eq!(dummy_env.registers.general_purpose[2], 1);
+        assert_eq!(dummy_env.registers.general_purpose[7], 0);
+    }
+
+    #[test]
+    fn test_syscall_sets_result_and_error_flag() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        write_syscall(&mut dummy_env);
+
+        // fcntl(42, F_GETFL) fails with EBADF, 42 not being an open file
+        dummy_env.registers.general_purpose[4] = 42;
+        dummy_env.registers.general_purpose[5] = 3;
+        interpret_rtype(&mut dummy_env, RTypeInstruction::SyscallFcntl);
+        assert_eq!(dummy_env.registers.general_purpose[2], 0xFFFFFFFF);
+        assert_eq!(dummy_env.registers.general_purpose[7], 0x9);
+
+        // fcntl(stdout, F_GETFL) succeeds, stdout being open for writing
+        dummy_env.registers.general_purpose[4] = 1;
+        interpret_rtype(&mut dummy_env, RTypeInstruction::SyscallFcntl);
+        assert_eq!(dummy_env.registers.general_purpose[2], 1);
+        assert_eq!(dummy_env.registers.general_purpose[7], 0);
+    }
+
+    #[test]
+    fn test_syscall_brk_grows_heap() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        dummy_env.registers.heap_pointer = 0x4000_0000;
+        let new_brk = dummy_env.syscall_brk(&0x4000_2000);
+        assert_eq!(new_brk, 0x4000_2000);
+        assert_eq!(dummy_env.registers.heap_pointer, 0x4000_2000);
+        // Up to the start of the stack
+        dummy_env.reset_scratch_state();
+        dummy_env.instruction_counter += 1;
+        let new_brk = dummy_env.syscall_brk(&(STACK_START_ADDRESS as u64 - 1));
+        assert_eq!(new_brk, STACK_START_ADDRESS as u64 - 1);
+        assert_eq!(dummy_env.registers.heap_pointer, STACK_START_ADDRESS - 1);
+    }
+
+    #[test]
+    fn test_syscall_brk_rejects_invalid_requests() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        dummy_env.registers.heap_pointer = 0x4000_2000;
+        // Shrinking the heap is refused and the current break is returned
+        let new_brk = dummy_env.syscall_brk(&0x4000_0000);
+        assert_eq!(new_brk, 0x4000_2000);
+        assert_eq!(dummy_env.registers.heap_pointer, 0x4000_2000);
+        // So is growing into the stack
+        dummy_env.reset_scratch_state();
+        dummy_env.instruction_counter += 1;
+        let new_brk = dummy_env.syscall_brk(&(STACK_START_ADDRESS as u64));
+        assert_eq!(new_brk, 0x4000_2000);
+        assert_eq!(dummy_env.registers.heap_pointer, 0x4000_2000);
+    }
+
+    #[test]
+    fn test_syscall_brk_returns_the_new_break() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        dummy_env.registers.heap_pointer = 0x4000_0000;
+        dummy_env.registers.general_purpose[2] = SYSCALL_BRK;
+        dummy_env.registers.general_purpose[4] = 0x4000_2000;
+        write_syscall(&mut dummy_env);
+        interpret_rtype(&mut dummy_env, RTypeInstruction::SyscallOther);
+        assert_eq!(dummy_env.registers.heap_pointer, 0x4000_2000);
+        assert_eq!(dummy_env.registers.general_purpose[2], 0x4000_2000);
+        assert_eq!(dummy_env.registers.general_purpose[7], 0);
+    }
+
+    #[test]
+    fn test_preimage_offset_advances_across_chunks() {
+        let mut rng = o1_utils::tests::make_test_rng();
+        let mut dummy_env = dummy_env(&mut rng);
+        // Skip the 8 bytes encoding the length, as if the preimage had already
+        // been requested to the oracle
+        dummy_env.preimage = Some(b"some preimage".to_vec());
+        dummy_env.registers.preimage_offset = 8;
+
+        let read_len = dummy_env.read_preimage_chunk(&0, &4);
+        assert_eq!(read_len, 4);
+        assert_eq!(dummy_env.registers.preimage_offset, 12);
+
+        // the next chunk is read by another instruction
+        dummy_env.reset_scratch_state();
+        dummy_env.instruction_counter += 1;
+        let read_len = dummy_env.read_preimage_chunk(&4, &4);
+        assert_eq!(read_len, 4);
+        assert_eq!(dummy_env.registers.preimage_offset, 16);
+
+        // A new preimage key resets the offset
+        dummy_env.reset_scratch_state();
+        dummy_env.instruction_counter += 1;
+        dummy_env.reset_preimage_offset();
+        assert_eq!(dummy_env.registers.preimage_offset, 0);
+    }
 }
@@ -3,7 +3,7 @@ use crate::{
     mips::{
         column::{MIPS_COLUMNS, MIPS_REL_COLS, MIPS_SEL_COLS},
         constraints::Env,
-        interpreter::{interpret_instruction, Instruction},
+        interpreter::{interpret_decoded_instruction, Instruction},
     },
     trace::{Trace, Tracer},
 };
@@ -41,11 +41,12 @@ impl
                     cols: Box::new(std::array::from_fn(|_| Vec::with_capacity(domain_size))),
                 },
             );
-            interpret_instruction(env, instr);
+            interpret_decoded_instruction(env, instr);
             circuit.constraints.insert(instr, env.constraints.clone());
             circuit.lookups.insert(instr, env.lookups.clone());
             env.scratch_state_idx = 0; // Reset the scratch state index for the next instruction
             env.constraints = vec![]; // Clear the constraints for the next instruction
+            env.labeled_constraints = vec![]; // Clear the labels for the next instruction
             env.lookups = vec![]; // Clear the lookups for the next instruction
         }
         circuit
@@ -8,12 +8,11 @@ use crate::{
     mips::{
         column::{
             ColumnAlias as Column, MIPS_BYTES_READ_OFFSET, MIPS_CHUNK_BYTES_LENGTH,
-            MIPS_HASH_COUNTER_OFFSET, MIPS_HAS_N_BYTES_OFFSET, MIPS_IS_SYSCALL_OFFSET,
-            MIPS_PREIMAGE_BYTES_OFFSET, MIPS_PREIMAGE_LEFT_OFFSET, MIPS_READING_PREIMAGE_OFFSET,
-        },
-        interpreter::{
-            self, ITypeInstruction, Instruction, InterpreterEnv, JTypeInstruction, RTypeInstruction,
+            MIPS_HASH_COUNTER_OFFSET, MIPS_HAS_N_BYTES_OFFSET, MIPS_HINT_COUNTER_OFFSET,
+            MIPS_IS_SYSCALL_OFFSET, MIPS_PREIMAGE_BYTES_OFFSET, MIPS_PREIMAGE_LEFT_CURR_OFFSET,
+            MIPS_PREIMAGE_LEFT_OFFSET, MIPS_READING_PREIMAGE_OFFSET, MIPS_ROW_BYTES_OFFSET,
         },
+        interpreter::{self, Instruction, InstructionDecoder, InterpreterEnv},
         registers::Registers,
     },
     preimage_oracle::PreImageOracle,
@@ -33,7 +32,29 @@ pub const NUM_INSTRUCTION_LOOKUP_TERMS: usize = 5;
 pub const NUM_LOOKUP_TERMS: usize =
     NUM_GLOBAL_LOOKUP_TERMS + NUM_DECODING_LOOKUP_TERMS + NUM_INSTRUCTION_LOOKUP_TERMS;
 // TODO: Delete and use a vector instead
-pub const SCRATCH_SIZE: usize = 93; // MIPS + hash_counter + is_syscall + bytes_read + bytes_left + bytes + has_n_bytes + reading_preimage
+pub const SCRATCH_SIZE: usize = 116; // MIPS + hash_counter + is_syscall + bytes_read + bytes_left + bytes + has_n_bytes + reading_preimage + hint_counter + bytes_left_before_read + row_bytes
+/// The number of steps a program may execute, i.e. the largest step budget
+/// accepted by the range check of
+/// [crate::mips::constraints::Env::constrain_step_budget]
+pub const MAX_STEP_BUDGET: u64 = u32::MAX as u64;
+
+/// Errors raised when checking the values written in the witness
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WitnessError {
+    /// The value of `column` is not a boolean
+    UnsatisfiedBooleanConstraint { column: Column, value: String },
+}
+
+impl std::fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WitnessError::UnsatisfiedBooleanConstraint { column, value } => write!(
+                f,
+                "unsatisfied boolean constraint on the column {column:?}: {value} is not a boolean (0 or 1)"
+            ),
+        }
+    }
+}
 
 #[derive(Clone, Default)]
 pub struct SyscallEnv {
@@ -65,6 +86,8 @@ pub struct Env<Fp> {
     pub scratch_state_idx: usize,
     pub scratch_state: [Fp; SCRATCH_SIZE],
     pub halt: bool,
+    pub exit_code: Fp,
+    pub step_budget: u64,
     pub syscall_env: SyscallEnv,
     pub preimage_oracle: PreImageOracle,
     pub preimage: Option<Vec<u8>>,
@@ -72,6 +95,7 @@ pub struct Env<Fp> {
     pub preimage_key: Option<[u8; 32]>,
     pub keccak_env: Option<KeccakEnv<Fp>>,
     pub hash_counter: u64,
+    pub hint_counter: u64,
 }
 
 fn fresh_scratch_state<Fp: Field, const N: usize>() -> [Fp; N] {
@@ -157,7 +181,10 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
     }
 
     fn check_boolean(x: &Self::Variable) {
-        if !(*x == 0 || *x == 1) {
+        // x (x - 1) = 0 modulo 2^64 only for 0 and 1, as one of the factors is
+        // odd and the other one must then be a multiple of 2^64. Only the
+        // outcome of the check is branched on, not the value.
+        if x.wrapping_mul(x.wrapping_sub(1)) != 0 {
             panic!("The value {} is not a boolean", *x);
         }
     }
@@ -170,6 +197,13 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         self.instruction_counter
     }
 
+    fn access_time(&self, last_accessed_location: &Self::Position) -> Self::Variable {
+        match last_accessed_location {
+            Column::ScratchState(idx) => self.instruction_start_time() + *idx as u64 + 1,
+            _ => panic!("The access times are stored in the scratch columns"),
+        }
+    }
+
     unsafe fn fetch_register(
         &mut self,
         idx: &Self::Variable,
@@ -277,10 +311,9 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         lowest_bit: u32,
         position: Self::Position,
     ) -> Self::Variable {
-        let x: u32 = (*x).try_into().unwrap();
-        let res = (x >> lowest_bit) & ((1 << (highest_bit - lowest_bit)) - 1);
-        let res = res as u64;
+        let res = (*x >> lowest_bit) & ((1 << (highest_bit - lowest_bit)) - 1);
         self.write_column(position, res);
+        self.range_check_bits(res, highest_bit - lowest_bit);
         res
     }
 
@@ -295,6 +328,7 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         let res = x << by;
         let res = res as u64;
         self.write_column(position, res);
+        self.range_check_bits(res, 32);
         res
     }
 
@@ -309,6 +343,7 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         let res = x >> by;
         let res = res as u64;
         self.write_column(position, res);
+        self.range_check_bits(res, 32);
         res
     }
 
@@ -346,6 +381,22 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         }
     }
 
+    fn equal(&mut self, x: &Self::Variable, y: &Self::Variable) -> Self::Variable {
+        // x - y may underflow as u64, so compute the difference in the field
+        let res = {
+            let pos = self.alloc_scratch();
+            let res = if *x == *y { 1 } else { 0 };
+            self.write_column(pos, res);
+            res
+        };
+        {
+            let pos = self.alloc_scratch();
+            let diff = Fp::from(*x) - Fp::from(*y);
+            self.write_field_column(pos, diff.inverse().unwrap_or(Fp::zero()));
+        }
+        res
+    }
+
     unsafe fn test_less_than(
         &mut self,
         x: &Self::Variable,
@@ -440,11 +491,12 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         let u64_res = x + y;
         let x: u32 = (*x).try_into().unwrap();
         let y: u32 = (*y).try_into().unwrap();
-        let u32_res = x + y;
+        let u32_res = x.wrapping_add(y);
         let u32_res = u32_res as u64;
         let overflows = if u32_res == u64_res { 0u64 } else { 1u64 };
         self.write_column(out_position, u32_res);
         self.write_column(overflow_position, overflows);
+        self.range_check_bits(u32_res, 32);
         (u32_res, overflows)
     }
 
@@ -455,14 +507,15 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         out_position: Self::Position,
         underflow_position: Self::Position,
     ) -> (Self::Variable, Self::Variable) {
-        let u64_res = x - y;
+        let u64_res = x.wrapping_sub(*y);
         let x: u32 = (*x).try_into().unwrap();
         let y: u32 = (*y).try_into().unwrap();
-        let u32_res = x - y;
+        let u32_res = x.wrapping_sub(y);
         let u32_res = u32_res as u64;
         let underflows = if u32_res == u64_res { 0u64 } else { 1u64 };
         self.write_column(out_position, u32_res);
         self.write_column(underflow_position, underflows);
+        self.range_check_bits(u32_res, 32);
         (u32_res, underflows)
     }
 
@@ -527,8 +580,16 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
     ) -> (Self::Variable, Self::Variable) {
         let x: u32 = (*x).try_into().unwrap();
         let y: u32 = (*y).try_into().unwrap();
-        let q = ((x as i32) / (y as i32)) as u32;
-        let r = ((x as i32) % (y as i32)) as u32;
+        // The results are unpredictable when dividing by 0
+        let (q, r) = if y == 0 {
+            (0, 0)
+        } else {
+            (
+                (x as i32).wrapping_div(y as i32) as u32,
+                (x as i32).wrapping_rem(y as i32) as u32,
+            )
+        };
+        self.write_divmod(x, y, q, r, true);
         let q = q as u64;
         let r = r as u64;
         self.write_column(position_quotient, q);
@@ -545,8 +606,9 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
     ) -> (Self::Variable, Self::Variable) {
         let x: u32 = (*x).try_into().unwrap();
         let y: u32 = (*y).try_into().unwrap();
-        let q = x / y;
-        let r = x % y;
+        // The results are unpredictable when dividing by 0
+        let (q, r) = if y == 0 { (0, 0) } else { (x / y, x % y) };
+        self.write_divmod(x, y, q, r, false);
         let q = q as u64;
         let r = r as u64;
         self.write_column(position_quotient, q);
@@ -563,6 +625,7 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         let res = x.leading_zeros();
         let res = res as u64;
         self.write_column(position, res);
+        self.write_leading_zeros(x);
         res
     }
 
@@ -575,6 +638,7 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         let res = x.leading_ones();
         let res = res as u64;
         self.write_column(position, res);
+        self.write_leading_zeros(!x);
         res
     }
 
@@ -593,11 +657,31 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         }
     }
 
+    fn consume_step(&mut self) {
+        // Like the instruction counter, the row holds the budget left after
+        // the step, the step halting the program included. No step is
+        // executed once the budget is exhausted, see [Env::step].
+        self.step_budget = self.step_budget.saturating_sub(1);
+        if self.step_budget == 0 && !self.halt {
+            self.halt = true;
+            println!(
+                "Halted at step={} as the step budget is exhausted",
+                self.instruction_counter + 1
+            );
+        }
+        let budget = self.step_budget;
+        self.range_check_bits(budget, 32);
+        self.is_zero(&budget);
+    }
+
     fn report_exit(&mut self, exit_code: &Self::Variable) {
         println!(
             "Exited with code {} at step {}",
             *exit_code, self.instruction_counter
         );
+        // The halt flag is only raised right after, but the exit code is
+        // reported on the row where the program halts.
+        self.copy(exit_code, Column::ExitCode);
     }
 
     fn request_preimage_write(
@@ -637,6 +721,12 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         let preimage_len = preimage.len();
         let preimage_offset = self.registers.preimage_offset as u64;
 
+        // How many bytes are left to be read before this request
+        self.write_column(
+            Column::ScratchState(MIPS_PREIMAGE_LEFT_CURR_OFFSET),
+            (preimage_len as u64) - self.preimage_bytes_read,
+        );
+
         let max_read_len =
             std::cmp::min(preimage_offset + len, (preimage_len + LENGTH_SIZE) as u64)
                 - preimage_offset;
@@ -653,7 +743,7 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
                 let length_byte = u64::to_be_bytes(preimage_len as u64)[idx];
                 unsafe {
                     self.push_memory(&(*addr + i), length_byte as u64);
-                    self.push_memory_access(&(*addr + i), self.instruction_counter + 1);
+                    self.push_memory_access(&(*addr + i), self.next_instruction_start_time());
                 }
             } else {
                 preimage_read_len += 1; // At most, it will be actual_read_len
@@ -667,12 +757,18 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
                 );
                 unsafe {
                     self.push_memory(&(*addr + i), preimage_byte as u64);
-                    self.push_memory_access(&(*addr + i), self.instruction_counter + 1);
+                    self.push_memory_access(&(*addr + i), self.next_instruction_start_time());
                 }
             }
         }
         self.write_column(pos, actual_read_len);
 
+        // The number of bytes of the preimage read in this row
+        self.write_column(
+            Column::ScratchState(MIPS_ROW_BYTES_OFFSET),
+            preimage_read_len,
+        );
+
         // Update the flags to count how many bytes are contained at least
         for i in 0..MIPS_CHUNK_BYTES_LENGTH {
             if preimage_read_len > i as u64 {
@@ -680,6 +776,9 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
             }
         }
 
+        // The bytes read are bounded by the requested length
+        self.range_check_bits(*len - preimage_read_len, 32);
+
         // Update the total number of preimage bytes read so far
         self.preimage_bytes_read += preimage_read_len;
         self.write_column(
@@ -688,10 +787,12 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         );
 
         // Update how many bytes are left to be read
+        let preimage_left = (preimage_len as u64) - self.preimage_bytes_read;
         self.write_column(
             Column::ScratchState(MIPS_PREIMAGE_LEFT_OFFSET),
-            (preimage_len as u64) - self.preimage_bytes_read,
+            preimage_left,
         );
+        self.is_zero(&preimage_left);
 
         // If we've read the entire preimage, trigger Keccak workflow
         if self.preimage_bytes_read == preimage_len as u64 {
@@ -732,7 +833,7 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
         // This should really be handled by the keccak oracle.
         for i in 0..*len {
             // Push memory access
-            unsafe { self.push_memory_access(&(*addr + i), self.instruction_counter + 1) };
+            unsafe { self.push_memory_access(&(*addr + i), self.next_instruction_start_time()) };
             // Fetch the value without allocating witness columns
             let value = {
                 let addr: u32 = (*addr).try_into().unwrap();
@@ -759,6 +860,15 @@ impl<Fp: Field> InterpreterEnv for Env<Fp> {
 
         let remaining = last_hint[idx..len].to_vec();
 
+        // COMMUNICATION CHANNEL: only on constraint side
+
+        // Update hint counter column
+        self.write_column(
+            Column::ScratchState(MIPS_HINT_COUNTER_OFFSET),
+            self.hint_counter,
+        );
+        self.hint_counter += 1;
+
         self.syscall_env.last_hint = Some(remaining);
     }
 }
@@ -823,6 +933,8 @@ impl<Fp: Field> Env<Fp> {
             scratch_state_idx: 0,
             scratch_state: fresh_scratch_state(),
             halt: state.exited,
+            exit_code: Fp::zero(),
+            step_budget: MAX_STEP_BUDGET,
             syscall_env,
             preimage_oracle,
             preimage: state.preimage,
@@ -830,6 +942,7 @@ impl<Fp: Field> Env<Fp> {
             preimage_key: None,
             keccak_env: None,
             hash_counter: 0,
+            hint_counter: 0,
         }
     }
 
@@ -838,6 +951,95 @@ impl<Fp: Field> Env<Fp> {
         self.scratch_state = fresh_scratch_state();
     }
 
+    /// Fill the scratch columns used by the constraints to range check `x`
+    /// on `bits` bits, i.e. the high 16-bits limb of values wider than 16 bits.
+    pub fn range_check_bits(&mut self, x: u64, bits: u32) {
+        if bits > 16 {
+            let high = x >> 16;
+            let pos = self.alloc_scratch();
+            self.write_column(pos, high);
+        }
+    }
+
+    /// Fill the scratch columns used by the constraints to check the number of
+    /// leading zeros of `x`, i.e. the 32 bits of `x` followed by the one-hot
+    /// mask of its highest set bit.
+    pub fn write_leading_zeros(&mut self, x: u32) {
+        for i in 0..32 {
+            let pos = self.alloc_scratch();
+            self.write_column(pos, ((x >> i) & 1) as u64);
+        }
+        // None when x is 0
+        let highest_bit = 31u32.checked_sub(x.leading_zeros());
+        for i in 0..32 {
+            let pos = self.alloc_scratch();
+            self.write_column(pos, (highest_bit == Some(i)) as u64);
+        }
+    }
+
+    /// Fill the scratch columns used by the constraints to check that `q` and
+    /// `r` are the quotient and the remainder of the division of `x` by `y`,
+    /// see [crate::mips::constraints::Env::constrain_divmod]: the flag set when
+    /// `y` is 0, `|y| - |r| - 1` (or 0 when `y` is 0), the sign and the range
+    /// check limb of `x`, `y`, `q` and `r` followed by the flag set for the
+    /// division of `-2^31` by `-1` in the signed case, or the range check
+    /// limbs of `q` and `r` otherwise, and the range check limb of
+    /// `|y| - |r| - 1`.
+    pub fn write_divmod(&mut self, x: u32, y: u32, q: u32, r: u32, signed: bool) {
+        self.is_zero(&(y as u64));
+        let gap = if y == 0 {
+            0
+        } else if signed {
+            (y as i32).unsigned_abs() - (r as i32).unsigned_abs() - 1
+        } else {
+            y - r - 1
+        };
+        let pos = self.alloc_scratch();
+        self.write_column(pos, gap as u64);
+        if signed {
+            for v in [x, y, q, r] {
+                let pos = self.alloc_scratch();
+                self.write_column(pos, (v >> 31) as u64);
+                self.range_check_bits((v & 0x7FFF_FFFF) as u64, 31);
+            }
+            // 0 only when x = -2^31 and y = -1
+            let distance = (x & 0x7FFF_FFFF) as u64
+                + (0x7FFF_FFFF - (y & 0x7FFF_FFFF)) as u64
+                + (1 - (x >> 31)) as u64
+                + (1 - (y >> 31)) as u64;
+            self.is_zero(&distance);
+        } else {
+            self.range_check_bits(q as u64, 32);
+            self.range_check_bits(r as u64, 32);
+        }
+        self.range_check_bits(gap as u64, 32);
+    }
+
+    /// Check that the value of `column` is a boolean, i.e. that
+    /// `x (x - 1) = 0`, like the constraint added by
+    /// [InterpreterEnv::assert_boolean]. The error names the offending column.
+    pub fn check_boolean_column(&self, column: Column) -> Result<(), WitnessError> {
+        let x = self.read_field_column(column);
+        if (x * (x - Fp::one())).is_zero() {
+            Ok(())
+        } else {
+            Err(WitnessError::UnsatisfiedBooleanConstraint {
+                column,
+                value: x.to_string(),
+            })
+        }
+    }
+
+    pub fn read_field_column(&self, column: Column) -> Fp {
+        match column {
+            Column::ScratchState(idx) => self.scratch_state[idx],
+            Column::InstructionCounter => Fp::from(self.instruction_counter),
+            Column::Halted => Fp::from(self.halt),
+            Column::ExitCode => self.exit_code,
+            Column::StepBudget => Fp::from(self.step_budget),
+        }
+    }
+
     pub fn write_column(&mut self, column: Column, value: u64) {
         self.write_field_column(column, value.into())
     }
@@ -845,7 +1047,10 @@ impl<Fp: Field> Env<Fp> {
     pub fn write_field_column(&mut self, column: Column, value: Fp) {
         match column {
             Column::ScratchState(idx) => self.scratch_state[idx] = value,
-            Column::InstructionCounter => panic!("Cannot overwrite the column {:?}", column),
+            Column::ExitCode => self.exit_code = value,
+            Column::InstructionCounter | Column::Halted | Column::StepBudget => {
+                panic!("Cannot overwrite the column {:?}", column)
+            }
         }
     }
 
@@ -909,6 +1114,15 @@ impl<Fp: Field> Env<Fp> {
         self.memory[memory_idx].1[page_address]
     }
 
+    /// Set the number of steps the program may execute from now on.
+    pub fn set_step_budget(&mut self, budget: u64) {
+        assert!(
+            budget <= MAX_STEP_BUDGET,
+            "The step budget {budget} exceeds the maximum {MAX_STEP_BUDGET}"
+        );
+        self.step_budget = budget;
+    }
+
     pub fn decode_instruction(&mut self) -> (Instruction, u32) {
         let instruction =
             ((self.get_memory_direct(self.registers.current_instruction_pointer) as u32) << 24)
@@ -917,130 +1131,7 @@ impl<Fp: Field> Env<Fp> {
                 | ((self.get_memory_direct(self.registers.current_instruction_pointer + 2) as u32)
                     << 8)
                 | (self.get_memory_direct(self.registers.current_instruction_pointer + 3) as u32);
-        let opcode = {
-            match instruction >> 26 {
-                0x00 => match instruction & 0x3F {
-                    0x00 => Instruction::RType(RTypeInstruction::ShiftLeftLogical),
-                    0x02 => Instruction::RType(RTypeInstruction::ShiftRightLogical),
-                    0x03 => Instruction::RType(RTypeInstruction::ShiftRightArithmetic),
-                    0x04 => Instruction::RType(RTypeInstruction::ShiftLeftLogicalVariable),
-                    0x06 => Instruction::RType(RTypeInstruction::ShiftRightLogicalVariable),
-                    0x07 => Instruction::RType(RTypeInstruction::ShiftRightArithmeticVariable),
-                    0x08 => Instruction::RType(RTypeInstruction::JumpRegister),
-                    0x09 => Instruction::RType(RTypeInstruction::JumpAndLinkRegister),
-                    0x0a => Instruction::RType(RTypeInstruction::MoveZero),
-                    0x0b => Instruction::RType(RTypeInstruction::MoveNonZero),
-                    0x0c => match self.registers.general_purpose[2] {
-                        4090 => Instruction::RType(RTypeInstruction::SyscallMmap),
-                        4045 => {
-                            // sysBrk
-                            Instruction::RType(RTypeInstruction::SyscallOther)
-                        }
-                        4120 => {
-                            // sysClone
-                            Instruction::RType(RTypeInstruction::SyscallOther)
-                        }
-                        4246 => Instruction::RType(RTypeInstruction::SyscallExitGroup),
-                        4003 => match self.registers.general_purpose[4] {
-                            interpreter::FD_HINT_READ => {
-                                Instruction::RType(RTypeInstruction::SyscallReadHint)
-                            }
-                            interpreter::FD_PREIMAGE_READ => {
-                                Instruction::RType(RTypeInstruction::SyscallReadPreimage)
-                            }
-                            _ => Instruction::RType(RTypeInstruction::SyscallReadOther),
-                        },
-                        4004 => match self.registers.general_purpose[4] {
-                            interpreter::FD_PREIMAGE_WRITE => {
-                                Instruction::RType(RTypeInstruction::SyscallWritePreimage)
-                            }
-                            interpreter::FD_HINT_WRITE => {
-                                Instruction::RType(RTypeInstruction::SyscallWriteHint)
-                            }
-                            _ => Instruction::RType(RTypeInstruction::SyscallWriteOther),
-                        },
-                        4055 => Instruction::RType(RTypeInstruction::SyscallFcntl),
-                        _ => {
-                            // NB: This has well-defined behavior. Don't panic!
-                            Instruction::RType(RTypeInstruction::SyscallOther)
-                        }
-                    },
-                    0x0f => Instruction::RType(RTypeInstruction::Sync),
-                    0x10 => Instruction::RType(RTypeInstruction::MoveFromHi),
-                    0x11 => Instruction::RType(RTypeInstruction::MoveToHi),
-                    0x12 => Instruction::RType(RTypeInstruction::MoveFromLo),
-                    0x13 => Instruction::RType(RTypeInstruction::MoveToLo),
-                    0x18 => Instruction::RType(RTypeInstruction::Multiply),
-                    0x19 => Instruction::RType(RTypeInstruction::MultiplyUnsigned),
-                    0x1a => Instruction::RType(RTypeInstruction::Div),
-                    0x1b => Instruction::RType(RTypeInstruction::DivUnsigned),
-                    0x20 => Instruction::RType(RTypeInstruction::Add),
-                    0x21 => Instruction::RType(RTypeInstruction::AddUnsigned),
-                    0x22 => Instruction::RType(RTypeInstruction::Sub),
-                    0x23 => Instruction::RType(RTypeInstruction::SubUnsigned),
-                    0x24 => Instruction::RType(RTypeInstruction::And),
-                    0x25 => Instruction::RType(RTypeInstruction::Or),
-                    0x26 => Instruction::RType(RTypeInstruction::Xor),
-                    0x27 => Instruction::RType(RTypeInstruction::Nor),
-                    0x2a => Instruction::RType(RTypeInstruction::SetLessThan),
-                    0x2b => Instruction::RType(RTypeInstruction::SetLessThanUnsigned),
-                    _ => {
-                        panic!("Unhandled instruction {:#X}", instruction)
-                    }
-                },
-                0x01 => {
-                    // RegImm instructions
-                    match (instruction >> 16) & 0x1F {
-                        0x0 => Instruction::IType(ITypeInstruction::BranchLtZero),
-                        0x1 => Instruction::IType(ITypeInstruction::BranchGeqZero),
-                        _ => panic!("Unhandled instruction {:#X}", instruction),
-                    }
-                }
-                0x02 => Instruction::JType(JTypeInstruction::Jump),
-                0x03 => Instruction::JType(JTypeInstruction::JumpAndLink),
-                0x04 => Instruction::IType(ITypeInstruction::BranchEq),
-                0x05 => Instruction::IType(ITypeInstruction::BranchNeq),
-                0x06 => Instruction::IType(ITypeInstruction::BranchLeqZero),
-                0x07 => Instruction::IType(ITypeInstruction::BranchGtZero),
-                0x08 => Instruction::IType(ITypeInstruction::AddImmediate),
-                0x09 => Instruction::IType(ITypeInstruction::AddImmediateUnsigned),
-                0x0A => Instruction::IType(ITypeInstruction::SetLessThanImmediate),
-                0x0B => Instruction::IType(ITypeInstruction::SetLessThanImmediateUnsigned),
-                0x0C => Instruction::IType(ITypeInstruction::AndImmediate),
-                0x0D => Instruction::IType(ITypeInstruction::OrImmediate),
-                0x0E => Instruction::IType(ITypeInstruction::XorImmediate),
-                0x0F => Instruction::IType(ITypeInstruction::LoadUpperImmediate),
-                0x1C => match instruction & 0x3F {
-                    0x02 => Instruction::RType(RTypeInstruction::MultiplyToRegister),
-                    0x20 => Instruction::RType(RTypeInstruction::CountLeadingZeros),
-                    0x21 => Instruction::RType(RTypeInstruction::CountLeadingOnes),
-                    _ => panic!("Unhandled instruction {:#X}", instruction),
-                },
-                0x20 => Instruction::IType(ITypeInstruction::Load8),
-                0x21 => Instruction::IType(ITypeInstruction::Load16),
-                0x22 => Instruction::IType(ITypeInstruction::LoadWordLeft),
-                0x23 => Instruction::IType(ITypeInstruction::Load32),
-                0x24 => Instruction::IType(ITypeInstruction::Load8Unsigned),
-                0x25 => Instruction::IType(ITypeInstruction::Load16Unsigned),
-                0x26 => Instruction::IType(ITypeInstruction::LoadWordRight),
-                0x28 => Instruction::IType(ITypeInstruction::Store8),
-                0x29 => Instruction::IType(ITypeInstruction::Store16),
-                0x2a => Instruction::IType(ITypeInstruction::StoreWordLeft),
-                0x2b => Instruction::IType(ITypeInstruction::Store32),
-                0x2e => Instruction::IType(ITypeInstruction::StoreWordRight),
-                0x30 => {
-                    // Note: This is ll (LoadLinked), but we're only simulating a single processor.
-                    Instruction::IType(ITypeInstruction::Load32)
-                }
-                0x38 => {
-                    // Note: This is sc (StoreConditional), but we're only simulating a single processor.
-                    Instruction::IType(ITypeInstruction::Store32Conditional)
-                }
-                _ => {
-                    panic!("Unhandled instruction {:#X}", instruction)
-                }
-            }
-        };
+        let opcode = self.registers.decode_instruction(instruction);
         (opcode, instruction)
     }
 
@@ -1058,7 +1149,15 @@ impl<Fp: Field> Env<Fp> {
         self.pp_info(&config.info_at, metadata, start);
         self.snapshot_state_at(&config.snapshot_state_at);
 
-        // Force stops at given iteration
+        // Force stops at given iteration, or when no step is left
+        if self.step_budget == 0 {
+            self.halt = true;
+            println!(
+                "Halted at step={} instruction={:?} as the step budget is exhausted",
+                self.instruction_counter, opcode
+            );
+            return opcode;
+        }
         if self.should_trigger_at(&config.stop_at) {
             self.halt = true;
             println!(
@@ -1068,7 +1167,7 @@ impl<Fp: Field> Env<Fp> {
             return opcode;
         }
 
-        interpreter::interpret_instruction(self, opcode);
+        interpreter::interpret_decoded_instruction(self, opcode);
 
         self.instruction_counter += 1;
 
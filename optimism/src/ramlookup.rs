@@ -2,7 +2,7 @@ use ark_ff::{Field, One, Zero};
 use kimchi_msm::{Logup, LookupTableID};
 
 /// Enum representing the two different modes of a RAMLookup
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum LookupMode {
     Read,
     Write,
@@ -96,6 +96,22 @@ where
     }
 }
 
+impl<T, ID: LookupTableID> RAMLookup<T, ID> {
+    /// Transforms the magnitude and the values of this lookup with `f`, e.g. to
+    /// evaluate the expressions of a lookup over a row of the witness
+    pub fn map_expr<F, B>(self, mut f: F) -> RAMLookup<B, ID>
+    where
+        F: FnMut(T) -> B,
+    {
+        RAMLookup {
+            table_id: self.table_id,
+            mode: self.mode,
+            magnitude: f(self.magnitude),
+            value: self.value.into_iter().map(&mut f).collect(),
+        }
+    }
+}
+
 impl<F: std::fmt::Display + Field, ID: LookupTableID> std::fmt::Display for RAMLookup<F, ID> {
     fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let numerator = match self.mode {